@@ -1,9 +1,9 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::quote::shell_quote;
 use anyhow::Result;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
 
 pub struct AliasCommand;
 
@@ -13,35 +13,52 @@ impl BuiltinCommand for AliasCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        _current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        parser: &mut crate::core::parser::Parser,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
+        match command.args.first().map(|s| s.as_str()) {
+            Some("--save") => return save_aliases(&command.args[1..], ctx),
+            Some("--remove") => return remove_aliases(&command.args[1..], ctx),
+            Some("--list-persistent") => {
+                let mut aliases: Vec<_> = ctx.config.get().aliases.iter().collect();
+                aliases.sort();
+                for (name, value) in aliases {
+                    println!("alias {}={}", name, shell_quote(value));
+                }
+                return Ok(0);
+            }
+            _ => {}
+        }
+
         if command.args.is_empty() {
-            let aliases = parser.list_aliases();
+            let aliases = ctx.parser.list_aliases();
             if aliases.is_empty() {
                 println!("alias: no aliases defined");
             } else {
                 for (name, value) in aliases {
-                    println!("alias {}='{}'", name, value);
+                    println!("alias {}={}", name, shell_quote(value));
                 }
             }
         } else {
+            let mut status = 0;
             for arg in &command.args {
-                if let Some(eq_pos) = arg.find('=') {
-                    let (name, value) = arg.split_at(eq_pos);
-                    let value = &value[1..]; // Skip the '=' character
-                    parser.set_alias(name.trim().to_string(), value.trim().to_string());
-                    println!("alias {}='{}'", name.trim(), value.trim());
+                if let Some((name, value)) = split_definition(arg) {
+                    if is_refused(&name, &value, ctx) {
+                        status = 1;
+                        continue;
+                    }
+                    ctx.parser.set_alias(name.clone(), value.clone());
+                    println!("alias {}={}", name, shell_quote(&value));
                 } else {
-                    let aliases = parser.list_aliases();
+                    let aliases = ctx.parser.list_aliases();
                     if let Some(val) = aliases.get(arg) {
-                        println!("alias {}='{}'", arg, val);
+                        println!("alias {}={}", arg, shell_quote(val));
                     } else {
                         println!("alias: {}: not found", arg);
                     }
                 }
             }
+            return Ok(status);
         }
         Ok(0)
     }
@@ -55,6 +72,64 @@ impl BuiltinCommand for AliasCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "alias [name=value ...] [name ...]\n  name=value  Create or update alias\n  name        Display specific alias"
+        "alias [name=value ...] [name ...]\n  name=value                 Create or update alias\n  name                       Display specific alias\n  --save name=value ...      Define alias and save it to the config file\n  --remove name ...          Remove alias from the session and the config file\n  --list-persistent          Show only aliases stored in the config file"
+    }
+}
+/// With `prevent_recursive_aliases` set, reports and refuses an alias that leads
+/// back to itself.
+fn is_refused(name: &str, value: &str, ctx: &ShellContext<'_>) -> bool {
+    let refused = ctx.config.get().completion.prevent_recursive_aliases && ctx.parser.alias_refers_to_itself(name, value);
+    if refused {
+        eprintln!("alias: {}: alias refers to itself", name);
+    }
+    refused
+}
+
+fn split_definition(arg: &str) -> Option<(String, String)> {
+    let (name, value) = arg.split_once('=')?;
+    Some((name.trim().to_string(), value.trim().to_string()))
+}
+
+fn save_aliases(args: &[String], ctx: &mut ShellContext<'_>) -> Result<i32> {
+    if args.is_empty() {
+        eprintln!("alias: --save requires name=value");
+        return Ok(1);
     }
-}
\ No newline at end of file
+
+    for arg in args {
+        let Some((name, value)) = split_definition(arg) else {
+            eprintln!("alias: {}: expected name=value", arg);
+            return Ok(1);
+        };
+        if is_refused(&name, &value, ctx) {
+            return Ok(1);
+        }
+        ctx.parser.set_alias(name.clone(), value.clone());
+        ctx.config.get_mut().aliases.insert(name.clone(), value.clone());
+        println!("alias {}={} (saved)", name, shell_quote(&value));
+    }
+
+    ctx.config.save_aliases()?;
+    Ok(0)
+}
+
+fn remove_aliases(args: &[String], ctx: &mut ShellContext<'_>) -> Result<i32> {
+    if args.is_empty() {
+        eprintln!("alias: --remove requires a name");
+        return Ok(1);
+    }
+
+    let mut status = 0;
+    for name in args {
+        let persistent = ctx.config.get_mut().aliases.remove(name).is_some();
+        let session = ctx.parser.list_aliases().contains_key(name);
+        ctx.parser.remove_alias(name);
+        if !persistent && !session {
+            eprintln!("alias: {}: not found", name);
+            status = 1;
+        }
+    }
+
+    ctx.config.save_aliases()?;
+    Ok(status)
+}
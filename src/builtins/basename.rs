@@ -0,0 +1,70 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::path;
+use anyhow::Result;
+
+pub struct BasenameCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for BasenameCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        _ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut multiple = false;
+        let mut suffix = String::new();
+        let mut args = command.args.iter().peekable();
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            match arg.as_str() {
+                "--" => break,
+                "-a" => multiple = true,
+                "-s" => match args.next() {
+                    Some(value) => {
+                        suffix = value.clone();
+                        multiple = true;
+                    }
+                    None => {
+                        eprintln!("basename: option requires an argument -- 's'");
+                        return Ok(1);
+                    }
+                },
+                _ => {
+                    eprintln!("basename: {}: invalid option", arg);
+                    eprintln!("basename: usage: {}", self.usage());
+                    return Ok(1);
+                }
+            }
+        }
+
+        let mut names: Vec<&String> = args.collect();
+        if names.is_empty() {
+            eprintln!("basename: missing operand");
+            return Ok(1);
+        }
+        // The POSIX form `basename name suffix`; more operands are all names
+        if !multiple && names.len() == 2 {
+            suffix = names.pop().unwrap().clone();
+        }
+
+        for name in names {
+            println!("{}", path::basename(name, &suffix));
+        }
+        Ok(0)
+    }
+
+    fn name(&self) -> &'static str {
+        "basename"
+    }
+
+    fn description(&self) -> &'static str {
+        "Strip the directory and suffix from a path"
+    }
+
+    fn usage(&self) -> &'static str {
+        "basename name [suffix]\n       basename [-a] [-s suffix] name ...\n  -a         Treat every argument as a name\n  -s suffix  Remove suffix from each name (implies -a)"
+    }
+}
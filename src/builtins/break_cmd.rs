@@ -0,0 +1,49 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::errors::LoopControl;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct BreakCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for BreakCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        if ctx.state.loop_depth == 0 {
+            eprintln!("break: only meaningful in a `for', `while', or `until' loop");
+            return Ok(1);
+        }
+
+        let levels = match command.args.first() {
+            None => 1,
+            Some(arg) => match arg.parse::<usize>() {
+                Ok(levels) if levels > 0 => levels,
+                _ => {
+                    eprintln!("break: {}: loop count out of range", arg);
+                    return Ok(1);
+                }
+            },
+        };
+
+        // Unwinds to the loop; asking for more levels than exist stops at the outermost
+        Err(LoopControl::Break(levels.min(ctx.state.loop_depth)).into())
+    }
+
+    fn name(&self) -> &'static str {
+        "break"
+    }
+
+    fn description(&self) -> &'static str {
+        "Exit from a for, while or until loop"
+    }
+
+    fn usage(&self) -> &'static str {
+        "break [n]\n  n  Number of enclosing loops to exit (default: 1)"
+    }
+}
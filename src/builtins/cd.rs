@@ -1,10 +1,10 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
-use crate::utils::path::expand_tilde;
+use crate::core::ShellContext;
+use crate::utils::path::{expand_tilde, normalize_logical};
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
+use std::path::{Path, PathBuf};
 
 pub struct CdCommand;
 
@@ -13,29 +13,51 @@ impl BuiltinCommand for CdCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
-        let target_dir = if command.args.is_empty() {
+        if ctx.options.restricted {
+            eprintln!("cd: restricted");
+            return Ok(1);
+        }
+
+        let mut physical = false;
+        let mut args = command.args.as_slice();
+        while let Some(flag) = args.first() {
+            match flag.as_str() {
+                "-L" => physical = false,
+                "-P" => physical = true,
+                "--" => {
+                    args = &args[1..];
+                    break;
+                }
+                _ => break,
+            }
+            args = &args[1..];
+        }
+
+        let target_dir = if args.is_empty() {
             dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?
         } else {
-            let path = &command.args[0];
+            let path = &args[0];
             if path == "-" {
                 std::env::var("OLDPWD")
                     .map(PathBuf::from)
-                    .unwrap_or_else(|_| current_dir.clone())
+                    .unwrap_or_else(|_| ctx.current_dir.clone())
             } else {
                 // Expand tilde and normalize the path
                 let expanded_path = expand_tilde(path);
-                let mut target = if expanded_path.is_absolute() {
+                if expanded_path.is_absolute() {
                     expanded_path
+                } else if let Some(found) = search_cdpath(path, ctx.current_dir) {
+                    // bash prints the directory when CDPATH picked it
+                    println!("{}", found.display());
+                    found
                 } else {
-                    let mut t = current_dir.clone();
+                    let mut t = ctx.current_dir.clone();
                     t.push(&expanded_path);
                     t
-                };
-                target
+                }
             }
         };
 
@@ -52,13 +74,17 @@ impl BuiltinCommand for CdCommand {
         // Note: We don't test read_dir here because some directories allow navigation
         // but not listing. The actual directory change operation will tell us if access is denied.
 
-        std::env::set_var("OLDPWD", current_dir.to_string_lossy().to_string());
-
-        // Canonicalize the target directory to resolve ./ and ../ properly
-        let canonical_dir = if let Ok(canonical) = target_dir.canonicalize() {
-            canonical
+        // -P resolves symlinks; -L (the default) keeps them and only folds ./ and ../
+        let canonical_dir = if physical {
+            target_dir.canonicalize().unwrap_or(target_dir)
         } else {
-            target_dir
+            let logical = normalize_logical(&target_dir);
+            if logical.is_dir() {
+                logical
+            } else {
+                // `link/..` may not exist lexically; fall back to the physical path like bash
+                target_dir.canonicalize().unwrap_or(target_dir)
+            }
         };
 
         // Try to actually change directory before updating shell state
@@ -75,7 +101,8 @@ impl BuiltinCommand for CdCommand {
         }
 
         // Update shell state only if directory change succeeded
-        *current_dir = canonical_dir.clone();
+        std::env::set_var("OLDPWD", crate::utils::path::logical_dir(ctx.current_dir).to_string_lossy().to_string());
+        *ctx.current_dir = canonical_dir.clone();
         std::env::set_var("PWD", canonical_dir.to_string_lossy().to_string());
 
         Ok(0)
@@ -90,6 +117,32 @@ impl BuiltinCommand for CdCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "cd [directory]\n  directory  The directory to change to (default: home directory)\n  -          Change to previous directory"
+        "cd [-L|-P] [directory]\n  -L         Keep symlinks in the new working directory (default)\n  -P         Resolve symlinks to the physical directory\n  directory  The directory to change to (default: home directory)\n  -          Change to previous directory\n\nRelative directories are looked up in each entry of $CDPATH first."
+    }
+}
+
+/// Looks a relative directory up in `$CDPATH`. Paths starting with `~`, `.` or
+/// `..` are never searched. An empty `CDPATH` entry stands for the current directory.
+fn search_cdpath(path: &str, current_dir: &Path) -> Option<PathBuf> {
+    if path.starts_with('~') || path.starts_with('.') {
+        return None;
     }
+    let cdpath = std::env::var_os("CDPATH")?;
+
+    for dir in std::env::split_paths(&cdpath) {
+        if dir.as_os_str().is_empty() {
+            // Plain current-directory lookups are handled by the caller
+            if current_dir.join(path).is_dir() {
+                return None;
+            }
+            continue;
+        }
+        let base = if dir.is_absolute() { dir } else { current_dir.join(dir) };
+        let candidate = base.join(path);
+        if candidate.is_dir() {
+            return Some(candidate);
+        }
+    }
+
+    None
 }
\ No newline at end of file
@@ -1,11 +1,10 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
 use anyhow::Result;
-use crossterm::{execute, terminal::{Clear, ClearType}};
-use std::collections::HashMap;
-use std::io;
-use std::path::PathBuf;
-use tokio::process::Child;
+use crossterm::{cursor::MoveTo, execute, terminal::{self, Clear, ClearType}};
+use std::io::{self, Write};
 
 pub struct ClearCommand;
 
@@ -13,13 +12,33 @@ pub struct ClearCommand;
 impl BuiltinCommand for ClearCommand {
     async fn execute(
         &self,
-        _command: &ParsedCommand,
-        _current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
-        execute!(io::stdout(), Clear(ClearType::All))?;
-        execute!(io::stdout(), crossterm::cursor::MoveTo(0, 0))?;
+        let mut mode = ctx.config.get().clear_mode.clone();
+        for arg in &command.args {
+            match arg.as_str() {
+                "-x" => mode = "soft".to_string(),
+                _ => {
+                    eprintln!("clear: {}: invalid option", arg);
+                    eprintln!("clear: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            }
+        }
+
+        let mut stdout = io::stdout();
+        match mode.as_str() {
+            "soft" => {
+                // Scroll the screen's contents up into the scroll-back
+                let (_, height) = terminal::size().unwrap_or((80, 24));
+                write!(stdout, "{}", "\n".repeat(height as usize))?;
+                execute!(stdout, MoveTo(0, 0))?;
+            }
+            "purge" => execute!(stdout, Clear(ClearType::All), Clear(ClearType::Purge), MoveTo(0, 0))?,
+            _ => execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?,
+        }
         Ok(0)
     }
 
@@ -32,6 +51,6 @@ impl BuiltinCommand for ClearCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "clear"
+        "clear [-x]\n  Clears the screen. -x scrolls the contents off-screen instead, keeping\n  them in the scroll-back; the clear_mode setting picks the default"
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,99 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::config::Config;
+use crate::terminal::colors::ColorScheme;
+use anyhow::Result;
+
+/// Roles shown in `color scheme list`, in order.
+const SWATCH_ROLES: &[&str] = &["command", "argument", "path", "number", "info", "success", "warning", "error"];
+
+pub struct ColorCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for ColorCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let args: Vec<&str> = command.args.iter().map(String::as_str).collect();
+        let Some((&"scheme", args)) = args.split_first() else {
+            eprintln!("color: usage: {}", self.usage());
+            return Ok(2);
+        };
+
+        match args {
+            [] => println!("{}", ctx.config.get().colors.scheme),
+            ["list"] => {
+                let active = &ctx.config.get().colors.scheme;
+                for scheme in ctx.config.schemes() {
+                    let marker = if scheme.name == *active { '*' } else { ' ' };
+                    let swatches: Vec<String> = SWATCH_ROLES.iter().map(|role| scheme.paint(role, role)).collect();
+                    println!("{} {:<10} {}", marker, scheme.name, swatches.join(" "));
+                }
+            }
+            ["preview", name] => {
+                let Some(scheme) = find_scheme(ctx.config, name) else {
+                    return Ok(1);
+                };
+                print_preview(&scheme);
+            }
+            [name, rest @ ..] if rest.is_empty() || rest == ["--save"] => {
+                let Some(scheme) = find_scheme(ctx.config, name) else {
+                    return Ok(1);
+                };
+                ctx.config.get_mut().colors.scheme = scheme.name;
+                if !rest.is_empty() {
+                    ctx.config.save()?;
+                }
+            }
+            _ => {
+                eprintln!("color: usage: {}", self.usage());
+                return Ok(2);
+            }
+        }
+        Ok(0)
+    }
+
+    fn name(&self) -> &'static str {
+        "color"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show, preview and switch color schemes"
+    }
+
+    fn usage(&self) -> &'static str {
+        "color scheme [list | preview name | name [--save]]\n  Prints the active scheme, lists the available ones, shows a sample in a\n  scheme, or switches to it (saving the choice to the config with --save)"
+    }
+}
+
+fn find_scheme(config: &Config, name: &str) -> Option<ColorScheme> {
+    let scheme = config.find_scheme(name);
+    if scheme.is_none() {
+        eprintln!("color: {}: no such scheme (see `color scheme list`)", name);
+    }
+    scheme
+}
+
+/// A sample prompt, command line and messages in each of the scheme's roles.
+fn print_preview(scheme: &ColorScheme) {
+    println!(
+        "[{}@{} {}]{} {} {} {}",
+        scheme.paint("prompt_user", "user"),
+        scheme.paint("prompt_host", "host"),
+        scheme.paint("prompt_path", "~/project"),
+        scheme.paint("prompt_symbol", "$"),
+        scheme.paint("command", "grep"),
+        scheme.paint("argument", "-n TODO"),
+        scheme.paint("path", "src/main.rs"),
+    );
+    println!("{}", scheme.paint("info", "info: 3 matches"));
+    println!("{}", scheme.paint("success", "success: build finished"));
+    println!("{}", scheme.paint("warning", "warning: unused variable"));
+    println!("{}", scheme.paint("error", "error: file not found"));
+    println!("{} {}", scheme.paint("number", "42"), scheme.paint("path", "/usr/local/bin"));
+}
@@ -0,0 +1,66 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::state::CompletionSpec;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct CompdefCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for CompdefCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let completions = &mut ctx.state.completion_specs;
+        match command.args.as_slice() {
+            [] => {
+                let mut entries: Vec<_> = completions
+                    .iter()
+                    .filter_map(|(program, spec)| match spec {
+                        CompletionSpec::Function(function) => Some((program, function)),
+                        _ => None,
+                    })
+                    .collect();
+                entries.sort();
+                for (program, function) in entries {
+                    println!("compdef {} {}", program, function);
+                }
+                Ok(0)
+            }
+            [flag, programs @ ..] if flag == "-d" => {
+                let mut status = 0;
+                for program in programs {
+                    if completions.remove(program).is_none() {
+                        eprintln!("compdef: {}: no completion specification", program);
+                        status = 1;
+                    }
+                }
+                Ok(status)
+            }
+            [program, function] if !program.starts_with('-') => {
+                completions.insert(program.clone(), CompletionSpec::Function(function.clone()));
+                Ok(0)
+            }
+            _ => {
+                eprintln!("compdef: usage: {}", self.usage());
+                Ok(2)
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "compdef"
+    }
+
+    fn description(&self) -> &'static str {
+        "Register a function to complete a command's arguments"
+    }
+
+    fn usage(&self) -> &'static str {
+        "compdef [command function | -d command ...]\n  Completes command's arguments with function, which runs with COMP_WORDS\n  and COMP_CWORD set and prints one candidate per line. -d removes the\n  registration; with no arguments, lists them"
+    }
+}
@@ -0,0 +1,64 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::param_expand;
+use anyhow::Result;
+
+pub struct CompgenCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for CompgenCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        _ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut wordlist = None;
+        let mut args = command.args.iter().peekable();
+        while let Some(flag) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            match flag.as_str() {
+                "-W" => match args.next() {
+                    Some(words) => wordlist = Some(words),
+                    None => {
+                        eprintln!("compgen: -W: option requires an argument");
+                        return Ok(2);
+                    }
+                },
+                "--" => break,
+                _ => {
+                    eprintln!("compgen: {}: invalid option", flag);
+                    eprintln!("compgen: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            }
+        }
+        let prefix = args.next().map(String::as_str).unwrap_or("");
+
+        // The list is split like an unquoted expansion
+        let ifs = std::env::var("IFS").unwrap_or_else(|_| param_expand::DEFAULT_IFS.to_string());
+        let matches: Vec<String> = wordlist
+            .map(|words| param_expand::ifs_split(words, &ifs))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|word| word.starts_with(prefix))
+            .collect();
+        for word in &matches {
+            println!("{}", word);
+        }
+        Ok(if matches.is_empty() { 1 } else { 0 })
+    }
+
+    fn name(&self) -> &'static str {
+        "compgen"
+    }
+
+    fn description(&self) -> &'static str {
+        "Generate completion candidates"
+    }
+
+    fn usage(&self) -> &'static str {
+        "compgen -W wordlist [--] [word]\n  Prints each word of wordlist that starts with word, one per line, for use\n  in completion functions"
+    }
+}
@@ -0,0 +1,113 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::state::CompletionSpec;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct CompleteCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for CompleteCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut spec = None;
+        let mut print = false;
+        let mut remove = false;
+        let mut args = command.args.iter().peekable();
+        while let Some(flag) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            let action = match flag.as_str() {
+                "-F" | "-W" => match args.next() {
+                    Some(value) if flag == "-F" => CompletionSpec::Function(value.clone()),
+                    Some(value) => CompletionSpec::Words(value.clone()),
+                    None => {
+                        eprintln!("complete: {}: option requires an argument", flag);
+                        return Ok(2);
+                    }
+                },
+                "-f" => CompletionSpec::Files,
+                "-d" => CompletionSpec::Directories,
+                "-b" => CompletionSpec::Builtins,
+                "-c" => CompletionSpec::Commands,
+                "-p" => {
+                    print = true;
+                    continue;
+                }
+                "-r" => {
+                    remove = true;
+                    continue;
+                }
+                "--" => break,
+                _ => {
+                    eprintln!("complete: {}: invalid option", flag);
+                    eprintln!("complete: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            };
+            if spec.replace(action).is_some() {
+                eprintln!("complete: only one of -F, -W, -f, -d, -b and -c can be given");
+                return Ok(2);
+            }
+        }
+        let programs: Vec<&String> = args.collect();
+        let specs = &mut ctx.state.completion_specs;
+
+        // Without names, -p and -r apply to every registration
+        let mut status = 0;
+        match spec {
+            Some(_) if print || remove || programs.is_empty() => {
+                eprintln!("complete: usage: {}", self.usage());
+                return Ok(2);
+            }
+            Some(spec) => {
+                for program in programs {
+                    specs.insert(program.clone(), spec.clone());
+                }
+            }
+            None if remove && programs.is_empty() => specs.clear(),
+            None if remove => {
+                for program in programs {
+                    if specs.remove(program).is_none() {
+                        eprintln!("complete: {}: no completion specification", program);
+                        status = 1;
+                    }
+                }
+            }
+            None if programs.is_empty() => {
+                let mut entries: Vec<_> = specs.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                for (program, spec) in entries {
+                    println!("complete {} {}", spec.options(), program);
+                }
+            }
+            None => {
+                for program in programs {
+                    match specs.get(program) {
+                        Some(spec) => println!("complete {} {}", spec.options(), program),
+                        None => {
+                            eprintln!("complete: {}: no completion specification", program);
+                            status = 1;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "complete"
+    }
+
+    fn description(&self) -> &'static str {
+        "Set how a command's arguments complete"
+    }
+
+    fn usage(&self) -> &'static str {
+        "complete -F function | -W wordlist | -f | -d | -b | -c command ...\n       complete [-p | -r] [command ...]\n  -F  Complete with the candidates function prints, as with compdef\n  -W  Complete with the words of wordlist\n  -f  Complete file paths\n  -d  Complete directory paths\n  -b  Complete builtin names\n  -c  Complete command names\n  -p  Print the registrations for commands, or all of them\n  -r  Remove the registrations for commands, or all of them"
+    }
+}
@@ -0,0 +1,70 @@
+use super::BuiltinCommand;
+use crate::config::Config;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct ConfigCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for ConfigCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let args: Vec<&str> = command.args.iter().map(|s| s.as_str()).collect();
+
+        match args.as_slice() {
+            ["show"] => {
+                print!("{}", ctx.config.dump()?);
+                Ok(0)
+            }
+            ["profile", "list"] | ["profile"] => {
+                let profiles = Config::list_profiles();
+                if profiles.is_empty() {
+                    println!("config: no profiles defined");
+                }
+                for name in profiles {
+                    let marker = if ctx.config.profile() == Some(name.as_str()) { "*" } else { " " };
+                    println!("{} {}", marker, name);
+                }
+                Ok(0)
+            }
+            ["profile", "new", name] => {
+                let path = match Config::profile_path(name) {
+                    Some(path) => path,
+                    None => {
+                        eprintln!("config: could not determine profiles directory");
+                        return Ok(1);
+                    }
+                };
+                if path.exists() {
+                    eprintln!("config: profile '{}' already exists", name);
+                    return Ok(1);
+                }
+                ctx.config.get().save(&path)?;
+                println!("config: created profile '{}' at {}", name, path.display());
+                Ok(0)
+            }
+            _ => {
+                eprintln!("config: usage: {}", self.usage());
+                Ok(1)
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "config"
+    }
+
+    fn description(&self) -> &'static str {
+        "Manage shell configuration and profiles"
+    }
+
+    fn usage(&self) -> &'static str {
+        "config <show | profile <list|new name>>\n  show              Print the configuration in effect as TOML\n  profile list      List available profiles (* marks the active one)\n  profile new name  Save the current configuration as a new profile"
+    }
+}
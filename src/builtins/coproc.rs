@@ -0,0 +1,81 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+/// The name a coprocess gets when none is given, as in bash.
+const DEFAULT_NAME: &str = "COPROC";
+
+pub struct CoprocCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for CoprocCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        // `coproc NAME { ...; }` is parsed as a compound command; what reaches the
+        // builtin is the simple command form and the options that manage coprocesses
+        match command.args.first().map(String::as_str) {
+            None => {
+                let mut coprocs: Vec<_> = ctx.state.coprocs.iter().collect();
+                coprocs.sort_by(|a, b| a.0.cmp(b.0));
+                for (name, coproc) in coprocs {
+                    println!("{} {}", name, coproc.pid);
+                }
+                Ok(0)
+            }
+            Some("-c") => {
+                let names = &command.args[1..];
+                let names = if names.is_empty() { vec![DEFAULT_NAME.to_string()] } else { names.to_vec() };
+                let mut status = 0;
+                for name in names {
+                    match ctx.state.coprocs.get_mut(&name) {
+                        Some(coproc) => {
+                            coproc.close_input();
+                            if let Some(fds) = ctx.state.arrays.get_mut(&name) {
+                                fds.truncate(1);
+                            }
+                        }
+                        None => {
+                            eprintln!("coproc: {}: no such coprocess", name);
+                            status = 1;
+                        }
+                    }
+                }
+                Ok(status)
+            }
+            Some(flag) if flag.starts_with('-') && flag != "--" => {
+                eprintln!("coproc: {}: invalid option", flag);
+                eprintln!("coproc: usage: {}", self.usage());
+                Ok(2)
+            }
+            Some(_) => {
+                let args = command.args.strip_prefix(&["--".to_string()]).unwrap_or(&command.args);
+                let Some((program, args)) = args.split_first() else {
+                    eprintln!("coproc: usage: {}", self.usage());
+                    return Ok(2);
+                };
+                let mut body = ParsedCommand::new(program.clone());
+                body.args = args.to_vec();
+                executor.start_coproc(DEFAULT_NAME.to_string(), vec![body], ctx).await
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "coproc"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run a command in the background with pipes to and from the shell"
+    }
+
+    fn usage(&self) -> &'static str {
+        "coproc [NAME] { commands; }\n       coproc command [arg ...]\n       coproc [-c [NAME ...]]\n  Starts a coprocess: ${NAME[1]} writes to its input (echo hi >&${NAME[1]})\n  and ${NAME[0]} reads its output (read line <&${NAME[0]}). NAME defaults to\n  COPROC, and NAME_PID is its process ID. Not supported on Windows.\n  -c  Close the input of the named coprocesses, which ends most commands\n  Without arguments, lists the running coprocesses"
+    }
+}
+
@@ -0,0 +1,111 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use std::fmt::{Display, Write};
+
+/// The format used without a `+FORMAT` argument, as in `Thu Mar 07 14:02:11 +01:00 2024`.
+const DEFAULT_FORMAT: &str = "%a %b %d %H:%M:%S %Z %Y";
+
+pub struct DateCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for DateCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        _ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut utc = false;
+        let mut reference = None;
+        let mut format = DEFAULT_FORMAT;
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-u" | "--utc" => utc = true,
+                "-r" => match args.next() {
+                    Some(file) => reference = Some(file),
+                    None => {
+                        eprintln!("date: option requires an argument -- 'r'");
+                        return Ok(1);
+                    }
+                },
+                "-d" => {
+                    eprintln!("date: -d: parsing dates is not supported yet");
+                    return Ok(1);
+                }
+                _ if arg.starts_with('+') => format = &arg[1..],
+                _ => {
+                    eprintln!("date: {}: invalid argument", arg);
+                    eprintln!("date: usage: {}", self.usage());
+                    return Ok(1);
+                }
+            }
+        }
+
+        let time: DateTime<Utc> = match reference {
+            Some(file) => match std::fs::metadata(file).and_then(|metadata| metadata.modified()) {
+                Ok(modified) => modified.into(),
+                Err(e) => {
+                    eprintln!("date: {}: {}", file, e);
+                    return Ok(1);
+                }
+            },
+            None => Utc::now(),
+        };
+
+        let formatted = if utc {
+            format_time(&time, format)
+        } else {
+            format_time(&Local.from_utc_datetime(&time.naive_utc()), format)
+        };
+        match formatted {
+            Some(text) => {
+                println!("{}", text);
+                Ok(0)
+            }
+            None => {
+                eprintln!("date: invalid format '{}'", format);
+                Ok(1)
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "date"
+    }
+
+    fn description(&self) -> &'static str {
+        "Print the current date and time"
+    }
+
+    fn usage(&self) -> &'static str {
+        "date [-u] [-r file] [+format]\n  -u       Use UTC instead of local time\n  -r file  Show the file's last modification time\n  +format  strftime-style format, e.g. +%Y-%m-%d"
+    }
+}
+
+/// Formats `time` with a strftime-style `format`; `None` if it has an unknown specifier.
+fn format_time<Tz: TimeZone>(time: &DateTime<Tz>, format: &str) -> Option<String>
+where
+    Tz::Offset: Display,
+{
+    let mut text = String::new();
+    write!(text, "{}", time.format(format)).ok()?;
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        let time = Utc.with_ymd_and_hms(2024, 3, 7, 14, 2, 11).unwrap();
+        assert_eq!(format_time(&time, DEFAULT_FORMAT).unwrap(), "Thu Mar 07 14:02:11 UTC 2024");
+        assert_eq!(format_time(&time, "%Y-%m-%d").unwrap(), "2024-03-07");
+        assert_eq!(format_time(&time, "%Q"), None);
+    }
+}
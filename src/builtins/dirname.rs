@@ -0,0 +1,44 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::path;
+use anyhow::Result;
+
+pub struct DirnameCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for DirnameCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        _ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let names = match command.args.first().map(String::as_str) {
+            Some("--") => &command.args[1..],
+            _ => &command.args[..],
+        };
+        if names.is_empty() {
+            eprintln!("dirname: missing operand");
+            return Ok(1);
+        }
+
+        for name in names {
+            println!("{}", path::dirname(name));
+        }
+        Ok(0)
+    }
+
+    fn name(&self) -> &'static str {
+        "dirname"
+    }
+
+    fn description(&self) -> &'static str {
+        "Strip the last component from a path"
+    }
+
+    fn usage(&self) -> &'static str {
+        "dirname name ...\n  Prints each name without its last component, or . if it has no directory part"
+    }
+}
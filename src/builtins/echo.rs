@@ -1,9 +1,9 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::escape::{unescape, UnescapeMode};
 use anyhow::Result;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
 
 pub struct EchoCommand;
 
@@ -12,24 +12,30 @@ impl BuiltinCommand for EchoCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        _current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        _executor: &mut Executor,
+        _ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
         let mut newline = true;
-        let mut args = command.args.iter();
+        let mut mode = UnescapeMode::Basic;
 
-        if let Some(first_arg) = args.next() {
-            if first_arg == "-n" {
-                newline = false;
-            } else {
-                print!("{}", expand_variables(first_arg));
+        // Leading words made only of the flags n, e and E are options, e.g. `-ne`
+        let mut args = command.args.as_slice();
+        while let Some(flags) = args.first().and_then(|arg| arg.strip_prefix('-')) {
+            if flags.is_empty() || !flags.chars().all(|c| matches!(c, 'n' | 'e' | 'E')) {
+                break;
             }
+            for flag in flags.chars() {
+                match flag {
+                    'n' => newline = false,
+                    'e' => mode = UnescapeMode::Extended,
+                    _ => mode = UnescapeMode::Off,
+                }
+            }
+            args = &args[1..];
         }
 
-        for arg in args {
-            print!(" {}", expand_variables(arg));
-        }
+        let words: Vec<String> = args.iter().map(|arg| unescape(arg, mode)).collect();
+        print!("{}", words.join(" "));
 
         if newline {
             println!();
@@ -47,77 +53,6 @@ impl BuiltinCommand for EchoCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "echo [-n] [string ...]\n  -n  Do not output trailing newline"
+        "echo [-neE] [string ...]\n  -n  Do not output trailing newline\n  -e  Interpret escapes such as \\e, \\xHH and \\uXXXX\n  -E  Do not interpret any escapes"
     }
 }
-
-fn expand_variables(text: &str) -> String {
-    let mut result = String::new();
-    let mut chars = text.chars().peekable();
-
-    while let Some(ch) = chars.next() {
-        if ch == '$' {
-            if let Some(&'{') = chars.peek() {
-                chars.next(); // consume '{'
-                let mut var_name = String::new();
-                let mut found_closing = false;
-
-                while let Some(ch) = chars.next() {
-                    if ch == '}' {
-                        found_closing = true;
-                        break;
-                    }
-                    var_name.push(ch);
-                }
-
-                if found_closing {
-                    if let Ok(value) = std::env::var(&var_name) {
-                        result.push_str(&value);
-                    }
-                } else {
-                    result.push('$');
-                    result.push('{');
-                    result.push_str(&var_name);
-                }
-            } else if let Some(&next_ch) = chars.peek() {
-                if next_ch.is_alphabetic() || next_ch == '_' {
-                    let mut var_name = String::new();
-                    while let Some(&ch) = chars.peek() {
-                        if ch.is_alphanumeric() || ch == '_' {
-                            var_name.push(chars.next().unwrap());
-                        } else {
-                            break;
-                        }
-                    }
-
-                    if let Ok(value) = std::env::var(&var_name) {
-                        result.push_str(&value);
-                    }
-                } else {
-                    result.push(ch);
-                }
-            } else {
-                result.push(ch);
-            }
-        } else if ch == '\\' {
-            if let Some(escaped) = chars.next() {
-                match escaped {
-                    'n' => result.push('\n'),
-                    't' => result.push('\t'),
-                    'r' => result.push('\r'),
-                    '\\' => result.push('\\'),
-                    _ => {
-                        result.push('\\');
-                        result.push(escaped);
-                    }
-                }
-            } else {
-                result.push(ch);
-            }
-        } else {
-            result.push(ch);
-        }
-    }
-
-    result
-}
\ No newline at end of file
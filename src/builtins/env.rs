@@ -1,9 +1,9 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::quote::shell_quote;
 use anyhow::Result;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
 
 pub struct EnvCommand;
 
@@ -12,10 +12,13 @@ impl BuiltinCommand for EnvCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        _current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
+        if command.args.first().is_some_and(|arg| arg.starts_with('-')) {
+            return run_with_environment(command, executor, ctx).await;
+        }
+
         if command.args.is_empty() {
             let mut env_vars: Vec<_> = std::env::vars().collect();
             env_vars.sort_by(|a, b| a.0.cmp(&b.0));
@@ -28,6 +31,10 @@ impl BuiltinCommand for EnvCommand {
                 if let Some(eq_pos) = arg.find('=') {
                     let (key, value) = arg.split_at(eq_pos);
                     let value = &value[1..]; // Skip the '=' character
+                    if ctx.is_readonly(key) {
+                        eprintln!("env: {}: readonly variable", key);
+                        return Ok(1);
+                    }
                     std::env::set_var(key, value);
                     println!("Set {}={}", key, value);
                 } else {
@@ -54,6 +61,87 @@ impl BuiltinCommand for EnvCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "env [VAR=value ...] [variable ...]\n  VAR=value  Set environment variable\n  variable   Display specific variable"
+        "env [VAR=value ...] [variable ...]\n       env [-i] [-u VAR] [VAR=value ...] [command [arg ...]]\n  VAR=value  Set environment variable\n  variable   Display specific variable\n  -i         Run command with an empty environment\n  -u VAR     Run command without VAR\n  With options, VAR=value applies only to the command, and without a command\n  the resulting environment is printed"
+    }
+}
+
+/// `env` with options: builds the modified environment and runs the command in it,
+/// or prints it when there is no command. The shell's own environment is unchanged.
+async fn run_with_environment(
+    command: &ParsedCommand,
+    executor: &mut Executor,
+    ctx: &mut ShellContext<'_>,
+) -> Result<i32> {
+    let mut clear = false;
+    let mut unset = Vec::new();
+    let mut args = command.args.iter().peekable();
+
+    while let Some(arg) = args.next_if(|arg| arg.starts_with('-')) {
+        match arg.as_str() {
+            "--" => break,
+            "-i" | "-" => clear = true,
+            "-u" => match args.next() {
+                Some(name) => unset.push(name.clone()),
+                None => {
+                    eprintln!("env: option requires an argument -- 'u'");
+                    return Ok(125);
+                }
+            },
+            _ if arg.starts_with("-u") => unset.push(arg[2..].to_string()),
+            _ => {
+                eprintln!("env: invalid option -- '{}'", arg.trim_start_matches('-'));
+                eprintln!("Usage: env [-i] [-u VAR] [VAR=value ...] [command [arg ...]]");
+                return Ok(125);
+            }
+        }
     }
-}
\ No newline at end of file
+
+    let mut assignments = Vec::new();
+    while let Some(arg) = args.next_if(|arg| arg.contains('=')) {
+        let (key, value) = arg.split_once('=').unwrap();
+        assignments.push((key.to_string(), value.to_string()));
+    }
+
+    // A restricted shell's PATH holds even for the one command
+    let protected = unset.iter().chain(assignments.iter().map(|(name, _)| name)).find(|name| ctx.is_readonly(name));
+    if let Some(name) = protected {
+        eprintln!("env: {}: readonly variable", name);
+        return Ok(125);
+    }
+    if clear && ctx.options.restricted {
+        eprintln!("env: -i: restricted");
+        return Ok(125);
+    }
+
+    let Some(program) = args.next() else {
+        let mut env_vars: Vec<(String, String)> = if clear { Vec::new() } else { std::env::vars().collect() };
+        env_vars.retain(|(key, _)| !unset.contains(key) && !assignments.iter().any(|(name, _)| name == key));
+        env_vars.extend(assignments);
+        env_vars.sort();
+        for (key, value) in env_vars {
+            println!("{}={}", key, value);
+        }
+        return Ok(0);
+    };
+
+    // Like the real `env`, only run programs, never builtins or functions
+    let Some(program_path) = executor.remember_program(program) else {
+        eprintln!("env: '{}': No such file or directory", program);
+        return Ok(127);
+    };
+
+    let mut env_command = ParsedCommand::new(program_path.to_string_lossy().to_string());
+    env_command.args = args.cloned().collect();
+    // Values are already expanded, so quote them against being expanded again
+    env_command.environment = assignments
+        .into_iter()
+        .map(|(key, value)| (key, shell_quote(&value)))
+        .collect();
+    env_command.clear_environment = clear;
+    env_command.unset_environment = unset;
+    env_command.input_redirect = command.input_redirect.clone();
+    env_command.output_redirect = command.output_redirect.clone();
+    env_command.append_redirect = command.append_redirect.clone();
+
+    executor.execute(env_command, ctx).await
+}
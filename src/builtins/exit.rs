@@ -1,9 +1,9 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::errors::ExitRequest;
+use crate::core::ShellContext;
 use anyhow::Result;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
 
 pub struct ExitCommand;
 
@@ -12,18 +12,22 @@ impl BuiltinCommand for ExitCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        _current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        _executor: &mut Executor,
+        _ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
-        let _exit_code = if command.args.is_empty() {
-            0
-        } else {
-            command.args[0].parse::<i32>().unwrap_or(0)
+        let exit_code = match command.args.first() {
+            None => 0,
+            Some(arg) => match arg.parse::<i32>() {
+                Ok(code) => code,
+                Err(_) => {
+                    eprintln!("exit: {}: numeric argument required", arg);
+                    2
+                }
+            },
         };
 
-        // Special exit code to signal the shell to exit
-        Ok(130)
+        // Unwinds to the shell, which stops with this status
+        Err(ExitRequest(exit_code).into())
     }
 
     fn name(&self) -> &'static str {
@@ -0,0 +1,75 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::{self, ParsedCommand};
+use crate::core::ShellContext;
+use crate::utils::quote::shell_quote;
+use anyhow::Result;
+
+pub struct ExportCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for ExportCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut args = command.args.iter().peekable();
+        while let Some(flag) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            match flag.as_str() {
+                "-p" => {}
+                "--" => break,
+                _ => {
+                    eprintln!("export: {}: invalid option", flag);
+                    eprintln!("export: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            }
+        }
+
+        if args.peek().is_none() {
+            let mut vars: Vec<(String, String)> = std::env::vars().collect();
+            vars.sort();
+            for (name, value) in vars {
+                println!("export {}={}", name, shell_quote(&value));
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            if !parser::is_name(name) {
+                eprintln!("export: `{}': not a valid identifier", arg);
+                status = 1;
+                continue;
+            }
+            if value.is_some() && ctx.is_readonly(name) {
+                eprintln!("export: {}: readonly variable", name);
+                status = 1;
+                continue;
+            }
+            // Every variable is already in the environment, so a bare name needs nothing
+            if let Some(value) = value {
+                std::env::set_var(name, value);
+            }
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "export"
+    }
+
+    fn description(&self) -> &'static str {
+        "Set environment variables or list them"
+    }
+
+    fn usage(&self) -> &'static str {
+        "export [-p] [name[=value] ...]\n  Sets each name to value. With no names, or with -p, prints every variable\n  as an export command that can be run again to restore it"
+    }
+}
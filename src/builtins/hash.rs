@@ -0,0 +1,62 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct HashCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for HashCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        executor: &mut Executor,
+        _ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut names = Vec::new();
+        for arg in &command.args {
+            match arg.as_str() {
+                "-r" => executor.clear_path_cache(),
+                flag if flag.starts_with('-') => {
+                    eprintln!("hash: {}: invalid option", flag);
+                    eprintln!("hash: usage: {}", self.usage());
+                    return Ok(2);
+                }
+                name => names.push(name),
+            }
+        }
+
+        if command.args.is_empty() {
+            let cached = executor.cached_paths();
+            if cached.is_empty() {
+                println!("hash: hash table empty");
+            }
+            for (name, path) in cached {
+                println!("{}\t{}", name, path.display());
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+        for name in names {
+            if executor.remember_program(name).is_none() {
+                eprintln!("hash: {}: not found", name);
+                status = 1;
+            }
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "hash"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show or reset the command path cache"
+    }
+
+    fn usage(&self) -> &'static str {
+        "hash [-r] [name ...]\n  -r    Forget all cached command locations\n  name  Look up a command and remember its location\n  With no arguments, lists the cached commands"
+    }
+}
@@ -1,9 +1,16 @@
+use super::which::find_all_in_path;
 use super::{BuiltinCommand, list_builtins, get_builtin};
+use crate::core::command_cache::CommandCache;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
 use anyhow::Result;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// How long `command --help` may run when looking for a description.
+const HELP_FLAG_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub struct HelpCommand;
 
@@ -12,11 +19,10 @@ impl BuiltinCommand for HelpCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        _current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
-        if command.args.is_empty() {
+        if command.args.is_empty() || command.args[0] == "--all" {
             println!("Flex-SH - A high-performance, modern system shell");
             println!();
             println!("Built-in commands:");
@@ -27,8 +33,13 @@ impl BuiltinCommand for HelpCommand {
                     println!("  {:10} - {}", builtin.name(), builtin.description());
                 }
             }
-            println!();
-            println!("Use 'help <command>' for detailed information about a specific command.");
+            if command.args.is_empty() {
+                println!();
+                println!("Use 'help <command>' for detailed information about a specific command.");
+                println!("Use 'help --all' to also list the commands in PATH.");
+            } else {
+                print_external_commands(ctx).await;
+            }
         } else {
             let command_name = &command.args[0];
             if let Some(builtin) = get_builtin(command_name) {
@@ -36,6 +47,8 @@ impl BuiltinCommand for HelpCommand {
                 println!();
                 println!("Usage:");
                 println!("{}", builtin.usage());
+            } else if let Some(description) = external_description(command_name, &mut ctx.state.external_help).await {
+                println!("{} - {}", command_name, description);
             } else {
                 println!("Unknown command: {}", command_name);
                 return Ok(1);
@@ -54,6 +67,134 @@ impl BuiltinCommand for HelpCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "help [command]\n  command  Show help for a specific command"
+        "help [--all | command]\n  command  Show help for a specific command. For commands in PATH this is\n           their summary from the whatis database, or the first line of\n           their --help output\n  --all    Also list every command in PATH, with whatis summaries"
+    }
+}
+
+/// Lists the programs in PATH with their whatis summaries. Running each one with
+/// `--help` would take too long and could have side effects, so that's left to
+/// `help <command>`.
+async fn print_external_commands(ctx: &mut ShellContext<'_>) {
+    let cache = CommandCache::scan(&std::env::var("PATH").unwrap_or_default());
+    let programs: Vec<&str> = cache.matching("").collect();
+    let help = &mut ctx.state.external_help;
+
+    let unknown: Vec<&str> = programs.iter().copied().filter(|name| !help.contains_key(*name)).collect();
+    let mut found = whatis(&unknown).await;
+    for name in unknown {
+        help.insert(name.to_string(), found.remove(name));
+    }
+
+    println!();
+    println!("Commands in PATH:");
+    for name in programs {
+        match help.get(name).cloned().flatten() {
+            Some(description) => println!("  {:20} - {}", name, description),
+            None => println!("  {}", name),
+        }
+    }
+}
+
+/// A one-line description of the external command `name`, from the whatis database
+/// or failing that the first line of its `--help` output. Results, including
+/// failures, are kept in `cache` for the session.
+async fn external_description(name: &str, cache: &mut HashMap<String, Option<String>>) -> Option<String> {
+    if let Some(cached) = cache.get(name) {
+        return cached.clone();
+    }
+    let path = find_all_in_path(name).into_iter().next()?;
+
+    let description = match whatis(&[name]).await.remove(name) {
+        Some(description) => Some(description),
+        None => help_flag_summary(&path).await,
+    };
+    cache.insert(name.to_string(), description.clone());
+    description
+}
+
+/// Summaries of `names` from `whatis` (or `man -f`), by name.
+#[cfg(unix)]
+async fn whatis(names: &[&str]) -> HashMap<String, String> {
+    if names.is_empty() {
+        return HashMap::new();
+    }
+    for program in [&["whatis"][..], &["man", "-f"][..]] {
+        let output = tokio::process::Command::new(program[0])
+            .args(&program[1..])
+            .args(names)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .await;
+        if let Ok(output) = output {
+            return parse_whatis(&String::from_utf8_lossy(&output.stdout));
+        }
+        // Not installed; try the next one
+    }
+    HashMap::new()
+}
+
+/// Reads `whatis` output. Lines look like `git (1)             - the stupid content
+/// tracker`, or `name, alias (section) - ...` on some systems; the first line for
+/// each name wins.
+#[cfg(unix)]
+fn parse_whatis(output: &str) -> HashMap<String, String> {
+    let mut summaries = HashMap::new();
+    for line in output.lines() {
+        let Some((names, description)) = line.split_once(" - ") else {
+            continue;
+        };
+        for name in names.split('(').next().unwrap_or("").split(',') {
+            summaries
+                .entry(name.trim().to_string())
+                .or_insert_with(|| description.trim().to_string());
+        }
+    }
+    summaries
+}
+
+/// There's no whatis database here, so descriptions come from `--help` alone.
+#[cfg(not(unix))]
+async fn whatis(_names: &[&str]) -> HashMap<String, String> {
+    HashMap::new()
+}
+
+/// The first non-blank line `program --help` prints, on stdout or else stderr.
+async fn help_flag_summary(program: &std::path::Path) -> Option<String> {
+    let child = tokio::process::Command::new(program)
+        .arg("--help")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .ok()?;
+    let output = tokio::time::timeout(HELP_FLAG_TIMEOUT, child.wait_with_output()).await.ok()?.ok()?;
+    let first_line = |bytes: &[u8]| {
+        String::from_utf8_lossy(bytes)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(String::from)
+    };
+    first_line(&output.stdout).or_else(|| first_line(&output.stderr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_whatis() {
+        let summaries = parse_whatis(
+            "git (1)              - the stupid content tracker\n\
+             git (7)              - ignored, the first line wins\n\
+             gzip, gunzip (1)     - compress or expand files\n\
+             nothing appropriate.\n",
+        );
+        assert_eq!(summaries["git"], "the stupid content tracker");
+        assert_eq!(summaries["gunzip"], "compress or expand files");
+        assert_eq!(summaries.len(), 3);
     }
-}
\ No newline at end of file
+}
@@ -1,9 +1,11 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::history::{History, HistoryFormat};
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
 use anyhow::Result;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
+use std::collections::VecDeque;
+use std::path::Path;
 
 pub struct HistoryCommand;
 
@@ -12,23 +14,44 @@ impl BuiltinCommand for HistoryCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        _current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
-        // TODO: Integrate with actual history system
-        if command.args.is_empty() {
-            println!("history: command not yet fully implemented");
-        } else {
-            match command.args[0].as_str() {
-                "-c" => {
-                    println!("history: cleared");
-                }
-                _ => {
-                    println!("history: unknown option: {}", command.args[0]);
+        let history = &mut *ctx.history;
+        let mut args = command.args.iter();
+
+        match args.next().map(String::as_str) {
+            None => print_entries(history.get_entries(), usize::MAX),
+            Some("-c") => history.clear()?,
+            Some("-d") => {
+                let Some(arg) = args.next() else {
+                    eprintln!("history: -d: option requires an argument");
+                    return Ok(2);
+                };
+                let deleted = match arg.parse::<usize>() {
+                    Ok(number) => history.delete(number)?,
+                    Err(_) => false,
+                };
+                if !deleted {
+                    eprintln!("history: {}: history position out of range", arg);
                     return Ok(1);
                 }
             }
+            // Commands are appended to the file as they run, so there is nothing left to add
+            Some("-a") => {}
+            Some("-w") => history.write_file()?,
+            Some("-r") => history.reload()?,
+            Some("--export" | "--import" | "--format") => {
+                return transfer(&command.args, history, ctx.current_dir);
+            }
+            Some(arg) => match arg.parse::<usize>() {
+                Ok(count) => print_entries(history.get_entries(), count),
+                Err(_) => {
+                    eprintln!("history: {}: invalid option", arg);
+                    eprintln!("history: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            },
         }
 
         Ok(0)
@@ -43,6 +66,75 @@ impl BuiltinCommand for HistoryCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "history [-c]\n  -c  Clear history"
+        "history [N | -c | -d N | -a | -w | -r]\n       history [--format FMT] --export [FMT] [FILE]\n       history [--format FMT] --import [FMT] FILE\n  N     Show the last N entries\n  -c    Clear history\n  -d N  Delete entry N\n  -a    Append new entries to the history file (already done as commands run)\n  -w    Write the whole history to the history file\n  -r    Reload history from the history file\n  --export  Write the history to FILE, or print it\n  --import  Add the entries in FILE to the history\n  FMT is text (one entry per line, the default), json or csv"
+    }
+}
+
+/// Prints the last `count` entries, numbered from the start of the history.
+fn print_entries(entries: &VecDeque<String>, count: usize) {
+    let skip = entries.len().saturating_sub(count);
+    for (index, entry) in entries.iter().enumerate().skip(skip) {
+        println!("{:>5}  {}", index + 1, entry);
+    }
+}
+
+/// `history --export` and `--import`, with the format given after either flag or
+/// with `--format`. Exports go to stdout without a file.
+fn transfer(args: &[String], history: &mut History, current_dir: &Path) -> Result<i32> {
+    let mut format = HistoryFormat::Text;
+    let mut import = false;
+    let mut file = None;
+    let mut args = args.iter().peekable();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let Some(name) = args.next() else {
+                    eprintln!("history: --format: option requires an argument");
+                    return Ok(2);
+                };
+                let Some(parsed) = HistoryFormat::parse(name) else {
+                    eprintln!("history: {}: unknown format (expected text, json or csv)", name);
+                    return Ok(2);
+                };
+                format = parsed;
+            }
+            "--export" | "--import" => {
+                import = arg == "--import";
+                if let Some(parsed) = args.next_if(|name| HistoryFormat::parse(name).is_some()) {
+                    format = HistoryFormat::parse(parsed).unwrap_or(format);
+                }
+            }
+            _ if file.is_none() && !arg.starts_with('-') => file = Some(current_dir.join(arg)),
+            _ => {
+                eprintln!("history: {}: invalid option", arg);
+                return Ok(2);
+            }
+        }
+    }
+
+    if import {
+        let Some(path) = file else {
+            eprintln!("history: --import: a file is required");
+            return Ok(2);
+        };
+        let imported = std::fs::File::open(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut reader| history.import(format, &mut reader));
+        if let Err(e) = imported {
+            eprintln!("history: {}: {}", path.display(), e);
+            return Ok(1);
+        }
+    } else if let Some(path) = file {
+        let exported = std::fs::File::create(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut writer| history.export(format, &mut writer));
+        if let Err(e) = exported {
+            eprintln!("history: {}: {}", path.display(), e);
+            return Ok(1);
+        }
+    } else {
+        history.export(format, &mut std::io::stdout().lock())?;
     }
-}
\ No newline at end of file
+    Ok(0)
+}
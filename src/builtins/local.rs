@@ -0,0 +1,71 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::{self, ParsedCommand};
+use crate::core::ShellContext;
+use crate::utils::quote::shell_quote;
+use anyhow::Result;
+
+pub struct LocalCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for LocalCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let Some(scope) = ctx.state.local_scopes.last() else {
+            eprintln!("local: can only be used in a function");
+            return Ok(1);
+        };
+
+        if command.args.is_empty() {
+            let mut names: Vec<&String> = scope.keys().collect();
+            names.sort();
+            for name in names {
+                if let Ok(value) = std::env::var(name) {
+                    println!("{}={}", name, shell_quote(&value));
+                }
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+        for arg in &command.args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            if !parser::is_name(name) {
+                eprintln!("local: `{}': not a valid identifier", arg);
+                status = 1;
+                continue;
+            }
+            if ctx.is_readonly(name) {
+                eprintln!("local: {}: readonly variable", name);
+                status = 1;
+                continue;
+            }
+            ctx.state.declare_local(name);
+            // A bare name starts out unset rather than inheriting the caller's value
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn description(&self) -> &'static str {
+        "Declare variables local to a function"
+    }
+
+    fn usage(&self) -> &'static str {
+        "local [name[=value] ...]\n  Sets each name for the rest of the running function and the functions it\n  calls; the previous value comes back when the function returns"
+    }
+}
@@ -1,11 +1,14 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::hyperlink::{self, make_file_link};
+use crate::utils::ls_colors::{LsColorKey, LsColors};
 use anyhow::Result;
 use colored::*;
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
-use tokio::process::Child;
 
 pub struct LsCommand;
 
@@ -14,45 +17,43 @@ impl BuiltinCommand for LsCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
-        let mut long_format = false;
-        let mut show_hidden = false;
-        let mut human_readable = false;
+        let mut opts = LsOptions {
+            colors: LsColors::from_env(),
+            hyperlinks: hyperlink::links_enabled(&ctx.state.capabilities),
+            ..Default::default()
+        };
         let mut paths = Vec::new();
 
         for arg in &command.args {
-            match arg.as_str() {
-                "-l" => long_format = true,
-                "-a" => show_hidden = true,
-                "-h" => human_readable = true,
-                "-la" | "-al" => {
-                    long_format = true;
-                    show_hidden = true;
-                }
-                "-lh" | "-hl" => {
-                    long_format = true;
-                    human_readable = true;
-                }
-                "-lah" | "-alh" | "-hal" | "-hla" | "-ahl" | "-lha" => {
-                    long_format = true;
-                    show_hidden = true;
-                    human_readable = true;
-                }
-                path if !path.starts_with('-') => {
-                    paths.push(PathBuf::from(path));
-                }
-                _ => {
-                    eprintln!("ls: unknown option: {}", arg);
-                    return Ok(1);
+            if arg.starts_with('-') && arg.len() > 1 {
+                for flag in arg[1..].chars() {
+                    match flag {
+                        'l' => opts.long_format = true,
+                        'a' => opts.show_hidden = true,
+                        'h' => opts.human_readable = true,
+                        'R' => opts.recursive = true,
+                        't' => opts.sort_mode = SortMode::Time,
+                        'S' => opts.sort_mode = SortMode::Size,
+                        'v' => opts.sort_mode = SortMode::Version,
+                        'r' => opts.reverse = true,
+                        'F' => opts.classify = true,
+                        'i' => opts.show_inode = true,
+                        _ => {
+                            eprintln!("ls: unknown option: -{}", flag);
+                            return Ok(1);
+                        }
+                    }
                 }
+            } else {
+                paths.push(PathBuf::from(arg));
             }
         }
 
         if paths.is_empty() {
-            paths.push(current_dir.clone());
+            paths.push(ctx.current_dir.clone());
         }
 
         for (i, path) in paths.iter().enumerate() {
@@ -63,14 +64,20 @@ impl BuiltinCommand for LsCommand {
             let absolute_path = if path.is_absolute() {
                 path.clone()
             } else {
-                current_dir.join(path)
+                ctx.current_dir.join(path)
             };
 
-            if paths.len() > 1 {
-                println!("{}:", absolute_path.display());
-            }
+            let result = if opts.recursive {
+                let mut visited = HashSet::new();
+                list_directory_recursive(&absolute_path, &opts, 0, opts.max_depth.unwrap_or(u32::MAX), &mut visited)
+            } else {
+                if paths.len() > 1 {
+                    println!("{}:", absolute_path.display());
+                }
+                list_directory(&absolute_path, &opts).map(|_| ())
+            };
 
-            if let Err(e) = list_directory(&absolute_path, long_format, show_hidden, human_readable) {
+            if let Err(e) = result {
                 eprintln!("ls: {}: {}", absolute_path.display(), e);
                 return Ok(1);
             }
@@ -88,11 +95,95 @@ impl BuiltinCommand for LsCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "ls [options] [path...]\n  -l  Use long listing format\n  -a  Show hidden files\n  -h  Human readable sizes"
+        "ls [options] [path...]\n  -l  Use long listing format\n  -a  Show hidden files\n  -h  Human readable sizes\n  -R  List subdirectories recursively\n  -t  Sort by modification time, newest first\n  -S  Sort by file size, largest first\n  -v  Natural sort of version numbers within names\n  -r  Reverse the sort order\n  -F  Append an indicator (one of /*@|=) to entries\n  -i  Print the inode number of each file"
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LsOptions {
+    pub long_format: bool,
+    pub show_hidden: bool,
+    pub human_readable: bool,
+    pub recursive: bool,
+    pub sort_mode: SortMode,
+    pub reverse: bool,
+    pub classify: bool,
+    pub show_inode: bool,
+    /// Colors from `LS_COLORS`/`LSCOLORS`; built-in colors are used when unset.
+    pub colors: Option<LsColors>,
+    /// Deepest level `-R` descends to; `None` means unlimited.
+    pub max_depth: Option<u32>,
+    /// Make names OSC 8 hyperlinks to their files
+    pub hyperlinks: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortMode {
+    #[default]
+    Name,
+    Time,
+    Size,
+    Version,
+}
+
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+/// Identifies a directory so recursion can detect loops (device + inode on Unix).
+fn dir_id(path: &Path) -> Option<DirId> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.canonicalize().ok()
+    }
+}
+
+fn list_directory_recursive(
+    path: &Path,
+    opts: &LsOptions,
+    depth: u32,
+    max_depth: u32,
+    visited: &mut HashSet<DirId>,
+) -> Result<()> {
+    if let Some(id) = dir_id(path) {
+        if !visited.insert(id) {
+            eprintln!("ls: {}: not listing already-listed directory", path.display());
+            return Ok(());
+        }
+    }
+
+    if depth > 0 {
+        println!();
+    }
+    println!("{}:", path.display());
+    let entries = list_directory(path, opts)?;
+
+    if depth >= max_depth {
+        return Ok(());
+    }
+
+    for entry in entries {
+        // Don't follow symlinked directories, like GNU ls
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if !is_dir {
+            continue;
+        }
+        if let Err(e) = list_directory_recursive(&entry.path(), opts, depth + 1, max_depth, visited) {
+            eprintln!("ls: {}: {}", entry.path().display(), e);
+        }
     }
+
+    Ok(())
 }
 
-fn list_directory(path: &Path, long_format: bool, show_hidden: bool, human_readable: bool) -> Result<()> {
+fn list_directory(path: &Path, opts: &LsOptions) -> Result<Vec<fs::DirEntry>> {
     let mut entries = Vec::new();
 
     let dir_iter = match fs::read_dir(path) {
@@ -104,7 +195,7 @@ fn list_directory(path: &Path, long_format: bool, show_hidden: bool, human_reada
         match entry_result {
             Ok(entry) => {
                 let file_name = entry.file_name().to_string_lossy().to_string();
-                if !show_hidden && file_name.starts_with('.') {
+                if !opts.show_hidden && file_name.starts_with('.') {
                     continue;
                 }
                 entries.push(entry);
@@ -116,23 +207,34 @@ fn list_directory(path: &Path, long_format: bool, show_hidden: bool, human_reada
         }
     }
 
-    entries.sort_by(|a, b| {
-        let a_name = a.file_name();
-        let b_name = b.file_name();
-        a_name.cmp(&b_name)
-    });
+    sort_entries(&mut entries, opts.sort_mode, opts.reverse);
 
-    if long_format {
-        print_long_format_aligned(&entries, human_readable)?;
+    if opts.long_format {
+        print_long_format_aligned(&entries, opts)?;
     } else {
         let names: Vec<String> = entries
             .iter()
             .map(|entry| {
                 let name = entry.file_name().to_string_lossy().to_string();
-                if entry.path().is_dir() {
-                    name.bright_blue().to_string()
-                } else {
-                    name
+                let path = entry.path();
+                match entry.file_type() {
+                    Ok(file_type) => {
+                        let mut colored_name = colorize(&name, &file_type, &path, opts);
+                        if opts.hyperlinks {
+                            colored_name = make_file_link(&path, &colored_name);
+                        }
+                        if opts.classify {
+                            if let Some(indicator) = type_indicator(&file_type, &path) {
+                                colored_name.push(indicator);
+                            }
+                        }
+                        if opts.show_inode {
+                            let inode = entry.metadata().map(|m| inode_number(&m)).unwrap_or(0);
+                            colored_name = format!("{} {}", inode, colored_name);
+                        }
+                        colored_name
+                    }
+                    Err(_) => name,
                 }
             })
             .collect();
@@ -148,10 +250,199 @@ fn list_directory(path: &Path, long_format: bool, show_hidden: bool, human_reada
         }
     }
 
-    Ok(())
+    Ok(entries)
 }
 
-fn print_long_format_aligned(entries: &[fs::DirEntry], human_readable: bool) -> Result<()> {
+fn sort_entries(entries: &mut [fs::DirEntry], mode: SortMode, reverse: bool) {
+    match mode {
+        SortMode::Name => entries.sort_by_key(|e| e.file_name()),
+        SortMode::Version => entries.sort_by(|a, b| {
+            natural_cmp(&a.file_name().to_string_lossy(), &b.file_name().to_string_lossy())
+        }),
+        SortMode::Time => entries.sort_by_cached_key(|e| {
+            let modified = e.metadata().and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
+            (std::cmp::Reverse(modified), e.file_name())
+        }),
+        SortMode::Size => entries.sort_by_cached_key(|e| {
+            let len = e.metadata().map(|m| m.len()).unwrap_or(0);
+            (std::cmp::Reverse(len), e.file_name())
+        }),
+    }
+
+    if reverse {
+        entries.reverse();
+    }
+}
+
+/// Compares names so that embedded numbers order numerically (`file9` < `file10`).
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let mut x_num = String::new();
+                while let Some(c) = a_chars.peek().copied().filter(|c| c.is_ascii_digit()) {
+                    x_num.push(c);
+                    a_chars.next();
+                }
+                let mut y_num = String::new();
+                while let Some(c) = b_chars.peek().copied().filter(|c| c.is_ascii_digit()) {
+                    y_num.push(c);
+                    b_chars.next();
+                }
+                let x_trim = x_num.trim_start_matches('0');
+                let y_trim = y_num.trim_start_matches('0');
+                let ordering = x_trim.len().cmp(&y_trim.len()).then_with(|| x_trim.cmp(y_trim));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+/// Colors a file name using `LS_COLORS` when available, otherwise the built-in
+/// scheme (directories blue, executables green, symlinks cyan, dangling links red).
+fn colorize(name: &str, file_type: &fs::FileType, path: &Path, opts: &LsOptions) -> String {
+    // A dangling link has no target metadata
+    let orphan = file_type.is_symlink() && fs::metadata(path).is_err();
+    let executable = file_type.is_file() && is_executable(path);
+
+    if let Some(colors) = &opts.colors {
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            return name.to_string();
+        }
+
+        let extension = if file_type.is_file() { colors.for_extension(name) } else { None };
+        let key = if orphan {
+            LsColorKey::Orphan
+        } else if file_type.is_symlink() {
+            LsColorKey::Symlink
+        } else if file_type.is_dir() {
+            LsColorKey::Directory
+        } else if executable {
+            LsColorKey::Executable
+        } else {
+            special_file_key(file_type).unwrap_or(LsColorKey::File)
+        };
+        // `or` falls back to the symlink color, as in GNU ls
+        let code = extension
+            .or_else(|| colors.get(&key))
+            .or_else(|| if orphan { colors.get(&LsColorKey::Symlink) } else { None });
+
+        return match code {
+            Some(code) => LsColors::paint(code, name),
+            None => name.to_string(),
+        };
+    }
+
+    if orphan {
+        name.bright_red().to_string()
+    } else if file_type.is_symlink() {
+        name.bright_cyan().to_string()
+    } else if file_type.is_dir() {
+        name.bright_blue().to_string()
+    } else if executable {
+        name.bright_green().to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn special_file_key(file_type: &fs::FileType) -> Option<LsColorKey> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return Some(LsColorKey::Fifo);
+        }
+        if file_type.is_socket() {
+            return Some(LsColorKey::Socket);
+        }
+        if file_type.is_block_device() {
+            return Some(LsColorKey::BlockDevice);
+        }
+        if file_type.is_char_device() {
+            return Some(LsColorKey::CharDevice);
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = file_type;
+
+    None
+}
+
+/// The `-F` suffix for an entry, based on its (unfollowed) file type.
+fn type_indicator(file_type: &fs::FileType, path: &Path) -> Option<char> {
+    if file_type.is_symlink() {
+        return Some('@');
+    }
+    if file_type.is_dir() {
+        return Some('/');
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return Some('|');
+        }
+        if file_type.is_socket() {
+            return Some('=');
+        }
+    }
+
+    if file_type.is_file() && is_executable(path) {
+        return Some('*');
+    }
+    None
+}
+
+/// The leading character of the long-format mode string (`d`, `l`, `-`, ...).
+fn type_char(file_type: &fs::FileType) -> char {
+    if file_type.is_symlink() {
+        return 'l';
+    }
+    if file_type.is_dir() {
+        return 'd';
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return 'p';
+        }
+        if file_type.is_socket() {
+            return 's';
+        }
+        if file_type.is_char_device() {
+            return 'c';
+        }
+        if file_type.is_block_device() {
+            return 'b';
+        }
+    }
+
+    '-'
+}
+
+fn print_long_format_aligned(entries: &[fs::DirEntry], opts: &LsOptions) -> Result<()> {
     if entries.is_empty() {
         return Ok(());
     }
@@ -159,6 +450,7 @@ fn print_long_format_aligned(entries: &[fs::DirEntry], human_readable: bool) ->
     let mut formatted_entries = Vec::new();
     let mut max_size_width = 0;
     let mut max_user_width = 0;
+    let mut max_inode_width = 0;
 
     for entry in entries {
         let metadata = match entry.metadata() {
@@ -170,13 +462,7 @@ fn print_long_format_aligned(entries: &[fs::DirEntry], human_readable: bool) ->
         };
         let file_name = entry.file_name().to_string_lossy().to_string();
 
-        let file_type = if metadata.is_dir() {
-            'd'
-        } else if metadata.is_file() {
-            '-'
-        } else {
-            'l'
-        };
+        let file_type = type_char(&metadata.file_type());
 
         #[cfg(unix)]
         let permissions = {
@@ -207,7 +493,7 @@ fn print_long_format_aligned(entries: &[fs::DirEntry], human_readable: bool) ->
             )
         };
 
-        let size_str = if human_readable {
+        let size_str = if opts.human_readable {
             format_human_readable(metadata.len())
         } else {
             metadata.len().to_string()
@@ -223,19 +509,34 @@ fn print_long_format_aligned(entries: &[fs::DirEntry], human_readable: bool) ->
         let datetime: chrono::DateTime<chrono::Local> = modified.into();
         let time_str = datetime.format("%b %d %H:%M").to_string();
 
-        let colored_name = if metadata.is_dir() {
-            file_name.bright_blue().to_string()
-        } else if is_executable(&entry.path()) {
-            file_name.bright_green().to_string()
-        } else {
-            file_name.clone()
-        };
+        let path = entry.path();
+        let mut colored_name = colorize(&file_name, &metadata.file_type(), &path, opts);
+        if opts.hyperlinks {
+            colored_name = make_file_link(&path, &colored_name);
+        }
+        let mut name_len = file_name.len();
+
+        if metadata.file_type().is_symlink() {
+            if let Ok(target) = fs::read_link(&path) {
+                let arrow = format!(" -> {}", target.display());
+                name_len += arrow.len();
+                colored_name.push_str(&arrow);
+            }
+        } else if opts.classify {
+            if let Some(indicator) = type_indicator(&metadata.file_type(), &path) {
+                colored_name.push(indicator);
+                name_len += 1;
+            }
+        }
 
         let user = get_user_name(&metadata);
         max_size_width = max_size_width.max(size_str.len());
         max_user_width = max_user_width.max(user.len());
+        let inode = inode_number(&metadata);
+        max_inode_width = max_inode_width.max(inode.to_string().len());
 
         formatted_entries.push((
+            inode,
             file_type,
             permissions,
             1, // link count (simplified)
@@ -243,13 +544,19 @@ fn print_long_format_aligned(entries: &[fs::DirEntry], human_readable: bool) ->
             size_str,
             time_str,
             colored_name,
-            file_name.len(), // uncolored length for wrapping logic
+            name_len, // uncolored length for wrapping logic
         ));
     }
 
-    for (file_type, permissions, links, user, size, time, colored_name, name_len) in formatted_entries {
+    for (inode, file_type, permissions, links, user, size, time, colored_name, name_len) in formatted_entries {
+        let inode_column = if opts.show_inode {
+            format!("{:>width$} ", inode, width = max_inode_width)
+        } else {
+            String::new()
+        };
         let prefix = format!(
-            "{}{} {:>3} {:width1$} {:>width2$} {} ",
+            "{}{}{} {:>3} {:width1$} {:>width2$} {} ",
+            inode_column,
             file_type,
             permissions,
             links,
@@ -271,6 +578,21 @@ fn print_long_format_aligned(entries: &[fs::DirEntry], human_readable: bool) ->
     Ok(())
 }
 
+fn inode_number(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.ino()
+    }
+
+    // Windows has no inodes
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
 fn get_user_name(_metadata: &fs::Metadata) -> String {
     std::env::var("USERNAME").or_else(|_| std::env::var("USER")).unwrap_or_else(|_| "user".to_string())
 }
@@ -311,4 +633,35 @@ fn is_executable(path: &Path) -> bool {
     }
 
     false
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn sorted_names(dir: &Path, mode: SortMode, reverse: bool) -> Vec<String> {
+        let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir).unwrap().map(|e| e.unwrap()).collect();
+        sort_entries(&mut entries, mode, reverse);
+        entries.iter().map(|e| e.file_name().to_string_lossy().to_string()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_time() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [("old", 300), ("newest", 0), ("middle", 100)] {
+            let file = fs::File::create(temp_dir.path().join(name)).unwrap();
+            file.set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+
+        assert_eq!(sorted_names(temp_dir.path(), SortMode::Time, false), vec!["newest", "middle", "old"]);
+        assert_eq!(sorted_names(temp_dir.path(), SortMode::Time, true), vec!["old", "middle", "newest"]);
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec!["file10", "file9", "file1", "file02"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, vec!["file1", "file02", "file9", "file10"]);
+    }
+}
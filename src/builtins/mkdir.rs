@@ -0,0 +1,121 @@
+use super::BuiltinCommand;
+use crate::core::errors::io_error_message;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+use std::path::Path;
+
+pub struct MkdirCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for MkdirCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut parents = false;
+        let mut verbose = false;
+        let mut mode = None;
+        let mut args = command.args.iter().peekable();
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            if arg == "--" {
+                break;
+            }
+            let mut flags = arg[1..].chars();
+            while let Some(flag) = flags.next() {
+                match flag {
+                    'p' => parents = true,
+                    'v' => verbose = true,
+                    'm' => {
+                        // The mode is the rest of this word (`-m755`) or the next one
+                        let rest: String = flags.by_ref().collect();
+                        let value = if rest.is_empty() { args.next().cloned() } else { Some(rest) };
+                        let Some(value) = value else {
+                            eprintln!("mkdir: option requires an argument -- 'm'");
+                            return Ok(1);
+                        };
+                        match u32::from_str_radix(&value, 8) {
+                            Ok(bits) if bits <= 0o7777 => mode = Some(bits),
+                            _ => {
+                                eprintln!("mkdir: invalid mode '{}'", value);
+                                return Ok(1);
+                            }
+                        }
+                    }
+                    _ => {
+                        eprintln!("mkdir: invalid option -- '{}'", flag);
+                        eprintln!("mkdir: usage: {}", self.usage());
+                        return Ok(1);
+                    }
+                }
+            }
+        }
+
+        let directories: Vec<&String> = args.collect();
+        if directories.is_empty() {
+            eprintln!("mkdir: missing operand");
+            return Ok(1);
+        }
+
+        let mut status = 0;
+        for directory in directories {
+            let path = ctx.current_dir.join(directory);
+            // With -p, the directories that will be created, outermost first
+            let created: Vec<&Path> = if parents {
+                let mut missing: Vec<&Path> = path.ancestors().take_while(|p| !p.exists()).collect();
+                missing.reverse();
+                missing
+            } else {
+                vec![path.as_path()]
+            };
+
+            if let Err(e) = create_directory(&path, parents, mode) {
+                eprintln!("mkdir: cannot create directory '{}': {}", directory, io_error_message(&e));
+                status = 1;
+                // Without -p the remaining directories are left alone
+                if parents {
+                    continue;
+                }
+                break;
+            }
+            if verbose {
+                for created in created {
+                    let name = created.strip_prefix(ctx.current_dir.as_path()).unwrap_or(created);
+                    println!("mkdir: created directory '{}'", name.display());
+                }
+            }
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "mkdir"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create directories"
+    }
+
+    fn usage(&self) -> &'static str {
+        "mkdir [-pv] [-m mode] directory ...\n  -p       Create missing parent directories; no error if the directory exists\n  -v       Print each directory as it is created\n  -m mode  Set the permissions of new directories (octal, Unix only)"
+    }
+}
+
+fn create_directory(path: &Path, parents: bool, mode: Option<u32>) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(parents);
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(mode);
+        builder.create(path)?;
+        // The builder's mode is reduced by the umask; -m sets it exactly
+        return std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode));
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    builder.create(path)
+}
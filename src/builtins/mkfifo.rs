@@ -0,0 +1,174 @@
+use super::BuiltinCommand;
+use crate::core::errors::io_error_message;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+use std::io;
+use std::path::Path;
+
+pub struct MkfifoCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for MkfifoCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut strict = false;
+        let mut keep_going = false;
+        let mut mode = None;
+        let mut args = command.args.iter().peekable();
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            if arg == "--" {
+                break;
+            }
+            let mut flags = arg[1..].chars();
+            while let Some(flag) = flags.next() {
+                match flag {
+                    'e' => strict = true,
+                    'k' => keep_going = true,
+                    'm' => {
+                        // The mode is the rest of this word (`-m600`) or the next one
+                        let rest: String = flags.by_ref().collect();
+                        let value = if rest.is_empty() { args.next().cloned() } else { Some(rest) };
+                        let Some(value) = value else {
+                            eprintln!("mkfifo: option requires an argument -- 'm'");
+                            return Ok(1);
+                        };
+                        match u32::from_str_radix(&value, 8) {
+                            Ok(bits) if bits <= 0o7777 => mode = Some(bits),
+                            _ => {
+                                eprintln!("mkfifo: invalid mode '{}'", value);
+                                return Ok(1);
+                            }
+                        }
+                    }
+                    _ => {
+                        eprintln!("mkfifo: invalid option -- '{}'", flag);
+                        eprintln!("mkfifo: usage: {}", self.usage());
+                        return Ok(1);
+                    }
+                }
+            }
+        }
+
+        let paths: Vec<&String> = args.collect();
+        if paths.is_empty() {
+            eprintln!("mkfifo: missing operand");
+            return Ok(1);
+        }
+
+        let mut status = 0;
+        for path in paths {
+            let message = match create_fifo(&ctx.current_dir.join(path), mode) {
+                Ok(()) => continue,
+                // A FIFO that's already there is what was asked for, unless -e says otherwise
+                Err(FifoError::Exists) if !strict => continue,
+                Err(FifoError::Exists) => "File exists".to_string(),
+                Err(FifoError::Io(e)) => io_error_message(&e),
+            };
+            eprintln!("mkfifo: cannot create fifo '{}': {}", path, message);
+            status = 1;
+            // Without -k the remaining paths are left alone
+            if !keep_going {
+                break;
+            }
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "mkfifo"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create named pipes (FIFOs)"
+    }
+
+    fn usage(&self) -> &'static str {
+        "mkfifo [-ek] [-m mode] path ...\n  -e       Fail if a path is already a FIFO, rather than leaving it be\n  -k       Keep going with the other paths after one fails\n  -m mode  Set the permissions of new FIFOs (octal; default 666 less the umask)\n  On Windows, creates the named pipe \\\\.\\pipe\\NAME, where NAME is the full path with\n  \\, / and : replaced by _; it lasts until the shell exits"
+    }
+}
+
+/// Why a FIFO wasn't created.
+enum FifoError {
+    /// The path is already a FIFO
+    Exists,
+    /// Any other failure, such as a path that exists as something else
+    Io(io::Error),
+}
+
+impl From<io::Error> for FifoError {
+    fn from(e: io::Error) -> Self {
+        FifoError::Io(e)
+    }
+}
+
+#[cfg(unix)]
+fn create_fifo(path: &Path, mode: Option<u32>) -> Result<(), FifoError> {
+    use nix::sys::stat::Mode;
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo()) {
+        return Err(FifoError::Exists);
+    }
+    // mkfifo reduces the mode by the umask; -m sets it exactly
+    nix::unistd::mkfifo(path, Mode::from_bits_truncate(0o666)).map_err(io::Error::from)?;
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+/// Windows named pipes live in their own namespace and only while a handle to
+/// them is open, so the shell keeps each one it creates open until it exits.
+#[cfg(windows)]
+fn create_fifo(path: &Path, mode: Option<u32>) -> Result<(), FifoError> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{FromRawHandle, OwnedHandle};
+    use std::sync::Mutex;
+    use winapi::shared::winerror::ERROR_ACCESS_DENIED;
+    use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+    use winapi::um::winbase::{
+        CreateNamedPipeW, FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    static PIPES: Mutex<Vec<OwnedHandle>> = Mutex::new(Vec::new());
+
+    let _ = mode;
+    let name: String = path.to_string_lossy().chars().map(|c| if matches!(c, '\\' | '/' | ':') { '_' } else { c }).collect();
+    let pipe_name: Vec<u16> = std::ffi::OsStr::new(&format!(r"\\.\pipe\{}", name))
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    // SAFETY: the name is NUL-terminated and the other arguments are plain values
+    let handle = unsafe {
+        CreateNamedPipeW(
+            pipe_name.as_ptr(),
+            PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            PIPE_TYPE_BYTE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            4096,
+            4096,
+            0,
+            std::ptr::null_mut(),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        let error = io::Error::last_os_error();
+        // FILE_FLAG_FIRST_PIPE_INSTANCE refuses a name that's already a pipe
+        return Err(if error.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+            FifoError::Exists
+        } else {
+            error.into()
+        });
+    }
+    // SAFETY: CreateNamedPipeW just returned this handle, which nothing else owns
+    let handle = unsafe { OwnedHandle::from_raw_handle(handle as _) };
+    PIPES.lock().unwrap_or_else(|e| e.into_inner()).push(handle);
+    Ok(())
+}
@@ -1,8 +1,7 @@
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
 use anyhow::Result;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
 
 pub mod cd;
 pub mod echo;
@@ -15,15 +14,42 @@ pub mod alias;
 pub mod env;
 pub mod which;
 pub mod clear;
+pub mod config;
+pub mod set;
+pub mod setopt;
+pub mod shift;
+pub mod return_cmd;
+pub mod break_cmd;
+pub mod continue_cmd;
+pub mod hash;
+pub mod time;
+pub mod basename;
+pub mod dirname;
+pub mod date;
+pub mod mkdir;
+pub mod mkfifo;
+pub mod touch;
+pub mod export;
+pub mod local;
+pub mod readonly;
+pub mod unset;
+pub mod read;
+pub mod coproc;
+pub mod tee;
+pub mod xargs;
+pub mod compdef;
+pub mod compgen;
+pub mod complete;
+pub mod color;
+pub mod terminal;
 
 #[async_trait::async_trait]
 pub trait BuiltinCommand: Send + Sync {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        current_dir: &mut PathBuf,
-        background_processes: &mut HashMap<u32, Child>,
-        parser: &mut crate::core::parser::Parser,
+        executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32>;
 
     fn name(&self) -> &'static str;
@@ -44,12 +70,44 @@ pub fn get_builtin(name: &str) -> Option<Box<dyn BuiltinCommand>> {
         "env" => Some(Box::new(env::EnvCommand)),
         "which" => Some(Box::new(which::WhichCommand)),
         "clear" => Some(Box::new(clear::ClearCommand)),
+        "config" => Some(Box::new(config::ConfigCommand)),
+        "set" => Some(Box::new(set::SetCommand)),
+        "setopt" => Some(Box::new(setopt::SetoptCommand)),
+        "unsetopt" => Some(Box::new(setopt::UnsetoptCommand)),
+        "shift" => Some(Box::new(shift::ShiftCommand)),
+        "return" => Some(Box::new(return_cmd::ReturnCommand)),
+        "break" => Some(Box::new(break_cmd::BreakCommand)),
+        "continue" => Some(Box::new(continue_cmd::ContinueCommand)),
+        "hash" => Some(Box::new(hash::HashCommand)),
+        "time" => Some(Box::new(time::TimeCommand)),
+        "basename" => Some(Box::new(basename::BasenameCommand)),
+        "dirname" => Some(Box::new(dirname::DirnameCommand)),
+        "date" => Some(Box::new(date::DateCommand)),
+        "mkdir" => Some(Box::new(mkdir::MkdirCommand)),
+        "mkfifo" => Some(Box::new(mkfifo::MkfifoCommand)),
+        "touch" => Some(Box::new(touch::TouchCommand)),
+        "export" => Some(Box::new(export::ExportCommand)),
+        "local" => Some(Box::new(local::LocalCommand)),
+        "readonly" => Some(Box::new(readonly::ReadonlyCommand)),
+        "unset" => Some(Box::new(unset::UnsetCommand)),
+        "read" => Some(Box::new(read::ReadCommand)),
+        "coproc" => Some(Box::new(coproc::CoprocCommand)),
+        "tee" => Some(Box::new(tee::TeeCommand)),
+        "xargs" => Some(Box::new(xargs::XargsCommand)),
+        "compdef" => Some(Box::new(compdef::CompdefCommand)),
+        "compgen" => Some(Box::new(compgen::CompgenCommand)),
+        "complete" => Some(Box::new(complete::CompleteCommand)),
+        "color" => Some(Box::new(color::ColorCommand)),
+        "terminal" => Some(Box::new(terminal::TerminalCommand)),
         _ => None,
     }
 }
 
 pub fn list_builtins() -> Vec<&'static str> {
     vec![
-        "cd", "echo", "exit", "help", "history", "ls", "pwd", "alias", "env", "which", "clear"
+        "cd", "echo", "exit", "help", "history", "ls", "pwd", "alias", "env", "which", "clear", "config", "set",
+        "setopt", "unsetopt", "shift", "return", "break", "continue", "hash", "time",
+        "basename", "dirname", "date", "mkdir", "mkfifo", "touch", "export", "local", "readonly", "unset",
+        "read", "coproc", "tee", "xargs", "compdef", "compgen", "complete", "color", "terminal"
     ]
 }
\ No newline at end of file
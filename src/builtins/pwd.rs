@@ -1,9 +1,9 @@
 use super::BuiltinCommand;
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::hyperlink;
 use anyhow::Result;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Child;
 
 pub struct PwdCommand;
 
@@ -11,18 +11,43 @@ pub struct PwdCommand;
 impl BuiltinCommand for PwdCommand {
     async fn execute(
         &self,
-        _command: &ParsedCommand,
-        current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
-        // Try to get the canonical path, fall back to current if failed
-        let display_dir = if let Ok(canonical) = current_dir.canonicalize() {
+        let mut physical = false;
+        let mut windows = false;
+        for arg in &command.args {
+            match arg.as_str() {
+                "-L" => physical = false,
+                "-P" => physical = true,
+                "-W" => windows = true,
+                _ => {
+                    eprintln!("pwd: {}: invalid option", arg);
+                    eprintln!("pwd: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            }
+        }
+
+        let display_dir = if !physical {
+            crate::utils::path::logical_dir(ctx.current_dir)
+        } else if let Ok(canonical) = ctx.current_dir.canonicalize() {
             crate::utils::path::strip_windows_prefix(&canonical)
         } else {
-            current_dir.clone()
+            ctx.current_dir.clone()
+        };
+        let display_dir = if windows {
+            crate::utils::path::wsl_to_windows(&display_dir).unwrap_or(display_dir)
+        } else {
+            display_dir
         };
-        println!("{}", display_dir.display());
+        let shown = display_dir.display().to_string();
+        if hyperlink::links_enabled(&ctx.state.capabilities) {
+            println!("{}", hyperlink::make_file_link(&display_dir, &shown));
+        } else {
+            println!("{}", shown);
+        }
         Ok(0)
     }
 
@@ -35,6 +60,6 @@ impl BuiltinCommand for PwdCommand {
     }
 
     fn usage(&self) -> &'static str {
-        "pwd"
+        "pwd [-L|-P] [-W]\n  -L  Print the logical directory from $PWD, keeping symlinks (default)\n  -P  Print the physical directory, with symlinks resolved\n  -W  Print a directory under WSL's /mnt/<drive> mounts as a Windows path"
     }
 }
\ No newline at end of file
@@ -0,0 +1,133 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::{self, ParsedCommand};
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct ReadCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for ReadCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut raw = false;
+        let mut args = command.args.iter().peekable();
+        while let Some(flag) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            match flag.as_str() {
+                "-r" => raw = true,
+                "--" => break,
+                _ => {
+                    eprintln!("read: {}: invalid option", flag);
+                    eprintln!("read: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            }
+        }
+        let names: Vec<&String> = args.collect();
+        if let Some(name) = names.iter().find(|name| !parser::is_name(name)) {
+            eprintln!("read: `{}': not a valid identifier", name);
+            return Ok(1);
+        }
+
+        let (line, complete) = read_line(raw)?;
+        if names.is_empty() {
+            ctx.set_var("REPLY", &line)?;
+        } else {
+            let ifs = std::env::var("IFS").unwrap_or_else(|_| " \t\n".to_string());
+            for (name, value) in names.iter().zip(split_fields(&line, &ifs, names.len())) {
+                ctx.set_var(name, &value)?;
+            }
+        }
+        // A last line without a newline still sets the variables, but fails
+        Ok(if complete { 0 } else { 1 })
+    }
+
+    fn name(&self) -> &'static str {
+        "read"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read a line of input into variables"
+    }
+
+    fn usage(&self) -> &'static str {
+        "read [-r] [name ...]\n  Splits a line of standard input on IFS into the names, the last one taking\n  the rest of the line; without names the line goes in REPLY\n  -r  Keep backslashes, rather than escaping the next character or newline"
+    }
+}
+
+/// Reads one line of standard input without the newline, and whether the newline
+/// was there. Bytes are read one at a time so nothing after the line is taken from
+/// a pipe the shell shares, such as a coprocess's output.
+fn read_line(raw: bool) -> Result<(String, bool)> {
+    let mut line = Vec::new();
+    let mut escaped = false;
+    let complete = loop {
+        let Some(byte) = read_byte()? else {
+            break false;
+        };
+        match byte {
+            b'\n' if escaped => escaped = false,
+            b'\n' => break true,
+            b'\\' if !raw && !escaped => escaped = true,
+            _ => {
+                escaped = false;
+                line.push(byte);
+            }
+        }
+    };
+    Ok((String::from_utf8_lossy(&line).into_owned(), complete))
+}
+
+#[cfg(unix)]
+fn read_byte() -> Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match nix::unistd::read(std::io::stdin(), &mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn read_byte() -> Result<Option<u8>> {
+    use std::io::Read;
+    let mut byte = [0];
+    Ok((std::io::stdin().read(&mut byte)? > 0).then_some(byte[0]))
+}
+
+/// Splits `line` into at most `count` fields on the characters of `ifs`, the last
+/// field keeping the rest of the line less surrounding separators.
+fn split_fields(line: &str, ifs: &str, count: usize) -> Vec<String> {
+    let is_separator = |c: char| ifs.contains(c);
+    let mut fields = Vec::with_capacity(count);
+    let mut rest = line.trim_matches(is_separator);
+    while fields.len() + 1 < count && !rest.is_empty() {
+        let end = rest.find(is_separator).unwrap_or(rest.len());
+        fields.push(rest[..end].to_string());
+        rest = rest[end..].trim_start_matches(is_separator);
+    }
+    fields.push(rest.to_string());
+    // Names past the fields are set to empty, as in other shells
+    fields.resize(count, String::new());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_fields() {
+        assert_eq!(split_fields("  one two  three ", " \t\n", 2), vec!["one", "two  three"]);
+        assert_eq!(split_fields("a:b:c", ":", 3), vec!["a", "b", "c"]);
+        assert_eq!(split_fields("only", " ", 3), vec!["only", "", ""]);
+        assert_eq!(split_fields("whole line", " ", 1), vec!["whole line"]);
+    }
+}
@@ -0,0 +1,78 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::{self, ParsedCommand};
+use crate::core::ShellContext;
+use crate::utils::quote::shell_quote;
+use anyhow::Result;
+
+pub struct ReadonlyCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for ReadonlyCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut args = command.args.iter().peekable();
+        while let Some(flag) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            match flag.as_str() {
+                "-p" => {}
+                "--" => break,
+                _ => {
+                    eprintln!("readonly: {}: invalid option", flag);
+                    eprintln!("readonly: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            }
+        }
+
+        if args.peek().is_none() {
+            let mut names: Vec<&String> = ctx.state.readonly_vars.iter().collect();
+            names.sort();
+            for name in names {
+                match std::env::var(name) {
+                    Ok(value) => println!("readonly {}={}", name, shell_quote(&value)),
+                    Err(_) => println!("readonly {}", name),
+                }
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            if !parser::is_name(name) {
+                eprintln!("readonly: `{}': not a valid identifier", arg);
+                status = 1;
+                continue;
+            }
+            if value.is_some() && ctx.is_readonly(name) {
+                eprintln!("readonly: {}: readonly variable", name);
+                status = 1;
+                continue;
+            }
+            if let Some(value) = value {
+                std::env::set_var(name, value);
+            }
+            ctx.state.readonly_vars.insert(name.to_string());
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "readonly"
+    }
+
+    fn description(&self) -> &'static str {
+        "Mark variables so they can't be changed or unset"
+    }
+
+    fn usage(&self) -> &'static str {
+        "readonly [-p] [name[=value] ...]\n  Sets each name to value, if given, and makes it readonly. With no names, or\n  with -p, prints every readonly variable as a readonly command"
+    }
+}
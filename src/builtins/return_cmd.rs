@@ -0,0 +1,48 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::errors::{ExitRequest, ReturnValue};
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct ReturnCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for ReturnCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let status = match command.args.first() {
+            None => ctx.state.special.last_exit,
+            Some(arg) => match arg.parse::<i32>() {
+                Ok(code) => code,
+                Err(_) => {
+                    eprintln!("return: {}: numeric argument required", arg);
+                    2
+                }
+            },
+        };
+
+        // Unwinds to the function call or sourced file, which reports this status;
+        // anywhere else it ends the shell
+        if ctx.state.function_depth == 0 && ctx.state.source_depth == 0 {
+            return Err(ExitRequest(status).into());
+        }
+        Err(ReturnValue(status).into())
+    }
+
+    fn name(&self) -> &'static str {
+        "return"
+    }
+
+    fn description(&self) -> &'static str {
+        "Return from a shell function or sourced file"
+    }
+
+    fn usage(&self) -> &'static str {
+        "return [status]\n  status  Status the function returns (default: the last command's status)\n  Outside a function it ends the file being run, or else the shell"
+    }
+}
@@ -0,0 +1,80 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::options::ShellOptions;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct SetCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for SetCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        if command.args.is_empty() {
+            print_options(ctx.options);
+            return Ok(0);
+        }
+
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            let enable = match arg.chars().next() {
+                Some('-') => true,
+                Some('+') => false,
+                _ => {
+                    eprintln!("set: {}: invalid argument", arg);
+                    eprintln!("set: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            };
+
+            let flags = &arg[1..];
+            if flags == "o" {
+                match args.next() {
+                    Some(name) => {
+                        if !ctx.options.set_named(name, enable) {
+                            eprintln!("set: {}: invalid option name", name);
+                            return Ok(2);
+                        }
+                    }
+                    None => print_options(ctx.options),
+                }
+                continue;
+            }
+
+            for flag in flags.chars() {
+                if !ctx.options.set_flag(flag, enable) {
+                    eprintln!("set: {}{}: invalid option", &arg[..1], flag);
+                    eprintln!("set: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            }
+        }
+
+        Ok(0)
+    }
+
+    fn name(&self) -> &'static str {
+        "set"
+    }
+
+    fn description(&self) -> &'static str {
+        "Set or unset shell options"
+    }
+
+    fn usage(&self) -> &'static str {
+        "set [-+abefux] [-+o option]\n  -e  Exit immediately if a command fails (errexit)\n  -x  Print commands before executing them (xtrace)\n  -f  Don't expand glob patterns (noglob)\n  -u  Treat unset variables as an error (nounset)\n  -a  Export all assigned variables (allexport)\n  -b  Report finished background jobs immediately (notify)\n  -o option  Enable an option by name (pipefail, vi, emacs, ...)\n  Using + instead of - disables the option; no arguments lists all options"
+    }
+}
+
+/// Lists every option and whether it's on, as `set -o` and `setopt` do.
+pub fn print_options(options: &ShellOptions) {
+    for name in ShellOptions::NAMES {
+        let state = if options.get_named(name).unwrap_or(false) { "on" } else { "off" };
+        println!("{:<15} {}", name, state);
+    }
+}
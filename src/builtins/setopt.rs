@@ -0,0 +1,117 @@
+use super::BuiltinCommand;
+use super::set::print_options;
+use crate::core::executor::Executor;
+use crate::core::options::ShellOptions;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+/// zsh names for options that are called something else here.
+const ZSH_NAMES: &[(&str, &str)] = &[
+    ("extendedglob", "extglob"),
+    ("appendhistory", "histappend"),
+];
+
+pub struct SetoptCommand;
+
+pub struct UnsetoptCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for SetoptCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        Ok(set_options("setopt", &command.args, true, ctx.options))
+    }
+
+    fn name(&self) -> &'static str {
+        "setopt"
+    }
+
+    fn description(&self) -> &'static str {
+        "Turn shell options on, as in zsh"
+    }
+
+    fn usage(&self) -> &'static str {
+        "setopt [option ...]\n  Turns on each option, like set -o; names ignore case and underscores,\n  and a \"no\" prefix turns the option off. No arguments lists all options"
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinCommand for UnsetoptCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        Ok(set_options("unsetopt", &command.args, false, ctx.options))
+    }
+
+    fn name(&self) -> &'static str {
+        "unsetopt"
+    }
+
+    fn description(&self) -> &'static str {
+        "Turn shell options off, as in zsh"
+    }
+
+    fn usage(&self) -> &'static str {
+        "unsetopt [option ...]\n  Turns off each option, like set +o; names ignore case and underscores,\n  and a \"no\" prefix turns the option on. No arguments lists all options"
+    }
+}
+
+/// Turns each named option on or off, reporting unknown names, and returns the status.
+fn set_options(builtin: &str, names: &[String], enable: bool, options: &mut ShellOptions) -> i32 {
+    if names.is_empty() {
+        print_options(options);
+        return 0;
+    }
+    let mut status = 0;
+    for name in names {
+        let Some((option, inverted)) = resolve(name) else {
+            eprintln!("{}: no such option: {}", builtin, name);
+            status = 1;
+            continue;
+        };
+        options.set_named(option, enable != inverted);
+    }
+    status
+}
+
+/// The option `name` refers to, as zsh spells option names (`AUTO_CD`, `extendedglob`,
+/// `noautocd`), and whether a `no` prefix inverted it.
+fn resolve(name: &str) -> Option<(&'static str, bool)> {
+    let name: String = name.chars().filter(|c| *c != '_').collect::<String>().to_lowercase();
+    let lookup = |name: &str| -> Option<&'static str> {
+        let name = ZSH_NAMES.iter().find(|(zsh, _)| *zsh == name).map_or(name, |(_, option)| option);
+        ShellOptions::NAMES.iter().copied().find(|option| *option == name)
+    };
+    // Some options, such as `noglob`, have the prefix in their own name
+    lookup(&name)
+        .map(|option| (option, false))
+        .or_else(|| lookup(name.strip_prefix("no")?).map(|option| (option, true)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("autocd"), Some(("autocd", false)));
+        assert_eq!(resolve("AUTO_CD"), Some(("autocd", false)));
+        assert_eq!(resolve("noautocd"), Some(("autocd", true)));
+        assert_eq!(resolve("extended_glob"), Some(("extglob", false)));
+        assert_eq!(resolve("noglob"), Some(("noglob", false)));
+        assert_eq!(resolve("bogus"), None);
+
+        let mut options = ShellOptions::default();
+        assert_eq!(set_options("setopt", &["autocd".to_string(), "nohistappend".to_string()], true, &mut options), 0);
+        assert!(options.auto_cd && !options.histappend);
+        assert_eq!(set_options("unsetopt", &["bogus".to_string()], false, &mut options), 1);
+    }
+}
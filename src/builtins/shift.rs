@@ -0,0 +1,48 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct ShiftCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for ShiftCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let count = match command.args.first() {
+            None => 1,
+            Some(arg) => match arg.parse::<usize>() {
+                Ok(count) => count,
+                Err(_) => {
+                    eprintln!("shift: {}: numeric argument required", arg);
+                    return Ok(1);
+                }
+            },
+        };
+
+        let positional = &mut ctx.state.positional;
+        if count > positional.len() {
+            eprintln!("shift: {}: shift count out of range", count);
+            return Ok(1);
+        }
+        positional.drain(..count);
+        Ok(0)
+    }
+
+    fn name(&self) -> &'static str {
+        "shift"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shift the positional parameters to the left"
+    }
+
+    fn usage(&self) -> &'static str {
+        "shift [n]\n  n  Number of parameters to drop from the front (default: 1); $n+1 becomes $1"
+    }
+}
@@ -0,0 +1,154 @@
+use super::BuiltinCommand;
+use crate::core::errors::io_error_message;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::signal;
+
+pub struct TeeCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for TeeCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut append = false;
+        let mut ignore_interrupts = false;
+        let mut args = command.args.iter().peekable();
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            if arg == "--" {
+                break;
+            }
+            for flag in arg[1..].chars() {
+                match flag {
+                    'a' => append = true,
+                    'i' => ignore_interrupts = true,
+                    _ => {
+                        eprintln!("tee: invalid option -- '{}'", flag);
+                        eprintln!("tee: usage: {}", self.usage());
+                        return Ok(1);
+                    }
+                }
+            }
+        }
+
+        // Files that can't be opened are reported, and the rest still written
+        let mut status = 0;
+        let mut files = Vec::new();
+        for name in args {
+            let opened = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(append)
+                .truncate(!append)
+                .open(ctx.current_dir.join(name));
+            match opened {
+                Ok(file) => files.push((name.clone(), file)),
+                Err(e) => {
+                    eprintln!("tee: {}: {}", name, io_error_message(&e));
+                    status = 1;
+                }
+            }
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut copy = tokio::task::spawn_blocking({
+            let stop = stop.clone();
+            move || copy_input(files, &stop)
+        });
+        loop {
+            tokio::select! {
+                copied = &mut copy => {
+                    return Ok(if copied?? { status } else { 1 });
+                }
+                // Waiting for Ctrl+C also keeps it from stopping the shell with -i
+                _ = signal::ctrl_c() => {
+                    if !ignore_interrupts {
+                        stop.store(true, Ordering::Relaxed);
+                        let _ = copy.await;
+                        return Ok(130);
+                    }
+                }
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "tee"
+    }
+
+    fn description(&self) -> &'static str {
+        "Copy standard input to standard output and files"
+    }
+
+    fn usage(&self) -> &'static str {
+        "tee [-ai] [file ...]\n  -a  Append to the files rather than overwriting them\n  -i  Ignore Ctrl+C, so the copy only ends with its input"
+    }
+}
+
+/// Copies standard input to standard output and `files` until the input ends or
+/// `stop` is set, flushing after each chunk. False if writing failed somewhere;
+/// a file that fails is reported and left out from then on.
+fn copy_input(mut files: Vec<(String, File)>, stop: &AtomicBool) -> io::Result<bool> {
+    let mut succeeded = true;
+    let mut stdout = io::stdout();
+    let mut buf = [0; 8192];
+    while !stop.load(Ordering::Relaxed) {
+        if !input_ready()? {
+            continue;
+        }
+        let n = match io::stdin().read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if let Err(e) = stdout.write_all(&buf[..n]).and_then(|_| stdout.flush()) {
+            // A closed pipe downstream ends the copy, as it would for an external tee
+            if e.kind() == io::ErrorKind::BrokenPipe {
+                return Ok(false);
+            }
+            eprintln!("tee: standard output: {}", io_error_message(&e));
+            succeeded = false;
+        }
+        files.retain_mut(|(name, file)| match file.write_all(&buf[..n]).and_then(|_| file.flush()) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("tee: {}: {}", name, io_error_message(&e));
+                succeeded = false;
+                false
+            }
+        });
+    }
+    Ok(succeeded)
+}
+
+/// Waits briefly for standard input to have something to read (or to end), so the
+/// copy can notice `stop` without a read blocking it.
+#[cfg(unix)]
+fn input_ready() -> io::Result<bool> {
+    use nix::poll::{poll, PollFd, PollFlags};
+    use std::os::fd::AsFd;
+
+    let stdin = io::stdin();
+    let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+    match poll(&mut fds, 100u16) {
+        Ok(ready) => Ok(ready > 0),
+        Err(nix::errno::Errno::EINTR) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Without `poll`, reads block until there's input, so Ctrl+C takes effect after it.
+#[cfg(not(unix))]
+fn input_ready() -> io::Result<bool> {
+    Ok(true)
+}
@@ -0,0 +1,40 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct TerminalCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for TerminalCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        if command.args != ["capabilities"] {
+            eprintln!("terminal: usage: {}", self.usage());
+            return Ok(2);
+        }
+        let capabilities = ctx.state.capabilities;
+        for (name, supported) in capabilities.list() {
+            println!("{:<18} {}", name, if supported { "yes" } else { "no" });
+        }
+        println!("{:<18} {}", "multiplexer", capabilities.multiplexer.name());
+        Ok(0)
+    }
+
+    fn name(&self) -> &'static str {
+        "terminal"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show what the terminal supports"
+    }
+
+    fn usage(&self) -> &'static str {
+        "terminal capabilities\n  Prints the features detected from $TERM_PROGRAM, $TERM, $COLORTERM and\n  $VTE_VERSION at startup, and the multiplexer ($TMUX, or a $TERM of\n  screen*). Without truecolor, #RRGGBB colors use the nearest of 256 colors"
+    }
+}
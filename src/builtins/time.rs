@@ -0,0 +1,160 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+/// Used when `TIMEFORMAT` is unset, matching bash.
+const DEFAULT_TIMEFORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+
+pub struct TimeCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for TimeCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let cpu_before = children_cpu_time();
+        let started = Instant::now();
+
+        let status = match command.args.split_first() {
+            Some((program, args)) => {
+                // `time a | b` times the whole pipeline
+                let mut timed = ParsedCommand::new(program.clone());
+                timed.args = args.to_vec();
+                timed.pipes = command.pipes.clone();
+                timed.input_redirect = command.input_redirect.clone();
+                timed.output_redirect = command.output_redirect.clone();
+                timed.append_redirect = command.append_redirect.clone();
+                timed.stderr_redirect = command.stderr_redirect.clone();
+                timed.stderr_append_redirect = command.stderr_append_redirect.clone();
+                executor.execute(timed, ctx).await?
+            }
+            None => 0,
+        };
+
+        let real = started.elapsed();
+        let cpu_after = children_cpu_time();
+        let times = Times {
+            real,
+            user: cpu_after.0.saturating_sub(cpu_before.0),
+            sys: cpu_after.1.saturating_sub(cpu_before.1),
+        };
+        let format = std::env::var("TIMEFORMAT").unwrap_or_else(|_| DEFAULT_TIMEFORMAT.to_string());
+        eprintln!("{}", times.format(&format));
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "time"
+    }
+
+    fn description(&self) -> &'static str {
+        "Report the time a command takes"
+    }
+
+    fn usage(&self) -> &'static str {
+        "time [command [arg ...]]\n  Runs the command, then prints the elapsed real time and the user and\n  system CPU time to stderr. $TIMEFORMAT sets the format: %R, %U and %S\n  are the three times, %P the CPU percentage; %3lR gives 3 decimals in\n  the long 0m0.000s form"
+    }
+}
+
+struct Times {
+    real: Duration,
+    user: Duration,
+    sys: Duration,
+}
+
+impl Times {
+    /// Expands a `TIMEFORMAT` string. Each `%[p][l]R`, `%[p][l]U` and `%[p][l]S`
+    /// prints a time with `p` decimals (0 to 3, default 3), in minutes and seconds
+    /// with `l`; `%P` is the CPU percentage and `%%` a literal `%`.
+    fn format(&self, format: &str) -> String {
+        let mut result = String::new();
+        let mut chars = format.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                result.push(ch);
+                continue;
+            }
+            let precision = match chars.peek().and_then(|c| c.to_digit(10)) {
+                Some(digit) => {
+                    chars.next();
+                    digit.min(3) as usize
+                }
+                None => 3,
+            };
+            let long = chars.next_if_eq(&'l').is_some();
+            match chars.next() {
+                Some('R') => result.push_str(&format_time(self.real, precision, long)),
+                Some('U') => result.push_str(&format_time(self.user, precision, long)),
+                Some('S') => result.push_str(&format_time(self.sys, precision, long)),
+                Some('P') => {
+                    let cpu = (self.user + self.sys).as_secs_f64();
+                    let percent = if self.real.is_zero() { 0.0 } else { cpu * 100.0 / self.real.as_secs_f64() };
+                    result.push_str(&format!("{:.2}", percent));
+                }
+                Some('%') => result.push('%'),
+                Some(other) => {
+                    result.push('%');
+                    result.push(other);
+                }
+                None => result.push('%'),
+            }
+        }
+        result
+    }
+}
+
+fn format_time(time: Duration, precision: usize, long: bool) -> String {
+    let seconds = time.as_secs_f64();
+    if long {
+        let minutes = (seconds / 60.0).floor();
+        format!("{}m{:.*}s", minutes, precision, seconds - minutes * 60.0)
+    } else {
+        format!("{:.*}", precision, seconds)
+    }
+}
+
+/// User and system CPU time used by the shell's finished child processes so far.
+#[cfg(unix)]
+fn children_cpu_time() -> (Duration, Duration) {
+    use nix::sys::resource::{getrusage, UsageWho};
+    use nix::sys::time::TimeValLike;
+
+    match getrusage(UsageWho::RUSAGE_CHILDREN) {
+        Ok(usage) => (
+            Duration::from_micros(usage.user_time().num_microseconds().max(0) as u64),
+            Duration::from_micros(usage.system_time().num_microseconds().max(0) as u64),
+        ),
+        Err(_) => (Duration::ZERO, Duration::ZERO),
+    }
+}
+
+/// Child CPU times aren't collected on this platform, so they report as zero.
+#[cfg(not(unix))]
+fn children_cpu_time() -> (Duration, Duration) {
+    (Duration::ZERO, Duration::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let times = Times {
+            real: Duration::from_millis(61_234),
+            user: Duration::from_millis(123),
+            sys: Duration::from_millis(1),
+        };
+        assert_eq!(times.format(DEFAULT_TIMEFORMAT), "\nreal\t1m1.234s\nuser\t0m0.123s\nsys\t0m0.001s");
+        assert_eq!(times.format("%R %1U %0lS %%"), "61.234 0.1 0m0s %");
+        assert_eq!(times.format("%P"), "0.20");
+        assert_eq!(times.format("%x%"), "%x%");
+    }
+}
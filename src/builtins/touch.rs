@@ -0,0 +1,174 @@
+use super::BuiltinCommand;
+use crate::core::errors::io_error_message;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use anyhow::Result;
+use chrono::{Datelike, Local, TimeZone};
+use std::fs::{File, FileTimes, OpenOptions};
+use std::path::Path;
+use std::time::SystemTime;
+
+pub struct TouchCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for TouchCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut access_only = false;
+        let mut modification_only = false;
+        let mut no_create = false;
+        let now = SystemTime::now();
+        let (mut accessed, mut modified) = (now, now);
+
+        let mut args = command.args.iter().peekable();
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            match arg.as_str() {
+                "--" => break,
+                "--no-create" => no_create = true,
+                "-t" | "-r" => {
+                    let Some(value) = args.next() else {
+                        eprintln!("touch: option requires an argument -- '{}'", &arg[1..]);
+                        return Ok(1);
+                    };
+                    if arg == "-t" {
+                        let Some(time) = parse_timestamp(value) else {
+                            eprintln!("touch: invalid date format '{}'", value);
+                            return Ok(1);
+                        };
+                        (accessed, modified) = (time, time);
+                    } else {
+                        let reference = std::fs::metadata(ctx.current_dir.join(value))
+                            .and_then(|metadata| Ok((metadata.accessed()?, metadata.modified()?)));
+                        match reference {
+                            Ok(times) => (accessed, modified) = times,
+                            Err(e) => {
+                                eprintln!("touch: failed to get attributes of '{}': {}", value, io_error_message(&e));
+                                return Ok(1);
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    for flag in arg[1..].chars() {
+                        match flag {
+                            'a' => access_only = true,
+                            'm' => modification_only = true,
+                            'c' => no_create = true,
+                            _ => {
+                                eprintln!("touch: invalid option -- '{}'", flag);
+                                eprintln!("touch: usage: {}", self.usage());
+                                return Ok(1);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let files: Vec<&String> = args.collect();
+        if files.is_empty() {
+            eprintln!("touch: missing file operand");
+            return Ok(1);
+        }
+
+        // -a and -m together are the same as neither
+        let mut times = FileTimes::new();
+        if !modification_only || access_only {
+            times = times.set_accessed(accessed);
+        }
+        if !access_only || modification_only {
+            times = times.set_modified(modified);
+        }
+
+        let mut status = 0;
+        for file in files {
+            let path = ctx.current_dir.join(file);
+            if no_create && !path.exists() {
+                continue;
+            }
+            if let Err(e) = open_for_touch(&path).and_then(|handle| handle.set_times(times)) {
+                eprintln!("touch: cannot touch '{}': {}", file, io_error_message(&e));
+                status = 1;
+            }
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "touch"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create files or update their timestamps"
+    }
+
+    fn usage(&self) -> &'static str {
+        "touch [-acm] [-r file | -t [[CC]YY]MMDDhhmm[.ss]] file ...\n  -a       Change only the access time\n  -m       Change only the modification time\n  -c       Don't create files that don't exist (--no-create)\n  -r file  Use the times of another file\n  -t time  Use the given local time instead of now"
+    }
+}
+
+/// Opens `path`, creating it if needed. Read-only files we own can still have
+/// their times changed, so fall back to opening for reading.
+fn open_for_touch(path: &Path) -> std::io::Result<File> {
+    match OpenOptions::new().write(true).create(true).truncate(false).open(path) {
+        Ok(file) => Ok(file),
+        Err(e) if path.exists() => File::open(path).map_err(|_| e),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parses the `-t` argument, `[[CC]YY]MMDDhhmm[.ss]`, as a local time. A two-digit
+/// year of 69 to 99 is in the 1900s, anything lower in the 2000s.
+fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let (digits, seconds) = match value.split_once('.') {
+        Some((digits, seconds)) if seconds.len() == 2 => (digits, seconds.parse().ok()?),
+        Some(_) => return None,
+        None => (value, 0),
+    };
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| digits[range].parse::<u32>().ok();
+    let (year, rest) = match digits.len() {
+        8 => (Local::now().year(), 0),
+        10 => {
+            let short = number(0..2)? as i32;
+            (if short >= 69 { 1900 + short } else { 2000 + short }, 2)
+        }
+        12 => (number(0..4)? as i32, 4),
+        _ => return None,
+    };
+    let time = Local
+        .with_ymd_and_hms(
+            year,
+            number(rest..rest + 2)?,
+            number(rest + 2..rest + 4)?,
+            number(rest + 4..rest + 6)?,
+            number(rest + 6..rest + 8)?,
+            seconds,
+        )
+        .single()?;
+    Some(time.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let expected: SystemTime = Local.with_ymd_and_hms(2024, 3, 7, 14, 2, 30).unwrap().into();
+        assert_eq!(parse_timestamp("202403071402.30"), Some(expected));
+        assert_eq!(parse_timestamp("2403071402.30"), Some(expected));
+        let expected: SystemTime = Local.with_ymd_and_hms(1999, 12, 31, 23, 59, 0).unwrap().into();
+        assert_eq!(parse_timestamp("9912312359"), Some(expected));
+        assert_eq!(parse_timestamp("2403071402.3"), None);
+        assert_eq!(parse_timestamp("24031314xx"), None);
+        assert_eq!(parse_timestamp("2413071402"), None);
+    }
+}
@@ -0,0 +1,65 @@
+use super::BuiltinCommand;
+use crate::core::executor::Executor;
+use crate::core::parser::{self, ParsedCommand};
+use crate::core::ShellContext;
+use anyhow::Result;
+
+pub struct UnsetCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for UnsetCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut functions = false;
+        let mut args = command.args.iter().peekable();
+        while let Some(flag) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            match flag.as_str() {
+                "-v" => functions = false,
+                "-f" => functions = true,
+                "--" => break,
+                _ => {
+                    eprintln!("unset: {}: invalid option", flag);
+                    eprintln!("unset: usage: {}", self.usage());
+                    return Ok(2);
+                }
+            }
+        }
+
+        let mut status = 0;
+        for name in args {
+            if functions {
+                ctx.state.functions.remove(name);
+                continue;
+            }
+            if !parser::is_name(name) {
+                eprintln!("unset: `{}': not a valid identifier", name);
+                status = 1;
+                continue;
+            }
+            if ctx.is_readonly(name) {
+                eprintln!("unset: {}: cannot unset: readonly variable", name);
+                status = 1;
+                continue;
+            }
+            std::env::remove_var(name);
+            ctx.state.arrays.remove(name.as_str());
+        }
+        Ok(status)
+    }
+
+    fn name(&self) -> &'static str {
+        "unset"
+    }
+
+    fn description(&self) -> &'static str {
+        "Remove variables or functions"
+    }
+
+    fn usage(&self) -> &'static str {
+        "unset [-v | -f] name ...\n  -v  Remove the variables (default)\n  -f  Remove the functions"
+    }
+}
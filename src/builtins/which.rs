@@ -1,9 +1,9 @@
-use super::BuiltinCommand;
+use super::{BuiltinCommand, get_builtin};
+use crate::core::executor::Executor;
 use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
 use anyhow::Result;
-use std::collections::HashMap;
 use std::path::PathBuf;
-use tokio::process::Child;
 
 pub struct WhichCommand;
 
@@ -12,24 +12,72 @@ impl BuiltinCommand for WhichCommand {
     async fn execute(
         &self,
         command: &ParsedCommand,
-        _current_dir: &mut PathBuf,
-        _background_processes: &mut HashMap<u32, Child>,
-        _parser: &mut crate::core::parser::Parser,
+        _executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
     ) -> Result<i32> {
-        if command.args.is_empty() {
+        let mut all = false;
+        let mut kind_filter: Option<CommandKind> = None;
+        let mut programs = Vec::new();
+
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            let kind = match arg.as_str() {
+                "-a" => {
+                    all = true;
+                    continue;
+                }
+                "--type" => args.next().map(String::as_str),
+                _ if arg.starts_with("--type=") => Some(&arg["--type=".len()..]),
+                flag if flag.starts_with('-') => {
+                    eprintln!("which: {}: invalid option", flag);
+                    eprintln!("which: usage: {}", self.usage());
+                    return Ok(2);
+                }
+                program => {
+                    programs.push(program);
+                    continue;
+                }
+            };
+            match kind.and_then(CommandKind::parse) {
+                Some(kind) => kind_filter = Some(kind),
+                None => {
+                    eprintln!("which: --type must be one of alias, builtin, file or function");
+                    return Ok(2);
+                }
+            }
+        }
+
+        if programs.is_empty() {
             eprintln!("which: missing operand");
             return Ok(1);
         }
 
         let mut found_all = true;
 
-        for program in &command.args {
-            if let Some(path) = find_in_path(program) {
-                println!("{}", path.display());
-            } else {
+        for program in programs {
+            let mut matches = Vec::new();
+            if let Some(value) = ctx.parser.list_aliases().get(program) {
+                matches.push((CommandKind::Alias, format!("{}: aliased to {}", program, value)));
+            }
+            if ctx.state.functions.contains_key(program) {
+                matches.push((CommandKind::Function, format!("{}: shell function", program)));
+            }
+            if get_builtin(program).is_some() {
+                matches.push((CommandKind::Builtin, format!("{}: shell built-in command", program)));
+            }
+            for path in find_all_in_path(program) {
+                matches.push((CommandKind::File, path.display().to_string()));
+            }
+
+            matches.retain(|(kind, _)| kind_filter.is_none_or(|filter| filter == *kind));
+            if matches.is_empty() {
                 eprintln!("which: no {} in PATH", program);
                 found_all = false;
             }
+            let shown = if all { matches.len() } else { 1 };
+            for (_, line) in matches.into_iter().take(shown) {
+                println!("{}", line);
+            }
         }
 
         Ok(if found_all { 0 } else { 1 })
@@ -40,15 +88,38 @@ impl BuiltinCommand for WhichCommand {
     }
 
     fn description(&self) -> &'static str {
-        "Locate a command in the PATH"
+        "Show what a command name runs: an alias, function, builtin or program"
     }
 
     fn usage(&self) -> &'static str {
-        "which program [program ...]"
+        "which [-a] [--type kind] program [program ...]\n  -a           Print every match: aliases, functions, builtins, then PATH order\n  --type kind  Only report alias, builtin, file or function matches"
+    }
+}
+
+/// The kinds of thing a command name can refer to, in the order they take precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandKind {
+    Alias,
+    Function,
+    Builtin,
+    File,
+}
+
+impl CommandKind {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "alias" => Some(Self::Alias),
+            "function" => Some(Self::Function),
+            "builtin" => Some(Self::Builtin),
+            "file" => Some(Self::File),
+            _ => None,
+        }
     }
 }
 
-fn find_in_path(program: &str) -> Option<PathBuf> {
+/// Every executable named `program` in PATH, in PATH order.
+pub(crate) fn find_all_in_path(program: &str) -> Vec<PathBuf> {
+    let mut found = Vec::new();
     if let Ok(path_env) = std::env::var("PATH") {
         let path_separator = if cfg!(windows) { ';' } else { ':' };
         let executable_extensions = if cfg!(windows) {
@@ -72,11 +143,13 @@ fn find_in_path(program: &str) -> Option<PathBuf> {
                             }
                         }
                     }
-                    return Some(full_path);
+                    if !found.contains(&full_path) {
+                        found.push(full_path);
+                    }
                 }
             }
         }
     }
 
-    None
+    found
 }
\ No newline at end of file
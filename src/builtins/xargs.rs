@@ -0,0 +1,297 @@
+use super::BuiltinCommand;
+use crate::core::errors::CommandNotFound;
+use crate::core::executor::Executor;
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::quote::shell_quote;
+use anyhow::Result;
+use std::io::Read;
+use std::process::{ExitStatus, Stdio};
+use tokio::process::Command as TokioCommand;
+use tokio::task::{JoinError, JoinSet};
+
+/// Most bytes of arguments one command is given before the rest go to another, well
+/// under the system's limit (which also counts the environment), as in GNU xargs.
+#[cfg(not(windows))]
+const MAX_COMMAND_BYTES: usize = 128 * 1024;
+/// Windows command lines are limited to 32767 characters.
+#[cfg(windows)]
+const MAX_COMMAND_BYTES: usize = 32 * 1024 - 1024;
+
+pub struct XargsCommand;
+
+#[async_trait::async_trait]
+impl BuiltinCommand for XargsCommand {
+    async fn execute(
+        &self,
+        command: &ParsedCommand,
+        executor: &mut Executor,
+        ctx: &mut ShellContext<'_>,
+    ) -> Result<i32> {
+        let mut max_args = None;
+        let mut jobs = 1;
+        let mut null_separated = false;
+        let mut replace = None;
+        let mut trace = false;
+        let mut args = command.args.iter().peekable();
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            if arg == "--" {
+                break;
+            }
+            let mut flags = arg[1..].chars();
+            while let Some(flag) = flags.next() {
+                match flag {
+                    '0' => null_separated = true,
+                    't' => trace = true,
+                    'n' | 'P' | 'I' => {
+                        // The value is the rest of this word (`-n2`) or the next one
+                        let rest: String = flags.by_ref().collect();
+                        let value = if rest.is_empty() { args.next().cloned() } else { Some(rest) };
+                        let Some(value) = value else {
+                            eprintln!("xargs: option requires an argument -- '{}'", flag);
+                            return Ok(1);
+                        };
+                        if flag == 'I' {
+                            replace = Some(value);
+                            continue;
+                        }
+                        let Ok(number) = value.parse::<usize>() else {
+                            eprintln!("xargs: invalid number '{}' for -{}", value, flag);
+                            return Ok(1);
+                        };
+                        match flag {
+                            'n' if number == 0 => {
+                                eprintln!("xargs: value for -n must be at least 1");
+                                return Ok(1);
+                            }
+                            'n' => max_args = Some(number),
+                            _ => jobs = number,
+                        }
+                    }
+                    _ => {
+                        eprintln!("xargs: invalid option -- '{}'", flag);
+                        eprintln!("xargs: usage: {}", self.usage());
+                        return Ok(1);
+                    }
+                }
+            }
+        }
+        let mut template: Vec<String> = args.cloned().collect();
+        if template.is_empty() {
+            template.push("echo".to_string());
+        }
+
+        let mut input = Vec::new();
+        std::io::stdin().read_to_end(&mut input)?;
+        let input = String::from_utf8_lossy(&input);
+
+        let commands: Vec<Vec<String>> = match &replace {
+            // With -I, each line of input is one command
+            Some(placeholder) => {
+                let separator = if null_separated { '\0' } else { '\n' };
+                input
+                    .split(separator)
+                    .map(|line| line.trim_start())
+                    .filter(|line| !line.is_empty())
+                    .map(|line| template.iter().map(|word| word.replace(placeholder.as_str(), line)).collect())
+                    .collect()
+            }
+            None => {
+                let items = if null_separated {
+                    input.split('\0').filter(|item| !item.is_empty()).map(String::from).collect()
+                } else {
+                    match split_words(&input) {
+                        Ok(items) => items,
+                        Err(message) => {
+                            eprintln!("xargs: {}", message);
+                            return Ok(1);
+                        }
+                    }
+                };
+                let template_bytes = template.iter().map(|word| word.len() + 1).sum();
+                batches(items, template_bytes, max_args)
+                    .into_iter()
+                    .map(|batch| template.iter().cloned().chain(batch).collect())
+                    .collect()
+            }
+        };
+
+        // Only external programs can run alongside each other; builtins and functions
+        // run in the shell, one at a time
+        let program = &template[0];
+        if jobs != 1 && !runs_in_shell(program, ctx) {
+            let Some(path) = executor.remember_program(program) else {
+                eprintln!("xargs: {}: No such file or directory", program);
+                return Ok(127);
+            };
+            let mut running = JoinSet::new();
+            let mut failed = false;
+            for words in commands {
+                if jobs != 0 && running.len() >= jobs {
+                    if let Some(joined) = running.join_next().await {
+                        failed |= !succeeded(joined);
+                    }
+                }
+                if trace {
+                    eprintln!("{}", words.join(" "));
+                }
+                // Exported variables are in the shell's environment, which the
+                // command inherits along with the assignments xargs was run with
+                let mut child = TokioCommand::new(&path);
+                child.args(&words[1..]).current_dir(&*ctx.current_dir).stdin(Stdio::null());
+                if command.clear_environment {
+                    child.env_clear();
+                }
+                for name in &command.unset_environment {
+                    child.env_remove(name);
+                }
+                child.envs(&command.environment);
+                running.spawn(async move { child.status().await });
+            }
+            while let Some(joined) = running.join_next().await {
+                failed |= !succeeded(joined);
+            }
+            return Ok(if failed { 123 } else { 0 });
+        }
+
+        let mut failed = false;
+        for words in commands {
+            if trace {
+                eprintln!("{}", words.join(" "));
+            }
+            let mut invocation = ParsedCommand::new(words[0].clone());
+            invocation.args = words[1..].to_vec();
+            // Values are already expanded, so quote them against being expanded again
+            invocation.environment = command
+                .environment
+                .iter()
+                .map(|(key, value)| (key.clone(), shell_quote(value)))
+                .collect();
+            invocation.clear_environment = command.clear_environment;
+            invocation.unset_environment = command.unset_environment.clone();
+            match executor.execute(invocation, ctx).await {
+                Ok(status) => failed |= status != 0,
+                Err(e) if e.is::<CommandNotFound>() => {
+                    eprintln!("xargs: {}: No such file or directory", words[0]);
+                    return Ok(127);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        // As in GNU xargs, 123 reports that some command failed
+        Ok(if failed { 123 } else { 0 })
+    }
+
+    fn name(&self) -> &'static str {
+        "xargs"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run a command with arguments read from standard input"
+    }
+
+    fn usage(&self) -> &'static str {
+        "xargs [-0t] [-n max-args] [-P jobs] [-I replace] [command [arg ...]]\n  Runs command (default: echo) with the words of standard input added to its\n  arguments, split over several runs when there are too many for one\n  -0          Input items end with a NUL byte rather than blanks, as from find -print0\n  -n max-args Add at most max-args items to each command\n  -P jobs     Run up to jobs commands at once (0: as many as there are); external\n              programs only\n  -I replace  Run the command once per input line, with replace in its words\n              standing for the line\n  -t          Print each command to standard error before running it"
+    }
+}
+
+/// True if `program` runs in the shell rather than as a process of its own.
+fn runs_in_shell(program: &str, ctx: &ShellContext<'_>) -> bool {
+    super::get_builtin(program).is_some() || ctx.state.functions.contains_key(program)
+}
+
+/// True for a command started with -P that ran and exited successfully.
+fn succeeded(joined: Result<std::io::Result<ExitStatus>, JoinError>) -> bool {
+    matches!(joined, Ok(Ok(status)) if status.success())
+}
+
+/// Splits input into items at blanks and newlines, as xargs does by default:
+/// quotes and backslashes keep blanks within an item.
+fn split_words(input: &str) -> Result<Vec<String>, String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut in_item = false;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                in_item = true;
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\n') | None => {
+                            let quote = if c == '\'' { "single" } else { "double" };
+                            return Err(format!("unmatched {} quote", quote));
+                        }
+                        Some(inner) => current.push(inner),
+                    }
+                }
+            }
+            '\\' => {
+                in_item = true;
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_item {
+                    items.push(std::mem::take(&mut current));
+                    in_item = false;
+                }
+            }
+            c => {
+                in_item = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_item {
+        items.push(current);
+    }
+    Ok(items)
+}
+
+/// Groups `items` into the argument lists of successive commands: each gets at most
+/// `max_args` items, and no more than fit in `MAX_COMMAND_BYTES` with the
+/// `template_bytes` of the command itself. Without items there is one empty list,
+/// so the command still runs once.
+fn batches(items: Vec<String>, template_bytes: usize, max_args: Option<usize>) -> Vec<Vec<String>> {
+    let mut batches = Vec::new();
+    let mut batch: Vec<String> = Vec::new();
+    let mut bytes = template_bytes;
+    for item in items {
+        let full = max_args.is_some_and(|max| batch.len() >= max);
+        if !batch.is_empty() && (full || bytes + item.len() + 1 > MAX_COMMAND_BYTES) {
+            batches.push(std::mem::take(&mut batch));
+            bytes = template_bytes;
+        }
+        bytes += item.len() + 1;
+        batch.push(item);
+    }
+    if !batch.is_empty() || batches.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("a  b\nc\n").unwrap(), vec!["a", "b", "c"]);
+        assert_eq!(split_words("'two words' \"it's\" back\\ slash ''").unwrap(), vec!["two words", "it's", "back slash", ""]);
+        assert_eq!(split_words("'open").unwrap_err(), "unmatched single quote");
+    }
+
+    #[test]
+    fn test_batches() {
+        let items = |n: usize| (0..n).map(|i| i.to_string()).collect::<Vec<_>>();
+        assert_eq!(batches(items(5), 5, Some(2)), vec![items(2), vec!["2".to_string(), "3".to_string()], vec!["4".to_string()]]);
+        assert_eq!(batches(Vec::new(), 5, None), vec![Vec::<String>::new()]);
+
+        let long = "x".repeat(MAX_COMMAND_BYTES / 3);
+        assert_eq!(batches(vec![long.clone(); 4], 5, None).len(), 2);
+    }
+}
@@ -13,6 +13,10 @@ pub struct Cli {
     #[arg(long)]
     pub config: Option<PathBuf>,
 
+    /// Configuration profile to layer over the base config (falls back to $FLEXSH_PROFILE)
+    #[arg(long)]
+    pub profile: Option<String>,
+
     /// Execute a single command and exit
     #[arg(short = 'c', long)]
     pub command: Option<String>,
@@ -25,10 +29,41 @@ pub struct Cli {
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
 
+    /// Exit as soon as a command fails (same as `set -e`)
+    #[arg(short = 'e', long)]
+    pub exit_on_error: bool,
+
+    /// Run as a restricted shell: no `cd`, no command names containing `/`, no
+    /// output redirections, and PATH, SHELL, ENV and BASH_ENV can't be changed
+    #[arg(short = 'r', long)]
+    pub restricted: bool,
+
+    /// Run as a login shell: source /etc/profile and ~/.flexshrc (or ~/.profile)
+    /// at startup and ~/.flexsh_logout on exit. Also implied when the shell is
+    /// started with a name beginning with `-`
+    #[arg(short = 'l', long)]
+    pub login: bool,
+
+    /// Check the script's syntax without running it, reporting every error
+    #[arg(short = 'n', long = "check", requires = "script")]
+    pub check_only: bool,
+
+    /// Print the configuration in effect as TOML, defaults included, and exit
+    #[arg(long)]
+    pub dump_config: bool,
+
+    /// Print how long each phase of startup took
+    #[arg(long, hide = true)]
+    pub timings: bool,
+
     /// Disable colors
     #[arg(long)]
     pub no_color: bool,
 
     /// Script file to execute
     pub script: Option<PathBuf>,
+
+    /// Arguments for the script, its positional parameters `$1`, `$2`, ...
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, requires = "script")]
+    pub script_args: Vec<String>,
 }
\ No newline at end of file
@@ -1,9 +1,10 @@
 pub mod settings;
+pub mod watcher;
 
 pub use settings::Config;
 
 use anyhow::Result;
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -15,6 +16,57 @@ pub struct ShellConfig {
     pub completion: CompletionConfig,
     pub aliases: std::collections::HashMap<String, String>,
     pub environment: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    pub keybindings: std::collections::HashMap<String, String>,
+    /// Change into a directory when its path is entered as a command.
+    #[serde(default)]
+    pub auto_cd: bool,
+    /// Print startup phase timings, as with `--timings`.
+    #[serde(default)]
+    pub startup_profiler: bool,
+    /// Keep pasted text in the line being edited, newlines included, instead of
+    /// running each pasted line as it arrives.
+    #[serde(default = "default_bracketed_paste")]
+    pub bracketed_paste: bool,
+    /// Emit OSC 133 prompt marks and OSC 7 directory reports in terminals that
+    /// support them (iTerm2, kitty, WezTerm).
+    #[serde(default = "default_shell_integration")]
+    pub shell_integration: bool,
+    /// Where completion scripts are sourced from at startup, instead of
+    /// `~/.config/flex-sh/completions`.
+    #[serde(default)]
+    pub completions_dir: Option<PathBuf>,
+    /// What `clear` does: "standard" clears the screen, "soft" scrolls its
+    /// contents into the scroll-back (as `clear -x`), and "purge" also erases the
+    /// scroll-back where the terminal supports it.
+    #[serde(default = "default_clear_mode")]
+    pub clear_mode: String,
+    /// Under WSL, read Windows paths such as `C:\Users\me` as their `/mnt/c/...`
+    /// mounts in `cd` and other paths the shell resolves.
+    #[serde(default = "default_wsl_interop")]
+    pub wsl_interop: bool,
+    /// Show the working directory in the window title (the window name in tmux
+    /// and Screen) at each prompt.
+    #[serde(default = "default_window_title")]
+    pub window_title: bool,
+    /// Reload the config file when it changes while the shell is at the prompt.
+    #[serde(default)]
+    pub auto_reload_config: bool,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginsConfig {
+    /// Built-in plugins to load at startup, by name.
+    #[serde(default = "default_plugins")]
+    pub enabled: Vec<String>,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self { enabled: default_plugins() }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +75,43 @@ pub struct PromptConfig {
     pub show_git: bool,
     pub show_time: bool,
     pub show_exit_code: bool,
+    /// Shown while a command continues onto another line (open quote, trailing `\`).
+    #[serde(default = "default_continuation_prompt")]
+    pub continuation_prompt: String,
+    /// Longest `{cwd}` shown; a longer one keeps its end, after `…`.
+    #[serde(default)]
+    pub max_cwd_length: Option<usize>,
+    /// Fill in `{pyenv}` and `{nvm}`, which run `pyenv` or look at nvm's setup.
+    #[serde(default)]
+    pub show_tool_versions: bool,
+}
+
+fn default_continuation_prompt() -> String {
+    "> ".to_string()
+}
+
+fn default_bracketed_paste() -> bool {
+    true
+}
+
+fn default_shell_integration() -> bool {
+    true
+}
+
+fn default_clear_mode() -> String {
+    "standard".to_string()
+}
+
+fn default_wsl_interop() -> bool {
+    true
+}
+
+fn default_window_title() -> bool {
+    true
+}
+
+fn default_plugins() -> Vec<String> {
+    vec!["git".to_string()]
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +130,12 @@ pub struct HistoryConfig {
     pub file_path: Option<PathBuf>,
     pub ignore_duplicates: bool,
     pub ignore_space_prefixed: bool,
+    /// Glob patterns (as in `HISTIGNORE`) for command lines never to save
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    /// Commands never saved, whatever their arguments
+    #[serde(default)]
+    pub sensitive_commands: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +143,40 @@ pub struct CompletionConfig {
     pub enabled: bool,
     pub case_sensitive: bool,
     pub fuzzy_matching: bool,
+    /// Initial value of the `globstar` option, letting `**` match in subdirectories
+    #[serde(default)]
+    pub globstar_enabled: bool,
+    /// Commands whose first argument completes from the system's host names
+    #[serde(default = "default_hostname_commands")]
+    pub hostname_commands: Vec<String>,
+    /// Commands whose first argument completes from the system's user names
+    #[serde(default = "default_user_completion_commands")]
+    pub user_completion_commands: Vec<String>,
+    /// Include system accounts (UID below 1000) in user name completion
+    #[serde(default)]
+    pub show_system_users: bool,
+    /// Refuse aliases that lead back to themselves, like `alias ls='ls -la'`,
+    /// instead of expanding them once
+    #[serde(default)]
+    pub prevent_recursive_aliases: bool,
+    /// Bind Ctrl+T to the file picker, which inserts a path at the cursor
+    #[serde(default = "default_file_picker")]
+    pub file_picker: bool,
+}
+
+fn default_file_picker() -> bool {
+    true
+}
+
+fn default_user_completion_commands() -> Vec<String> {
+    ["su", "chown", "id", "finger", "passwd"].iter().map(|command| command.to_string()).collect()
+}
+
+fn default_hostname_commands() -> Vec<String> {
+    ["ssh", "scp", "sftp", "mosh", "ping", "curl", "telnet", "traceroute", "host", "dig", "nslookup"]
+        .iter()
+        .map(|command| command.to_string())
+        .collect()
 }
 
 impl Default for ShellConfig {
@@ -58,6 +187,9 @@ impl Default for ShellConfig {
                 show_git: true,
                 show_time: false,
                 show_exit_code: true,
+                continuation_prompt: default_continuation_prompt(),
+                max_cwd_length: None,
+                show_tool_versions: false,
             },
             colors: ColorConfig {
                 enabled: true,
@@ -72,25 +204,68 @@ impl Default for ShellConfig {
                 file_path: None,
                 ignore_duplicates: true,
                 ignore_space_prefixed: true,
+                ignore_patterns: Vec::new(),
+                sensitive_commands: Vec::new(),
             },
             completion: CompletionConfig {
                 enabled: true,
                 case_sensitive: false,
                 fuzzy_matching: true,
+                globstar_enabled: false,
+                hostname_commands: default_hostname_commands(),
+                user_completion_commands: default_user_completion_commands(),
+                show_system_users: false,
+                prevent_recursive_aliases: false,
+                file_picker: default_file_picker(),
             },
             aliases: std::collections::HashMap::new(),
             environment: std::collections::HashMap::new(),
+            keybindings: std::collections::HashMap::new(),
+            auto_cd: false,
+            startup_profiler: false,
+            bracketed_paste: true,
+            shell_integration: true,
+            completions_dir: None,
+            clear_mode: default_clear_mode(),
+            wsl_interop: default_wsl_interop(),
+            window_title: default_window_title(),
+            auto_reload_config: false,
+            plugins: PluginsConfig::default(),
         }
     }
 }
 
 impl ShellConfig {
-    pub fn load(path: Option<PathBuf>) -> Result<Self> {
+    pub fn load(path: Option<PathBuf>, profile: Option<&str>) -> Result<Self> {
+        let mut value = match Self::find_config_file(path) {
+            Some(config_path) => {
+                let content = std::fs::read_to_string(&config_path)?;
+                toml::from_str::<toml::Value>(&content)?
+            }
+            None => toml::Value::try_from(ShellConfig::default())?,
+        };
+
+        if let Some(name) = profile {
+            match profiles_dir().map(|d| d.join(format!("{}.toml", name))) {
+                Some(profile_path) if profile_path.exists() => {
+                    debug!("Applying profile '{}' from {:?}", name, profile_path);
+                    let content = std::fs::read_to_string(&profile_path)?;
+                    let overlay: toml::Value = toml::from_str(&content)?;
+                    merge_toml(&mut value, overlay);
+                }
+                _ => warn!("Profile '{}' not found, using base configuration", name),
+            }
+        }
+
+        expand_env(&mut value, "");
+        Ok(value.try_into()?)
+    }
+
+    /// Returns the first existing config file, preferring an explicit path.
+    pub fn find_config_file(path: Option<PathBuf>) -> Option<PathBuf> {
         if let Some(config_path) = path {
             if config_path.exists() {
-                let content = std::fs::read_to_string(&config_path)?;
-                let config: ShellConfig = toml::from_str(&content)?;
-                return Ok(config);
+                return Some(config_path);
             }
         }
 
@@ -113,13 +288,11 @@ impl ShellConfig {
             debug!("Checking config path: {:?}", config_path);
             if config_path.exists() {
                 debug!("Found config at: {:?}", config_path);
-                let content = std::fs::read_to_string(config_path)?;
-                let config: ShellConfig = toml::from_str(&content)?;
-                return Ok(config);
+                return Some(config_path.clone());
             }
         }
 
-        Ok(ShellConfig::default())
+        None
     }
 
     pub fn save(&self, path: &PathBuf) -> Result<()> {
@@ -130,4 +303,235 @@ impl ShellConfig {
         std::fs::write(path, content)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Directory holding named profiles (`<name>.toml`) layered over the base config.
+pub fn profiles_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("flex-sh").join("profiles"))
+}
+
+/// Default location of the completion scripts sourced at startup.
+pub fn completions_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("flex-sh").join("completions"))
+}
+
+/// Directory holding the user's color schemes (`<name>.toml`).
+pub fn schemes_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("flex-sh").join("schemes"))
+}
+
+/// Recursively merges `overlay` into `base`; overlay values take precedence.
+fn merge_toml(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base_table), toml::Value::Table(overlay_table)) => {
+            for (key, value) in overlay_table {
+                match base_table.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base_table.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Expands `${VAR}` and `$VAR` references in a config value using the process
+/// environment. References to unset variables are left untouched.
+pub fn expand_config_env(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '$' {
+            result.push(ch);
+            continue;
+        }
+
+        if chars.peek() == Some(&'{') {
+            chars.next(); // consume '{'
+            let mut var_name = String::new();
+            let mut found_closing = false;
+            for ch in chars.by_ref() {
+                if ch == '}' {
+                    found_closing = true;
+                    break;
+                }
+                var_name.push(ch);
+            }
+
+            match std::env::var(&var_name) {
+                Ok(val) if found_closing => result.push_str(&val),
+                _ => {
+                    result.push_str("${");
+                    result.push_str(&var_name);
+                    if found_closing {
+                        result.push('}');
+                    }
+                }
+            }
+        } else if matches!(chars.peek(), Some(c) if c.is_alphabetic() || *c == '_') {
+            let mut var_name = String::new();
+            while let Some(&ch) = chars.peek() {
+                if ch.is_alphanumeric() || ch == '_' {
+                    var_name.push(ch);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            match std::env::var(&var_name) {
+                Ok(val) => result.push_str(&val),
+                Err(_) => {
+                    result.push('$');
+                    result.push_str(&var_name);
+                }
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+
+    result
+}
+
+/// Expands `${VAR}` / `$VAR` references in every string of `value`, `key` being its
+/// dotted path, and warns about references to unset variables, which are kept.
+fn expand_env(value: &mut toml::Value, key: &str) {
+    match value {
+        toml::Value::String(text) => {
+            for var in env_var_refs(text) {
+                if std::env::var(&var).is_err() {
+                    warn!("Config value for '{}' references unset variable '{}'", key, var);
+                }
+            }
+            *text = expand_config_env(text);
+        }
+        toml::Value::Array(items) => {
+            for item in items {
+                expand_env(item, key);
+            }
+        }
+        toml::Value::Table(table) => {
+            for (name, item) in table.iter_mut() {
+                let key = if key.is_empty() { name.clone() } else { format!("{}.{}", key, name) };
+                expand_env(item, &key);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the names of all `${VAR}` / `$VAR` references in a value.
+fn env_var_refs(value: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '$' {
+            continue;
+        }
+        let mut var_name = String::new();
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            for ch in chars.by_ref() {
+                if ch == '}' {
+                    break;
+                }
+                var_name.push(ch);
+            }
+        } else {
+            while let Some(&ch) = chars.peek() {
+                if ch.is_alphanumeric() || ch == '_' {
+                    var_name.push(ch);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+        }
+        if !var_name.is_empty() && !var_name.starts_with(|c: char| c.is_ascii_digit()) {
+            names.push(var_name);
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_config_env() {
+        std::env::set_var("FLEXSH_TEST_DIR", "/opt/flex");
+        assert_eq!(expand_config_env("${FLEXSH_TEST_DIR}/history"), "/opt/flex/history");
+        assert_eq!(expand_config_env("$FLEXSH_TEST_DIR/history"), "/opt/flex/history");
+        assert_eq!(expand_config_env("[{user}]$ "), "[{user}]$ ");
+    }
+
+    #[test]
+    fn test_expand_env_every_string() {
+        std::env::set_var("FLEXSH_TEST_PATHS", "/opt/flex");
+        let mut value = toml::Value::try_from(ShellConfig::default()).unwrap();
+        let overlay: toml::Value = toml::from_str(
+            "completions_dir = \"$FLEXSH_TEST_PATHS/completions\"\n\
+             [history]\nfile_path = \"${FLEXSH_TEST_PATHS}/history\"\nignore_patterns = [\"^${FLEXSH_TEST_PATHS}\"]\n\
+             [aliases]\ncdf = \"cd $FLEXSH_TEST_PATHS\"\n",
+        ).unwrap();
+        merge_toml(&mut value, overlay);
+        expand_env(&mut value, "");
+
+        let config: ShellConfig = value.try_into().unwrap();
+        assert_eq!(config.history.file_path, Some(PathBuf::from("/opt/flex/history")));
+        assert_eq!(config.completions_dir, Some(PathBuf::from("/opt/flex/completions")));
+        assert_eq!(config.history.ignore_patterns, vec!["^/opt/flex"]);
+        assert_eq!(config.aliases.get("cdf"), Some(&"cd /opt/flex".to_string()));
+    }
+
+    #[test]
+    fn test_profile_overlay_takes_precedence() {
+        let mut base = toml::Value::try_from(ShellConfig::default()).unwrap();
+        let overlay: toml::Value = toml::from_str(
+            "[colors]\nscheme = \"monokai\"\n[aliases]\ngs = \"git status\"\n",
+        ).unwrap();
+        merge_toml(&mut base, overlay);
+
+        let config: ShellConfig = base.try_into().unwrap();
+        assert_eq!(config.colors.scheme, "monokai");
+        assert!(config.colors.enabled);
+        assert_eq!(config.aliases.get("gs"), Some(&"git status".to_string()));
+    }
+
+    #[test]
+    fn test_bracketed_paste_defaults_on() {
+        let mut value = toml::Value::try_from(ShellConfig::default()).unwrap();
+        value.as_table_mut().unwrap().remove("bracketed_paste");
+        let config: ShellConfig = value.try_into().unwrap();
+        assert!(config.bracketed_paste);
+    }
+
+    #[test]
+    fn test_plugins_config() {
+        let mut value = toml::Value::try_from(ShellConfig::default()).unwrap();
+        value.as_table_mut().unwrap().remove("plugins");
+        let config: ShellConfig = value.clone().try_into().unwrap();
+        assert_eq!(config.plugins.enabled, vec!["git"]);
+
+        // Settings tables for individual plugins are left alone
+        let overlay: toml::Value = toml::from_str("[plugins]\nenabled = []\n[plugins.git]\nshow_branch = true\n").unwrap();
+        merge_toml(&mut value, overlay);
+        let config: ShellConfig = value.try_into().unwrap();
+        assert!(config.plugins.enabled.is_empty());
+    }
+
+    #[test]
+    fn test_unset_variables_left_as_is() {
+        std::env::remove_var("FLEXSH_TEST_UNSET");
+        assert_eq!(expand_config_env("${FLEXSH_TEST_UNSET}/x"), "${FLEXSH_TEST_UNSET}/x");
+        assert_eq!(expand_config_env("$FLEXSH_TEST_UNSET/x"), "$FLEXSH_TEST_UNSET/x");
+        assert_eq!(env_var_refs("${FLEXSH_TEST_UNSET}/x"), vec!["FLEXSH_TEST_UNSET"]);
+    }
+}
@@ -1,21 +1,51 @@
-use super::ShellConfig;
+use super::{profiles_dir, schemes_dir, ShellConfig};
+use crate::terminal::colors::ColorScheme;
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, TableLike};
 
+#[derive(Clone)]
 pub struct Config {
     config: ShellConfig,
     config_path: Option<PathBuf>,
+    profile: Option<String>,
+    /// Schemes loaded from the schemes directory
+    user_schemes: Vec<ColorScheme>,
+    /// The config as last loaded or saved, to tell which settings have changed
+    saved: ShellConfig,
+    /// The file saves go to, as written: unexpanded and unmerged
+    document: DocumentMut,
 }
 
 impl Config {
-    pub fn new(path: Option<PathBuf>) -> Result<Self> {
-        let config = ShellConfig::load(path.clone())?;
+    pub fn new(path: Option<PathBuf>, profile: Option<String>) -> Result<Self> {
+        let profile = profile.or_else(|| std::env::var("FLEXSH_PROFILE").ok());
+        let config_path = ShellConfig::find_config_file(path);
+        let config = ShellConfig::load(config_path.clone(), profile.as_deref())?;
+        let document = load_document(&save_path(config_path.as_deref(), profile.as_deref()))?;
         Ok(Self {
+            saved: config.clone(),
             config,
-            config_path: path,
+            config_path,
+            profile,
+            user_schemes: load_user_schemes(),
+            document,
         })
     }
 
+    /// The configuration in effect as TOML, with defaults for the settings that
+    /// weren't set, headed by comments saying where it was loaded from.
+    pub fn dump(&self) -> Result<String> {
+        let mut header = match &self.config_path {
+            Some(path) => format!("# Loaded from {}\n", path.display()),
+            None => "# No config file found; these are the defaults\n".to_string(),
+        };
+        if let Some(profile) = &self.profile {
+            header.push_str(&format!("# With profile '{}'\n", profile));
+        }
+        Ok(format!("{}\n{}", header, toml::to_string_pretty(&self.config)?))
+    }
+
     pub fn get(&self) -> &ShellConfig {
         &self.config
     }
@@ -24,20 +54,185 @@ impl Config {
         &mut self.config
     }
 
-    pub fn save(&self) -> Result<()> {
-        if let Some(ref path) = self.config_path {
-            self.config.save(path)?;
-        } else {
-            let default_path = dirs::config_dir()
-                .map(|d| d.join("flex-sh").join("config.toml"))
-                .unwrap_or_else(|| PathBuf::from(".flexsh.toml"));
-            self.config.save(&default_path)?;
-        }
+    /// The config file loaded, if one was found.
+    pub fn path(&self) -> Option<&Path> {
+        self.config_path.as_deref()
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Saves to the active profile if one is selected, otherwise to the base config file.
+    /// Only the settings changed since loading are written, so the rest of the file
+    /// keeps its comments and `${VAR}` references, and a profile doesn't take in
+    /// settings from the base.
+    pub fn save(&mut self) -> Result<()> {
+        let path = save_path(self.config_path.as_deref(), self.profile.as_deref());
+        let before = toml::Table::try_from(&self.saved)?;
+        let after = toml::Table::try_from(&self.config)?;
+        patch_table(self.document.as_table_mut(), &before, &after)?;
+        write_document(&path, &self.document)?;
+        self.saved = self.config.clone();
+        Ok(())
+    }
+
+    /// Saves the aliases alone to the file `save` writes, patching its `[aliases]`
+    /// table as it is on disk now and leaving the rest of the file untouched.
+    pub fn save_aliases(&mut self) -> Result<()> {
+        let path = save_path(self.config_path.as_deref(), self.profile.as_deref());
+        let mut document = load_document(&path)?;
+        let before = toml::Table::try_from(&self.saved.aliases)?;
+        let after = toml::Table::try_from(&self.config.aliases)?;
+        let aliases = document
+            .as_table_mut()
+            .entry("aliases")
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow::anyhow!("{}: aliases is not a table", path.display()))?;
+        patch_table(aliases, &before, &after)?;
+        write_document(&path, &document)?;
+        self.document = document;
+        self.saved.aliases = self.config.aliases.clone();
         Ok(())
     }
 
     pub fn reload(&mut self) -> Result<()> {
-        self.config = ShellConfig::load(self.config_path.clone())?;
+        self.config = ShellConfig::load(self.config_path.clone(), self.profile.as_deref())?;
+        self.saved = self.config.clone();
+        self.document = load_document(&save_path(self.config_path.as_deref(), self.profile.as_deref()))?;
+        self.user_schemes = load_user_schemes();
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// The built-in color schemes followed by the user's; a user scheme with a
+    /// built-in's name replaces it.
+    pub fn schemes(&self) -> Vec<ColorScheme> {
+        let mut schemes: Vec<ColorScheme> = ColorScheme::get_available_schemes()
+            .into_iter()
+            .filter(|builtin| !self.user_schemes.iter().any(|scheme| scheme.name == builtin.name))
+            .collect();
+        schemes.extend(self.user_schemes.iter().cloned());
+        schemes
+    }
+
+    pub fn find_scheme(&self, name: &str) -> Option<ColorScheme> {
+        self.schemes().into_iter().find(|scheme| scheme.name == name)
+    }
+
+    pub fn profile_path(name: &str) -> Option<PathBuf> {
+        profiles_dir().map(|d| d.join(format!("{}.toml", name)))
+    }
+
+    /// Names of all profiles found in the profiles directory, sorted.
+    pub fn list_profiles() -> Vec<String> {
+        let mut profiles: Vec<String> = profiles_dir()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                    .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        profiles.sort();
+        profiles
+    }
+}
+
+fn load_user_schemes() -> Vec<ColorScheme> {
+    schemes_dir().map(|dir| ColorScheme::load_from_dir(&dir)).unwrap_or_default()
+}
+
+/// The file saves go to: the profile's if one is selected, otherwise the base
+/// config file, or the default location when there is none.
+fn save_path(config_path: Option<&Path>, profile: Option<&str>) -> PathBuf {
+    if let Some(path) = profile.and_then(Config::profile_path) {
+        return path;
+    }
+    match config_path {
+        Some(path) => path.to_path_buf(),
+        None => dirs::config_dir()
+            .map(|d| d.join("flex-sh").join("config.toml"))
+            .unwrap_or_else(|| PathBuf::from(".flexsh.toml")),
+    }
+}
+
+/// The TOML document at `path`, or an empty one if there's no file yet.
+fn load_document(path: &Path) -> Result<DocumentMut> {
+    if !path.exists() {
+        return Ok(DocumentMut::new());
+    }
+    Ok(std::fs::read_to_string(path)?.parse()?)
+}
+
+fn write_document(path: &Path, document: &DocumentMut) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+/// Writes the settings that differ between `before` and `after` into `table`,
+/// removing those `after` no longer has. Tables are patched key by key, and
+/// created when `table` lacks them, so only what changed is written.
+fn patch_table(table: &mut dyn TableLike, before: &toml::Table, after: &toml::Table) -> Result<()> {
+    for (key, value) in after {
+        let old = before.get(key);
+        if old == Some(value) {
+            continue;
+        }
+        if let toml::Value::Table(new) = value {
+            if !table.get(key).is_some_and(|item| item.is_table_like()) {
+                let mut created = toml_edit::Table::new();
+                created.set_implicit(true);
+                table.insert(key, Item::Table(created));
+            }
+            let empty = toml::Table::new();
+            let old = match old {
+                Some(toml::Value::Table(old)) => old,
+                _ => &empty,
+            };
+            if let Some(inner) = table.get_mut(key).and_then(Item::as_table_like_mut) {
+                patch_table(inner, old, new)?;
+            }
+            continue;
+        }
+        table.insert(key, Item::Value(value.to_string().parse()?));
+    }
+    for key in before.keys() {
+        if !after.contains_key(key) {
+            table.remove(key);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_table_writes_only_changes() {
+        let source = "# My settings\n[history]\nfile_path = \"${HOME}/.hist\"\n\n[aliases]\nll = \"ls -l\"\n";
+        let mut document: DocumentMut = source.parse().unwrap();
+        let before: toml::Table = toml::from_str(
+            "[history]\nfile_path = \"/home/me/.hist\"\nmax_entries = 1000\n[aliases]\nll = \"ls -l\"\ngs = \"git status\"\n",
+        ).unwrap();
+        let after: toml::Table = toml::from_str(
+            "[history]\nfile_path = \"/home/me/.hist\"\nmax_entries = 50\n[aliases]\nll = \"ls -la\"\n[colors]\nscheme = \"nord\"\n",
+        ).unwrap();
+        patch_table(document.as_table_mut(), &before, &after).unwrap();
+
+        let patched = document.to_string();
+        assert!(patched.starts_with("# My settings\n"));
+        assert!(patched.contains("file_path = \"${HOME}/.hist\""));
+        assert!(patched.contains("max_entries = 50"));
+        assert!(patched.contains("ll = \"ls -la\""));
+        assert!(!patched.contains("gs ="));
+        assert!(patched.contains("[colors]\nscheme = \"nord\""));
+    }
+}
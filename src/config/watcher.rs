@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// How long the file has to go without changes before it's reloaded, so that an
+/// editor saving in several writes causes one reload.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches the config file, for `auto_reload_config`. The directory is watched
+/// rather than the file, as editors often save by replacing the file.
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+    changes: UnboundedReceiver<()>,
+}
+
+impl ConfigWatcher {
+    /// Starts watching `path`. Needs a tokio runtime, where the changes are debounced.
+    pub fn new(path: &Path) -> Result<Self> {
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let name = path.file_name().context("config path has no file name")?.to_owned();
+        let (event_sender, mut events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            let written = matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_));
+            if written && event.paths.iter().any(|changed| changed.file_name() == Some(&name)) {
+                let _ = event_sender.send(());
+            }
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        let (change_sender, changes) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while events.recv().await.is_some() {
+                // Wait for a quiet moment, taking in the events that arrive meanwhile
+                while let Ok(Some(())) = tokio::time::timeout(DEBOUNCE, events.recv()).await {}
+                if change_sender.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self { _watcher: watcher, changes })
+    }
+
+    /// Whether the file has changed since the last call.
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        while self.changes.try_recv().is_ok() {
+            changed = true;
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_config_watcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "a = 1\n").unwrap();
+        let mut watcher = ConfigWatcher::new(&path).unwrap();
+        assert!(!watcher.changed());
+
+        std::fs::write(dir.path().join("other.toml"), "").unwrap();
+        std::fs::write(&path, "a = 2\n").unwrap();
+        std::fs::write(&path, "a = 3\n").unwrap();
+        let mut changed = false;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            changed = watcher.changed();
+            if changed {
+                break;
+            }
+        }
+        assert!(changed);
+        // The writes were close together, so they count as one change
+        tokio::time::sleep(DEBOUNCE * 2).await;
+        assert!(!watcher.changed());
+    }
+}
@@ -0,0 +1,101 @@
+use nix::unistd::{dup, dup2_stdin, dup2_stdout, pipe};
+use std::io::{self, Read, Write};
+use std::os::fd::OwnedFd;
+use std::thread::JoinHandle;
+
+/// The shell's standard input and output swapped for pipes while a builtin in a
+/// pipeline runs in the shell process: standard input reads the given input, and
+/// what's written to standard output is collected. They can also be swapped for the
+/// descriptors of a builtin's `<&` and `>&` redirections. The originals are put back
+/// by `finish`, or when this is dropped.
+pub struct SwappedStdio {
+    /// The original standard input, while it's swapped
+    stdin: Option<OwnedFd>,
+    /// The original standard output, while it's swapped
+    stdout: Option<OwnedFd>,
+    feeder: Option<JoinHandle<()>>,
+    collector: Option<JoinHandle<Vec<u8>>>,
+}
+
+impl SwappedStdio {
+    /// Feeds `input` to standard input when there is some, and collects standard
+    /// output when `capture` is set.
+    pub fn new(input: Option<Vec<u8>>, capture: bool) -> io::Result<Self> {
+        io::stdout().flush()?;
+        let mut swapped = Self { stdin: None, stdout: None, feeder: None, collector: None };
+        if let Some(input) = input {
+            let (reader, feeder) = feed(input)?;
+            swapped.stdin = Some(dup(io::stdin())?);
+            dup2_stdin(&reader)?;
+            swapped.feeder = Some(feeder);
+        }
+        if capture {
+            let (reader, writer) = pipe()?;
+            swapped.stdout = Some(dup(io::stdout())?);
+            dup2_stdout(&writer)?;
+            swapped.collector = Some(drain(std::fs::File::from(reader)));
+        }
+        Ok(swapped)
+    }
+
+    /// Points standard input and output at the given descriptors, where there are some.
+    pub fn redirected(stdin: Option<OwnedFd>, stdout: Option<OwnedFd>) -> io::Result<Self> {
+        io::stdout().flush()?;
+        let mut swapped = Self { stdin: None, stdout: None, feeder: None, collector: None };
+        if let Some(fd) = stdin {
+            swapped.stdin = Some(dup(io::stdin())?);
+            dup2_stdin(&fd)?;
+        }
+        if let Some(fd) = stdout {
+            swapped.stdout = Some(dup(io::stdout())?);
+            dup2_stdout(&fd)?;
+        }
+        Ok(swapped)
+    }
+
+    /// Puts the original standard input and output back and returns what was
+    /// collected from standard output.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.restore();
+        self.collector.take().and_then(|collector| collector.join().ok()).unwrap_or_default()
+    }
+
+    fn restore(&mut self) {
+        let _ = io::stdout().flush();
+        if let Some(stdout) = self.stdout.take() {
+            let _ = dup2_stdout(&stdout);
+        }
+        if let Some(stdin) = self.stdin.take() {
+            let _ = dup2_stdin(&stdin);
+        }
+        if let Some(feeder) = self.feeder.take() {
+            let _ = feeder.join();
+        }
+    }
+}
+
+impl Drop for SwappedStdio {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// The read end of a pipe that yields `input`, and the thread writing it. It's
+/// written from a thread so a builtin can read as it goes; one that stops reading
+/// early closes the pipe, which ends the write.
+pub fn feed(input: Vec<u8>) -> io::Result<(OwnedFd, JoinHandle<()>)> {
+    let (reader, mut writer) = io::pipe()?;
+    let feeder = std::thread::spawn(move || {
+        let _ = writer.write_all(&input);
+    });
+    Ok((reader.into(), feeder))
+}
+
+/// A thread reading `reader` to its end, which returns what it read.
+pub fn drain(mut reader: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = reader.read_to_end(&mut output);
+        output
+    })
+}
@@ -0,0 +1,177 @@
+use log::debug;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+/// How long the startup PATH scan may take before completion falls back to scanning on demand.
+const INDEX_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Names of the executables on a PATH, used for command completion.
+#[derive(Debug, Clone, Default)]
+pub struct CommandCache {
+    /// The PATH that was scanned; `None` until the first scan finishes
+    path_value: Option<String>,
+    /// Sorted, without duplicates
+    programs: Vec<String>,
+    /// PATH directories that could be read
+    directories: usize,
+    /// How long the scan took
+    build_time: Duration,
+}
+
+/// A `CommandCache` filled in by a background task and read by the line editor.
+pub type SharedCommandCache = Arc<RwLock<CommandCache>>;
+
+impl CommandCache {
+    /// Lists the executables in every directory of `path_var`.
+    pub fn scan(path_var: &str) -> Self {
+        let started = Instant::now();
+        #[cfg(windows)]
+        let path_separator = ";";
+        #[cfg(not(windows))]
+        let path_separator = ":";
+
+        #[cfg(windows)]
+        let executable_extensions = ["exe", "bat", "cmd", "com"];
+
+        let mut programs = Vec::new();
+        let mut directories = 0;
+        for path_dir in path_var.split(path_separator) {
+            if path_dir.is_empty() {
+                continue;
+            }
+
+            let Ok(entries) = Path::new(path_dir).read_dir() else {
+                continue;
+            };
+            directories += 1;
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+
+                #[cfg(windows)]
+                let name = {
+                    let is_executable = path
+                        .extension()
+                        .map(|ext| executable_extensions.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+                        .unwrap_or(false);
+                    // Leave off the extension for cleaner completion
+                    match path.file_stem() {
+                        Some(stem) if is_executable => stem.to_string_lossy().to_string(),
+                        _ => continue,
+                    }
+                };
+
+                #[cfg(not(windows))]
+                let name = {
+                    let is_executable = path.is_file()
+                        && entry.metadata().map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false);
+                    if !is_executable {
+                        continue;
+                    }
+                    entry.file_name().to_string_lossy().to_string()
+                };
+
+                programs.push(name);
+            }
+        }
+
+        programs.sort();
+        programs.dedup();
+        Self {
+            path_value: Some(path_var.to_string()),
+            programs,
+            directories,
+            build_time: started.elapsed(),
+        }
+    }
+
+    /// Whether this cache was built from `path_var`.
+    pub fn is_current(&self, path_var: &str) -> bool {
+        self.path_value.as_deref() == Some(path_var)
+    }
+
+    /// Programs whose names start with `prefix`, in order.
+    pub fn matching<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let start = self.programs.partition_point(|name| name.as_str() < prefix);
+        self.programs[start..]
+            .iter()
+            .take_while(move |name| name.starts_with(prefix))
+            .map(|name| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    /// Whether a scan has filled this cache yet.
+    pub fn is_ready(&self) -> bool {
+        self.path_value.is_some()
+    }
+
+    pub fn directories(&self) -> usize {
+        self.directories
+    }
+
+    pub fn build_time(&self) -> Duration {
+        self.build_time
+    }
+}
+
+/// Scans the current PATH on a background task and stores the result in `cache`.
+/// A scan that takes longer than a second is abandoned.
+pub fn spawn_indexer(cache: SharedCommandCache) {
+    let path_var = std::env::var("PATH").unwrap_or_default();
+    tokio::spawn(async move {
+        let scan = tokio::task::spawn_blocking(move || CommandCache::scan(&path_var));
+        match tokio::time::timeout(INDEX_TIMEOUT, scan).await {
+            Ok(Ok(scanned)) => {
+                debug!("Command cache populated with {} programs", scanned.len());
+                if let Ok(mut cache) = cache.write() {
+                    *cache = scanned;
+                }
+            }
+            Ok(Err(e)) => debug!("Command cache scan failed: {}", e),
+            Err(_) => debug!("Command cache scan timed out after {:?}", INDEX_TIMEOUT),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching() {
+        let cache = CommandCache {
+            path_value: Some("/bin".to_string()),
+            programs: vec!["cargo".to_string(), "cat".to_string(), "cd".to_string(), "git".to_string()],
+            ..CommandCache::default()
+        };
+        assert_eq!(cache.matching("ca").collect::<Vec<_>>(), vec!["cargo", "cat"]);
+        assert_eq!(cache.matching("z").count(), 0);
+        assert!(cache.is_current("/bin"));
+        assert!(!CommandCache::default().is_current(""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = dir.path().join("flexsh-tool");
+        std::fs::write(&tool, "").unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let path_var = format!("{}:/flexsh-missing-dir", dir.path().display());
+        let cache = CommandCache::scan(&path_var);
+        assert_eq!(cache.matching("").collect::<Vec<_>>(), vec!["flexsh-tool"]);
+        assert_eq!(cache.directories(), 1);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::config::Config;
+use crate::core::executor::Executor;
+use crate::core::history::History;
+use crate::core::options::ShellOptions;
+use crate::core::parser::{ParsedCommand, Parser};
+use crate::core::state::{CompletionSpec, ShellState};
+use crate::core::ShellContext;
+use std::path::PathBuf;
+
+/// What a completion function needs to run while the line editor has the terminal:
+/// a copy of the shell as it was when the prompt was drawn.
+pub struct CompletionSnapshot {
+    pub state: ShellState,
+    pub options: ShellOptions,
+    pub config: Config,
+    pub current_dir: PathBuf,
+}
+
+/// The words of `line` and the index of the one the cursor at `pos` is in, which is
+/// a new empty word after trailing whitespace.
+pub fn completion_words(line: &str, pos: usize) -> (Vec<String>, usize) {
+    let before = &line[..pos];
+    let mut words: Vec<String> = before.split_whitespace().map(String::from).collect();
+    if before.is_empty() || before.ends_with(char::is_whitespace) {
+        words.push(String::new());
+    }
+    let current = words.len() - 1;
+    // The rest of the word under the cursor isn't a word of its own
+    let after = &line[pos..];
+    let partial = usize::from(!after.starts_with(char::is_whitespace));
+    words.extend(after.split_whitespace().skip(partial).map(String::from));
+    (words, current)
+}
+
+impl CompletionSnapshot {
+    /// Runs the completion function registered for the command on `line` with
+    /// `complete -F` or `compdef`, if any, and returns the candidates it printed,
+    /// one per line.
+    ///
+    /// As in bash, `COMP_WORDS` holds the line's words (space-separated here) and
+    /// `COMP_CWORD` the index of the one being completed; the function's arguments
+    /// are the command, the current word and the word before it.
+    pub fn complete(&self, line: &str, pos: usize) -> Option<Vec<String>> {
+        let (words, current) = completion_words(line, pos);
+        let Some(CompletionSpec::Function(function)) = self.state.completion_specs.get(words.first()?) else {
+            return None;
+        };
+        if current == 0 || !self.state.functions.contains_key(function) {
+            return None;
+        }
+
+        let mut call = ParsedCommand::new(function.clone());
+        call.args = vec![
+            words[0].clone(),
+            words[current].clone(),
+            words[current - 1].clone(),
+        ];
+        let vars = [("COMP_WORDS", words.join(" ")), ("COMP_CWORD", current.to_string())];
+
+        let output = self.capture_output(call, &vars)?;
+        Some(output.lines().filter(|line| !line.is_empty()).map(String::from).collect())
+    }
+
+    /// Runs `command` in a forked child with `vars` set and its stdout sent down a
+    /// pipe, so the function can't change the shell and everything it prints is collected.
+    #[cfg(unix)]
+    fn capture_output(&self, command: ParsedCommand, vars: &[(&str, String)]) -> Option<String> {
+        use nix::sys::wait::waitpid;
+        use nix::unistd::{dup2_stdout, fork, pipe, ForkResult};
+        use std::io::{Read, Write};
+
+        std::io::stdout().flush().ok()?;
+        let (reader, writer) = pipe().ok()?;
+
+        // SAFETY: the child only runs shell code on a fresh thread and runtime, then exits
+        match unsafe { fork() }.ok()? {
+            ForkResult::Child => {
+                drop(reader);
+                for (name, value) in vars {
+                    std::env::set_var(name, value);
+                }
+                let code = match dup2_stdout(&writer) {
+                    Ok(()) => {
+                        drop(writer);
+                        self.run_in_child(command)
+                    }
+                    Err(_) => 1,
+                };
+                let _ = std::io::stdout().flush();
+                std::process::exit(code);
+            }
+            ForkResult::Parent { child } => {
+                drop(writer);
+                let mut output = String::new();
+                let _ = std::fs::File::from(reader).read_to_string(&mut output);
+                waitpid(child, None).ok()?;
+                Some(output)
+            }
+        }
+    }
+
+    /// Completion functions need `fork` to run in isolation, so they aren't supported here.
+    #[cfg(not(unix))]
+    fn capture_output(&self, _command: ParsedCommand, _vars: &[(&str, String)]) -> Option<String> {
+        None
+    }
+
+    #[cfg(unix)]
+    fn run_in_child(&self, command: ParsedCommand) -> i32 {
+        let mut state = self.state.clone();
+        let mut options = self.options.clone();
+        let mut config = self.config.clone();
+        let mut current_dir = self.current_dir.clone();
+        let mut parser = Parser::new();
+        let mut history = History::in_memory(config.get().history.clone());
+
+        let result = std::thread::scope(|scope| {
+            scope
+                .spawn(|| -> anyhow::Result<i32> {
+                    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+                    let mut ctx = ShellContext {
+                        current_dir: &mut current_dir,
+                        parser: &mut parser,
+                        config: &mut config,
+                        options: &mut options,
+                        state: &mut state,
+                        history: &mut history,
+                        in_condition: false,
+                    };
+                    runtime.block_on(Executor::new().execute(command, &mut ctx))
+                })
+                .join()
+        });
+        match result {
+            Ok(Ok(code)) => code,
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::parser::CompoundCommand;
+
+    #[test]
+    fn test_completion_words() {
+        assert_eq!(completion_words("git ch", 6), (vec!["git".to_string(), "ch".to_string()], 1));
+        assert_eq!(completion_words("git ", 4), (vec!["git".to_string(), String::new()], 1));
+        let (words, current) = completion_words("cp a b", 4);
+        assert_eq!((words.len(), current), (3, 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_complete_with_function() {
+        let definition = Parser::new()
+            // An external command, as the test harness captures what builtins print
+            .parse("complete_mycmd() { printf '%s\\n' foo bar baz \"[$2]\"; }")
+            .unwrap();
+        let Some(CompoundCommand::FunctionDef { name, body }) = definition.compound.as_deref() else {
+            panic!("expected a function definition");
+        };
+
+        let mut state = ShellState::default();
+        state.functions.insert(name.clone(), body.clone());
+        state.completion_specs.insert("mycmd".to_string(), CompletionSpec::Function(name.clone()));
+        let snapshot = CompletionSnapshot {
+            state,
+            options: ShellOptions::default(),
+            config: Config::new(None, None).unwrap(),
+            current_dir: std::env::current_dir().unwrap(),
+        };
+
+        let candidates = snapshot.complete("mycmd --flag b", 14).unwrap();
+        assert_eq!(candidates, vec!["foo", "bar", "baz", "[b]"]);
+        assert_eq!(snapshot.complete("other b", 7), None);
+        assert_eq!(snapshot.complete("mycm", 4), None);
+    }
+}
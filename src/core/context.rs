@@ -0,0 +1,52 @@
+use crate::config::Config;
+use crate::core::history::History;
+use crate::core::options::ShellOptions;
+use crate::core::parser::Parser;
+use crate::core::state::ShellState;
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+/// Mutable view of the shell state that builtins are allowed to touch.
+pub struct ShellContext<'a> {
+    pub current_dir: &'a mut PathBuf,
+    pub parser: &'a mut Parser,
+    pub config: &'a mut Config,
+    pub options: &'a mut ShellOptions,
+    pub state: &'a mut ShellState,
+    pub history: &'a mut History,
+    /// Set while running a command whose status is being tested, which `set -e` ignores
+    pub in_condition: bool,
+}
+
+/// The view plugins get, without the shell's internals.
+impl ShellContext<'_> {
+    /// The shell's working directory.
+    pub fn working_dir(&self) -> &Path {
+        self.current_dir
+    }
+
+    /// The value of the shell variable `name`, if it's set.
+    pub fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    /// True if `name` can't be changed: it was marked `readonly`, or this is a
+    /// restricted shell and it's one of the variables that protects.
+    pub fn is_readonly(&self, name: &str) -> bool {
+        self.state.readonly_vars.contains(name) || self.options.is_protected_variable(name)
+    }
+
+    /// Sets the shell variable `name`, unless it's one the shell protects.
+    pub fn set_var(&mut self, name: &str, value: &str) -> Result<()> {
+        if self.is_readonly(name) {
+            bail!("{}: readonly variable", name);
+        }
+        std::env::set_var(name, value);
+        Ok(())
+    }
+
+    /// The exit status of the last command, `$?`.
+    pub fn last_exit(&self) -> i32 {
+        self.state.special.last_exit
+    }
+}
@@ -0,0 +1,91 @@
+use thiserror::Error;
+
+/// Raised by the `exit` builtin; unwinds to the shell, which stops with this status.
+#[derive(Debug, Error)]
+#[error("exit {0}")]
+pub struct ExitRequest(pub i32);
+
+/// Raised when `set -e` is on and a command fails outside a tested context.
+#[derive(Debug, Error)]
+#[error("exiting: command failed with status {0} (set -e)")]
+pub struct ErrExit(pub i32);
+
+/// Raised by the `return` builtin; unwinds to the function call, which reports this status.
+#[derive(Debug, Error)]
+#[error("return {0}")]
+pub struct ReturnValue(pub i32);
+
+/// Raised by `break` and `continue`; unwinds to the loop the given number of levels out.
+#[derive(Debug, Error)]
+pub enum LoopControl {
+    #[error("break")]
+    Break(usize),
+    #[error("continue")]
+    Continue(usize),
+}
+
+/// The input ended inside a construct that needs more lines, e.g. an unclosed `{`.
+#[derive(Debug, Error)]
+#[error("syntax error: unexpected end of file (expecting `{0}')")]
+pub struct IncompleteInput(pub String);
+
+/// The input ended inside a quoted string or right after a line-continuation `\`.
+#[derive(Debug, Error)]
+pub enum UnterminatedInput {
+    #[error("Unterminated quote")]
+    Quote,
+    #[error("unexpected end of file after `\\'")]
+    Continuation,
+}
+
+/// A syntax error more input can't fix, at the 1-based line and column of the
+/// offending character.
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct ParseError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+/// Raised under `set -u` when an unset variable is expanded; aborts the rest of the command list.
+#[derive(Debug, Error)]
+#[error("{0}: unbound variable")]
+pub struct UnboundVariable(pub String);
+
+#[derive(Debug, Error)]
+#[error("program not found: {0}")]
+pub struct CommandNotFound(pub String);
+
+/// True for errors that unwind the shell's control flow rather than report a failure.
+pub fn is_control_flow(error: &anyhow::Error) -> bool {
+    error.is::<ExitRequest>()
+        || error.is::<ErrExit>()
+        || error.is::<ReturnValue>()
+        || error.is::<LoopControl>()
+        || error.is::<UnboundVariable>()
+}
+
+/// True for parse errors that more input lines could fix; scripts and the interactive
+/// prompt read another line and parse again.
+pub fn needs_more_input(error: &anyhow::Error) -> bool {
+    error.is::<IncompleteInput>() || error.is::<UnterminatedInput>()
+}
+
+/// The exit status a failed command should report: 127 when it wasn't found, 1 otherwise.
+pub fn failure_status(error: &anyhow::Error) -> i32 {
+    if error.is::<CommandNotFound>() {
+        127
+    } else {
+        1
+    }
+}
+
+/// An I/O error worded as the system tools print it, without Rust's `(os error N)` suffix.
+pub fn io_error_message(error: &std::io::Error) -> String {
+    let message = error.to_string();
+    match message.find(" (os error") {
+        Some(end) => message[..end].to_string(),
+        None => message,
+    }
+}
@@ -1,80 +1,548 @@
-use crate::core::parser::ParsedCommand;
+use crate::core::errors::{self, CommandNotFound, ErrExit, LoopControl, ReturnValue};
+use crate::core::jobs::{JobNotification, JobTable};
+use crate::core::parser::{ArrayAssignment, CaseArm, CaseTerminator, ChainOp, CompoundCommand, ParsedCommand};
+use crate::core::options::ShellOptions;
+use crate::core::ShellContext;
+#[cfg(unix)]
+use crate::core::builtin_stdio::{self, SwappedStdio};
 use crate::builtins::{self, BuiltinCommand};
-use anyhow::Result;
+use crate::utils::{glob_expand, param_expand};
+use crate::utils::quote::shell_quote;
+use anyhow::{anyhow, Result};
 use log::debug;
 use std::collections::HashMap;
+#[cfg(unix)]
+use std::os::fd::OwnedFd;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+#[cfg(unix)]
+use std::thread::JoinHandle;
 use ctrlc;
-use tokio::process::{Child, Command as TokioCommand};
+use tokio::process::Command as TokioCommand;
 use tokio::fs::File;
+use tokio::io::AsyncReadExt;
 use tokio::signal;
 
 pub struct Executor {
-	background_processes: HashMap<u32, Child>,
+	jobs: JobTable,
 	interrupt_flag: Arc<AtomicBool>,
+	/// Command substitution nesting level; sets the number of `+` in xtrace output
+	subst_depth: usize,
+	/// PATH lookups by program name; `None` records a program that wasn't found
+	path_cache: HashMap<String, Option<PathBuf>>,
+	/// The PATH the cache was built from; a different PATH invalidates it
+	cached_path_value: String,
+	/// Process ID of the job the last command started with `&`, until `$!` takes it
+	spawned_pid: Option<u32>,
+	/// Standard output collected for `execute_capturing`; `None` while output goes
+	/// where it normally would
+	captured: Option<Vec<u8>>,
+}
+
+impl Default for Executor {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Executor {
 	pub fn new() -> Self {
 		let interrupt_flag = Arc::new(AtomicBool::new(false));
 		Self {
-			background_processes: HashMap::new(),
+			jobs: JobTable::new(),
 			interrupt_flag,
+			subst_depth: 0,
+			path_cache: HashMap::new(),
+			cached_path_value: String::new(),
+			spawned_pid: None,
+			captured: None,
 		}
 	}
 
-	fn resolve_program_path(&self, program_name: &str) -> Option<PathBuf> {
+	/// Finds a program, consulting the PATH cache before searching directories.
+	fn resolve_program_path(&mut self, program_name: &str) -> Option<PathBuf> {
 		debug!("Resolving program path for: '{}'", program_name);
 		if program_name.contains('/') || program_name.contains('\\') {
 			let path = Path::new(program_name);
 			return if path.exists() { Some(path.to_path_buf()) } else { None };
 		}
-		if let Ok(path_var) = std::env::var("PATH") {
-			#[cfg(windows)]
-			let path_separator = ";";
-			#[cfg(not(windows))]
-			let path_separator = ":";
-			#[cfg(windows)]
-			let executable_extensions = vec!["exe", "bat", "cmd", "com"];
-			for path_dir in path_var.split(path_separator) {
-				if path_dir.is_empty() { continue; }
-				let dir_path = Path::new(path_dir);
-				if !dir_path.exists() || !dir_path.is_dir() { continue; }
-				#[cfg(windows)] {
-					let exe_candidate = dir_path.join(format!("{}.exe", program_name));
-					if exe_candidate.exists() && exe_candidate.is_file() {
-						return Some(exe_candidate);
-					}
-					for ext in &executable_extensions {
-						if ext == &"exe" { continue; }
-						let candidate_with_ext = dir_path.join(format!("{}.{}", program_name, ext));
-						if candidate_with_ext.exists() && candidate_with_ext.is_file() {
-							return Some(candidate_with_ext);
-						}
-					}
+
+		let path_var = std::env::var("PATH").unwrap_or_default();
+		if path_var != self.cached_path_value {
+			self.path_cache.clear();
+			self.cached_path_value = path_var;
+		}
+		match self.path_cache.get(program_name) {
+			// A cached program that has since been removed is looked up again
+			Some(Some(path)) if path.is_file() => return Some(path.clone()),
+			Some(None) => return None,
+			_ => {}
+		}
+
+		let resolved = search_path(program_name, &self.cached_path_value);
+		self.path_cache.insert(program_name.to_string(), resolved.clone());
+		resolved
+	}
+
+	/// Forgets every cached PATH lookup (`hash -r`).
+	pub fn clear_path_cache(&mut self) {
+		self.path_cache.clear();
+	}
+
+	/// Programs found through the PATH cache, sorted by name.
+	pub fn cached_paths(&self) -> Vec<(&str, &Path)> {
+		let mut paths: Vec<(&str, &Path)> = self
+			.path_cache
+			.iter()
+			.filter_map(|(name, path)| Some((name.as_str(), path.as_deref()?)))
+			.collect();
+		paths.sort();
+		paths
+	}
+
+	/// Resolves `program_name` through the PATH cache, adding it if needed (`hash name`).
+	pub fn remember_program(&mut self, program_name: &str) -> Option<PathBuf> {
+		self.resolve_program_path(program_name)
+	}
+
+	/// Runs `command` as `execute` does, but returns what it writes to standard output
+	/// instead of printing it. External commands write to a pipe; builtins and
+	/// subshells have the shell's standard output swapped for one, which isn't
+	/// possible on Windows, where their output is printed as usual.
+	pub async fn execute_capturing(&mut self, command: ParsedCommand, ctx: &mut ShellContext<'_>) -> Result<(i32, String)> {
+		let outer = self.captured.replace(Vec::new());
+		let result = self.execute(command, ctx).await;
+		let output = std::mem::replace(&mut self.captured, outer).unwrap_or_default();
+		Ok((result?, String::from_utf8_lossy(&output).into_owned()))
+	}
+
+	/// Runs a command together with any `;`/`&&`/`||` list it heads.
+	pub async fn execute(&mut self, mut command: ParsedCommand, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		debug!("Executing command: {:?}", command);
+		let chain = std::mem::take(&mut command.chain);
+		if chain.is_empty() {
+			let status = self.execute_pipeline_unit(command, ctx).await?;
+			ctx.state.special.last_exit = status;
+			return self.check_errexit(status, false, ctx);
+		}
+
+		let mut commands = vec![(ChainOp::Sequence, command)];
+		commands.extend(chain);
+		let count = commands.len();
+		let ops: Vec<ChainOp> = commands.iter().map(|(op, _)| *op).collect();
+
+		let mut status = 0;
+		for (i, (op, command)) in commands.into_iter().enumerate() {
+			let skip = match op {
+				ChainOp::And => status != 0,
+				ChainOp::Or => status == 0,
+				ChainOp::Sequence => false,
+			};
+			if skip {
+				continue;
+			}
+
+			// A command whose status decides whether the next one runs is "tested"
+			let tested = matches!(ops.get(i + 1), Some(ChainOp::And | ChainOp::Or));
+			status = match self.execute_pipeline_unit(command, ctx).await {
+				Ok(code) => code,
+				Err(e) if i + 1 < count && !errors::is_control_flow(&e) => {
+					eprintln!("flex-sh: {}", e);
+					errors::failure_status(&e)
 				}
-				#[cfg(not(windows))] {
-					let candidate = dir_path.join(program_name);
-					if candidate.exists() && candidate.is_file() {
-						return Some(candidate);
+				Err(e) => return Err(e),
+			};
+			ctx.state.special.last_exit = status;
+			status = self.check_errexit(status, tested, ctx)?;
+		}
+		Ok(status)
+	}
+
+	/// Turns a failure into `ErrExit` when `set -e` applies to it.
+	fn check_errexit(&self, status: i32, tested: bool, ctx: &ShellContext<'_>) -> Result<i32> {
+		if status != 0 && ctx.options.exit_on_error && !tested && !ctx.in_condition {
+			return Err(ErrExit(status).into());
+		}
+		Ok(status)
+	}
+
+	/// Runs one pipeline (or builtin) without looking at its `chain`.
+	async fn execute_pipeline_unit(&mut self, mut command: ParsedCommand, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		if let Some(compound) = command.compound.take() {
+			return self.execute_compound(*compound, ctx).await;
+		}
+
+		expand_arguments(&mut command, ctx)?;
+		for piped in &mut command.pipes {
+			expand_arguments(piped, ctx)?;
+		}
+		#[cfg(not(unix))]
+		if std::iter::once(&command).chain(command.pipes.iter()).any(|c| c.input_fd.is_some() || c.output_fd.is_some()) {
+			return Err(anyhow!("<& and >& redirections aren't supported on Windows"));
+		}
+
+		if ctx.options.xtrace {
+			let prefix = "+".repeat(self.subst_depth + 1);
+			for traced in std::iter::once(&command).chain(command.pipes.iter()) {
+				eprintln!("{} {}", prefix, format_xtrace(traced));
+			}
+		}
+		if ctx.options.restricted {
+			if let Some(message) = std::iter::once(&command).chain(command.pipes.iter()).find_map(|c| restriction(c, ctx.options)) {
+				eprintln!("flex-sh: {}", message);
+				return Ok(1);
+			}
+		}
+		if let Some(name) = readonly_assignment(&command, ctx) {
+			eprintln!("flex-sh: {}: readonly variable", name);
+			return Ok(1);
+		}
+		if command.program.is_empty() {
+			for assignment in std::mem::take(&mut command.array_assignments) {
+				assign_array(assignment, ctx)?;
+			}
+			// Bare `NAME=value` words set shell variables, or an array's first element
+			for (key, value) in &command.environment {
+				match ctx.state.arrays.get_mut(key) {
+					Some(elements) if elements.is_empty() => elements.push(value.clone()),
+					Some(elements) => elements[0] = value.clone(),
+					None => std::env::set_var(key, value),
+				}
+			}
+			return Ok(0);
+		}
+		if let Some(body) = ctx.state.functions.get(&command.program).cloned() {
+			return self.call_function(body, command.args, ctx).await;
+		}
+		#[cfg(unix)]
+		if !command.pipes.is_empty() && is_builtin_pipeline(&command, ctx) {
+			return self.execute_builtin_pipeline(command, ctx).await;
+		}
+		if command.pipes.is_empty() {
+			if let Some(builtin) = builtins::get_builtin(&command.program) {
+				return self.execute_builtin(builtin, &command, ctx).await;
+			}
+			let status = self.execute_single_command(command, ctx.current_dir).await?;
+			if let Some(pid) = self.spawned_pid.take() {
+				ctx.state.special.last_bg_pid = Some(pid);
+			}
+			Ok(status)
+		} else {
+			self.execute_pipeline(command, ctx).await
+		}
+	}
+
+	async fn execute_compound(&mut self, compound: CompoundCommand, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		match compound {
+			CompoundCommand::FunctionDef { name, body } => {
+				ctx.state.functions.insert(name, body);
+				Ok(0)
+			}
+			CompoundCommand::Group(body) => self.execute_body(body, ctx).await,
+			CompoundCommand::Subshell(body) => self.execute_subshell(body, ctx).await,
+			CompoundCommand::Conditional { condition, then_body, elif_branches, else_body } => {
+				self.execute_conditional(condition, then_body, elif_branches, else_body, ctx).await
+			}
+			CompoundCommand::Case { word, arms } => self.execute_case(word, arms, ctx).await,
+			CompoundCommand::WhileLoop { condition, body, negate } => {
+				ctx.state.loop_depth += 1;
+				let result = self.execute_while(condition, body, negate, ctx).await;
+				ctx.state.loop_depth -= 1;
+				result
+			}
+			CompoundCommand::ForLoop { var, items, body } => {
+				ctx.state.loop_depth += 1;
+				let result = self.execute_for(var, items, body, ctx).await;
+				ctx.state.loop_depth -= 1;
+				result
+			}
+			CompoundCommand::Coproc { name, body } => self.start_coproc(name, body, ctx).await,
+		}
+	}
+
+	/// Runs the arm whose pattern matches `word`, continuing into later arms as
+	/// their `;&`/`;;&` terminators ask. The status is 0 if no arm ran.
+	async fn execute_case(&mut self, word: String, arms: Vec<CaseArm>, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		let word = param_expand::expand_text(&word, ctx.state, ctx.options.unbound_error)?;
+		let mut status = 0;
+		let mut fall_through = false;
+		for arm in arms {
+			if !fall_through {
+				let mut matched = false;
+				for pattern in &arm.patterns {
+					let pattern = param_expand::expand_text(pattern, ctx.state, ctx.options.unbound_error)?;
+					if case_matches(&pattern, &word) {
+						matched = true;
+						break;
 					}
 				}
+				if !matched {
+					continue;
+				}
+			}
+			status = self.execute_body(arm.body, ctx).await?;
+			match arm.terminator {
+				CaseTerminator::Break => break,
+				CaseTerminator::FallThrough => fall_through = true,
+				CaseTerminator::Continue => fall_through = false,
 			}
 		}
-		None
+		Ok(status)
 	}
 
-	pub async fn execute(&mut self, command: ParsedCommand, current_dir: &mut PathBuf, parser: &mut crate::core::parser::Parser) -> Result<i32> {
-		debug!("Executing command: {:?}", command);
-		if let Some(builtin) = builtins::get_builtin(&command.program) {
-			return self.execute_builtin(builtin, &command, current_dir, parser).await;
+	/// Runs `body` while the condition succeeds (or, with `negate`, until it does).
+	/// The status is that of the last body command run, 0 if the body never ran.
+	async fn execute_while(
+		&mut self,
+		condition: Vec<ParsedCommand>,
+		body: Vec<ParsedCommand>,
+		negate: bool,
+		ctx: &mut ShellContext<'_>,
+	) -> Result<i32> {
+		let mut status = 0;
+		loop {
+			let succeeded = self.execute_condition(condition.clone(), ctx).await? == 0;
+			if succeeded == negate {
+				return Ok(status);
+			}
+			match loop_iteration(self.execute_body(body.clone(), ctx).await)? {
+				Iteration::Next(code) => status = code,
+				Iteration::Break(code) => return Ok(code),
+			}
 		}
-		if command.pipes.is_empty() {
-			self.execute_single_command(command, current_dir).await
-		} else {
-			self.execute_pipeline(command, current_dir).await
+	}
+
+	/// Runs `body` once per item, with `var` set to the item. Unquoted items
+	/// containing glob characters expand to the matching paths.
+	async fn execute_for(
+		&mut self,
+		var: String,
+		items: Vec<String>,
+		body: Vec<ParsedCommand>,
+		ctx: &mut ShellContext<'_>,
+	) -> Result<i32> {
+		let values = expand_words(&items, ctx)?;
+
+		let mut status = 0;
+		for value in values {
+			std::env::set_var(&var, value);
+			match loop_iteration(self.execute_body(body.clone(), ctx).await)? {
+				Iteration::Next(code) => status = code,
+				Iteration::Break(code) => return Ok(code),
+			}
+		}
+		Ok(status)
+	}
+
+	/// Runs the body of the first branch whose condition succeeds; 0 if none does.
+	async fn execute_conditional(
+		&mut self,
+		condition: Vec<ParsedCommand>,
+		then_body: Vec<ParsedCommand>,
+		elif_branches: Vec<(Vec<ParsedCommand>, Vec<ParsedCommand>)>,
+		else_body: Option<Vec<ParsedCommand>>,
+		ctx: &mut ShellContext<'_>,
+	) -> Result<i32> {
+		let branches = std::iter::once((condition, then_body)).chain(elif_branches);
+		for (condition, body) in branches {
+			if self.execute_condition(condition, ctx).await? == 0 {
+				return self.execute_body(body, ctx).await;
+			}
+		}
+		match else_body {
+			Some(body) => self.execute_body(body, ctx).await,
+			None => Ok(0),
+		}
+	}
+
+	/// Runs a condition list, whose failure `set -e` must not treat as fatal.
+	async fn execute_condition(&mut self, condition: Vec<ParsedCommand>, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		let outer = std::mem::replace(&mut ctx.in_condition, true);
+		let result = self.execute_body(condition, ctx).await;
+		ctx.in_condition = outer;
+		result
+	}
+
+	/// Runs the commands of a compound body in order, returning the last status.
+	async fn execute_body(&mut self, body: Vec<ParsedCommand>, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		let mut status = 0;
+		for command in body {
+			// Boxed because bodies can nest (and functions can recurse) without bound
+			status = match Box::pin(self.execute(command, ctx)).await {
+				Ok(code) => code,
+				Err(e) if !errors::is_control_flow(&e) => {
+					eprintln!("flex-sh: {}", e);
+					errors::failure_status(&e)
+				}
+				Err(e) => return Err(e),
+			};
+		}
+		Ok(status)
+	}
+
+	/// Runs a subshell body in a forked child and waits for it.
+	#[cfg(unix)]
+	async fn execute_subshell(&mut self, body: Vec<ParsedCommand>, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		use nix::sys::wait::{waitpid, WaitStatus};
+		use nix::unistd::{fork, ForkResult};
+		use std::io::Write;
+
+		// Anything still buffered would otherwise be written by both processes
+		std::io::stdout().flush()?;
+		std::io::stderr().flush()?;
+		// The child writes to the swapped standard output rather than collecting
+		// output it couldn't hand back
+		let stdio = self.captured.is_some().then(|| SwappedStdio::new(None, true)).transpose()?;
+
+		// SAFETY: the child only runs shell code on a fresh thread and runtime, then exits
+		match unsafe { fork() }? {
+			ForkResult::Child => self.run_forked(body, ctx),
+			ForkResult::Parent { child } => {
+				let status = tokio::task::spawn_blocking(move || waitpid(child, None)).await??;
+				if let Some(stdio) = stdio {
+					self.collect(stdio.finish());
+				}
+				Ok(match status {
+					WaitStatus::Exited(_, code) => code,
+					WaitStatus::Signaled(_, signal, _) => 128 + signal as i32,
+					_ => 1,
+				})
+			}
+		}
+	}
+
+	/// Runs `body` in a child made by `fork`, then exits with its status. The child gets
+	/// its own single-threaded runtime, as the parent's worker threads don't survive `fork`.
+	#[cfg(unix)]
+	fn run_forked(&mut self, body: Vec<ParsedCommand>, ctx: &mut ShellContext<'_>) -> ! {
+		use std::io::Write;
+
+		self.captured = None;
+		let result = std::thread::scope(|scope| {
+			scope
+				.spawn(|| -> Result<i32> {
+					let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+					runtime.block_on(self.execute_body(body, ctx))
+				})
+				.join()
+		});
+		let code = match result {
+			Ok(result) => subshell_status(result),
+			Err(_) => 1,
+		};
+		let _ = std::io::stdout().flush();
+		std::process::exit(code);
+	}
+
+	/// Starts `body` as the coprocess `name`: a forked child whose standard input and
+	/// output are pipes the shell keeps. `NAME[0]` is the descriptor that reads its
+	/// output, `NAME[1]` the one that writes its input, and `NAME_PID` its process ID.
+	/// It runs until it exits by itself, which for most commands means until
+	/// `coproc -c NAME` closes its input.
+	#[cfg(unix)]
+	pub async fn start_coproc(&mut self, name: String, body: Vec<ParsedCommand>, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		use crate::core::state::Coproc;
+		use nix::sys::wait::waitpid;
+		use nix::unistd::{dup2_stdin, dup2_stdout, fork, ForkResult};
+		use std::io::Write;
+		use std::os::fd::{AsRawFd, OwnedFd};
+
+		if ctx.is_readonly(&name) {
+			eprintln!("flex-sh: {}: readonly variable", name);
+			return Ok(1);
+		}
+		// These pipes are close-on-exec, so commands run later don't hold them open
+		let (coproc_stdin, input) = std::io::pipe()?;
+		let (output, coproc_stdout) = std::io::pipe()?;
+		std::io::stdout().flush()?;
+		std::io::stderr().flush()?;
+
+		// SAFETY: as for execute_subshell
+		match unsafe { fork() }? {
+			ForkResult::Child => {
+				// The coprocess would never see the end of its input while it held
+				// the shell's end of the pipe, or of another coprocess's
+				drop((input, output));
+				ctx.state.coprocs.clear();
+				if dup2_stdin(&coproc_stdin).is_err() || dup2_stdout(&coproc_stdout).is_err() {
+					std::process::exit(1);
+				}
+				drop((coproc_stdin, coproc_stdout));
+				self.run_forked(body, ctx)
+			}
+			ForkResult::Parent { child } => {
+				drop((coproc_stdin, coproc_stdout));
+				// Nothing waits for a coprocess, so it's reaped when it exits
+				std::thread::spawn(move || waitpid(child, None));
+
+				let output = OwnedFd::from(output);
+				let input = OwnedFd::from(input);
+				let pid = child.as_raw() as u32;
+				std::env::remove_var(&name);
+				ctx.state.arrays.insert(name.clone(), vec![output.as_raw_fd().to_string(), input.as_raw_fd().to_string()]);
+				std::env::set_var(format!("{}_PID", name), pid.to_string());
+				ctx.state.special.last_bg_pid = Some(pid);
+				// Replacing a coprocess of the same name closes the shell's ends of its pipes
+				ctx.state.coprocs.insert(name, Coproc {
+					pid,
+					output: Arc::new(output),
+					input: Some(Arc::new(input)),
+				});
+				Ok(0)
+			}
+		}
+	}
+
+	/// Coprocesses need `fork`, which Windows doesn't have.
+	#[cfg(not(unix))]
+	pub async fn start_coproc(&mut self, _name: String, _body: Vec<ParsedCommand>, _ctx: &mut ShellContext<'_>) -> Result<i32> {
+		Err(anyhow!("coproc: coprocesses aren't supported on Windows"))
+	}
+
+	/// Without `fork`, the body runs in this process and everything it could change
+	/// (variables, directory, functions, options) is put back afterwards.
+	#[cfg(not(unix))]
+	async fn execute_subshell(&mut self, body: Vec<ParsedCommand>, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		let saved_vars: Vec<(String, String)> = std::env::vars().collect();
+		let saved_dir = ctx.current_dir.clone();
+		let saved_process_dir = std::env::current_dir().ok();
+		let saved_state = ctx.state.clone();
+		let saved_options = ctx.options.clone();
+
+		let result = self.execute_body(body, ctx).await;
+
+		for (key, _) in std::env::vars() {
+			std::env::remove_var(key);
+		}
+		for (key, value) in saved_vars {
+			std::env::set_var(key, value);
+		}
+		*ctx.current_dir = saved_dir;
+		if let Some(dir) = saved_process_dir {
+			let _ = std::env::set_current_dir(dir);
+		}
+		*ctx.state = saved_state;
+		*ctx.options = saved_options;
+		Ok(subshell_status(result))
+	}
+
+	/// Runs a function body with `args` as its positional parameters.
+	async fn call_function(&mut self, body: Vec<ParsedCommand>, args: Vec<String>, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		let saved_positional = std::mem::replace(&mut ctx.state.positional, args);
+		ctx.state.function_depth += 1;
+		ctx.state.push_scope();
+		let result = self.execute_body(body, ctx).await;
+		ctx.state.pop_scope();
+		ctx.state.function_depth -= 1;
+		ctx.state.positional = saved_positional;
+
+		match result {
+			Err(e) => match e.downcast_ref::<ReturnValue>() {
+				Some(ReturnValue(code)) => Ok(*code),
+				None => Err(e),
+			},
+			status => status,
 		}
 	}
 
@@ -82,17 +550,67 @@ impl Executor {
 		&mut self,
 		builtin: Box<dyn BuiltinCommand>,
 		command: &ParsedCommand,
-		current_dir: &mut PathBuf,
-		parser: &mut crate::core::parser::Parser,
+		ctx: &mut ShellContext<'_>,
 	) -> Result<i32> {
-		builtin.execute(command, current_dir, &mut self.background_processes, parser).await
+		#[cfg(unix)]
+		if command.input_fd.is_some() || command.output_fd.is_some() {
+			let stdin = command.input_fd.as_deref().map(duplicate_fd).transpose()?;
+			let stdout = command.output_fd.as_deref().map(duplicate_fd).transpose()?;
+			let stdio = SwappedStdio::redirected(stdin, stdout)?;
+			let result = builtin.execute(command, self, ctx).await;
+			stdio.finish();
+			return result;
+		}
+		#[cfg(unix)]
+		if self.captured.is_some() {
+			let stdio = SwappedStdio::new(None, true)?;
+			let result = builtin.execute(command, self, ctx).await;
+			self.collect(stdio.finish());
+			return result;
+		}
+		builtin.execute(command, self, ctx).await
+	}
+
+	/// Adds `output` to what `execute_capturing` is collecting.
+	fn collect(&mut self, output: Vec<u8>) {
+		if let Some(captured) = self.captured.as_mut() {
+			captured.extend(output);
+		}
 	}
 
+	/// Runs a pipeline of builtins in the shell process rather than starting a process
+	/// for each: every builtin but the last has its output collected, and the next
+	/// reads it as its input. The status is as for `execute_pipeline`.
+	#[cfg(unix)]
+	async fn execute_builtin_pipeline(&mut self, mut command: ParsedCommand, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		let pipes = std::mem::take(&mut command.pipes);
+		let stages: Vec<ParsedCommand> = std::iter::once(command).chain(pipes).collect();
+		let last = stages.len() - 1;
+		let mut input = None;
+		let mut codes = Vec::with_capacity(stages.len());
+		for (i, stage) in stages.iter().enumerate() {
+			let builtin = builtins::get_builtin(&stage.program).ok_or_else(|| CommandNotFound(stage.program.clone()))?;
+			let stdio = SwappedStdio::new(input.take(), i < last || self.captured.is_some())?;
+			let result = builtin.execute(stage, self, ctx).await;
+			let output = stdio.finish();
+			if i < last {
+				input = Some(output);
+			} else {
+				self.collect(output);
+			}
+			codes.push(result?);
+		}
+		Ok(pipeline_status(&codes, ctx.options.pipefail))
+	}
+
+	/// Runs an external command. Foreground commands share the shell's terminal
+	/// rather than a pseudo-terminal, so the kernel sends them `SIGWINCH` on resize
+	/// and they read the new size from the terminal themselves.
 	async fn execute_single_command(&mut self, command: ParsedCommand, current_dir: &PathBuf) -> Result<i32> {
 		let program_path = if let Some(resolved_path) = self.resolve_program_path(&command.program) {
 			resolved_path
 		} else {
-			return Err(anyhow::anyhow!("program not found: {}", command.program));
+			return Err(CommandNotFound(command.program.clone()).into());
 		};
 		let mut cmd = {
 			#[cfg(windows)] {
@@ -122,6 +640,12 @@ impl Executor {
 			}
 		};
 		cmd.current_dir(current_dir);
+		if command.clear_environment {
+			cmd.env_clear();
+		}
+		for name in &command.unset_environment {
+			cmd.env_remove(name);
+		}
 		for (key, value) in &command.environment {
 			cmd.env(key, value);
 		}
@@ -129,28 +653,35 @@ impl Executor {
 			let file = File::open(input_file).await?;
 			cmd.stdin(Stdio::from(file.into_std().await));
 		}
-		if let Some(output_file) = &command.output_redirect {
-			let file = File::create(output_file).await?;
-			cmd.stdout(Stdio::from(file.into_std().await));
-		} else if let Some(append_file) = &command.append_redirect {
-			let file = tokio::fs::OpenOptions::new()
-				.create(true)
-				.append(true)
-				.open(append_file)
-				.await?;
-			cmd.stdout(Stdio::from(file.into_std().await));
+		let (stdout, stderr) = open_output_redirects(&command).await?;
+		if let Some(file) = stdout {
+			cmd.stdout(Stdio::from(file));
+		} else if self.captured.is_some() && !command.background {
+			cmd.stdout(Stdio::piped());
+		}
+		if let Some(file) = stderr {
+			cmd.stderr(Stdio::from(file));
+		}
+		#[cfg(unix)]
+		if let Some(fd) = &command.input_fd {
+			cmd.stdin(Stdio::from(duplicate_fd(fd)?));
+		}
+		#[cfg(unix)]
+		if let Some(fd) = &command.output_fd {
+			cmd.stdout(Stdio::from(duplicate_fd(fd)?));
 		}
 		if command.background {
 			let child = cmd.spawn()?;
 			let pid = child.id().unwrap_or(0);
-			self.background_processes.insert(pid, child);
-			println!("[{}] {}", self.background_processes.len(), pid);
+			let id = self.jobs.spawn(child, format_xtrace(&command));
+			println!("[{}] {}", id, pid);
+			self.spawned_pid = Some(pid);
 			return Ok(0);
 		}
 		let mut child = cmd.spawn()?;
 		let child_id = child.id().unwrap_or(0);
 		let res = tokio::select! {
-			status = child.wait() => {
+			status = wait_capturing(&mut child, self.captured.as_mut()) => {
 				status?.code().unwrap_or(-1)
 			}
 			_ = signal::ctrl_c() => {
@@ -169,106 +700,564 @@ impl Executor {
 		Ok(res)
 	}
 
-	async fn execute_pipeline(&mut self, mut command: ParsedCommand, current_dir: &PathBuf) -> Result<i32> {
+	/// Runs `a | b | c`. The status is that of the last command, or with `pipefail`
+	/// the rightmost non-zero one. Builtins run in the shell once the programs have
+	/// started, one after another, on pipes to their neighbours; output headed for a
+	/// builtin after another builtin is held until its turn.
+	async fn execute_pipeline(&mut self, command: ParsedCommand, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		let current_dir = ctx.current_dir.clone();
 		if command.pipes.is_empty() {
-			return self.execute_single_command(command, current_dir).await;
+			return self.execute_single_command(command, &current_dir).await;
 		}
 		let mut commands = vec![command.clone()];
 		commands.extend(command.pipes.clone());
+		let last = commands.len() - 1;
+		let in_shell: Vec<bool> = commands.iter().map(|stage| runs_in_shell(stage, ctx)).collect();
+		#[cfg(unix)]
+		let mut shell_stages = Vec::new();
+		#[cfg(unix)]
+		let mut builtin_input = None;
+		#[cfg(unix)]
+		let mut held_output = None;
 		let mut processes = Vec::new();
 		let mut previous_stdout = None;
 		for (i, pipeline_cmd) in commands.iter().enumerate() {
-			let program_path = if let Some(resolved_path) = self.resolve_program_path(&pipeline_cmd.program) {
-				resolved_path
+			// A builtin is joined to its neighbours by pipes the shell makes. Captured
+			// output is read as it comes, as the builtins run before anything waits on
+			// the last command.
+			#[cfg(unix)]
+			let (reader, writer) = if i < last && (in_shell[i] || in_shell[i + 1])
+				|| i == last && self.captured.is_some() && in_shell.contains(&true)
+			{
+				let (reader, writer) = std::io::pipe()?;
+				(Some(reader), Some(writer))
 			} else {
-				return Err(anyhow::anyhow!("program not found: {}", pipeline_cmd.program));
+				(None, None)
 			};
-			let mut tokio_cmd = {
-				#[cfg(windows)] {
-					if let Some(ext) = program_path.extension() {
-						let ext = ext.to_string_lossy().to_lowercase();
-						if ext == "bat" || ext == "cmd" {
-							let mut cmd = TokioCommand::new("cmd");
-							cmd.arg("/c");
-							cmd.arg(&program_path);
-							cmd.args(&pipeline_cmd.args);
-							cmd
+			#[cfg(not(unix))]
+			let writer: Option<std::io::PipeWriter> = None;
+			if in_shell[i] {
+				#[cfg(unix)]
+				shell_stages.push(ShellStage { index: i, input: builtin_input.take(), output: writer.map(OwnedFd::from) });
+			} else {
+				let program_path = if let Some(resolved_path) = self.resolve_program_path(&pipeline_cmd.program) {
+					resolved_path
+				} else {
+					return Err(CommandNotFound(pipeline_cmd.program.clone()).into());
+				};
+				let mut tokio_cmd = {
+					#[cfg(windows)] {
+						if let Some(ext) = program_path.extension() {
+							let ext = ext.to_string_lossy().to_lowercase();
+							if ext == "bat" || ext == "cmd" {
+								let mut cmd = TokioCommand::new("cmd");
+								cmd.arg("/c");
+								cmd.arg(&program_path);
+								cmd.args(&pipeline_cmd.args);
+								cmd
+							} else {
+								let mut cmd = TokioCommand::new(&program_path);
+								cmd.args(&pipeline_cmd.args);
+								cmd
+							}
 						} else {
 							let mut cmd = TokioCommand::new(&program_path);
 							cmd.args(&pipeline_cmd.args);
 							cmd
 						}
-					} else {
+					}
+					#[cfg(not(windows))] {
 						let mut cmd = TokioCommand::new(&program_path);
 						cmd.args(&pipeline_cmd.args);
 						cmd
 					}
+				};
+				tokio_cmd.current_dir(&current_dir);
+				for (key, value) in &pipeline_cmd.environment {
+					tokio_cmd.env(key, value);
 				}
-				#[cfg(not(windows))] {
-					let mut cmd = TokioCommand::new(&program_path);
-					cmd.args(&pipeline_cmd.args);
-					cmd
+				if i == 0 {
+					if let Some(input_file) = &pipeline_cmd.input_redirect {
+						let file = File::open(input_file).await?;
+						tokio_cmd.stdin(Stdio::from(file.into_std().await));
+					} else {
+						tokio_cmd.stdin(Stdio::inherit());
+					}
+				} else {
+					tokio_cmd.stdin(previous_stdout.take().unwrap_or(Stdio::piped()));
 				}
-			};
-			tokio_cmd.current_dir(current_dir);
-			for (key, value) in &pipeline_cmd.environment {
-				tokio_cmd.env(key, value);
-			}
-			if i == 0 {
-				if let Some(input_file) = &pipeline_cmd.input_redirect {
-					let file = File::open(input_file).await?;
-					tokio_cmd.stdin(Stdio::from(file.into_std().await));
+				let (stdout, stderr) = open_output_redirects(pipeline_cmd).await?;
+				if i == last {
+					let unredirected = match writer {
+						Some(writer) => Stdio::from(writer),
+						None if self.captured.is_some() => Stdio::piped(),
+						None => Stdio::inherit(),
+					};
+					tokio_cmd.stdout(stdout.map(Stdio::from).unwrap_or(unredirected));
 				} else {
-					tokio_cmd.stdin(Stdio::inherit());
+					tokio_cmd.stdout(writer.map(Stdio::from).unwrap_or_else(Stdio::piped));
 				}
-			} else {
-				tokio_cmd.stdin(previous_stdout.take().unwrap_or(Stdio::piped()));
-			}
-			if i == commands.len() - 1 {
-				if let Some(output_file) = &pipeline_cmd.output_redirect {
-					let file = File::create(output_file).await?;
-					tokio_cmd.stdout(Stdio::from(file.into_std().await));
-				} else if let Some(append_file) = &pipeline_cmd.append_redirect {
-					let file = tokio::fs::OpenOptions::new()
-						.create(true)
-						.append(true)
-						.open(append_file)
-						.await?;
-					tokio_cmd.stdout(Stdio::from(file.into_std().await));
+				tokio_cmd.stderr(stderr.map(Stdio::from).unwrap_or_else(Stdio::inherit));
+				#[cfg(unix)]
+				if let Some(fd) = &pipeline_cmd.input_fd {
+					tokio_cmd.stdin(Stdio::from(duplicate_fd(fd)?));
+				}
+				#[cfg(unix)]
+				if let Some(fd) = &pipeline_cmd.output_fd {
+					tokio_cmd.stdout(Stdio::from(duplicate_fd(fd)?));
+				}
+				let mut child = tokio_cmd.spawn()?;
+				if i < last {
+					if let Some(stdout) = child.stdout.take() {
+						previous_stdout = Some(stdout.try_into()?);
+					}
+				}
+				processes.push((i, child));
+			}
+			#[cfg(unix)]
+			if let Some(reader) = reader {
+				if i == last {
+					held_output = Some(builtin_stdio::drain(reader));
+				} else if !in_shell[i + 1] {
+					previous_stdout = Some(Stdio::from(reader));
+				} else if in_shell[..=i].contains(&true) {
+					builtin_input = Some(PipedInput::Held(builtin_stdio::drain(reader)));
 				} else {
-					tokio_cmd.stdout(Stdio::inherit());
+					builtin_input = Some(PipedInput::Pipe(reader.into()));
 				}
-			} else {
-				tokio_cmd.stdout(Stdio::piped());
 			}
-			tokio_cmd.stderr(Stdio::inherit());
-			let mut child = tokio_cmd.spawn()?;
-			if i < commands.len() - 1 {
-				if let Some(_stdout) = child.stdout.take() {
-					previous_stdout = Some(Stdio::piped());
+		}
+		let mut codes = vec![0; commands.len()];
+		#[cfg(unix)]
+		for stage in shell_stages {
+			let index = stage.index;
+			codes[index] = self.execute_piped_builtin(&commands[index], stage, ctx).await?;
+		}
+		for (i, mut process) in processes {
+			let status = wait_capturing(&mut process, self.captured.as_mut()).await?;
+			codes[i] = status.code().unwrap_or(-1);
+		}
+		#[cfg(unix)]
+		if let Some(held) = held_output {
+			let output = held.join().unwrap_or_default();
+			self.collect(output);
+		}
+		Ok(pipeline_status(&codes, ctx.options.pipefail))
+	}
+
+	/// Runs a builtin of a pipeline with programs in it, on the pipes `stage` gives it.
+	/// `<&` and `>&` redirections take the place of the pipes.
+	#[cfg(unix)]
+	async fn execute_piped_builtin(&mut self, command: &ParsedCommand, stage: ShellStage, ctx: &mut ShellContext<'_>) -> Result<i32> {
+		let builtin = builtins::get_builtin(&command.program).ok_or_else(|| CommandNotFound(command.program.clone()))?;
+		let (stdin, feeder) = match stage.input {
+			Some(PipedInput::Pipe(fd)) => (Some(fd), None),
+			Some(PipedInput::Held(held)) => {
+				let (fd, feeder) = builtin_stdio::feed(held.join().unwrap_or_default())?;
+				(Some(fd), Some(feeder))
+			}
+			None => (None, None),
+		};
+		let stdin = command.input_fd.as_deref().map(duplicate_fd).transpose()?.or(stdin);
+		let stdout = command.output_fd.as_deref().map(duplicate_fd).transpose()?.or(stage.output);
+		let stdio = SwappedStdio::redirected(stdin, stdout)?;
+		let result = builtin.execute(command, self, ctx).await;
+		stdio.finish();
+		if let Some(feeder) = feeder {
+			let _ = feeder.join();
+		}
+		result
+	}
+
+	/// Number of background jobs that haven't been reported finished.
+	pub fn job_count(&self) -> usize {
+		self.jobs.len()
+	}
+
+	/// Background jobs that have finished since the last call.
+	pub fn finished_jobs(&mut self) -> Vec<JobNotification> {
+		self.jobs.finished()
+	}
+}
+
+/// Opens the files a command writes to: stdout for `>` and `>>`, stderr for `&>` and
+/// `&>>`. When both streams go to the same file it is opened once and shared, so
+/// their output interleaves instead of overwriting each other.
+async fn open_output_redirects(command: &ParsedCommand) -> Result<(Option<std::fs::File>, Option<std::fs::File>)> {
+	async fn open(path: &str, append: bool) -> Result<std::fs::File> {
+		let file = if append {
+			tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?
+		} else {
+			File::create(path).await?
+		};
+		Ok(file.into_std().await)
+	}
+
+	let stdout = match (&command.output_redirect, &command.append_redirect) {
+		(Some(path), _) => Some(open(path, false).await?),
+		(None, Some(path)) => Some(open(path, true).await?),
+		(None, None) => None,
+	};
+	let stdout_path = command.output_redirect.as_ref().or(command.append_redirect.as_ref());
+	let stderr = match (&command.stderr_redirect, &command.stderr_append_redirect) {
+		(Some(path), _) | (None, Some(path)) if stdout_path == Some(path) => {
+			stdout.as_ref().map(|file| file.try_clone()).transpose()?
+		}
+		(Some(path), _) => Some(open(path, false).await?),
+		(None, Some(path)) => Some(open(path, true).await?),
+		(None, None) => None,
+	};
+	Ok((stdout, stderr))
+}
+
+/// A close-on-exec duplicate of the descriptor a `<&fd` or `>&fd` redirection names.
+#[cfg(unix)]
+fn duplicate_fd(fd: &str) -> Result<std::os::fd::OwnedFd> {
+	use nix::fcntl::{fcntl, FcntlArg};
+	use std::os::fd::{BorrowedFd, FromRawFd, OwnedFd};
+
+	let bad_descriptor = || anyhow!("{}: bad file descriptor", fd);
+	let raw = fd.parse().ok().filter(|raw| *raw >= 0).ok_or_else(bad_descriptor)?;
+	// SAFETY: the descriptor is only duplicated, which fails with EBADF if it isn't open
+	let borrowed = unsafe { BorrowedFd::borrow_raw(raw) };
+	let duplicate = fcntl(borrowed, FcntlArg::F_DUPFD_CLOEXEC(0)).map_err(|_| bad_descriptor())?;
+	// SAFETY: fcntl just returned this descriptor, so nothing else owns it
+	Ok(unsafe { OwnedFd::from_raw_fd(duplicate) })
+}
+
+/// Waits for `child`, first reading its standard output into `captured` when it
+/// was piped for `execute_capturing`.
+async fn wait_capturing(child: &mut tokio::process::Child, captured: Option<&mut Vec<u8>>) -> std::io::Result<std::process::ExitStatus> {
+	if let (Some(stdout), Some(captured)) = (child.stdout.as_mut(), captured) {
+		stdout.read_to_end(captured).await?;
+	}
+	child.wait().await
+}
+
+/// The status of a pipeline whose commands exited with `codes`: the last one's, or
+/// with `pipefail` the rightmost non-zero one.
+fn pipeline_status(codes: &[i32], pipefail: bool) -> i32 {
+	if pipefail {
+		codes.iter().rev().find(|&&c| c != 0).copied().unwrap_or(0)
+	} else {
+		codes.last().copied().unwrap_or(0)
+	}
+}
+
+/// True for a pipeline made up only of builtins, none of them shadowed by a function.
+#[cfg(unix)]
+fn is_builtin_pipeline(command: &ParsedCommand, ctx: &ShellContext<'_>) -> bool {
+	std::iter::once(command).chain(command.pipes.iter()).all(|stage| runs_in_shell(stage, ctx))
+}
+
+/// True for a pipeline stage that's a builtin, not shadowed by a function. Elsewhere
+/// builtins in pipelines with programs are looked for on PATH.
+#[cfg(unix)]
+fn runs_in_shell(stage: &ParsedCommand, ctx: &ShellContext<'_>) -> bool {
+	builtins::get_builtin(&stage.program).is_some() && !ctx.state.functions.contains_key(&stage.program)
+}
+
+#[cfg(not(unix))]
+fn runs_in_shell(_stage: &ParsedCommand, _ctx: &ShellContext<'_>) -> bool {
+	false
+}
+
+/// A builtin in a pipeline with programs, waiting for its turn to run.
+#[cfg(unix)]
+struct ShellStage {
+	/// Its place in the pipeline
+	index: usize,
+	/// Where it reads from; `None` for the shell's standard input
+	input: Option<PipedInput>,
+	/// The pipe it writes to; `None` for the shell's standard output
+	output: Option<OwnedFd>,
+}
+
+/// Where a builtin in a pipeline with programs reads from.
+#[cfg(unix)]
+enum PipedInput {
+	/// A pipe from a program, when no builtin runs before this one
+	Pipe(OwnedFd),
+	/// Output read in while the builtins before this one ran
+	Held(JoinHandle<Vec<u8>>),
+}
+
+/// Searches the directories of `path_var` for an executable named `program_name`.
+fn search_path(program_name: &str, path_var: &str) -> Option<PathBuf> {
+	#[cfg(windows)]
+	let path_separator = ";";
+	#[cfg(not(windows))]
+	let path_separator = ":";
+	#[cfg(windows)]
+	let executable_extensions = vec!["exe", "bat", "cmd", "com"];
+	for path_dir in path_var.split(path_separator) {
+		if path_dir.is_empty() { continue; }
+		let dir_path = Path::new(path_dir);
+		if !dir_path.exists() || !dir_path.is_dir() { continue; }
+		#[cfg(windows)] {
+			let exe_candidate = dir_path.join(format!("{}.exe", program_name));
+			if exe_candidate.exists() && exe_candidate.is_file() {
+				return Some(exe_candidate);
+			}
+			for ext in &executable_extensions {
+				if ext == &"exe" { continue; }
+				let candidate_with_ext = dir_path.join(format!("{}.{}", program_name, ext));
+				if candidate_with_ext.exists() && candidate_with_ext.is_file() {
+					return Some(candidate_with_ext);
 				}
 			}
-			processes.push(child);
 		}
-		let mut exit_code = 0;
-		for mut process in processes {
-			let status = process.wait().await?;
-			exit_code = status.code().unwrap_or(-1);
+		#[cfg(not(windows))] {
+			let candidate = dir_path.join(program_name);
+			if candidate.exists() && candidate.is_file() {
+				return Some(candidate);
+			}
+		}
+	}
+	None
+}
+
+/// The exit status of a finished subshell body. `exit` and `set -e` end only the
+/// subshell; other errors are reported the way the top level would.
+fn subshell_status(result: Result<i32>) -> i32 {
+	match result {
+		Ok(code) => code,
+		Err(e) => {
+			if let Some(errors::ExitRequest(code)) = e.downcast_ref() {
+				return *code;
+			}
+			if let Some(ErrExit(code)) = e.downcast_ref() {
+				return *code;
+			}
+			if !e.is::<LoopControl>() {
+				eprintln!("flex-sh: {}", e);
+			}
+			errors::failure_status(&e)
+		}
+	}
+}
+
+/// Matches a `case` pattern with glob rules; a pattern that isn't a valid glob must match exactly.
+fn case_matches(pattern: &str, word: &str) -> bool {
+	match glob::Pattern::new(pattern) {
+		Ok(glob) => glob.matches(word),
+		Err(_) => pattern == word,
+	}
+}
+
+/// What a loop does after one run of its body.
+enum Iteration {
+	/// Keep looping; the body finished with this status
+	Next(i32),
+	/// Leave the loop with this status
+	Break(i32),
+}
+
+/// Applies `break`/`continue` raised by a loop body. Requests aimed at an outer
+/// loop are passed on with one level fewer.
+fn loop_iteration(result: Result<i32>) -> Result<Iteration> {
+	let error = match result {
+		Ok(code) => return Ok(Iteration::Next(code)),
+		Err(error) => error,
+	};
+	match error.downcast_ref::<LoopControl>() {
+		Some(LoopControl::Break(levels)) if *levels > 1 => Err(LoopControl::Break(levels - 1).into()),
+		Some(LoopControl::Continue(levels)) if *levels > 1 => Err(LoopControl::Continue(levels - 1).into()),
+		Some(LoopControl::Break(_)) => Ok(Iteration::Break(0)),
+		Some(LoopControl::Continue(_)) => Ok(Iteration::Next(0)),
+		None => Err(error),
+	}
+}
+
+/// Expands assignment values, and replaces `args` with the expansion of `raw_args`,
+/// including globs in unquoted words. Commands built in code rather than parsed have
+/// no raw words; their args are kept.
+fn expand_arguments(command: &mut ParsedCommand, ctx: &ShellContext<'_>) -> Result<()> {
+	for value in command.environment.values_mut() {
+		*value = param_expand::expand_text(value, ctx.state, ctx.options.unbound_error)?;
+	}
+	for fd in [&mut command.input_fd, &mut command.output_fd].into_iter().flatten() {
+		*fd = param_expand::expand_text(fd, ctx.state, ctx.options.unbound_error)?;
+	}
+	if command.raw_args.len() != command.args.len() {
+		return Ok(());
+	}
+	command.args = expand_words(&command.raw_args, ctx)?;
+	Ok(())
+}
+
+/// Expands words as written into arguments, including globs in unquoted words.
+fn expand_words(raw_words: &[String], ctx: &ShellContext<'_>) -> Result<Vec<String>> {
+	let mut words = Vec::with_capacity(raw_words.len());
+	for raw in raw_words {
+		let expanded = param_expand::expand_word(raw, ctx.state, ctx.options.unbound_error)?;
+		if raw.contains(['"', '\'']) {
+			words.extend(expanded);
+		} else {
+			words.extend(glob_expand::expand_args(&expanded, ctx.options)?);
 		}
-		Ok(exit_code)
 	}
+	Ok(words)
+}
 
-	pub async fn cleanup_background_processes(&mut self) -> Result<()> {
-		let mut completed = Vec::new();
-		for (pid, child) in &mut self.background_processes {
-			if let Ok(Some(status)) = child.try_wait() {
-				println!("[{}] Done    {}", pid, status.code().unwrap_or(-1));
-				completed.push(*pid);
+/// Runs `name=(...)` or `name[index]=value`. An array replaces a plain variable of
+/// the same name, whose value becomes its first element when only one element is set.
+fn assign_array(assignment: ArrayAssignment, ctx: &mut ShellContext<'_>) -> Result<()> {
+	match assignment {
+		ArrayAssignment::Whole { name, words } => {
+			let elements = expand_words(&words, ctx)?;
+			std::env::remove_var(&name);
+			ctx.state.arrays.insert(name, elements);
+		}
+		ArrayAssignment::Element { name, index, value } => {
+			let strict_vars = ctx.options.unbound_error;
+			let position = param_expand::evaluate_arithmetic(&param_expand::expand_text(&index, ctx.state, strict_vars)?)?;
+			let value = param_expand::expand_text(&value, ctx.state, strict_vars)?;
+			if !ctx.state.arrays.contains_key(&name) {
+				let existing = std::env::var(&name).ok();
+				std::env::remove_var(&name);
+				ctx.state.arrays.insert(name.clone(), existing.into_iter().collect());
 			}
+			let elements = ctx.state.arrays.entry(name.clone()).or_default();
+			let position = if position < 0 { elements.len() as i64 + position } else { position };
+			let Ok(position) = usize::try_from(position) else {
+				return Err(anyhow!("{}[{}]: bad array subscript", name, index));
+			};
+			if position >= elements.len() {
+				elements.resize(position + 1, String::new());
+			}
+			elements[position] = value;
 		}
-		for pid in completed {
-			self.background_processes.remove(&pid);
+	}
+	Ok(())
+}
+
+/// The first variable `command` or its pipeline assigns that is readonly.
+fn readonly_assignment(command: &ParsedCommand, ctx: &ShellContext<'_>) -> Option<String> {
+	std::iter::once(command)
+		.chain(command.pipes.iter())
+		.flat_map(|c| c.environment.keys().map(String::as_str).chain(c.array_assignments.iter().map(ArrayAssignment::name)))
+		.find(|name| ctx.state.readonly_vars.contains(*name))
+		.map(String::from)
+}
+
+/// Why a restricted shell refuses to run `command`, if it does: a command name with
+/// a `/`, an output redirection, or an assignment to a protected variable.
+fn restriction(command: &ParsedCommand, options: &ShellOptions) -> Option<String> {
+	if command.program.contains('/') {
+		return Some(format!("{}: restricted: cannot specify `/' in command names", command.program));
+	}
+	let redirects = [
+		&command.output_redirect,
+		&command.append_redirect,
+		&command.stderr_redirect,
+		&command.stderr_append_redirect,
+		&command.output_fd,
+	];
+	if let Some(file) = redirects.into_iter().flatten().next() {
+		return Some(format!("{}: restricted: cannot redirect output", file));
+	}
+	command
+		.environment
+		.iter()
+		.find(|(name, _)| options.is_protected_variable(name))
+		.map(|(name, _)| format!("{}: readonly variable", name))
+}
+
+/// Reconstructs a command for `set -x` output: assignments, words, then redirections.
+fn format_xtrace(command: &ParsedCommand) -> String {
+	let mut assignments: Vec<_> = command.environment.iter().collect();
+	assignments.sort();
+	let mut words: Vec<String> = assignments
+		.into_iter()
+		.map(|(key, value)| format!("{}={}", key, shell_quote(value)))
+		.collect();
+	if !command.program.is_empty() {
+		words.push(shell_quote(&command.program));
+	}
+	words.extend(command.args.iter().map(|arg| shell_quote(arg)));
+	if let Some(fd) = &command.input_fd {
+		words.push(format!("<&{}", fd));
+	}
+	if let Some(fd) = &command.output_fd {
+		words.push(format!(">&{}", fd));
+	}
+	if let Some(file) = &command.input_redirect {
+		words.push(format!("< {}", shell_quote(file)));
+	}
+	if let Some(file) = &command.output_redirect {
+		words.push(format!("> {}", shell_quote(file)));
+	}
+	if let Some(file) = &command.append_redirect {
+		words.push(format!(">> {}", shell_quote(file)));
+	}
+	// Only `&>` and `&>>` set these, so they name the same file as stdout
+	if command.stderr_redirect.is_some() || command.stderr_append_redirect.is_some() {
+		if let Some(last) = words.last_mut() {
+			last.insert(0, '&');
 		}
-		Ok(())
+	}
+	words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_format_xtrace() {
+		let mut command = ParsedCommand::new("echo".to_string());
+		command.args = vec!["hello world".to_string(), "it's".to_string()];
+		command.output_redirect = Some("out.txt".to_string());
+		command.environment.insert("LANG".to_string(), "C".to_string());
+		assert_eq!(format_xtrace(&command), "LANG=C echo 'hello world' 'it'\"'\"'s' > out.txt");
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_path_cache() {
+		let mut executor = Executor::new();
+		let sh = executor.resolve_program_path("sh").expect("sh should be on PATH");
+		assert_eq!(executor.cached_paths(), vec![("sh", sh.as_path())]);
+
+		assert!(executor.resolve_program_path("flexsh-no-such-program").is_none());
+		assert_eq!(executor.path_cache.get("flexsh-no-such-program"), Some(&None));
+
+		// A different PATH than the one the cache was built from empties it
+		executor.cached_path_value.push_str(":/flexsh-changed");
+		executor.resolve_program_path("sh");
+		assert_eq!(executor.path_cache.len(), 1);
+
+		executor.clear_path_cache();
+		assert!(executor.cached_paths().is_empty());
+	}
+
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn test_execute_capturing() {
+		use crate::config::Config;
+		use crate::core::history::History;
+		use crate::core::parser::Parser;
+		use crate::core::state::ShellState;
+
+		let mut config = Config::new(None, None).unwrap();
+		let mut history = History::in_memory(config.get().history.clone());
+		let parser = Parser::new();
+		let mut ctx = ShellContext {
+			current_dir: &mut std::env::current_dir().unwrap(),
+			parser: &mut Parser::new(),
+			config: &mut config,
+			options: &mut ShellOptions::default(),
+			state: &mut ShellState::default(),
+			history: &mut history,
+			in_condition: false,
+		};
+		let mut executor = Executor::new();
+
+		let command = parser.parse("sh -c 'echo hi'; printf 'a\\nb\\n' | tr a c").unwrap();
+		assert_eq!(executor.execute_capturing(command, &mut ctx).await.unwrap(), (0, "hi\nc\nb\n".to_string()));
+		let command = parser.parse("sh -c 'echo out; exit 3'").unwrap();
+		assert_eq!(executor.execute_capturing(command, &mut ctx).await.unwrap(), (3, "out\n".to_string()));
+		assert!(executor.captured.is_none());
 	}
 }
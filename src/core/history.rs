@@ -1,14 +1,64 @@
 use crate::config::HistoryConfig;
-use anyhow::Result;
+use crate::core::parser::is_name;
+use crate::utils::glob_expand::extglob_to_regex;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fs::OpenOptions;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{Read, Write};
 use std::path::PathBuf;
 
+/// Appends between compactions when `ignore_duplicates` is set.
+const COMPACT_INTERVAL: usize = 100;
+
+/// A history entry as exported and imported. The history file doesn't record when
+/// commands ran, so exported timestamps are empty; imported ones are ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub command: String,
+    /// Seconds since the Unix epoch
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+}
+
+/// Formats `history --export` and `--import` read and write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryFormat {
+    /// One entry per line, as in the history file
+    Text,
+    /// An array of `{"command": ..., "timestamp": ...}` objects
+    Json,
+    /// A `command,timestamp` header, then one row per entry
+    Csv,
+}
+
+impl HistoryFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
 pub struct History {
     entries: VecDeque<String>,
     config: HistoryConfig,
     file_path: Option<PathBuf>,
+    /// Entries currently in the history file, which can exceed `entries`
+    /// until the file is compacted.
+    file_entry_count: usize,
+    /// Appends since the file was last rewritten
+    writes_since_compaction: usize,
+    /// The file ends in a partial line (an interrupted write) and must be rewritten
+    needs_rewrite: bool,
+    /// Entries were removed or replaced, so the line editor's copy is out of date
+    modified: bool,
+    /// `ignore_patterns` and `$HISTIGNORE`, compiled
+    ignore_patterns: Vec<Regex>,
 }
 
 impl History {
@@ -22,11 +72,7 @@ impl History {
                     .or_else(|| dirs::config_dir().map(|c| c.join("flex-sh").join("history")))
             });
 
-        let mut history = Self {
-            entries: VecDeque::with_capacity(config.max_entries),
-            config,
-            file_path,
-        };
+        let mut history = Self { file_path, ..Self::in_memory(config) };
 
         if let Err(e) = history.load_from_file() {
             eprintln!("Warning: Failed to load history file: {}", e);
@@ -35,6 +81,28 @@ impl History {
         Ok(history)
     }
 
+    /// An empty history that is never read from or saved to a file.
+    pub fn in_memory(config: HistoryConfig) -> Self {
+        let histignore = std::env::var("HISTIGNORE").unwrap_or_default();
+        let ignore_patterns = config
+            .ignore_patterns
+            .iter()
+            .map(String::as_str)
+            .chain(histignore.split(':').filter(|pattern| !pattern.is_empty()))
+            .filter_map(|pattern| Regex::new(&format!("(?s){}", extglob_to_regex(pattern))).ok())
+            .collect();
+        Self {
+            entries: VecDeque::with_capacity(config.max_entries),
+            config,
+            file_path: None,
+            file_entry_count: 0,
+            writes_since_compaction: 0,
+            needs_rewrite: false,
+            modified: false,
+            ignore_patterns,
+        }
+    }
+
     pub fn add(&mut self, command: &String) -> Result<()> {
         if command.trim().is_empty() {
             return Ok(());
@@ -44,6 +112,10 @@ impl History {
             return Ok(());
         }
 
+        if self.is_ignored(command) {
+            return Ok(());
+        }
+
         if self.config.ignore_duplicates && self.entries.back() == Some(command) {
             return Ok(());
         }
@@ -53,11 +125,43 @@ impl History {
         }
 
         self.entries.push_back(command.clone());
-        self.save_to_file()?;
+
+        self.writes_since_compaction += 1;
+        let compact_due = self.config.ignore_duplicates && self.writes_since_compaction >= COMPACT_INTERVAL;
+        if self.needs_rewrite || compact_due || self.file_entry_count >= self.config.max_entries {
+            if compact_due {
+                self.entries.make_contiguous();
+                let mut seen = std::collections::HashSet::new();
+                // Keep the most recent use of each command
+                let deduped: VecDeque<String> = self.entries.iter().rev()
+                    .filter(|entry| seen.insert(entry.as_str()))
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .rev()
+                    .collect();
+                self.entries = deduped;
+            }
+            self.save_to_file()?;
+        } else {
+            self.append_to_file(command);
+        }
 
         Ok(())
     }
 
+    /// True if `command` matches an ignore pattern or runs a sensitive command.
+    fn is_ignored(&self, command: &str) -> bool {
+        if self.ignore_patterns.iter().any(|pattern| pattern.is_match(command)) {
+            return true;
+        }
+        // The command name comes after any `NAME=value` assignments
+        let name = command
+            .split_whitespace()
+            .find(|word| !word.split_once('=').is_some_and(|(name, _)| is_name(name)));
+        name.is_some_and(|name| self.config.sensitive_commands.iter().any(|sensitive| sensitive == name))
+    }
+
     pub fn get_entries(&self) -> &VecDeque<String> {
         &self.entries
     }
@@ -73,21 +177,120 @@ impl History {
 
     pub fn clear(&mut self) -> Result<()> {
         self.entries.clear();
+        self.modified = true;
         self.save_to_file()?;
         Ok(())
     }
 
+    /// Removes entry `number`, counting from 1 as `history` lists them.
+    /// Returns false if there is no such entry.
+    pub fn delete(&mut self, number: usize) -> Result<bool> {
+        if number == 0 || self.entries.remove(number - 1).is_none() {
+            return Ok(false);
+        }
+        self.modified = true;
+        self.save_to_file()?;
+        Ok(true)
+    }
+
+    /// Rewrites the history file from the entries in memory (`history -w`).
+    pub fn write_file(&mut self) -> Result<()> {
+        self.save_to_file()
+    }
+
+    /// Replaces the entries in memory with the history file's (`history -r`),
+    /// picking up commands other sessions have added.
+    pub fn reload(&mut self) -> Result<()> {
+        self.entries.clear();
+        self.file_entry_count = 0;
+        self.modified = true;
+        self.load_from_file()
+    }
+
+    /// Writes every entry to `writer` in `format`.
+    pub fn export(&self, format: HistoryFormat, writer: &mut dyn Write) -> Result<()> {
+        match format {
+            HistoryFormat::Text => {
+                for entry in &self.entries {
+                    writer.write_all(encode_entry(entry).as_bytes())?;
+                }
+            }
+            HistoryFormat::Json => self.export_json(writer)?,
+            HistoryFormat::Csv => {
+                writeln!(writer, "command,timestamp")?;
+                for entry in &self.entries {
+                    writeln!(writer, "{},", csv_field(entry))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the entries `reader` holds in `format`, as if each had been run, and
+    /// returns how many were read.
+    pub fn import(&mut self, format: HistoryFormat, reader: &mut dyn Read) -> Result<usize> {
+        let commands = match format {
+            HistoryFormat::Json => return self.import_json(reader),
+            HistoryFormat::Text => {
+                let mut contents = String::new();
+                reader.read_to_string(&mut contents)?;
+                decode_entries(&contents)
+            }
+            HistoryFormat::Csv => {
+                let mut contents = String::new();
+                reader.read_to_string(&mut contents)?;
+                let mut rows = parse_csv(&contents)?.into_iter();
+                if rows.next().is_some_and(|header| header.first().map(String::as_str) != Some("command")) {
+                    return Err(anyhow!("expected a `command,timestamp` header"));
+                }
+                rows.filter_map(|row| row.into_iter().next()).collect()
+            }
+        };
+        self.add_imported(commands.into_iter())
+    }
+
+    pub fn export_json(&self, writer: &mut dyn Write) -> Result<()> {
+        let entries: Vec<HistoryEntry> = self
+            .entries
+            .iter()
+            .map(|command| HistoryEntry { command: command.clone(), timestamp: None })
+            .collect();
+        serde_json::to_writer_pretty(&mut *writer, &entries)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    pub fn import_json(&mut self, reader: &mut dyn Read) -> Result<usize> {
+        let entries: Vec<HistoryEntry> = serde_json::from_reader(reader)?;
+        self.add_imported(entries.into_iter().map(|entry| entry.command))
+    }
+
+    fn add_imported(&mut self, commands: impl Iterator<Item = String>) -> Result<usize> {
+        let mut count = 0;
+        for command in commands {
+            self.add(&command)?;
+            count += 1;
+        }
+        self.modified = true;
+        Ok(count)
+    }
+
+    /// True once after entries were removed or replaced, telling the shell to
+    /// refresh the line editor's history.
+    pub fn take_modified(&mut self) -> bool {
+        std::mem::take(&mut self.modified)
+    }
+
     fn load_from_file(&mut self) -> Result<()> {
         if let Some(ref path) = self.file_path {
             if path.exists() {
-                let file = std::fs::File::open(path)?;
-                let reader = BufReader::new(file);
-
-                for line in reader.lines() {
-                    let line = line?;
-                    if !line.trim().is_empty() {
-                        self.entries.push_back(line);
-                    }
+                let contents = std::fs::read_to_string(path)?;
+                // A missing final newline means the last append was cut short
+                self.needs_rewrite = !contents.is_empty() && !contents.ends_with('\n');
+
+                for entry in decode_entries(&contents) {
+                    self.entries.push_back(entry);
+                    self.file_entry_count += 1;
                 }
 
                 while self.entries.len() > self.config.max_entries {
@@ -98,7 +301,38 @@ impl History {
         Ok(())
     }
 
-    fn save_to_file(&self) -> Result<()> {
+    /// Appends one entry to the history file. The entry and its newline go out in a
+    /// single write, so an interrupted shell never leaves half a line behind.
+    fn append_to_file(&mut self, entry: &str) {
+        let Some(ref path) = self.file_path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                eprintln!("Warning: Failed to create history directory {}: {}", parent.display(), e);
+                return;
+            }
+        }
+
+        let mut file = match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("Warning: Failed to open history file {}: {}", path.display(), e);
+                return;
+            }
+        };
+
+        if let Err(e) = file.write_all(encode_entry(entry).as_bytes()) {
+            eprintln!("Warning: Failed to write to history file: {}", e);
+            // Whatever reached the file may be incomplete; rewrite it next time
+            self.needs_rewrite = true;
+            return;
+        }
+        self.file_entry_count += 1;
+    }
+
+    /// Rewrites the whole history file from `entries`, compacting it.
+    fn save_to_file(&mut self) -> Result<()> {
         if let Some(ref path) = self.file_path {
             // Safely handle directory creation and file writing - don't fail the shell if history can't be saved
             if let Some(parent) = path.parent() {
@@ -108,49 +342,135 @@ impl History {
                 }
             }
 
+            // Write a sibling file and rename it over the old one, so the history
+            // file is never seen half-written
+            let temp_path = path.with_extension("tmp");
             let mut file = match OpenOptions::new()
                 .create(true)
                 .write(true)
                 .truncate(true)
-                .open(path) {
+                .open(&temp_path) {
                 Ok(f) => f,
                 Err(e) => {
-                    eprintln!("Warning: Failed to open history file {}: {}", path.display(), e);
+                    eprintln!("Warning: Failed to open history file {}: {}", temp_path.display(), e);
                     return Ok(()); // Don't fail the command if history can't be saved
                 }
             };
 
+            let mut contents = String::new();
             for entry in &self.entries {
-                if let Err(e) = writeln!(file, "{}", entry) {
-                    eprintln!("Warning: Failed to write to history file: {}", e);
-                    return Ok(()); // Don't fail the command if history can't be saved
-                }
+                contents.push_str(&encode_entry(entry));
+            }
+            if let Err(e) = file.write_all(contents.as_bytes()) {
+                eprintln!("Warning: Failed to write to history file: {}", e);
+                return Ok(()); // Don't fail the command if history can't be saved
             }
+            drop(file);
 
-            if let Err(e) = file.flush() {
-                eprintln!("Warning: Failed to flush history file: {}", e);
+            if let Err(e) = std::fs::rename(&temp_path, path) {
+                eprintln!("Warning: Failed to replace history file {}: {}", path.display(), e);
+                return Ok(());
             }
         }
+        self.file_entry_count = self.entries.len();
+        self.writes_since_compaction = 0;
+        self.needs_rewrite = false;
         Ok(())
     }
 }
 
+/// An entry as stored in the history file: one line, ending in a newline. The lines
+/// of a multiline command are joined with a trailing `\`, as they'd be typed.
+fn encode_entry(entry: &str) -> String {
+    format!("{}\n", entry.replace('\n', "\\\n"))
+}
+
+/// The entries of a history file's `contents`. A line ending in `\` continues a
+/// multiline entry (see `encode_entry`).
+fn decode_entries(contents: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut entry = String::new();
+    for line in contents.lines() {
+        if let Some(continued) = line.strip_suffix('\\') {
+            entry.push_str(continued);
+            entry.push('\n');
+            continue;
+        }
+        entry.push_str(line);
+        let entry = std::mem::take(&mut entry);
+        if !entry.trim().is_empty() {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+/// `field` as a CSV field, quoted when it holds a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// The rows of a CSV document, with quoted fields unquoted.
+fn parse_csv(contents: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if in_quotes => {
+                if chars.next_if_eq(&'"').is_some() {
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            ch => field.push(ch),
+        }
+    }
+    if in_quotes {
+        return Err(anyhow!("unterminated quoted field"));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn test_config() -> HistoryConfig {
+    /// A config whose history file lives in `dir`, away from the user's real history.
+    fn test_config(dir: &tempfile::TempDir) -> HistoryConfig {
         HistoryConfig {
             max_entries: 100,
-            file_path: None,
+            file_path: Some(dir.path().join("history")),
             ignore_duplicates: true,
             ignore_space_prefixed: true,
+            ignore_patterns: Vec::new(),
+            sensitive_commands: Vec::new(),
         }
     }
 
     #[test]
     fn test_add_command() {
-        let mut history = History::new(test_config()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::new(test_config(&dir)).unwrap();
         history.add(&"ls -la".to_string()).unwrap();
         assert_eq!(history.entries.len(), 1);
         assert_eq!(history.entries.back().unwrap(), "ls -la");
@@ -158,7 +478,8 @@ mod tests {
 
     #[test]
     fn test_ignore_duplicates() {
-        let mut history = History::new(test_config()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::new(test_config(&dir)).unwrap();
         history.add(&"ls".to_string()).unwrap();
         history.add(&"ls".to_string()).unwrap();
         assert_eq!(history.entries.len(), 1);
@@ -166,18 +487,43 @@ mod tests {
 
     #[test]
     fn test_ignore_space_prefixed() {
-        let mut history = History::new(test_config()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::new(test_config(&dir)).unwrap();
         history.add(&" secret command".to_string()).unwrap();
         assert_eq!(history.entries.len(), 0);
     }
 
+    #[test]
+    fn test_ignore_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = HistoryConfig {
+            ignore_patterns: vec!["PASSWORD=* *".to_string(), "aws sts assume-role*".to_string()],
+            sensitive_commands: vec!["vault".to_string()],
+            ..test_config(&dir)
+        };
+        let mut history = History::new(config).unwrap();
+        for command in [
+            "PASSWORD=hunter2 ./deploy",
+            "aws sts assume-role --role-arn x",
+            "aws s3 ls",
+            "vault login",
+            "TOKEN=x vault read secret",
+            "echo vault",
+        ] {
+            history.add(&command.to_string()).unwrap();
+        }
+        assert_eq!(history.entries, vec!["aws s3 ls", "echo vault"]);
+    }
+
     #[test]
     fn test_max_entries() {
+        let dir = tempfile::tempdir().unwrap();
         let config = HistoryConfig {
             max_entries: 2,
-            file_path: None,
+            file_path: Some(dir.path().join("history")),
             ignore_duplicates: false,
             ignore_space_prefixed: false,
+            ..test_config(&dir)
         };
         let mut history = History::new(config).unwrap();
 
@@ -192,7 +538,8 @@ mod tests {
 
     #[test]
     fn test_search() {
-        let mut history = History::new(test_config()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::new(test_config(&dir)).unwrap();
         history.add(&"git status".to_string()).unwrap();
         history.add(&"git commit".to_string()).unwrap();
         history.add(&"ls -la".to_string()).unwrap();
@@ -202,4 +549,122 @@ mod tests {
         assert!(results.contains(&"git status".to_string()));
         assert!(results.contains(&"git commit".to_string()));
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_appends_complete_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        // Dropping the history without any shutdown stands in for a crash
+        let mut history = History::new(test_config(&dir)).unwrap();
+        history.add(&"echo one".to_string()).unwrap();
+        drop(history);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "echo one\n");
+
+        let mut history = History::new(test_config(&dir)).unwrap();
+        history.add(&"echo two".to_string()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "echo one\necho two\n");
+        assert_eq!(history.file_entry_count, 2);
+    }
+
+    #[test]
+    fn test_partial_line_is_repaired() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+        std::fs::write(&path, "echo one\necho tw").unwrap();
+
+        let mut history = History::new(test_config(&dir)).unwrap();
+        history.add(&"echo three".to_string()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "echo one\necho tw\necho three\n");
+    }
+
+    #[test]
+    fn test_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+        let config = HistoryConfig { max_entries: 3, ..test_config(&dir) };
+        let mut history = History::new(config).unwrap();
+
+        for command in ["a", "b", "c"] {
+            history.add(&command.to_string()).unwrap();
+        }
+        assert_eq!(history.file_entry_count, 3);
+        // The file is full, so this add rewrites it instead of appending
+        history.add(&"d".to_string()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "b\nc\nd\n");
+
+        let config = HistoryConfig { max_entries: 1000, ..test_config(&dir) };
+        let mut history = History::new(config).unwrap();
+        for i in 0..COMPACT_INTERVAL {
+            history.add(&format!("cmd{}", i % 2)).unwrap();
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().collect::<Vec<_>>(), vec!["b", "c", "d", "cmd0", "cmd1"]);
+    }
+
+    #[test]
+    fn test_multiline_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let mut history = History::new(test_config(&dir)).unwrap();
+        history.add(&"echo \"line\none\"".to_string()).unwrap();
+        history.add(&"ls".to_string()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "echo \"line\\\none\"\nls\n");
+
+        let history = History::new(test_config(&dir)).unwrap();
+        assert_eq!(history.entries, vec!["echo \"line\none\"", "ls"]);
+        assert_eq!(history.file_entry_count, 2);
+    }
+    #[test]
+    fn test_delete_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::new(test_config(&dir)).unwrap();
+        for command in ["one", "two", "three"] {
+            history.add(&command.to_string()).unwrap();
+        }
+        assert!(!history.take_modified());
+
+        assert!(history.delete(2).unwrap());
+        assert!(!history.delete(3).unwrap());
+        assert!(!history.delete(0).unwrap());
+        assert!(history.take_modified());
+        assert_eq!(history.entries, vec!["one", "three"]);
+
+        let mut other_session = History::new(test_config(&dir)).unwrap();
+        other_session.add(&"four".to_string()).unwrap();
+        history.reload().unwrap();
+        assert_eq!(history.entries, vec!["one", "three", "four"]);
+    }
+
+    #[test]
+    fn test_export_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::new(test_config(&dir)).unwrap();
+        for command in ["ls", "echo \"a, b\"", "printf 'x\ny'"] {
+            history.add(&command.to_string()).unwrap();
+        }
+
+        for format in [HistoryFormat::Text, HistoryFormat::Json, HistoryFormat::Csv] {
+            let mut exported = Vec::new();
+            history.export(format, &mut exported).unwrap();
+            let mut imported = History::in_memory(test_config(&dir));
+            let count = imported.import(format, &mut exported.as_slice()).unwrap();
+            assert_eq!(count, 3, "{:?}", format);
+            assert_eq!(imported.entries, history.entries, "{:?}", format);
+            assert!(imported.take_modified());
+        }
+
+        let mut exported = Vec::new();
+        history.export(HistoryFormat::Csv, &mut exported).unwrap();
+        assert_eq!(
+            String::from_utf8(exported).unwrap(),
+            "command,timestamp\nls,\n\"echo \"\"a, b\"\"\",\n\"printf 'x\ny'\",\n"
+        );
+
+        let json = r#"[{"command": "git status", "timestamp": 1700000000}, {"command": "make"}]"#;
+        let mut imported = History::in_memory(test_config(&dir));
+        assert_eq!(imported.import_json(&mut json.as_bytes()).unwrap(), 2);
+        assert_eq!(imported.entries, vec!["git status", "make"]);
+        assert!(imported.import_json(&mut "not json".as_bytes()).is_err());
+    }
+}
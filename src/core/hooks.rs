@@ -0,0 +1,67 @@
+use crate::core::parser::ParsedCommand;
+use anyhow::Result;
+
+/// Runs before each interactive command; an `Err` cancels the command.
+pub type PreExecHook = Box<dyn Fn(&ParsedCommand) -> Result<()> + Send + Sync>;
+/// Runs after each interactive command with its exit code; an `Err` is reported.
+pub type PostExecHook = Box<dyn Fn(&ParsedCommand, i32) -> Result<()> + Send + Sync>;
+
+/// The hooks extensions register around interactive commands, run in the order
+/// they were added.
+#[derive(Default)]
+pub struct ExecHooks {
+    pre_exec_hooks: Vec<PreExecHook>,
+    post_exec_hooks: Vec<PostExecHook>,
+}
+
+impl ExecHooks {
+    pub fn add_pre_exec(&mut self, hook: PreExecHook) {
+        self.pre_exec_hooks.push(hook);
+    }
+
+    pub fn add_post_exec(&mut self, hook: PostExecHook) {
+        self.post_exec_hooks.push(hook);
+    }
+
+    /// Runs the pre-exec hooks, stopping at the first error, which cancels the command.
+    pub fn pre_exec(&self, command: &ParsedCommand) -> Result<()> {
+        self.pre_exec_hooks.iter().try_for_each(|hook| hook(command))
+    }
+
+    /// Runs every post-exec hook and returns the errors they reported.
+    pub fn post_exec(&self, command: &ParsedCommand, exit_code: i32) -> Vec<anyhow::Error> {
+        self.post_exec_hooks.iter().filter_map(|hook| hook(command, exit_code).err()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_exec_hooks() {
+        let mut hooks = ExecHooks::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let pre = seen.clone();
+        hooks.add_pre_exec(Box::new(move |command| {
+            pre.lock().unwrap().push(format!("pre {}", command.program));
+            anyhow::ensure!(command.program != "blocked", "not allowed");
+            Ok(())
+        }));
+        hooks.add_pre_exec(Box::new(|_| anyhow::bail!("second hook")));
+        hooks.add_post_exec(Box::new(|_, code| {
+            anyhow::ensure!(code == 0, "status {}", code);
+            Ok(())
+        }));
+
+        let blocked = ParsedCommand::new("blocked".to_string());
+        assert_eq!(hooks.pre_exec(&blocked).unwrap_err().to_string(), "not allowed");
+        let ls = ParsedCommand::new("ls".to_string());
+        assert_eq!(hooks.pre_exec(&ls).unwrap_err().to_string(), "second hook");
+        assert_eq!(*seen.lock().unwrap(), vec!["pre blocked", "pre ls"]);
+
+        assert!(hooks.post_exec(&ls, 0).is_empty());
+        assert_eq!(hooks.post_exec(&ls, 2)[0].to_string(), "status 2");
+    }
+}
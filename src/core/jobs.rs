@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use tokio::process::Child;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+/// Notifications that can queue up before the shell gets to print them.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Sent by a job's watcher task when its process exits.
+#[derive(Debug)]
+pub struct JobNotification {
+    pub id: usize,
+    pub command: String,
+    pub status: i32,
+    /// Whether this was the most recently started job (`+` in the report)
+    pub current: bool,
+}
+
+impl JobNotification {
+    /// The line printed at the prompt, e.g. `[1]+  Done                    sleep 5`.
+    pub fn message(&self) -> String {
+        let state = match self.status {
+            0 => "Done".to_string(),
+            status => format!("Exit {}", status),
+        };
+        let marker = if self.current { '+' } else { '-' };
+        format!("[{}]{}  {:<24}{}", self.id, marker, state, self.command)
+    }
+}
+
+/// Commands started with `&` by job number, until they're reported finished. Each
+/// job's process is awaited on its own task, which sends the exit status over a channel.
+pub struct JobTable {
+    jobs: BTreeMap<usize, String>,
+    sender: Sender<(usize, i32)>,
+    receiver: Receiver<(usize, i32)>,
+}
+
+impl Default for JobTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobTable {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        Self { jobs: BTreeMap::new(), sender, receiver }
+    }
+
+    /// Takes ownership of a started process and returns its job number.
+    pub fn spawn(&mut self, mut child: Child, command: String) -> usize {
+        let id = self.jobs.keys().next_back().map_or(1, |last| last + 1);
+        self.jobs.insert(id, command);
+
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let status = match child.wait().await {
+                Ok(status) => status.code().unwrap_or(-1),
+                Err(_) => -1,
+            };
+            // The receiver only goes away when the shell does
+            let _ = sender.send((id, status)).await;
+        });
+        id
+    }
+
+    /// Number of jobs not yet reported finished.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Jobs whose processes have exited since the last call, removed from the table.
+    pub fn finished(&mut self) -> Vec<JobNotification> {
+        let mut finished = Vec::new();
+        while let Ok((id, status)) = self.receiver.try_recv() {
+            let current = self.jobs.keys().next_back() == Some(&id);
+            if let Some(command) = self.jobs.remove(&id) {
+                finished.push(JobNotification { id, command, status, current });
+            }
+        }
+        finished.sort_by_key(|notification| notification.id);
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        let mut notification = JobNotification {
+            id: 2,
+            command: "sleep 1".to_string(),
+            status: 0,
+            current: true,
+        };
+        assert_eq!(notification.message(), "[2]+  Done                    sleep 1");
+        notification.status = 3;
+        notification.current = false;
+        assert_eq!(notification.message(), "[2]-  Exit 3                  sleep 1");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_finished_jobs() {
+        let mut table = JobTable::new();
+        let child = tokio::process::Command::new("sh").args(["-c", "exit 4"]).spawn().unwrap();
+        let id = table.spawn(child, "sh -c 'exit 4'".to_string());
+        assert_eq!(id, 1);
+        assert_eq!(table.len(), 1);
+
+        let mut finished = Vec::new();
+        for _ in 0..100 {
+            finished = table.finished();
+            if !finished.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].status, 4);
+        assert!(table.is_empty());
+    }
+}
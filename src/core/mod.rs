@@ -2,5 +2,17 @@ pub mod shell;
 pub mod parser;
 pub mod executor;
 pub mod history;
+pub mod context;
+pub mod errors;
+pub mod options;
+pub mod state;
+pub mod command_cache;
+pub mod timings;
+pub mod jobs;
+pub mod completion_functions;
+pub mod hooks;
+#[cfg(unix)]
+pub mod builtin_stdio;
 
-pub use shell::Shell;
\ No newline at end of file
+pub use shell::Shell;
+pub use context::ShellContext;
\ No newline at end of file
@@ -0,0 +1,166 @@
+/// Variables a restricted shell doesn't let commands set or unset.
+pub const RESTRICTED_VARIABLES: &[&str] = &["PATH", "SHELL", "ENV", "BASH_ENV"];
+
+/// Runtime shell options toggled with the `set` and `setopt` builtins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellOptions {
+    pub exit_on_error: bool,
+    pub xtrace: bool,
+    pub unbound_error: bool,
+    pub pipefail: bool,
+    pub notify: bool,
+    pub allexport: bool,
+    pub vi_mode: bool,
+    /// `**` matches files in all subdirectories
+    pub globstar: bool,
+    /// Patterns may use the `?(..)`, `*(..)`, `+(..)`, `@(..)` and `!(..)` groups
+    pub extglob: bool,
+    /// A bare directory path runs `cd` to it; starts from the `auto_cd` setting
+    pub auto_cd: bool,
+    /// Words aren't expanded as glob patterns (`set -f`)
+    pub noglob: bool,
+    /// File and builtin names complete regardless of case
+    pub nocasematch: bool,
+    /// Each command is appended to the history file as it runs; when off, the
+    /// file is rewritten from this session's history, dropping what other
+    /// sessions added
+    pub histappend: bool,
+    /// Commands other sessions add to the history file are picked up at each prompt
+    pub sharehistory: bool,
+    /// Restricted shell (`--restricted`): no `cd`, no command names with `/`, no
+    /// output redirections and no changes to `RESTRICTED_VARIABLES`. Can't be
+    /// turned off once on.
+    pub restricted: bool,
+}
+
+impl Default for ShellOptions {
+    fn default() -> Self {
+        Self {
+            exit_on_error: false,
+            xtrace: false,
+            unbound_error: false,
+            pipefail: false,
+            notify: false,
+            allexport: false,
+            vi_mode: false,
+            globstar: false,
+            extglob: false,
+            auto_cd: false,
+            noglob: false,
+            nocasematch: false,
+            histappend: true,
+            sharehistory: false,
+            restricted: false,
+        }
+    }
+}
+
+impl ShellOptions {
+    /// Long option names in the order `set -o` prints them.
+    pub const NAMES: &'static [&'static str] = &[
+        "allexport", "autocd", "emacs", "errexit", "extglob", "globstar", "histappend", "nocasematch", "noglob",
+        "notify", "nounset", "pipefail", "sharehistory", "vi", "xtrace",
+    ];
+
+    /// Toggles an option by its single-letter flag (`e` in `set -e`).
+    /// Returns false if the flag is unknown.
+    pub fn set_flag(&mut self, flag: char, enable: bool) -> bool {
+        let name = match flag {
+            'a' => "allexport",
+            'b' => "notify",
+            'e' => "errexit",
+            'f' => "noglob",
+            'u' => "nounset",
+            'x' => "xtrace",
+            _ => return false,
+        };
+        self.set_named(name, enable)
+    }
+
+    /// Toggles an option by its long name (`errexit` in `set -o errexit`).
+    /// Returns false if the name is unknown.
+    pub fn set_named(&mut self, name: &str, enable: bool) -> bool {
+        match name {
+            "allexport" => self.allexport = enable,
+            "autocd" => self.auto_cd = enable,
+            "histappend" => self.histappend = enable,
+            "nocasematch" => self.nocasematch = enable,
+            "noglob" => self.noglob = enable,
+            "sharehistory" => self.sharehistory = enable,
+            "errexit" => self.exit_on_error = enable,
+            "extglob" => self.extglob = enable,
+            "globstar" => self.globstar = enable,
+            "notify" => self.notify = enable,
+            "nounset" => self.unbound_error = enable,
+            "pipefail" => self.pipefail = enable,
+            "xtrace" => self.xtrace = enable,
+            "vi" => self.vi_mode = enable,
+            "emacs" => self.vi_mode = !enable,
+            _ => return false,
+        }
+        true
+    }
+
+    /// True if this is a restricted shell and `name` is one of the variables it protects.
+    pub fn is_protected_variable(&self, name: &str) -> bool {
+        self.restricted && RESTRICTED_VARIABLES.contains(&name)
+    }
+
+    pub fn get_named(&self, name: &str) -> Option<bool> {
+        match name {
+            "allexport" => Some(self.allexport),
+            "autocd" => Some(self.auto_cd),
+            "histappend" => Some(self.histappend),
+            "nocasematch" => Some(self.nocasematch),
+            "noglob" => Some(self.noglob),
+            "sharehistory" => Some(self.sharehistory),
+            "errexit" => Some(self.exit_on_error),
+            "extglob" => Some(self.extglob),
+            "globstar" => Some(self.globstar),
+            "notify" => Some(self.notify),
+            "nounset" => Some(self.unbound_error),
+            "pipefail" => Some(self.pipefail),
+            "xtrace" => Some(self.xtrace),
+            "vi" => Some(self.vi_mode),
+            "emacs" => Some(!self.vi_mode),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_and_names() {
+        let mut options = ShellOptions::default();
+        assert!(options.set_flag('e', true));
+        assert!(options.set_flag('x', true));
+        assert!(options.exit_on_error && options.xtrace);
+        assert!(options.set_flag('e', false));
+        assert!(!options.exit_on_error);
+
+        assert!(options.set_named("vi", true));
+        assert_eq!(options.get_named("emacs"), Some(false));
+        assert!(!options.set_named("bogus", true));
+        assert!(!options.set_flag('Z', true));
+
+        assert_eq!(options.get_named("histappend"), Some(true));
+        assert!(options.set_flag('f', true));
+        assert_eq!(options.get_named("noglob"), Some(true));
+        for name in ShellOptions::NAMES {
+            assert!(options.get_named(name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_protected_variables() {
+        let mut options = ShellOptions::default();
+        assert!(!options.is_protected_variable("PATH"));
+        options.restricted = true;
+        assert!(options.is_protected_variable("PATH"));
+        assert!(options.is_protected_variable("SHELL"));
+        assert!(!options.is_protected_variable("HOME"));
+    }
+}
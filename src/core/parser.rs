@@ -1,16 +1,128 @@
+use crate::core::errors::{self, IncompleteInput, ParseError, UnterminatedInput};
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// How a command in a list is joined to the one before it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChainOp {
+    /// `;`, `&` or a newline: always run
+    Sequence,
+    /// `&&`: run only if the previous command succeeded
+    And,
+    /// `||`: run only if the previous command failed
+    Or,
+}
+
+/// A command in a list together with the operator joining it to the previous one.
+pub type ChainedCommand = (ChainOp, ParsedCommand);
+
+/// Commands built from other commands.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompoundCommand {
+    /// `name() { ... }` or `function name { ... }`
+    FunctionDef { name: String, body: Vec<ParsedCommand> },
+    /// `{ ...; }`
+    Group(Vec<ParsedCommand>),
+    /// `( ... )`: runs in a child shell, so changes to variables or the directory don't leak out
+    Subshell(Vec<ParsedCommand>),
+    /// `while cond; do ...; done`, or `until` when `negate` is set
+    WhileLoop {
+        condition: Vec<ParsedCommand>,
+        body: Vec<ParsedCommand>,
+        negate: bool,
+    },
+    /// `for var in items; do ...; done`; items are words as written
+    ForLoop {
+        var: String,
+        items: Vec<String>,
+        body: Vec<ParsedCommand>,
+    },
+    /// `case word in pattern|pattern) ...;; esac`; patterns are words as written
+    Case { word: String, arms: Vec<CaseArm> },
+    /// `if ...; then ...; elif ...; then ...; else ...; fi`
+    Conditional {
+        condition: Vec<ParsedCommand>,
+        then_body: Vec<ParsedCommand>,
+        elif_branches: Vec<(Vec<ParsedCommand>, Vec<ParsedCommand>)>,
+        else_body: Option<Vec<ParsedCommand>>,
+    },
+    /// `coproc [name] { ...; }`: runs in the background with pipes to and from the shell
+    Coproc { name: String, body: Vec<ParsedCommand> },
+}
+
+/// An assignment to an array variable, with its words as written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrayAssignment {
+    /// `name=(word ...)` replaces the whole array
+    Whole { name: String, words: Vec<String> },
+    /// `name[index]=value` sets one element; the index is an arithmetic expression
+    Element { name: String, index: String, value: String },
+}
+
+impl ArrayAssignment {
+    /// The array being assigned.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Whole { name, .. } | Self::Element { name, .. } => name,
+        }
+    }
+}
+
+/// One `pattern) body ;;` branch of a `case` command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseArm {
+    pub patterns: Vec<String>,
+    pub body: Vec<ParsedCommand>,
+    pub terminator: CaseTerminator,
+}
+
+/// How a `case` arm ends, which decides what happens after its body runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaseTerminator {
+    /// `;;` (or `esac`): the case command is finished
+    Break,
+    /// `;&`: run the next arm's body without testing its patterns
+    FallThrough,
+    /// `;;&`: keep testing the remaining patterns
+    Continue,
+}
+
+/// Reserved words that end a compound command's body; only valid where one is open.
+const RESERVED_CLOSERS: &[&str] = &["}", "then", "elif", "else", "fi", "do", "done", "esac"];
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParsedCommand {
     pub program: String,
     pub args: Vec<String>,
+    /// `args` as written, quotes included; the executor expands these when the command runs
+    pub raw_args: Vec<String>,
     pub input_redirect: Option<String>,
     pub output_redirect: Option<String>,
     pub append_redirect: Option<String>,
+    /// Set with `output_redirect` by `&>`, sending stderr to the same file
+    pub stderr_redirect: Option<String>,
+    /// Set with `append_redirect` by `&>>`
+    pub stderr_append_redirect: Option<String>,
+    /// `<&fd`: the descriptor standard input is duplicated from, as written (expanded when run)
+    pub input_fd: Option<String>,
+    /// `>&fd`: the descriptor standard output is duplicated from, as written
+    pub output_fd: Option<String>,
     pub background: bool,
     pub pipes: Vec<ParsedCommand>,
+    /// `NAME=value` assignments before the program, values as written (expanded when run).
+    /// Without a program they set shell variables instead.
     pub environment: HashMap<String, String>,
+    /// Start the program with an empty environment before applying `environment` (`env -i`)
+    pub clear_environment: bool,
+    /// Variables removed from the program's environment (`env -u`)
+    pub unset_environment: Vec<String>,
+    /// Array assignments, which only appear without a program
+    pub array_assignments: Vec<ArrayAssignment>,
+    /// Commands that follow this one in a list (`a && b; c`), in order
+    pub chain: Vec<ChainedCommand>,
+    /// Set for compound commands, which have no program of their own
+    pub compound: Option<Box<CompoundCommand>>,
 }
 
 impl ParsedCommand {
@@ -18,172 +130,765 @@ impl ParsedCommand {
         Self {
             program,
             args: Vec::new(),
+            raw_args: Vec::new(),
             input_redirect: None,
             output_redirect: None,
             append_redirect: None,
+            stderr_redirect: None,
+            stderr_append_redirect: None,
+            input_fd: None,
+            output_fd: None,
             background: false,
             pipes: Vec::new(),
             environment: HashMap::new(),
+            clear_environment: false,
+            unset_environment: Vec::new(),
+            array_assignments: Vec::new(),
+            chain: Vec::new(),
+            compound: None,
         }
     }
+
+    fn compound(compound: CompoundCommand) -> Self {
+        let mut command = Self::new(String::new());
+        command.compound = Some(Box::new(compound));
+        command
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+struct Word {
+    /// The word with quotes removed
+    text: String,
+    /// The word as written
+    raw: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(Word),
+    /// An unquoted operator: `;`, `\n`, `&&`, `||`, `&`, `|`, `<`, `>`, `>>`, `(`, `)`,
+    /// or one of the `case` arm terminators `;;`, `;&` and `;;&`
+    Op(&'static str),
+}
+
+impl Token {
+    /// True for an unquoted word spelled exactly `text`, such as a reserved word.
+    fn is_word(&self, text: &str) -> bool {
+        matches!(self, Token::Word(word) if word.raw == text)
+    }
+}
+
+#[derive(Clone)]
 pub struct Parser {
     aliases: HashMap<String, String>,
+    /// Line number the next input starts on (1 for interactive input)
+    start_line: usize,
+    /// Position reached by the tokenizer; points at the offending character after an error
+    line: Cell<usize>,
+    column: Cell<usize>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Parser {
     pub fn new() -> Self {
         Self {
             aliases: HashMap::new(),
+            start_line: 1,
+            line: Cell::new(1),
+            column: Cell::new(1),
         }
     }
 
+    /// Sets the line number reported for the next parsed input (script mode).
+    pub fn set_line(&mut self, line: usize) {
+        self.start_line = line;
+    }
+
+    /// Current `(line, column)` of the tokenizer, both 1-based.
+    pub fn position(&self) -> (usize, usize) {
+        (self.line.get(), self.column.get())
+    }
+
+    /// Parses `input` into a command list. Syntax errors are `ParseError`s with their
+    /// position, except those more lines could fix (see `errors::needs_more_input`).
     pub fn parse(&self, input: &str) -> Result<ParsedCommand> {
-        let input = input.trim();
+        self.line.set(self.start_line);
+        self.column.set(1);
 
-        if input.is_empty() {
+        // Leading whitespace still counts towards the reported column
+        let input = input.trim_end();
+
+        if input.trim().is_empty() {
             return Err(anyhow!("Empty command"));
         }
 
-        let tokens = self.tokenize(input)?;
-        let mut tokens = tokens;
-        // Alias substitution: if first token is an alias, replace it
-        if !tokens.is_empty() {
-            if let Some(alias) = self.aliases.get(&tokens[0]) {
-                // Split alias value into tokens and replace the first token
-                let alias_tokens = self.tokenize(alias)?;
-                tokens.splice(0..1, alias_tokens);
+        let result = self.tokenize(input).and_then(|tokens| self.parse_list(tokens));
+        result.map_err(|e| if errors::needs_more_input(&e) { e } else { self.error_at(e).into() })
+    }
+
+    /// `error` from the last parse as a `ParseError` at the position reached.
+    pub fn error_at(&self, error: anyhow::Error) -> ParseError {
+        error.downcast::<ParseError>().unwrap_or_else(|error| {
+            let (line, col) = self.position();
+            ParseError { line, col, message: error.to_string() }
+        })
+    }
+
+    /// Parses a script without running it, as `flex-sh -n` does, and returns all of
+    /// its syntax errors. As when a script runs, a command continues over the next
+    /// lines while it's incomplete; after an error, checking resumes on the next line.
+    pub fn check(&mut self, source: &str) -> Vec<ParseError> {
+        let lines: Vec<&str> = source.lines().collect();
+        let mut found = Vec::new();
+        let mut next_line = 0;
+        while next_line < lines.len() {
+            let line = lines[next_line];
+            let line_number = next_line + 1;
+            next_line += 1;
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            self.set_line(line_number);
+            let first_line = next_line - 1;
+            loop {
+                match self.parse(&lines[first_line..next_line].join("\n")) {
+                    Ok(_) => break,
+                    Err(e) if errors::needs_more_input(&e) && next_line < lines.len() => next_line += 1,
+                    Err(e) => {
+                        found.push(self.error_at(e));
+                        break;
+                    }
+                }
             }
         }
-        self.parse_tokens(tokens)
+        self.set_line(1);
+        found
     }
 
-    fn tokenize(&self, input: &str) -> Result<Vec<String>> {
+    fn tokenize(&self, input: &str) -> Result<Vec<Token>> {
         let mut tokens = Vec::new();
         let mut current_token = String::new();
+        let mut raw = String::new();
         let mut in_quotes = false;
         let mut quote_char = '"';
         let mut escape_next = false;
+        let mut quote_start = (self.line.get(), self.column.get());
+        let mut in_comment = false;
+        let mut chars = input.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            let position = self.advance(ch);
+
+            if in_comment {
+                in_comment = ch != '\n';
+                if !in_comment {
+                    tokens.push(Token::Op("\n"));
+                }
+                continue;
+            }
 
-        for ch in input.chars() {
             if escape_next {
                 current_token.push(ch);
+                raw.push(ch);
                 escape_next = false;
                 continue;
             }
 
             match ch {
-                '\\' if in_quotes => {
+                // A backslash before a newline joins the lines, except inside single quotes
+                '\\' if chars.peek() == Some(&'\n') && !(in_quotes && quote_char == '\'') => {
+                    let newline = chars.next().unwrap();
+                    self.advance(newline);
+                }
+                '\\' if !in_quotes && chars.peek().is_none() => {
+                    return Err(UnterminatedInput::Continuation.into());
+                }
+                // In double quotes a backslash only escapes `$`, `` ` ``, `"` and `\`;
+                // in single quotes it is always literal
+                '\\' if in_quotes && quote_char == '"' && matches!(chars.peek(), Some('$' | '`' | '"' | '\\')) => {
                     escape_next = true;
+                    raw.push(ch);
                 }
                 '"' | '\'' => {
                     if !in_quotes {
                         in_quotes = true;
                         quote_char = ch;
+                        quote_start = position;
                     } else if ch == quote_char {
                         in_quotes = false;
                     } else {
                         current_token.push(ch);
                     }
+                    raw.push(ch);
+                }
+                // `$(...)` and `$((...))` stay part of the word, parentheses and all
+                '$' if chars.peek() == Some(&'(') && !(in_quotes && quote_char == '\'') => {
+                    let mut depth = 0;
+                    let mut substitution = String::from('$');
+                    for next in chars.by_ref() {
+                        self.advance(next);
+                        substitution.push(next);
+                        match next {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    if depth != 0 {
+                        return Err(IncompleteInput(")".to_string()).into());
+                    }
+                    current_token.push_str(&substitution);
+                    raw.push_str(&substitution);
+                }
+                // An extended glob group like `!(*.txt)` or the list in an array
+                // assignment `name=(a b)` stays part of the word
+                '(' if !in_quotes
+                    && (raw.ends_with(['?', '*', '+', '@', '!'])
+                        || raw.strip_suffix('=').is_some_and(is_name)) =>
+                {
+                    let mut depth = 1;
+                    let mut group = String::from('(');
+                    let mut group_quote = None;
+                    for next in chars.by_ref() {
+                        self.advance(next);
+                        group.push(next);
+                        match (group_quote, next) {
+                            (Some(quote), _) if next == quote => group_quote = None,
+                            (Some(_), _) => {}
+                            (None, '"' | '\'') => group_quote = Some(next),
+                            (None, '(') => depth += 1,
+                            (None, ')') => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    if depth != 0 {
+                        return Err(IncompleteInput(")".to_string()).into());
+                    }
+                    current_token.push_str(&group);
+                    raw.push_str(&group);
                 }
+                // A comment runs to the end of the line
+                '#' if !in_quotes && raw.is_empty() => in_comment = true,
                 ' ' | '\t' if !in_quotes => {
-                    if !current_token.is_empty() {
-                        tokens.push(current_token.clone());
-                        current_token.clear();
+                    if !raw.is_empty() {
+                        tokens.push(Token::Word(Word {
+                            text: std::mem::take(&mut current_token),
+                            raw: std::mem::take(&mut raw),
+                        }));
                     }
                 }
+                ';' | '\n' | '&' | '|' | '<' | '>' | '(' | ')' if !in_quotes => {
+                    if !raw.is_empty() {
+                        tokens.push(Token::Word(Word {
+                            text: std::mem::take(&mut current_token),
+                            raw: std::mem::take(&mut raw),
+                        }));
+                    }
+                    let doubled = chars.peek() == Some(&ch);
+                    let third = chars.clone().nth(1);
+                    let op = match ch {
+                        ';' if doubled && third == Some('&') => ";;&",
+                        ';' if doubled => ";;",
+                        ';' if chars.peek() == Some(&'&') => ";&",
+                        ';' => ";",
+                        '\n' => "\n",
+                        '&' if doubled => "&&",
+                        '&' if chars.peek() == Some(&'>') && third == Some('>') => "&>>",
+                        '&' if chars.peek() == Some(&'>') => "&>",
+                        '&' => "&",
+                        '|' if doubled => "||",
+                        '|' => "|",
+                        '<' if chars.peek() == Some(&'&') => "<&",
+                        '<' => "<",
+                        '>' if doubled => ">>",
+                        '>' if chars.peek() == Some(&'&') => ">&",
+                        '>' => ">",
+                        '(' => "(",
+                        _ => ")",
+                    };
+                    for _ in 1..op.len() {
+                        let next = chars.next().unwrap();
+                        self.advance(next);
+                    }
+                    tokens.push(Token::Op(op));
+                }
                 _ => {
                     current_token.push(ch);
+                    raw.push(ch);
                 }
             }
         }
 
         if in_quotes {
-            return Err(anyhow!("Unterminated quote"));
+            self.line.set(quote_start.0);
+            self.column.set(quote_start.1);
+            return Err(UnterminatedInput::Quote.into());
         }
 
-        if !current_token.is_empty() {
-            tokens.push(current_token);
+        if !raw.is_empty() {
+            tokens.push(Token::Word(Word { text: current_token, raw }));
         }
 
         Ok(tokens)
     }
 
-    fn parse_tokens(&self, mut tokens: Vec<String>) -> Result<ParsedCommand> {
-        if tokens.is_empty() {
-            return Err(anyhow!("No tokens to parse"));
+    /// Moves the tracked position past `ch`, returning the position `ch` was at.
+    fn advance(&self, ch: char) -> (usize, usize) {
+        let position = (self.line.get(), self.column.get());
+        if ch == '\n' {
+            self.line.set(position.0 + 1);
+            self.column.set(1);
+        } else {
+            self.column.set(position.1 + 1);
         }
+        position
+    }
 
-        let mut environment = HashMap::new();
-        let mut i = 0;
+    /// Splits a token stream on `;`, `&`, `&&`, `||` and newlines into a chain of pipelines.
+    fn parse_list(&self, tokens: Vec<Token>) -> Result<ParsedCommand> {
+        let mut pending = VecDeque::from(tokens);
+        let (commands, _) = self.parse_commands(&mut pending, &[])?;
 
-        while i < tokens.len() {
-            if let Some(eq_pos) = tokens[i].find('=') {
-                if eq_pos > 0 && tokens[i].chars().nth(0).unwrap().is_alphabetic() {
-                    let (var, value) = tokens[i].split_at(eq_pos);
-                    environment.insert(var.to_string(), value[1..].to_string());
-                    tokens.remove(i);
-                    continue;
+        let mut commands = commands.into_iter();
+        let (_, mut first) = commands.next().ok_or_else(|| anyhow!("Empty command"))?;
+        first.chain = commands.collect();
+        Ok(first)
+    }
+
+    /// Parses commands until one of the reserved words in `terminators` appears in
+    /// command position (consumed and returned), or until the input ends when there
+    /// are no terminators. The last terminator is the one reported if input runs out.
+    fn parse_commands(
+        &self,
+        pending: &mut VecDeque<Token>,
+        terminators: &[&'static str],
+    ) -> Result<(Vec<ChainedCommand>, Option<&'static str>)> {
+        let mut commands: Vec<ChainedCommand> = Vec::new();
+        let mut current: Vec<Token> = Vec::new();
+        // A finished compound command, which only a list operator may follow
+        let mut compound: Option<ParsedCommand> = None;
+        let mut next_op = ChainOp::Sequence;
+        let mut expanded_aliases: Vec<String> = Vec::new();
+
+        loop {
+            let Some(token) = pending.pop_front() else {
+                if let Some(terminator) = terminators.last() {
+                    return Err(IncompleteInput(terminator.to_string()).into());
+                }
+                break;
+            };
+
+            let at_command_start = current.is_empty() && compound.is_none();
+            match token {
+                Token::Op(op @ (";" | "\n" | "&" | "&&" | "||")) => {
+                    if at_command_start {
+                        // Blank lines (also after `&&`/`||`) are fine; a dangling operator is not
+                        if op == "\n" {
+                            continue;
+                        }
+                        return Err(anyhow!("syntax error near unexpected token `{}'", op));
+                    }
+
+                    let mut command = match compound.take() {
+                        Some(command) => command,
+                        None => self.parse_tokens(std::mem::take(&mut current))?,
+                    };
+                    command.background = op == "&";
+                    commands.push((next_op, command));
+                    expanded_aliases.clear();
+                    next_op = match op {
+                        "&&" => ChainOp::And,
+                        "||" => ChainOp::Or,
+                        _ => ChainOp::Sequence,
+                    };
+                }
+                Token::Op(op @ (";;" | ";&" | ";;&" | ")")) if terminators.contains(&op) => {
+                    if let Some(command) = compound.take() {
+                        commands.push((next_op, command));
+                    } else if !current.is_empty() {
+                        commands.push((next_op, self.parse_tokens(std::mem::take(&mut current))?));
+                    } else if next_op != ChainOp::Sequence {
+                        return Err(anyhow!("syntax error near unexpected token `{}'", op));
+                    }
+                    return Ok((commands, Some(op)));
+                }
+                token if compound.is_some() => {
+                    return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token)));
+                }
+                token if at_command_start && RESERVED_CLOSERS.iter().any(|word| token.is_word(word)) => {
+                    let found = terminators.iter().find(|t| token.is_word(t));
+                    match found {
+                        Some(terminator) if next_op == ChainOp::Sequence => return Ok((commands, Some(terminator))),
+                        _ => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+                    }
+                }
+                Token::Op("(") if at_command_start => {
+                    let (body, _) = self.parse_body(pending, &[")"])?;
+                    compound = Some(ParsedCommand::compound(CompoundCommand::Subshell(body)));
+                }
+                token if at_command_start && token.is_word("{") => {
+                    let (body, _) = self.parse_body(pending, &["}"])?;
+                    compound = Some(ParsedCommand::compound(CompoundCommand::Group(body)));
+                }
+                token if at_command_start && token.is_word("if") => {
+                    compound = Some(self.parse_conditional(pending)?);
+                }
+                token if at_command_start && (token.is_word("while") || token.is_word("until")) => {
+                    let (condition, _) = self.parse_body(pending, &["do"])?;
+                    let (body, _) = self.parse_body(pending, &["done"])?;
+                    compound = Some(ParsedCommand::compound(CompoundCommand::WhileLoop {
+                        condition,
+                        body,
+                        negate: token.is_word("until"),
+                    }));
                 }
+                token if at_command_start && token.is_word("for") => {
+                    compound = Some(self.parse_for(pending)?);
+                }
+                token if at_command_start && token.is_word("case") => {
+                    compound = Some(self.parse_case(pending)?);
+                }
+                token if at_command_start && token.is_word("coproc") && starts_coproc_body(pending) => {
+                    // Without a brace group, `coproc` is the builtin that runs a simple command
+                    let name = match pending.pop_front() {
+                        Some(Token::Word(word)) if word.raw != "{" => {
+                            pending.pop_front();
+                            word.text
+                        }
+                        _ => "COPROC".to_string(),
+                    };
+                    let (body, _) = self.parse_body(pending, &["}"])?;
+                    compound = Some(ParsedCommand::compound(CompoundCommand::Coproc { name, body }));
+                }
+                token if at_command_start && token.is_word("function") => {
+                    let name = match pending.pop_front() {
+                        Some(Token::Word(word)) => word.text,
+                        Some(token) => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+                        None => return Err(IncompleteInput("function name".to_string()).into()),
+                    };
+                    if pending.front() == Some(&Token::Op("(")) {
+                        pending.pop_front();
+                        self.expect_op(pending, ")")?;
+                    }
+                    compound = Some(self.parse_function(name, pending)?);
+                }
+                Token::Word(word)
+                    if at_command_start
+                        && pending.front() == Some(&Token::Op("("))
+                        && pending.get(1) == Some(&Token::Op(")")) =>
+                {
+                    pending.drain(..2);
+                    compound = Some(self.parse_function(word.text, pending)?);
+                }
+                Token::Word(word) if current.is_empty() && !expanded_aliases.contains(&word.text) => {
+                    // Alias substitution applies to the first word of each command
+                    match self.aliases.get(&word.text) {
+                        Some(alias) => {
+                            let position = self.position();
+                            let alias_tokens = self.tokenize(alias)?;
+                            self.line.set(position.0);
+                            self.column.set(position.1);
+                            for alias_token in alias_tokens.into_iter().rev() {
+                                pending.push_front(alias_token);
+                            }
+                            expanded_aliases.push(word.text);
+                        }
+                        None => current.push(Token::Word(word)),
+                    }
+                }
+                token => current.push(token),
             }
-            i += 1;
         }
 
-        if tokens.is_empty() {
-            return Err(anyhow!("No command found after environment variables"));
+        if let Some(command) = compound {
+            commands.push((next_op, command));
+        } else if !current.is_empty() {
+            commands.push((next_op, self.parse_tokens(current)?));
+        } else if next_op != ChainOp::Sequence {
+            let op = if next_op == ChainOp::And { "&&" } else { "||" };
+            return Err(anyhow!("Expected command after '{}'", op));
+        }
+
+        Ok((commands, None))
+    }
+
+    /// Parses the commands of a compound body up to one of `terminators`, returning
+    /// the body and the terminator that ended it. Each element of the body is one
+    /// `&&`/`||` list; `;` and newlines separate elements.
+    fn parse_body(
+        &self,
+        pending: &mut VecDeque<Token>,
+        terminators: &[&'static str],
+    ) -> Result<(Vec<ParsedCommand>, &'static str)> {
+        let (commands, terminator) = self.parse_commands(pending, terminators)?;
+        // parse_commands only stops early (rather than erroring) on a terminator
+        let terminator = terminator.unwrap_or_default();
+        if commands.is_empty() {
+            return Err(anyhow!("syntax error near unexpected token `{}'", terminator));
+        }
+        Ok((into_body(commands), terminator))
+    }
+
+    /// Parses `case` after the keyword: `word in [(]pat[|pat]...) body ;; ... esac`.
+    fn parse_case(&self, pending: &mut VecDeque<Token>) -> Result<ParsedCommand> {
+        let word = match pending.pop_front() {
+            Some(Token::Word(word)) => word.raw,
+            Some(token) => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+            None => return Err(IncompleteInput("esac".to_string()).into()),
+        };
+        self.skip_newlines(pending);
+        match pending.pop_front() {
+            Some(token) if token.is_word("in") => {}
+            Some(token) => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+            None => return Err(IncompleteInput("esac".to_string()).into()),
         }
 
-        let program = if let Some(alias) = self.aliases.get(&tokens[0]) {
-            alias.clone()
+        let mut arms = Vec::new();
+        loop {
+            self.skip_newlines(pending);
+            match pending.front() {
+                Some(token) if token.is_word("esac") => {
+                    pending.pop_front();
+                    break;
+                }
+                Some(Token::Op("(")) => {
+                    pending.pop_front();
+                }
+                Some(_) => {}
+                None => return Err(IncompleteInput("esac".to_string()).into()),
+            }
+
+            let mut patterns = Vec::new();
+            loop {
+                match pending.pop_front() {
+                    Some(Token::Word(pattern)) => patterns.push(pattern.raw),
+                    Some(token) => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+                    None => return Err(IncompleteInput("esac".to_string()).into()),
+                }
+                match pending.pop_front() {
+                    Some(Token::Op("|")) => continue,
+                    Some(Token::Op(")")) => break,
+                    Some(token) => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+                    None => return Err(IncompleteInput("esac".to_string()).into()),
+                }
+            }
+
+            let (commands, ended_by) = self.parse_commands(pending, &[";;", ";&", ";;&", "esac"])?;
+            let terminator = match ended_by {
+                Some(";&") => CaseTerminator::FallThrough,
+                Some(";;&") => CaseTerminator::Continue,
+                _ => CaseTerminator::Break,
+            };
+            arms.push(CaseArm { patterns, body: into_body(commands), terminator });
+            // The last arm may end with `esac` itself instead of `;;`
+            if ended_by == Some("esac") {
+                break;
+            }
+        }
+
+        Ok(ParsedCommand::compound(CompoundCommand::Case { word, arms }))
+    }
+
+    /// Parses `if` after the keyword: `cond; then body; [elif cond; then body;]... [else body;] fi`.
+    fn parse_conditional(&self, pending: &mut VecDeque<Token>) -> Result<ParsedCommand> {
+        let (condition, _) = self.parse_body(pending, &["then"])?;
+        let (then_body, mut terminator) = self.parse_body(pending, &["elif", "else", "fi"])?;
+
+        let mut elif_branches = Vec::new();
+        while terminator == "elif" {
+            let (elif_condition, _) = self.parse_body(pending, &["then"])?;
+            let (elif_body, next) = self.parse_body(pending, &["elif", "else", "fi"])?;
+            elif_branches.push((elif_condition, elif_body));
+            terminator = next;
+        }
+
+        let else_body = if terminator == "else" {
+            Some(self.parse_body(pending, &["fi"])?.0)
         } else {
-            tokens[0].clone()
+            None
+        };
+
+        Ok(ParsedCommand::compound(CompoundCommand::Conditional {
+            condition,
+            then_body,
+            elif_branches,
+            else_body,
+        }))
+    }
+
+    /// Parses `for` after the keyword: `name [in words...]; do body; done`.
+    /// Without `in`, the loop runs over `"$@"`.
+    fn parse_for(&self, pending: &mut VecDeque<Token>) -> Result<ParsedCommand> {
+        let var = match pending.pop_front() {
+            Some(Token::Word(word)) if is_name(&word.raw) => word.text,
+            Some(token) => return Err(anyhow!("`{}': not a valid identifier", token_text(&token))),
+            None => return Err(IncompleteInput("do".to_string()).into()),
+        };
+
+        self.skip_newlines(pending);
+        let mut items = vec!["\"$@\"".to_string()];
+        if pending.front().is_some_and(|token| token.is_word("in")) {
+            pending.pop_front();
+            items.clear();
+            while let Some(Token::Word(word)) = pending.front() {
+                items.push(word.raw.clone());
+                pending.pop_front();
+            }
+            match pending.pop_front() {
+                Some(Token::Op(";" | "\n")) => {}
+                Some(token) => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+                None => return Err(IncompleteInput("do".to_string()).into()),
+            }
+        } else if pending.front() == Some(&Token::Op(";")) {
+            pending.pop_front();
+        }
+
+        self.skip_newlines(pending);
+        match pending.pop_front() {
+            Some(token) if token.is_word("do") => {}
+            Some(token) => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+            None => return Err(IncompleteInput("do".to_string()).into()),
+        }
+        let (body, _) = self.parse_body(pending, &["done"])?;
+        Ok(ParsedCommand::compound(CompoundCommand::ForLoop { var, items, body }))
+    }
+
+    fn skip_newlines(&self, pending: &mut VecDeque<Token>) {
+        while pending.front() == Some(&Token::Op("\n")) {
+            pending.pop_front();
+        }
+    }
+
+    /// Parses the `{ ... }` body of a function whose name has been read.
+    fn parse_function(&self, name: String, pending: &mut VecDeque<Token>) -> Result<ParsedCommand> {
+        self.skip_newlines(pending);
+        match pending.pop_front() {
+            Some(token) if token.is_word("{") => {}
+            Some(token) => return Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+            None => return Err(IncompleteInput("{".to_string()).into()),
+        }
+        let (body, _) = self.parse_body(pending, &["}"])?;
+        Ok(ParsedCommand::compound(CompoundCommand::FunctionDef { name, body }))
+    }
+
+    fn expect_op(&self, pending: &mut VecDeque<Token>, expected: &'static str) -> Result<()> {
+        match pending.pop_front() {
+            Some(Token::Op(op)) if op == expected => Ok(()),
+            Some(token) => Err(anyhow!("syntax error near unexpected token `{}'", token_text(&token))),
+            None => Err(IncompleteInput(expected.to_string()).into()),
+        }
+    }
+
+    fn parse_tokens(&self, mut tokens: Vec<Token>) -> Result<ParsedCommand> {
+        if tokens.is_empty() {
+            return Err(anyhow!("No tokens to parse"));
+        }
+
+        let mut environment = HashMap::new();
+        let mut array_assignments = Vec::new();
+
+        // Only leading NAME=value words are assignments; later ones are plain arguments
+        while let Some(Token::Word(Word { raw: word, .. })) = tokens.first() {
+            let Some((var, value)) = word.split_once('=') else {
+                break;
+            };
+            if is_name(var) && value.starts_with('(') && value.ends_with(')') {
+                let words = self.array_words(&value[1..value.len() - 1])?;
+                array_assignments.push(ArrayAssignment::Whole { name: var.to_string(), words });
+            } else if is_name(var) {
+                environment.insert(var.to_string(), value.to_string());
+            } else if let Some((name, index)) = array_element(var) {
+                array_assignments.push(ArrayAssignment::Element {
+                    name: name.to_string(),
+                    index: index.to_string(),
+                    value: value.to_string(),
+                });
+            } else {
+                break;
+            }
+            tokens.remove(0);
+        }
+
+        let program = match tokens.first() {
+            Some(Token::Word(word)) if !array_assignments.is_empty() => {
+                return Err(anyhow!("syntax error near unexpected token `{}'", word.raw));
+            }
+            Some(Token::Word(word)) => word.text.clone(),
+            Some(Token::Op(op)) => return Err(anyhow!("syntax error near unexpected token `{}'", op)),
+            // Only assignments: they set shell variables
+            None => String::new(),
         };
 
         let mut command = ParsedCommand::new(program);
         command.environment = environment;
+        command.array_assignments = array_assignments;
 
         let mut i = 1;
         while i < tokens.len() {
-            match tokens[i].as_str() {
-                "<" => {
-                    if i + 1 < tokens.len() {
-                        command.input_redirect = Some(tokens[i + 1].clone());
-                        i += 2;
-                    } else {
-                        return Err(anyhow!("Expected filename after '<'"));
-                    }
-                }
-                ">" => {
-                    if i + 1 < tokens.len() {
-                        command.output_redirect = Some(tokens[i + 1].clone());
-                        i += 2;
-                    } else {
-                        return Err(anyhow!("Expected filename after '>'"));
+            match &tokens[i] {
+                Token::Op(op @ ("<" | ">" | ">>" | "&>" | "&>>")) => {
+                    let target = match tokens.get(i + 1) {
+                        Some(Token::Word(word)) => word.text.clone(),
+                        _ => return Err(anyhow!("Expected filename after '{}'", op)),
+                    };
+                    match *op {
+                        "<" => command.input_redirect = Some(target),
+                        ">" => command.output_redirect = Some(target),
+                        ">>" => command.append_redirect = Some(target),
+                        "&>" => {
+                            command.output_redirect = Some(target.clone());
+                            command.stderr_redirect = Some(target);
+                        }
+                        _ => {
+                            command.append_redirect = Some(target.clone());
+                            command.stderr_append_redirect = Some(target);
+                        }
                     }
+                    i += 2;
                 }
-                ">>" => {
-                    if i + 1 < tokens.len() {
-                        command.append_redirect = Some(tokens[i + 1].clone());
-                        i += 2;
+                Token::Op(op @ ("<&" | ">&")) => {
+                    let fd = match tokens.get(i + 1) {
+                        Some(Token::Word(word)) => word.raw.clone(),
+                        _ => return Err(anyhow!("Expected file descriptor after '{}'", op)),
+                    };
+                    if *op == "<&" {
+                        command.input_fd = Some(fd);
                     } else {
-                        return Err(anyhow!("Expected filename after '>>'"));
+                        command.output_fd = Some(fd);
                     }
+                    i += 2;
                 }
-                "&" => {
-                    command.background = true;
-                    i += 1;
-                }
-                "|" => {
-                    let remaining_tokens: Vec<String> = tokens[i + 1..].to_vec();
-                    if !remaining_tokens.is_empty() {
-                        let pipe_command = self.parse_tokens(remaining_tokens)?;
-                        command.pipes.push(pipe_command);
+                Token::Op("|") => {
+                    let remaining_tokens: Vec<Token> = tokens[i + 1..].to_vec();
+                    if remaining_tokens.is_empty() {
+                        return Err(anyhow!("Expected command after '|'"));
                     }
+                    // Keep the pipeline flat: a | b | c has pipes [b, c]
+                    let mut pipe_command = self.parse_tokens(remaining_tokens)?;
+                    let rest = std::mem::take(&mut pipe_command.pipes);
+                    command.pipes.push(pipe_command);
+                    command.pipes.extend(rest);
                     break;
                 }
-                _ => {
-                    command.args.push(tokens[i].clone());
+                Token::Op(op) => {
+                    return Err(anyhow!("syntax error near unexpected token `{}'", op));
+                }
+                Token::Word(word) => {
+                    command.args.push(word.text.clone());
+                    command.raw_args.push(word.raw.clone());
                     i += 1;
                 }
             }
@@ -192,10 +897,52 @@ impl Parser {
         Ok(command)
     }
 
+    /// The words between the parentheses of `name=(...)`, as written. Newlines
+    /// separate words like spaces do.
+    fn array_words(&self, list: &str) -> Result<Vec<String>> {
+        let position = self.position();
+        let tokens = self.tokenize(list);
+        self.line.set(position.0);
+        self.column.set(position.1);
+        tokens?
+            .into_iter()
+            .filter(|token| *token != Token::Op("\n"))
+            .map(|token| match token {
+                Token::Word(word) => Ok(word.raw),
+                Token::Op(op) => Err(anyhow!("syntax error near unexpected token `{}'", op)),
+            })
+            .collect()
+    }
+
     pub fn set_alias(&mut self, name: String, value: String) {
         self.aliases.insert(name, value);
     }
 
+    /// True if defining `name` as `value` would make the alias lead back to itself,
+    /// directly (`alias ls='ls -la'`) or through other aliases. Such an alias is
+    /// still only expanded once per command, so it runs the command it shadows.
+    pub fn alias_refers_to_itself(&self, name: &str, value: &str) -> bool {
+        let position = self.position();
+        let mut currently_expanding = HashSet::from([name.to_string()]);
+        let mut value = value.to_string();
+        let result = loop {
+            let first_word = match self.tokenize(&value).ok().and_then(|tokens| tokens.into_iter().next()) {
+                Some(Token::Word(word)) => word.text,
+                _ => break false,
+            };
+            if first_word == name {
+                break true;
+            }
+            match self.aliases.get(&first_word) {
+                Some(next) if currently_expanding.insert(first_word.clone()) => value = next.clone(),
+                _ => break false,
+            }
+        };
+        self.line.set(position.0);
+        self.column.set(position.1);
+        result
+    }
+
     pub fn remove_alias(&mut self, name: &str) {
         self.aliases.remove(name);
     }
@@ -205,9 +952,67 @@ impl Parser {
     }
 }
 
+/// Groups a parsed list into a compound body: one element per `&&`/`||` list,
+/// split wherever `;` or a newline separates commands.
+fn into_body(commands: Vec<ChainedCommand>) -> Vec<ParsedCommand> {
+    let mut body: Vec<ParsedCommand> = Vec::new();
+    for (op, command) in commands {
+        match body.last_mut() {
+            Some(list) if op != ChainOp::Sequence => list.chain.push((op, command)),
+            _ => body.push(command),
+        }
+    }
+    body
+}
+
+/// The name and index of an array element reference `name[index]`.
+pub fn array_element(word: &str) -> Option<(&str, &str)> {
+    let (name, index) = word.strip_suffix(']')?.split_once('[')?;
+    is_name(name).then_some((name, index))
+}
+
+/// True for a valid variable name: a letter or `_`, then letters, digits or `_`.
+pub fn is_name(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_alphabetic() || c == '_') && word.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// True if the words after `coproc` are `{` or `NAME {`, which begin its
+/// brace group form.
+fn starts_coproc_body(pending: &VecDeque<Token>) -> bool {
+    let opens_group = |token: Option<&Token>| token.is_some_and(|token| token.is_word("{"));
+    opens_group(pending.front())
+        || (matches!(pending.front(), Some(Token::Word(word)) if is_name(&word.raw)) && opens_group(pending.get(1)))
+}
+
+fn token_text(token: &Token) -> &str {
+    match token {
+        Token::Word(word) => &word.raw,
+        Token::Op("\n") => "newline",
+        Token::Op(op) => op,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::errors;
+
+    #[test]
+    fn test_recursive_alias() {
+        let mut parser = Parser::new();
+        parser.set_alias("ls".to_string(), "ls -la".to_string());
+        parser.set_alias("a".to_string(), "b x".to_string());
+        parser.set_alias("ll".to_string(), "ls -l".to_string());
+
+        let parsed = parser.parse("ls src").unwrap();
+        assert_eq!(parsed.program, "ls");
+        assert_eq!(parsed.args, vec!["-la", "src"]);
+
+        assert!(parser.alias_refers_to_itself("ls", "ls -la"));
+        assert!(parser.alias_refers_to_itself("b", "a y"));
+        assert!(!parser.alias_refers_to_itself("l", "ll"));
+        assert!(!parser.alias_refers_to_itself("g", "git status"));
+    }
 
     #[test]
     fn test_simple_command() {
@@ -234,6 +1039,31 @@ mod tests {
         assert_eq!(result.output_redirect, Some("output.txt".to_string()));
     }
 
+    #[test]
+    fn test_combined_redirection() {
+        let parser = Parser::new();
+        let result = parser.parse("ls /missing &> out.txt").unwrap();
+        assert_eq!(result.output_redirect, Some("out.txt".to_string()));
+        assert_eq!(result.stderr_redirect, Some("out.txt".to_string()));
+        assert!(!result.background);
+
+        let result = parser.parse("ls &>> log.txt").unwrap();
+        assert_eq!(result.append_redirect, Some("log.txt".to_string()));
+        assert_eq!(result.stderr_append_redirect, Some("log.txt".to_string()));
+
+        // A space keeps `&` and `>` apart
+        let result = parser.parse("sleep 1 & > out.txt");
+        assert!(result.is_err() || result.unwrap().stderr_redirect.is_none());
+    }
+
+    #[test]
+    fn test_extglob_word() {
+        let parser = Parser::new();
+        let result = parser.parse("ls !(*.txt) +(a|(b)) x@(y)").unwrap();
+        assert_eq!(result.args, vec!["!(*.txt)", "+(a|(b))", "x@(y)"]);
+        assert!(parser.parse("ls !(*.txt").is_err());
+    }
+
     #[test]
     fn test_background() {
         let parser = Parser::new();
@@ -243,6 +1073,206 @@ mod tests {
         assert!(result.background);
     }
 
+    #[test]
+    fn test_error_position() {
+        let mut parser = Parser::new();
+        parser.set_line(7);
+        assert!(parser.parse("echo 'oops").is_err());
+        assert_eq!(parser.position(), (7, 6));
+
+        parser.set_line(1);
+        assert!(parser.parse("echo ok\necho \"bad").is_err());
+        assert_eq!(parser.position(), (2, 6));
+    }
+
+    #[test]
+    fn test_check() {
+        let mut parser = Parser::new();
+        let script = "echo ok\nif true; then\n  echo hi\nfi\n\n# fine\necho a )\ncat <\nfor x in a b; do\n  echo $x\n";
+        let found: Vec<(usize, usize, String)> = parser
+            .check(script)
+            .into_iter()
+            .map(|error| (error.line, error.col, error.message))
+            .collect();
+        assert_eq!(found, vec![
+            (7, 9, "syntax error near unexpected token `)'".to_string()),
+            (8, 6, "Expected filename after '<'".to_string()),
+            (10, 10, "syntax error: unexpected end of file (expecting `done')".to_string()),
+        ]);
+        assert!(parser.check("echo a\nwhile false; do\n  :\ndone\n").is_empty());
+
+        let error = parser.parse("echo a)").unwrap_err();
+        assert!(error.is::<ParseError>());
+        assert!(!parser.parse("echo 'a").unwrap_err().is::<ParseError>());
+    }
+
+    #[test]
+    fn test_comments() {
+        let parser = Parser::new();
+        let result = parser.parse("echo hi # trailing comment").unwrap();
+        assert_eq!(result.args, vec!["hi"]);
+        assert!(parser.parse("# only a comment").is_err());
+    }
+
+    #[test]
+    fn test_command_lists() {
+        let parser = Parser::new();
+        let result = parser.parse("false && echo yes || echo no; echo done").unwrap();
+        assert_eq!(result.program, "false");
+        let chain: Vec<(ChainOp, &str)> = result.chain.iter().map(|(op, c)| (*op, c.program.as_str())).collect();
+        assert_eq!(chain, vec![(ChainOp::And, "echo"), (ChainOp::Or, "echo"), (ChainOp::Sequence, "echo")]);
+        assert_eq!(result.chain[2].1.args, vec!["done"]);
+
+        let result = parser.parse("sleep 1 & echo 'a;b'").unwrap();
+        assert!(result.background);
+        assert_eq!(result.chain[0].1.args, vec!["a;b"]);
+
+        assert!(parser.parse("; echo").is_err());
+        assert!(parser.parse("echo a &&").is_err());
+    }
+
+    #[test]
+    fn test_pipeline_is_flat() {
+        let parser = Parser::new();
+        let result = parser.parse("cat file|grep x | wc -l > out").unwrap();
+        let programs: Vec<&str> = result.pipes.iter().map(|c| c.program.as_str()).collect();
+        assert_eq!(programs, vec!["grep", "wc"]);
+        assert_eq!(result.pipes[1].output_redirect, Some("out".to_string()));
+    }
+
+    #[test]
+    fn test_function_definitions() {
+        let parser = Parser::new();
+        let result = parser.parse("greet() { echo hi; echo \"$1\" && true; }; greet").unwrap();
+        match result.compound.as_deref() {
+            Some(CompoundCommand::FunctionDef { name, body }) => {
+                assert_eq!(name, "greet");
+                assert_eq!(body.len(), 2);
+                assert_eq!(body[1].raw_args, vec!["\"$1\""]);
+                assert_eq!(body[1].chain[0].1.program, "true");
+            }
+            other => panic!("expected a function definition, got {:?}", other),
+        }
+        assert_eq!(result.chain[0].1.program, "greet");
+
+        let result = parser.parse("function greet\n{\n  echo }\n}").unwrap();
+        assert!(matches!(result.compound.as_deref(), Some(CompoundCommand::FunctionDef { .. })));
+
+        let incomplete = parser.parse("greet() {\n echo hi").unwrap_err();
+        assert!(incomplete.is::<IncompleteInput>());
+        assert!(parser.parse("{ echo hi; } extra").is_err());
+    }
+
+    #[test]
+    fn test_conditional() {
+        let parser = Parser::new();
+        let result = parser
+            .parse("if test -f a; then echo a; elif false\nthen echo b\nelse if true; then echo c; fi; fi")
+            .unwrap();
+        match result.compound.as_deref() {
+            Some(CompoundCommand::Conditional { condition, then_body, elif_branches, else_body }) => {
+                assert_eq!(condition[0].program, "test");
+                assert_eq!(then_body[0].args, vec!["a"]);
+                assert_eq!(elif_branches.len(), 1);
+                let else_body = else_body.as_ref().unwrap();
+                assert!(matches!(else_body[0].compound.as_deref(), Some(CompoundCommand::Conditional { .. })));
+            }
+            other => panic!("expected a conditional, got {:?}", other),
+        }
+
+        assert!(parser.parse("if true; then echo x").unwrap_err().is::<IncompleteInput>());
+        assert!(parser.parse("if true; fi").is_err());
+        assert!(parser.parse("then echo x").is_err());
+    }
+
+    #[test]
+    fn test_loops() {
+        let parser = Parser::new();
+        let result = parser.parse("for f in *.rs \"a b\"\ndo echo $f; done").unwrap();
+        match result.compound.as_deref() {
+            Some(CompoundCommand::ForLoop { var, items, body }) => {
+                assert_eq!(var, "f");
+                assert_eq!(items, &vec!["*.rs".to_string(), "\"a b\"".to_string()]);
+                assert_eq!(body[0].raw_args, vec!["$f"]);
+            }
+            other => panic!("expected a for loop, got {:?}", other),
+        }
+
+        let result = parser.parse("until test -f x; do sleep 1; done").unwrap();
+        assert!(matches!(result.compound.as_deref(), Some(CompoundCommand::WhileLoop { negate: true, .. })));
+
+        let result = parser.parse("i=$((i + 1))").unwrap();
+        assert_eq!(result.program, "");
+        assert_eq!(result.environment.get("i"), Some(&"$((i + 1))".to_string()));
+
+        assert!(parser.parse("while true; do echo").unwrap_err().is::<IncompleteInput>());
+        assert!(parser.parse("for 1x in a; do echo; done").is_err());
+        assert!(parser.parse("done").is_err());
+    }
+
+    #[test]
+    fn test_case() {
+        let parser = Parser::new();
+        let result = parser
+            .parse("case \"$1\" in\n  *.rs|*.toml) echo rust;;\n  (*.py) echo python;&\n  *) ;;&\nesac")
+            .unwrap();
+        match result.compound.as_deref() {
+            Some(CompoundCommand::Case { word, arms }) => {
+                assert_eq!(word, "\"$1\"");
+                assert_eq!(arms.len(), 3);
+                assert_eq!(arms[0].patterns, vec!["*.rs", "*.toml"]);
+                assert_eq!(arms[0].terminator, CaseTerminator::Break);
+                assert_eq!(arms[1].terminator, CaseTerminator::FallThrough);
+                assert!(arms[2].body.is_empty());
+                assert_eq!(arms[2].terminator, CaseTerminator::Continue);
+            }
+            other => panic!("expected a case command, got {:?}", other),
+        }
+
+        assert!(parser.parse("case x in a) echo a; esac").is_ok());
+        assert!(parser.parse("case x in a) echo a;;").unwrap_err().is::<IncompleteInput>());
+        assert!(parser.parse("echo a;; echo b").is_err());
+    }
+
+    #[test]
+    fn test_subshell() {
+        let parser = Parser::new();
+        let result = parser.parse("(cd /tmp && (ls)) || echo failed").unwrap();
+        match result.compound.as_deref() {
+            Some(CompoundCommand::Subshell(body)) => {
+                assert_eq!(body.len(), 1);
+                assert_eq!(body[0].program, "cd");
+                assert!(matches!(body[0].chain[0].1.compound.as_deref(), Some(CompoundCommand::Subshell(_))));
+            }
+            other => panic!("expected a subshell, got {:?}", other),
+        }
+        assert_eq!(result.chain[0].0, ChainOp::Or);
+
+        assert!(parser.parse("(echo a").unwrap_err().is::<IncompleteInput>());
+        assert!(parser.parse("echo a)").is_err());
+    }
+
+    #[test]
+    fn test_coproc() {
+        let parser = Parser::new();
+        let result = parser.parse("coproc SORTER { sort; }; echo hi >&${SORTER[1]}").unwrap();
+        match result.compound.as_deref() {
+            Some(CompoundCommand::Coproc { name, body }) => {
+                assert_eq!(name, "SORTER");
+                assert_eq!(body[0].program, "sort");
+            }
+            other => panic!("expected a coprocess, got {:?}", other),
+        }
+        assert_eq!(result.chain[0].1.output_fd, Some("${SORTER[1]}".to_string()));
+
+        let result = parser.parse("coproc { cat; }").unwrap();
+        assert!(matches!(result.compound.as_deref(), Some(CompoundCommand::Coproc { name, .. }) if name == "COPROC"));
+        // The simple command form is left to the builtin
+        assert_eq!(parser.parse("coproc cat -n").unwrap().program, "coproc");
+        assert_eq!(parser.parse("read line <&3").unwrap().input_fd, Some("3".to_string()));
+        assert!(parser.parse("echo >&").is_err());
+    }
+
     #[test]
     fn test_environment_variables() {
         let parser = Parser::new();
@@ -250,4 +1280,40 @@ mod tests {
         assert_eq!(result.program, "echo");
         assert_eq!(result.environment.get("VAR"), Some(&"value".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_array_assignments() {
+        let parser = Parser::new();
+        let result = parser.parse("arr=(1 \"two three\" $x) arr[i+1]=four").unwrap();
+        assert!(result.program.is_empty());
+        assert_eq!(
+            result.array_assignments,
+            vec![
+                ArrayAssignment::Whole {
+                    name: "arr".to_string(),
+                    words: vec!["1".to_string(), "\"two three\"".to_string(), "$x".to_string()],
+                },
+                ArrayAssignment::Element { name: "arr".to_string(), index: "i+1".to_string(), value: "four".to_string() },
+            ]
+        );
+        assert_eq!(parser.parse("arr=(a\nb \")\")").unwrap().array_assignments.len(), 1);
+        assert!(parser.parse("arr=(a b").unwrap_err().is::<IncompleteInput>());
+        assert!(parser.parse("arr=(a) echo").is_err());
+        assert_eq!(parser.parse("echo arr=(a)").unwrap().args, vec!["arr=(a)"]);
+    }
+    #[test]
+    fn test_line_continuation() {
+        let parser = Parser::new();
+        let result = parser.parse("echo one \\\ntwo \"three\\\nfour\"").unwrap();
+        assert_eq!(result.args, vec!["one", "two", "threefour"]);
+
+        let result = parser.parse("echo 'a\\\nb'").unwrap();
+        assert_eq!(result.args, vec!["a\\\nb"]);
+
+        let unterminated = parser.parse("echo \"line").unwrap_err();
+        assert!(errors::needs_more_input(&unterminated));
+        assert_eq!(unterminated.to_string(), "Unterminated quote");
+        assert!(errors::needs_more_input(&parser.parse("echo a \\").unwrap_err()));
+        assert!(!errors::needs_more_input(&parser.parse("echo a)").unwrap_err()));
+    }
+}
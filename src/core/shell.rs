@@ -1,29 +1,77 @@
 use crate::cli::Cli;
-use crate::config::Config;
+use crate::config::{CompletionConfig, Config};
+use crate::config::watcher::ConfigWatcher;
+use crate::terminal::colors::{self, ColorScheme};
 use crate::terminal::Terminal;
-use crate::core::{executor::Executor, history::History, parser::Parser};
-use anyhow::Result;
+use crate::terminal::keybindings::{self, KeyAction, PendingBinding, PendingSlot};
+use crate::terminal::{file_picker, palette};
+use crate::terminal::shell_integration;
+use crate::core::errors::{self, ErrExit, ExitRequest, ReturnValue, UnboundVariable};
+use crate::core::command_cache::{self, CommandCache, SharedCommandCache};
+use crate::core::completion_functions::{completion_words, CompletionSnapshot};
+use crate::utils::completion::{system_hosts, system_users};
+use crate::utils::signals::signal_names;
+use crate::utils::quote::shell_quote;
+use crate::utils::param_expand;
+use crate::builtins;
+use crate::utils::terminal_capabilities::TerminalCapabilities;
+use crate::utils::tool_versions;
+use crate::core::timings::StartupTimings;
+use crate::core::hooks::ExecHooks;
+use crate::plugins::{self, FlexPlugin, PluginAction, SharedPlugins};
+use crate::core::{executor::Executor, history::History, options::ShellOptions, state::{CompletionSpec, ShellState, SpecialVars}, parser::{ParsedCommand, Parser}, ShellContext};
+use anyhow::{Context as _, Result};
 use colored::*;
-use log::{debug, info, warn};
+use log::{debug, error, info, warn};
 use rustyline::{Editor, Helper, Context, Config as EditorConfig, CompletionType, EditMode};
+use rustyline::config::Configurer;
 use rustyline::completion::{Completer, Pair, extract_word};
 use std::path::{Path, PathBuf};
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
 use rustyline::history::DefaultHistory;
 use rustyline::highlight::Highlighter;
 use rustyline_derive::{Helper, Hinter, Validator};
 use std::borrow::Cow;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal, Write};
+use std::time::Instant;
 
 #[derive(Helper, Hinter, Validator)]
 struct ShellHelper {
     colored_prompt: String,
+    command_cache: SharedCommandCache,
+    /// Wrap the prompt in OSC 133 marks
+    shell_integration: bool,
+    /// Set while any `complete` or `compdef` specs are registered
+    completion_specs: Option<CompletionSnapshot>,
+    /// Commands whose first argument completes to a host name
+    hostname_commands: Vec<String>,
+    /// Commands whose first argument completes to a user name
+    user_completion_commands: Vec<String>,
+    /// Offer system accounts (UID below 1000) as user names too
+    show_system_users: bool,
+    /// File and builtin names match the word regardless of case (`nocasematch`)
+    ignore_case: bool,
+    /// Loaded plugins, asked for completions before the shell's own
+    plugins: SharedPlugins,
 }
 
 impl ShellHelper {
-    fn new() -> Self {
+    fn new(
+        command_cache: SharedCommandCache,
+        shell_integration: bool,
+        completion: &CompletionConfig,
+        plugins: SharedPlugins,
+    ) -> Self {
         ShellHelper {
             colored_prompt: String::new(),
+            command_cache,
+            shell_integration,
+            completion_specs: None,
+            hostname_commands: completion.hostname_commands.clone(),
+            user_completion_commands: completion.user_completion_commands.clone(),
+            show_system_users: completion.show_system_users,
+            ignore_case: false,
+            plugins,
         }
     }
 
@@ -52,29 +100,9 @@ impl ShellHelper {
                 }
 
                 // Apply the color based on the code
-                match color_code.as_str() {
-                    "red" => result.push_str("\x1b[31m"),
-                    "green" => result.push_str("\x1b[32m"),
-                    "blue" => result.push_str("\x1b[34m"),
-                    "yellow" => result.push_str("\x1b[33m"),
-                    "magenta" => result.push_str("\x1b[35m"),
-                    "cyan" => result.push_str("\x1b[36m"),
-                    "white" => result.push_str("\x1b[37m"),
-                    "black" => result.push_str("\x1b[30m"),
-                    "bright_red" => result.push_str("\x1b[91m"),
-                    "bright_green" => result.push_str("\x1b[92m"),
-                    "bright_blue" => result.push_str("\x1b[94m"),
-                    "bright_yellow" => result.push_str("\x1b[93m"),
-                    "bright_magenta" => result.push_str("\x1b[95m"),
-                    "bright_cyan" => result.push_str("\x1b[96m"),
-                    "bright_white" => result.push_str("\x1b[97m"),
-                    "bright_black" => result.push_str("\x1b[90m"),
-                    "reset" => result.push_str("\x1b[0m"),
-                    "bold" => result.push_str("\x1b[1m"),
-                    "dim" => result.push_str("\x1b[2m"),
-                    "italic" => result.push_str("\x1b[3m"),
-                    "underline" => result.push_str("\x1b[4m"),
-                    _ => {
+                match colors::ansi_code(&color_code) {
+                    Some(code) => result.push_str(&code),
+                    None => {
                         // Unknown color code, put it back as-is
                         result.push('{');
                         result.push_str(&color_code);
@@ -89,6 +117,148 @@ impl ShellHelper {
         result
     }
 
+    /// Host names for the first argument of commands such as `ssh`, keeping a `user@` prefix.
+    fn complete_hosts(&self, line: &str, pos: usize, word: &str) -> Option<Vec<Pair>> {
+        if !is_first_argument(line, pos, word, &self.hostname_commands) {
+            return None;
+        }
+
+        let (user, prefix) = match word.split_once('@') {
+            Some((user, host)) => (format!("{}@", user), host),
+            None => (String::new(), word),
+        };
+        let matches: Vec<Pair> = system_hosts()
+            .into_iter()
+            .filter(|host| host.starts_with(prefix))
+            .map(|host| Pair { replacement: format!("{}{}", user, host), display: host })
+            .collect();
+        (!matches.is_empty()).then_some(matches)
+    }
+
+    /// True if `name` starts with `prefix`, ignoring case under `nocasematch`.
+    fn matches_prefix(&self, name: &str, prefix: &str) -> bool {
+        match name.get(..prefix.len()) {
+            Some(start) if self.ignore_case => start.eq_ignore_ascii_case(prefix),
+            _ => name.starts_with(prefix),
+        }
+    }
+
+    /// Builtin names, then programs on `PATH`, that start with `word`.
+    fn complete_commands(&self, word: &str) -> Vec<Pair> {
+        let mut command_matches = Vec::new();
+
+        // Built-in commands first
+        let builtin_commands = [
+            "cd", "echo", "exit", "help", "history", "ls", "pwd",
+            "alias", "env", "which", "clear", "config", "set", "setopt", "unsetopt", "shift", "return",
+            "break", "continue", "hash", "time", "basename", "dirname", "date",
+            "mkdir", "mkfifo", "touch", "export", "local", "readonly", "unset", "read", "coproc", "tee", "xargs", "compdef", "compgen", "complete",
+            "color", "terminal"
+        ];
+
+        for cmd in &builtin_commands {
+            if self.matches_prefix(cmd, word) {
+                command_matches.push(Pair {
+                    display: cmd.to_string(),
+                    replacement: cmd.to_string(),
+                });
+            }
+        }
+
+        // Then add executable programs from PATH
+        if let Some(path_matches) = self.complete_programs(word) {
+            command_matches.extend(path_matches);
+        }
+        command_matches
+    }
+
+    /// The candidates the spec registered with `complete` or `compdef` gives for
+    /// `word`, when the command on `line` has one. Unless it names a function that
+    /// isn't defined, a spec replaces the usual completions even when it gives none.
+    fn complete_spec(&self, line: &str, pos: usize, word: &str, start: usize) -> Option<Vec<Pair>> {
+        let snapshot = self.completion_specs.as_ref()?;
+        let (words, current) = completion_words(line, pos);
+        if current == 0 {
+            return None;
+        }
+        let pairs = |names: Vec<String>| -> Vec<Pair> {
+            names
+                .into_iter()
+                .filter(|name| name.starts_with(word))
+                .map(|name| Pair { display: name.clone(), replacement: name })
+                .collect()
+        };
+        Some(match snapshot.state.completion_specs.get(&words[0])? {
+            CompletionSpec::Function(_) => {
+                let candidates = snapshot.complete(line, pos)?;
+                candidates.into_iter().map(|candidate| Pair { display: candidate.clone(), replacement: candidate }).collect()
+            }
+            CompletionSpec::Words(list) => {
+                // The list is split like an unquoted expansion, as by `compgen -W`
+                let ifs = std::env::var("IFS").unwrap_or_else(|_| param_expand::DEFAULT_IFS.to_string());
+                pairs(param_expand::ifs_split(list, &ifs))
+            }
+            CompletionSpec::Files => self.complete_complex_path(word, start).unwrap_or_default(),
+            CompletionSpec::Directories => self
+                .complete_complex_path(word, start)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|pair| pair.replacement.ends_with('/'))
+                .collect(),
+            CompletionSpec::Builtins => pairs(builtins::list_builtins().into_iter().map(String::from).collect()),
+            CompletionSpec::Commands => self.complete_commands(word),
+        })
+    }
+
+    /// The first completions a plugin offers for `word`.
+    fn complete_plugins(&self, line: &str, pos: usize, word: &str) -> Option<Vec<Pair>> {
+        let (words, current) = completion_words(line, pos);
+        let plugins = self.plugins.read().ok()?;
+        let candidates = plugins
+            .iter()
+            .map(|plugin| plugin.completions(word, &words[..current]))
+            .find(|candidates| !candidates.is_empty())?;
+        Some(candidates.into_iter().map(|candidate| Pair { display: candidate.display, replacement: candidate.text }).collect())
+    }
+
+    /// Signal names for `kill -SIG`, `kill -s SIG` and the signals after `trap`'s
+    /// command, offered both with and without the `SIG` prefix.
+    fn complete_signals(&self, line: &str, pos: usize, word: &str) -> Option<Vec<Pair>> {
+        let (words, current) = completion_words(line, pos);
+        let (dash, prefix) = match words[0].as_str() {
+            "kill" if current > 0 && words[current - 1] == "-s" => ("", word),
+            "kill" if current > 0 => ("-", word.strip_prefix('-')?),
+            "trap" if current > 1 => ("", word),
+            _ => return None,
+        };
+
+        let mut matches = Vec::new();
+        for (name, number) in signal_names() {
+            let mut forms = vec![name.clone(), format!("SIG{}", name)];
+            if !dash.is_empty() {
+                forms.push(number.to_string());
+            }
+            for form in forms.into_iter().filter(|form| form.starts_with(prefix)) {
+                matches.push(Pair { replacement: format!("{}{}", dash, form), display: form });
+            }
+        }
+        (!matches.is_empty()).then_some(matches)
+    }
+
+    /// Login names for the first argument of commands such as `su`, or the owner
+    /// part of `chown`'s `owner:group`.
+    fn complete_users(&self, line: &str, pos: usize, word: &str) -> Option<Vec<Pair>> {
+        if !is_first_argument(line, pos, word, &self.user_completion_commands) || word.contains(':') {
+            return None;
+        }
+        let matches: Vec<Pair> = system_users(self.show_system_users)
+            .into_iter()
+            .filter(|user| user.starts_with(word))
+            .map(|user| Pair { display: user.clone(), replacement: user })
+            .collect();
+        (!matches.is_empty()).then_some(matches)
+    }
+
     // Manual path completion for all path scenarios
     fn complete_complex_path(&self, word: &str, _start: usize) -> Option<Vec<Pair>> {
         debug!("Attempting path completion for: '{}'", word);
@@ -164,7 +334,7 @@ impl ShellHelper {
                 let name = file_name.to_string_lossy();
 
                 // Filter based on file_part prefix
-                if name.starts_with(&file_part) {
+                if self.matches_prefix(&name, &file_part) {
                     let display = name.to_string();
 
                     // Determine the proper replacement based on context
@@ -228,79 +398,25 @@ impl ShellHelper {
             return None; // Don't complete all programs with empty prefix
         }
 
-        let mut matches = Vec::new();
         let paths = std::env::var("PATH").unwrap_or_default();
+        let to_pairs = |cache: &CommandCache| -> Vec<Pair> {
+            cache
+                .matching(prefix)
+                .map(|name| Pair { display: name.to_string(), replacement: name.to_string() })
+                .collect()
+        };
 
-        #[cfg(windows)]
-        let path_separator = ";";
-        #[cfg(not(windows))]
-        let path_separator = ":";
-
-        #[cfg(windows)]
-        let executable_extensions = vec!["exe", "bat", "cmd", "com"];
-
-        for path_dir in paths.split(path_separator) {
-            if path_dir.is_empty() {
-                continue;
-            }
-
-            let dir_path = Path::new(path_dir);
-            if !dir_path.exists() || !dir_path.is_dir() {
-                continue;
-            }
-
-            if let Ok(entries) = dir_path.read_dir() {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        let file_name = entry.file_name();
-                        let name = file_name.to_string_lossy();
-
-                        // Check if it's an executable
-                        #[cfg(windows)]
-                        let is_executable = {
-                            if let Some(ext) = entry.path().extension() {
-                                let ext = ext.to_string_lossy().to_lowercase();
-                                executable_extensions.contains(&ext.as_str())
-                            } else {
-                                false
-                            }
-                        };
-
-                        #[cfg(not(windows))]
-                        let is_executable = {
-                            entry.path().is_file() &&
-                            entry.metadata().map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false)
-                        };
-
-                        if is_executable && name.starts_with(prefix) {
-                            // Remove file extension on Windows for cleaner completion
-                            #[cfg(windows)]
-                            let display_name = {
-                                if let Some(stem) = entry.path().file_stem() {
-                                    stem.to_string_lossy().to_string()
-                                } else {
-                                    name.to_string()
-                                }
-                            };
-
-                            #[cfg(not(windows))]
-                            let display_name = name.to_string();
-
-                            // Avoid duplicates
-                            if !matches.iter().any(|m: &Pair| m.display == display_name) {
-                                matches.push(Pair {
-                                    display: display_name.clone(),
-                                    replacement: display_name,
-                                });
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        // Use the background index when it's ready; never wait for it
+        let cached = match self.command_cache.try_read() {
+            Ok(cache) if cache.is_current(&paths) => Some(to_pairs(&cache)),
+            _ => None,
+        };
+        let mut matches = cached.unwrap_or_else(|| {
+            debug!("Command cache not ready, scanning PATH");
+            to_pairs(&CommandCache::scan(&paths))
+        });
 
-        // Sort matches and limit to reasonable number
-        matches.sort_by(|a, b| a.display.cmp(&b.display));
+        // Already sorted; limit to reasonable number
         matches.truncate(100); // Limit to 100 programs to avoid overwhelming user
 
         debug!("Found {} program matches", matches.len());
@@ -328,36 +444,34 @@ impl Completer for ShellHelper {
 
         debug!("Completion request for word: '{}' at position {} (start={})", word, pos, start);
 
-        // Handle command completion (only at start of line)
-        if start == 0 {
-            let mut command_matches = Vec::new();
-
-            // Built-in commands first
-            let builtin_commands = [
-                "cd", "echo", "exit", "help", "history", "ls", "pwd",
-                "alias", "env", "which", "clear"
-            ];
-
-            for cmd in &builtin_commands {
-                if cmd.starts_with(word) {
-                    command_matches.push(Pair {
-                        display: cmd.to_string(),
-                        replacement: cmd.to_string(),
-                    });
-                }
-            }
+        // A registered completion spec decides the candidates for its command
+        if let Some(spec_matches) = self.complete_spec(line, pos, word, start) {
+            return Ok((start, spec_matches));
+        }
 
-            // Then add executable programs from PATH
-            if let Some(path_matches) = self.complete_programs(&word) {
-                command_matches.extend(path_matches);
-            }
+        if let Some(plugin_matches) = self.complete_plugins(line, pos, word) {
+            return Ok((start, plugin_matches));
+        }
 
+        // Handle command completion (only at start of line)
+        if start == 0 {
+            let command_matches = self.complete_commands(word);
             if !command_matches.is_empty() {
                 debug!("Found {} command matches", command_matches.len());
                 return Ok((start, command_matches));
             }
         }
 
+        if let Some(host_matches) = self.complete_hosts(line, pos, word) {
+            return Ok((start, host_matches));
+        }
+        if let Some(user_matches) = self.complete_users(line, pos, word) {
+            return Ok((start, user_matches));
+        }
+        if let Some(signal_matches) = self.complete_signals(line, pos, word) {
+            return Ok((start, signal_matches));
+        }
+
         // Handle ALL other completion (files, paths, arguments) manually
         debug!("Attempting file/path completion");
         if let Some(file_matches) = self.complete_complex_path(&word, start) {
@@ -378,7 +492,11 @@ impl Highlighter for ShellHelper {
         prompt: &'p str,
         default: bool,
     ) -> Cow<'b, str> {
-        if default || self.colored_prompt.is_empty() {
+        // The marks go here rather than in the prompt itself, which rustyline
+        // measures and would count them as visible text
+        if default && self.shell_integration {
+            Cow::Owned(format!("{}{}{}", shell_integration::PROMPT_START, prompt, shell_integration::INPUT_START))
+        } else if default || self.colored_prompt.is_empty() {
             Cow::Borrowed(prompt)
         } else {
             // Return the colored version which has same display width as clean prompt
@@ -417,58 +535,433 @@ pub struct Shell {
     history: History,
     parser: Parser,
     executor: Executor,
+    options: ShellOptions,
+    state: ShellState,
     current_dir: PathBuf,
     exit_code: i32,
     should_exit: bool,
+    pending_binding: PendingSlot,
+    /// Text to start the next prompt with, before and after the cursor
+    initial_line: (String, String),
+    command_cache: SharedCommandCache,
+    /// Startup phases recorded so far; taken when the report is made
+    startup_timings: Option<StartupTimings>,
+    show_timings: bool,
+    /// Started with `-l` or a name beginning with `-`
+    login: bool,
+    /// Emit OSC 133 and OSC 7 sequences for the terminal
+    shell_integration: bool,
+    /// A command started since the last prompt, so its end mark is still due
+    command_running: bool,
+    /// Registered by extensions to run around interactive commands
+    exec_hooks: ExecHooks,
+    plugins: SharedPlugins,
+    /// A sandbox's environment and directory, put in place while it runs a command
+    sandbox_env: Option<ProcessEnv>,
+    /// Set while `auto_reload_config` has the interactive shell watching the config file
+    config_watcher: Option<ConfigWatcher>,
 }
 
-impl Shell {
-    pub async fn new(args: Cli) -> Result<Self> {
-        let config = Config::new(args.config)?;
-        let terminal = Terminal::new(config.get().colors.enabled && !args.no_color)?;
+/// A line editor set up for completion with the shell's key bindings.
+fn new_editor(
+    config: &Config,
+    capabilities: &TerminalCapabilities,
+    command_cache: &SharedCommandCache,
+    shell_integration: bool,
+    plugins: &SharedPlugins,
+    pending_binding: &PendingSlot,
+) -> Result<Editor<ShellHelper, DefaultHistory>> {
+    // With bracketed paste, a pasted block arrives as one multi-line entry that
+    // runs as a list of commands once Enter is pressed
+    let editor_config = EditorConfig::builder()
+        .completion_type(CompletionType::List)
+        .edit_mode(EditMode::Emacs)
+        .bracketed_paste(config.get().bracketed_paste && capabilities.bracketed_paste)
+        .build();
+
+    let mut editor = Editor::with_config(editor_config)?;
+    editor.set_helper(Some(ShellHelper::new(
+        command_cache.clone(),
+        shell_integration,
+        &config.get().completion,
+        plugins.clone(),
+    )));
+    keybindings::apply(&mut editor, &config.get().keybindings, pending_binding, config.get().completion.file_picker);
+    Ok(editor)
+}
+
+/// The process-wide part of a shell's state: its environment variables and working
+/// directory, which a sandbox keeps apart from its parent's.
+struct ProcessEnv {
+    vars: Vec<(OsString, OsString)>,
+    dir: Option<PathBuf>,
+}
+
+impl ProcessEnv {
+    fn capture() -> Self {
+        Self { vars: std::env::vars_os().collect(), dir: std::env::current_dir().ok() }
+    }
+
+    /// Puts this environment in place and returns the one it replaced.
+    fn swap(self) -> Self {
+        let replaced = Self::capture();
+        for (key, _) in &replaced.vars {
+            std::env::remove_var(key);
+        }
+        for (key, value) in self.vars {
+            std::env::set_var(key, value);
+        }
+        if let Some(dir) = self.dir {
+            let _ = std::env::set_current_dir(dir);
+        }
+        replaced
+    }
+}
 
-        // Configure the editor with proper settings for completion
-        let editor_config = EditorConfig::builder()
-            .completion_type(CompletionType::List)
-            .edit_mode(EditMode::Emacs)
-            .build();
+/// Reports the syntax errors in `script` as `file:line:col: error: message` and
+/// returns the exit status: 0 if there were none, 1 if there were.
+fn check_script(script: &Path) -> i32 {
+    let source = match std::fs::read_to_string(script) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}: cannot read script: {}", script.display(), errors::io_error_message(&e));
+            return 2;
+        }
+    };
+    let errors = Parser::new().check(&source);
+    for error in &errors {
+        eprintln!("{}:{}:{}: error: {}", script.display(), error.line, error.col, error.message);
+    }
+    i32::from(!errors.is_empty())
+}
 
-        let mut editor = Editor::with_config(editor_config)?;
-        editor.set_helper(Some(ShellHelper::new()));
+/// Defines the aliases from the `[aliases]` table, skipping ones that refer to
+/// themselves when `prevent_recursive_aliases` is set.
+fn add_config_aliases(parser: &mut Parser, config: &Config) {
+    let prevent_recursive_aliases = config.get().completion.prevent_recursive_aliases;
+    for (name, value) in &config.get().aliases {
+        if prevent_recursive_aliases && parser.alias_refers_to_itself(name, value) {
+            warn!("Skipping alias '{}', which refers to itself", name);
+            continue;
+        }
+        parser.set_alias(name.clone(), value.clone());
+    }
+}
 
+/// The prompt `format` with each `\n` and `{newline}` made a line break. rustyline
+/// measures each line of the prompt, so the cursor goes after the last one.
+fn expand_prompt_newlines(format: &str) -> String {
+    format.replace("{newline}", "\n").replace("\\n", "\n")
+}
+
+/// `line` split at the cursor position `pos`.
+fn split_at_cursor(mut line: String, pos: usize) -> (String, String) {
+    let after = line.split_off(pos.min(line.len()));
+    (line, after)
+}
+
+/// Whether `word`, ending at `pos`, is the first argument after one of `commands`
+/// that isn't an option.
+fn is_first_argument(line: &str, pos: usize, word: &str, commands: &[String]) -> bool {
+    let (words, current) = completion_words(line, pos);
+    current > 0
+        && commands.contains(&words[0])
+        && !word.starts_with('-')
+        && words[1..current].iter().all(|arg| arg.starts_with('-'))
+}
+
+/// Exits with status 143 (128 + SIGTERM) when the shell is told to terminate, after
+/// restoring the terminal. History needs no flushing, since each entry is written as
+/// it's added. This runs on its own task because the main loop blocks reading input.
+fn spawn_terminate_handler() {
+    #[cfg(unix)]
+    tokio::spawn(async {
+        use tokio::signal::unix::{signal, SignalKind};
+        let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+            return;
+        };
+        terminate.recv().await;
+        debug!("Received SIGTERM, exiting");
+        let _ = crossterm::terminal::disable_raw_mode();
+        std::process::exit(143);
+    });
+
+    // Closing the console window or shutting down Windows
+    #[cfg(windows)]
+    tokio::spawn(async {
+        use tokio::signal::windows::{ctrl_close, ctrl_shutdown};
+        let (Ok(mut close), Ok(mut shutdown)) = (ctrl_close(), ctrl_shutdown()) else {
+            return;
+        };
+        tokio::select! {
+            _ = close.recv() => {}
+            _ = shutdown.recv() => {}
+        }
+        let _ = crossterm::terminal::disable_raw_mode();
+        std::process::exit(143);
+    });
+}
+
+impl Shell {
+    pub async fn new(args: Cli) -> Result<Self> {
+        let mut timings = StartupTimings::default();
+        let started = Instant::now();
+        let config = Config::new(args.config, args.profile)?;
+        timings.record("config load", started.elapsed(), "");
+        let capabilities = TerminalCapabilities::detect();
+        colors::set_truecolor(capabilities.truecolor);
+        let scheme = config.find_scheme(&config.get().colors.scheme).unwrap_or_else(ColorScheme::default_scheme);
+        let terminal = Terminal::new(config.get().colors.enabled && !args.no_color, scheme, capabilities.multiplexer)?;
+
+        let command_cache = SharedCommandCache::default();
+        command_cache::spawn_indexer(command_cache.clone());
+        let shell_integration = config.get().shell_integration && capabilities.shell_integration;
+        let plugins = SharedPlugins::default();
+        let pending_binding = PendingSlot::default();
+        let editor = new_editor(&config, &capabilities, &command_cache, shell_integration, &plugins, &pending_binding)?;
+
+        let started = Instant::now();
         let history = History::new(config.get().history.clone())?;
-        let parser = Parser::new();
+        timings.record("history load", started.elapsed(), format!("{} entries", history.get_entries().len()));
+        let mut parser = Parser::new();
+        add_config_aliases(&mut parser, &config);
         let executor = Executor::new();
 
-        // Get current directory and canonicalize it
+        // Start from $PWD when it names the real working directory (keeps symlinks),
+        // otherwise canonicalize
         let raw_dir = std::env::current_dir()?;
-        let current_dir = if let Ok(canonical) = raw_dir.canonicalize() {
-            crate::utils::path::strip_windows_prefix(&canonical)
-        } else {
-            crate::utils::path::strip_windows_prefix(&raw_dir)
+        let current_dir = match std::env::var_os("PWD").map(PathBuf::from) {
+            Some(pwd) if pwd.is_absolute() && crate::utils::path::same_location(&pwd, &raw_dir) => pwd,
+            _ => if let Ok(canonical) = raw_dir.canonicalize() {
+                crate::utils::path::strip_windows_prefix(&canonical)
+            } else {
+                crate::utils::path::strip_windows_prefix(&raw_dir)
+            },
         };
 
+        std::env::set_var("PWD", &current_dir);
+        crate::utils::path::set_wsl_interop(config.get().wsl_interop);
+
         debug!("Shell initialized with config: {:?}", config.get());
 
-        Ok(Self {
+        let show_timings = args.timings || config.get().startup_profiler;
+        let globstar = config.get().completion.globstar_enabled;
+        let auto_cd = config.get().auto_cd;
+        // As with other shells, `login` or `exec -a -flex-sh` starts a login shell
+        let argv0 = std::env::args().next().unwrap_or_else(|| "flex-sh".to_string());
+        let login = args.login || argv0.starts_with('-');
+        let script_name = match &args.script {
+            Some(script) => script.display().to_string(),
+            None => {
+                let name = crate::utils::path::basename(argv0.trim_start_matches('-'), "");
+                if login { format!("-{}", name) } else { name }
+            }
+        };
+        let mut shell = Self {
+            shell_integration,
+            command_running: false,
             config,
             terminal,
             editor,
             history,
             parser,
             executor,
+            options: ShellOptions {
+                exit_on_error: args.exit_on_error,
+                restricted: args.restricted,
+                globstar,
+                auto_cd,
+                ..ShellOptions::default()
+            },
+            state: ShellState {
+                positional: args.script_args,
+                special: SpecialVars { script_name, ..SpecialVars::default() },
+                capabilities,
+                ..ShellState::default()
+            },
             current_dir,
             exit_code: 0,
             should_exit: false,
+            pending_binding,
+            initial_line: (String::new(), String::new()),
+            command_cache,
+            startup_timings: Some(timings),
+            show_timings,
+            login,
+            exec_hooks: ExecHooks::default(),
+            plugins,
+            sandbox_env: None,
+            config_watcher: None,
+        };
+        for name in shell.config.get().plugins.enabled.clone() {
+            let result = match plugins::builtin_plugin(&name) {
+                Some(plugin) => shell.add_plugin(plugin),
+                None => Err(anyhow::anyhow!("no such plugin")),
+            };
+            if let Err(e) = result {
+                warn!("Plugin '{}' not loaded: {:#}", name, e);
+            }
+        }
+        if login {
+            shell.load_login_profile().await;
+        }
+        Ok(shell)
+    }
+
+    /// A new shell starting from this one's configuration, variables, functions,
+    /// options, aliases and directory, with an empty in-memory history, no jobs and
+    /// no plugins. Nothing it does reaches this shell, including `cd` and `export`:
+    /// its own environment and directory are put in place only while
+    /// `execute_command` or `execute_capturing` runs. Meant for running scripts in
+    /// isolation, such as in tests.
+    pub fn sandbox(&self) -> Result<Shell> {
+        let command_cache = self.command_cache.clone();
+        let plugins = SharedPlugins::default();
+        let pending_binding = PendingSlot::default();
+        let editor = new_editor(
+            &self.config,
+            &self.state.capabilities,
+            &command_cache,
+            self.shell_integration,
+            &plugins,
+            &pending_binding,
+        )?;
+        Ok(Self {
+            config: self.config.clone(),
+            terminal: self.terminal.clone(),
+            editor,
+            history: History::in_memory(self.config.get().history.clone()),
+            parser: self.parser.clone(),
+            executor: Executor::new(),
+            options: self.options.clone(),
+            state: self.state.clone(),
+            current_dir: self.current_dir.clone(),
+            exit_code: self.exit_code,
+            should_exit: false,
+            pending_binding,
+            initial_line: (String::new(), String::new()),
+            command_cache,
+            startup_timings: None,
+            show_timings: false,
+            login: false,
+            shell_integration: self.shell_integration,
+            command_running: false,
+            exec_hooks: ExecHooks::default(),
+            plugins,
+            sandbox_env: Some(ProcessEnv::capture()),
+            config_watcher: None,
         })
     }
 
+    /// Runs the shell as the `flex-sh` binary does with the command line `args`:
+    /// one command, a script, or interactively, and returns the exit status.
+    pub async fn run_with_args(args: Cli) -> Result<i32> {
+        debug!("Starting Flex-SH v{}", env!("CARGO_PKG_VERSION"));
+
+        if args.dump_config {
+            let config = Config::new(args.config.clone(), args.profile.clone())?;
+            print!("{}", config.dump()?);
+            return Ok(0);
+        }
+        if let Some(script) = args.script.as_ref().filter(|_| args.check_only) {
+            return Ok(check_script(script));
+        }
+
+        let mut shell = Shell::new(args.clone()).await?;
+
+        if let Some(command) = args.command {
+            // Execute single command and exit
+            if let Err(e) = shell.execute_command(&command).await {
+                error!("Command error: {}", e);
+                return Ok(1);
+            }
+        } else if let Some(script) = args.script {
+            if let Err(e) = shell.execute_script(&script).await {
+                eprintln!("{:#}", e);
+                return Ok(2);
+            }
+        } else {
+            // Enter interactive mode
+            if let Err(e) = shell.run().await {
+                error!("Shell error: {}", e);
+                return Ok(1);
+            }
+        }
+
+        shell.logout().await;
+        Ok(shell.exit_code())
+    }
+
+    /// Sources the login files: `/etc/profile`, then `~/.flexshrc` or, when there
+    /// is none, `~/.profile`. On macOS `PATH` is first set up from `/etc/paths`
+    /// and `/etc/paths.d`, as `path_helper` does. Unlike the completion scripts,
+    /// these can set the exit status and `exit`.
+    async fn load_login_profile(&mut self) {
+        let started = Instant::now();
+        #[cfg(target_os = "macos")]
+        load_system_paths();
+
+        let mut files = vec![PathBuf::from("/etc/profile")];
+        if let Some(home) = dirs::home_dir() {
+            let flexshrc = home.join(".flexshrc");
+            files.push(if flexshrc.is_file() { flexshrc } else { home.join(".profile") });
+        }
+        let files: Vec<PathBuf> = files.into_iter().filter(|file| file.is_file()).collect();
+        for file in &files {
+            if let Err(e) = self.source_file(file).await {
+                eprintln!("{:#}", e);
+            }
+            if self.should_exit {
+                break;
+            }
+        }
+        if let Some(timings) = self.startup_timings.as_mut() {
+            timings.record("login profile", started.elapsed(), format!("{} files", files.len()));
+        }
+    }
+
+    /// Sources `~/.flexsh_logout` when a login shell finishes. Its status doesn't
+    /// replace the shell's.
+    pub async fn logout(&mut self) {
+        if !self.login {
+            return;
+        }
+        let Some(file) = dirs::home_dir().map(|home| home.join(".flexsh_logout")) else {
+            return;
+        };
+        if file.is_file() {
+            let exit_code = self.exit_code;
+            if let Err(e) = self.source_file(&file).await {
+                eprintln!("{:#}", e);
+            }
+            self.exit_code = exit_code;
+        }
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         debug!("Starting shell main loop");
-
+        self.load_completion_scripts().await;
+
+        // Preferred terminal settings, in `stty` syntax, go in before raw mode saves them
+        if let Ok(settings) = std::env::var("STTY") {
+            let applied = crate::utils::termios::parse_stty(&settings)
+                .and_then(|settings| crate::utils::termios::apply_stty(&settings));
+            if let Err(e) = applied {
+                warn!("Ignoring STTY: {:#}", e);
+            }
+        }
         if let Err(e) = self.terminal.enter_raw_mode().await {
             warn!("Failed to enter raw mode: {}", e);
         }
+        spawn_terminate_handler();
+        if self.config.get().auto_reload_config {
+            if let Some(path) = self.config.path() {
+                match ConfigWatcher::new(path) {
+                    Ok(watcher) => self.config_watcher = Some(watcher),
+                    Err(e) => warn!("Not watching {} for changes: {:#}", path.display(), e),
+                }
+            }
+        }
 
         loop {
             if self.should_exit {
@@ -479,7 +972,12 @@ impl Shell {
                 Ok(_) => {}
                 Err(e) => {
                     self.terminal.print_error(&format!("Shell error: {}", e)).await?;
-                    self.exit_code = 1;
+                    if !e.is::<ErrExit>() {
+                        self.exit_code = errors::failure_status(&e);
+                    }
+                    if self.options.exit_on_error {
+                        self.should_exit = true;
+                    }
                 }
             }
         }
@@ -490,49 +988,163 @@ impl Shell {
         Ok(())
     }
 
+    /// Loads the config file again after it changed, adding its aliases. Settings
+    /// read at startup, such as key bindings, keep their values.
+    async fn reload_config(&mut self) -> Result<()> {
+        if let Err(e) = self.config.reload() {
+            return self.terminal.print_error(&format!("[flex-sh] Config not reloaded: {:#}", e)).await;
+        }
+        add_config_aliases(&mut self.parser, &self.config);
+        self.options.auto_cd = self.config.get().auto_cd;
+        self.terminal.print_info("[flex-sh] Config reloaded").await
+    }
+
+    /// Sources the completion scripts (`*.completion` and `*.flexsh` files, usually
+    /// calling `compdef`) in the completions directory, in name order. A script that
+    /// fails is reported and the rest still load.
+    async fn load_completion_scripts(&mut self) {
+        let dir = match &self.config.get().completions_dir {
+            Some(dir) => crate::utils::path::expand_tilde(dir),
+            None => match crate::config::completions_dir() {
+                Some(dir) => dir,
+                None => return,
+            },
+        };
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return;
+        };
+        let mut scripts: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| matches!(path.extension().and_then(|ext| ext.to_str()), Some("completion" | "flexsh")))
+            .collect();
+        scripts.sort();
+
+        let started = Instant::now();
+        // A script's failures or `exit` don't carry over into the session
+        let exit_code = self.exit_code;
+        for script in &scripts {
+            if let Err(e) = self.source_file(script).await {
+                eprintln!("{:#}", e);
+            }
+        }
+        self.exit_code = exit_code;
+        self.should_exit = false;
+        if let Some(timings) = self.startup_timings.as_mut() {
+            timings.record("completion scripts", started.elapsed(), format!("{} files", scripts.len()));
+        }
+    }
+
     async fn run_interactive(&mut self) -> Result<()> {
+        for job in self.executor.finished_jobs() {
+            println!("{}", job.message());
+        }
+        if self.config_watcher.as_mut().is_some_and(|watcher| watcher.changed()) {
+            self.reload_config().await?;
+        }
+        if self.options.sharehistory {
+            self.history.reload()?;
+            self.history.take_modified();
+            self.sync_editor_history()?;
+        }
+        // `color scheme` switches the scheme by changing the config
+        let scheme_name = &self.config.get().colors.scheme;
+        if self.terminal.scheme().name != *scheme_name {
+            if let Some(scheme) = self.config.find_scheme(scheme_name) {
+                self.terminal.set_scheme(scheme);
+            }
+        }
+        if self.shell_integration {
+            self.emit_shell_integration();
+        }
+        // TERM=dumb means no escape sequences at all
+        let titled = io::stdout().is_terminal() && std::env::var("TERM").map_or(true, |term| term != "dumb");
+        if self.config.get().window_title && titled {
+            self.terminal.update_window_title(&self.window_title());
+        }
+
+        let started = Instant::now();
     let prompt = self.build_prompt()?;
 
+        self.editor.set_edit_mode(if self.options.vi_mode { EditMode::Vi } else { EditMode::Emacs });
+
         // Store the original prompt for highlighting
+        let completion_specs = (!self.state.completion_specs.is_empty()).then(|| CompletionSnapshot {
+            // Holding the coprocesses' pipes would keep `coproc -c` from closing them
+            state: ShellState { coprocs: Default::default(), ..self.state.clone() },
+            options: self.options.clone(),
+            config: self.config.clone(),
+            current_dir: self.current_dir.clone(),
+        });
         let colored_prompt = if let Some(helper) = self.editor.helper_mut() {
             helper.set_colored_prompt(&prompt);
+            helper.completion_specs = completion_specs;
+            helper.ignore_case = self.options.nocasematch;
             helper.colored_prompt.clone()
         } else {
             prompt.clone()
         };
+        if let Some(timings) = self.startup_timings.as_mut() {
+            timings.record("first prompt render", started.elapsed(), "");
+            self.report_startup_timings();
+        }
 
-        // Use colored prompt for display
-        match self.editor.readline(&colored_prompt) {
+        // Use colored prompt for display, restoring any line interrupted by a key binding
+        let (before_cursor, after_cursor) = std::mem::take(&mut self.initial_line);
+        match self.editor.readline_with_initial(&colored_prompt, (&before_cursor, &after_cursor)) {
             Ok(line) => {
-                let line = line.trim();
+                // A `run:` key binding accepts the line so its command can run instead
+                let bound = self.pending_binding.lock().ok().and_then(|mut p| p.take());
+                let line = match bound {
+                    Some(PendingBinding { action: KeyAction::Run(command), line, pos }) => {
+                        debug!("Key binding runs: {}", command);
+                        self.initial_line = split_at_cursor(line, pos);
+                        command
+                    }
+                    Some(PendingBinding { action: KeyAction::FilePicker, line, pos }) => {
+                        // The chosen path goes in at the cursor, quoted as needed
+                        let (mut before, after) = split_at_cursor(line, pos);
+                        if let Some(path) = file_picker::run(&self.current_dir)? {
+                            before.push_str(&shell_quote(&path.to_string_lossy()));
+                        }
+                        self.initial_line = (before, after);
+                        return Ok(());
+                    }
+                    Some(PendingBinding { line, .. }) => {
+                        // The chosen entry is edited at the next prompt, not run
+                        let entries: Vec<String> = self.history.get_entries().iter().cloned().collect();
+                        let line = palette::run(&entries, &line)?.unwrap_or(line);
+                        self.initial_line = (line, String::new());
+                        return Ok(());
+                    }
+                    None => {
+                        let Some(line) = self.read_continuation(line.trim().to_string())? else {
+                            return Ok(());
+                        };
+                        if !line.is_empty() {
+                            self.history.add(&line)?;
+                            if !self.options.histappend {
+                                self.history.write_file()?;
+                            }
+                            self.editor.add_history_entry(line.as_str())?;
+                        }
+                        line
+                    }
+                };
+                let line = line.as_str();
 
                 if line.is_empty() {
                     return Ok(());
                 }
 
-                self.history.add(&line.to_string())?;
-                self.editor.add_history_entry(line)?;
-
+                if self.shell_integration {
+                    print!("{}", self.state.capabilities.multiplexer.passthrough(shell_integration::OUTPUT_START));
+                    let _ = io::stdout().flush();
+                    self.command_running = true;
+                }
                 debug!("Processing command: {}", line);
-
                 let parsed_command = self.parser.parse(line)?;
-                debug!("Parsed command: {:?}", parsed_command);
-
-                self.exit_code = self.executor.execute(parsed_command, &mut self.current_dir, &mut self.parser).await?;
-
-                // Sync shell's current_dir with actual working directory after command execution
-                if let Ok(real_cwd) = std::env::current_dir() {
-                    let canonical_real_cwd = if let Ok(canonical) = real_cwd.canonicalize() {
-                        crate::utils::path::strip_windows_prefix(&canonical)
-                    } else {
-                        crate::utils::path::strip_windows_prefix(&real_cwd)
-                    };
-                    self.current_dir = canonical_real_cwd;
-                }
-
-                if self.exit_code == 130 {
-                    self.should_exit = true;
-                }
+                self.execute_hooked(parsed_command).await?;
             }
             Err(rustyline::error::ReadlineError::Interrupted) => {
                 self.terminal.print_info("^C").await?;
@@ -549,9 +1161,35 @@ impl Shell {
         Ok(())
     }
 
-    fn build_prompt(&self) -> Result<String> {
+    /// Reads more lines while `buffer` is an incomplete command, such as an open quote,
+    /// an `if` without `fi` or a trailing `\`. Returns `None` if Ctrl+C abandons it.
+    fn read_continuation(&mut self, mut buffer: String) -> Result<Option<String>> {
+        let prompt = self.config.get().prompt.continuation_prompt.clone();
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.set_colored_prompt(&prompt);
+        }
+
+        while let Err(e) = self.parser.parse(&buffer) {
+            if !errors::needs_more_input(&e) {
+                break;
+            }
+            match self.editor.readline(&prompt) {
+                Ok(next) => {
+                    buffer.push('\n');
+                    buffer.push_str(&next);
+                }
+                Err(rustyline::error::ReadlineError::Interrupted) => return Ok(None),
+                // Leave the incomplete command for the parser to report
+                Err(rustyline::error::ReadlineError::Eof) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Some(buffer))
+    }
+
+    fn build_prompt(&mut self) -> Result<String> {
         let config = self.config.get();
-        let mut prompt = config.prompt.format.clone();
+        let mut prompt = expand_prompt_newlines(&config.prompt.format);
 
         // Get user name
         let user = std::env::var("USER")
@@ -563,11 +1201,13 @@ impl Shell {
             .to_string_lossy()
             .to_string();
 
-        // Get current working directory (home-relative), handle inaccessible directories
+        // Get current working directory (home-relative), handle inaccessible directories.
+        // Shown as `pwd` prints it, with the symlinks `cd` went through
+        let logical_dir = crate::utils::path::logical_dir(&self.current_dir);
         let (cwd_home, current_dir_accessible) = if let Some(home) = dirs::home_dir() {
             // Test if current directory is accessible
-            let accessible = std::fs::read_dir(&self.current_dir).is_ok();
-            let current_dir = crate::utils::path::strip_windows_prefix(&self.current_dir);
+            let accessible = std::fs::read_dir(&logical_dir).is_ok();
+            let current_dir = crate::utils::path::strip_windows_prefix(&logical_dir);
             let home = crate::utils::path::strip_windows_prefix(&home);
 
             let cwd_display = if current_dir.starts_with(&home) {
@@ -582,23 +1222,42 @@ impl Shell {
             };
             (cwd_display, accessible)
         } else {
-            let accessible = std::fs::read_dir(&self.current_dir).is_ok();
-            let cwd_display = crate::utils::path::strip_windows_prefix(&self.current_dir).to_string_lossy().to_string();
+            let accessible = std::fs::read_dir(&logical_dir).is_ok();
+            let cwd_display = crate::utils::path::strip_windows_prefix(&logical_dir).to_string_lossy().to_string();
             (cwd_display, accessible)
         };
 
+        // Abbreviated as fish does, from the same home-relative start
+        let home = dirs::home_dir().map(|home| crate::utils::path::strip_windows_prefix(&home));
+        let cwd_short = crate::utils::path::shorten_path(
+            &crate::utils::path::strip_windows_prefix(&logical_dir),
+            home.as_deref(),
+        );
+
+        // Keep the end of a long path, where the directory names that matter are
+        let cwd_home = match config.prompt.max_cwd_length {
+            Some(max) if cwd_home.chars().count() > max => {
+                let skip = cwd_home.chars().count() - max.saturating_sub(1);
+                format!("…{}", cwd_home.chars().skip(skip).collect::<String>())
+            }
+            _ => cwd_home,
+        };
+
         // Add warning indicator for inaccessible directories
-        let cwd_home = if !current_dir_accessible {
-            format!("{}!", cwd_home)
+        let (cwd_home, cwd_short) = if !current_dir_accessible {
+            (format!("{}!", cwd_home), format!("{}!", cwd_short))
         } else {
-            cwd_home
+            (cwd_home, cwd_short)
         };
 
         // Get just directory name
-        let cwd_name = self.current_dir
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| "/".to_string());
+        let cwd_name = crate::utils::path::basename(&logical_dir.to_string_lossy(), "");
+
+        // Background jobs still running; finished ones were reported before the prompt
+        let jobs = match self.executor.job_count() {
+            0 => String::new(),
+            count => format!("[{}]", count),
+        };
 
         // Get current time
         let time = chrono::Local::now().format("%H:%M:%S").to_string();
@@ -608,8 +1267,25 @@ impl Shell {
         prompt = prompt.replace("{host}", &hostname);
         prompt = prompt.replace("{hostname}", &hostname);
         prompt = prompt.replace("{cwd}", &cwd_home);
+        prompt = prompt.replace("{cwd_short}", &cwd_short);
         prompt = prompt.replace("{cwd_name}", &cwd_name);
         prompt = prompt.replace("{time}", &time);
+        prompt = prompt.replace("{jobs}", &jobs);
+
+        // Version manager environments, empty where they don't apply
+        prompt = prompt.replace("{conda}", &tool_versions::conda_env());
+        prompt = prompt.replace("{node_ver}", &tool_versions::node_version(&logical_dir));
+        let show_tool_versions = config.prompt.show_tool_versions;
+        if prompt.contains("{pyenv}") {
+            let pyenv = if show_tool_versions {
+                tool_versions::pyenv_version(&logical_dir, &mut self.state.pyenv_cache)
+            } else {
+                String::new()
+            };
+            prompt = prompt.replace("{pyenv}", &pyenv);
+        }
+        let nvm = if show_tool_versions { tool_versions::nvm_version() } else { String::new() };
+        prompt = prompt.replace("{nvm}", &nvm);
 
         // Don't process color codes here - let rustyline Highlighter handle it
 
@@ -646,29 +1322,9 @@ impl Shell {
                 }
 
                 // Apply the color based on the code
-                match color_code.as_str() {
-                    "red" => result.push_str("\x1b[31m"),
-                    "green" => result.push_str("\x1b[32m"),
-                    "blue" => result.push_str("\x1b[34m"),
-                    "yellow" => result.push_str("\x1b[33m"),
-                    "magenta" => result.push_str("\x1b[35m"),
-                    "cyan" => result.push_str("\x1b[36m"),
-                    "white" => result.push_str("\x1b[37m"),
-                    "black" => result.push_str("\x1b[30m"),
-                    "bright_red" => result.push_str("\x1b[91m"),
-                    "bright_green" => result.push_str("\x1b[92m"),
-                    "bright_blue" => result.push_str("\x1b[94m"),
-                    "bright_yellow" => result.push_str("\x1b[93m"),
-                    "bright_magenta" => result.push_str("\x1b[95m"),
-                    "bright_cyan" => result.push_str("\x1b[96m"),
-                    "bright_white" => result.push_str("\x1b[97m"),
-                    "bright_black" => result.push_str("\x1b[90m"),
-                    "reset" => result.push_str("\x1b[0m"),
-                    "bold" => result.push_str("\x1b[1m"),
-                    "dim" => result.push_str("\x1b[2m"),
-                    "italic" => result.push_str("\x1b[3m"),
-                    "underline" => result.push_str("\x1b[4m"),
-                    _ => {
+                match colors::ansi_code(&color_code) {
+                    Some(code) => result.push_str(&code),
+                    None => {
                         // Unknown color code, put it back as-is
                         result.push('{');
                         result.push_str(&color_code);
@@ -705,18 +1361,404 @@ impl Shell {
         result
     }
 
+    /// Parses and runs one line of input, updating the exit code and working directory.
+    async fn execute_line(&mut self, line: &str) -> Result<()> {
+        debug!("Processing command: {}", line);
+
+        let parsed_command = self.parser.parse(line)?;
+        self.execute_parsed(parsed_command).await
+    }
+
+    /// Runs `command` and returns its exit code with what it wrote to standard
+    /// output, which isn't printed.
+    pub async fn execute_capturing(&mut self, command: &str) -> Result<(i32, String)> {
+        let parsed_command = self.parser.parse(command)?;
+        self.state.special.last_exit = self.exit_code;
+        let outer = self.sandbox_env.take().map(ProcessEnv::swap);
+        let mut ctx = ShellContext {
+            current_dir: &mut self.current_dir,
+            parser: &mut self.parser,
+            config: &mut self.config,
+            options: &mut self.options,
+            state: &mut self.state,
+            history: &mut self.history,
+            in_condition: false,
+        };
+        let result = self.executor.execute_capturing(parsed_command, &mut ctx).await;
+        if let Some(outer) = outer {
+            self.sandbox_env = Some(outer.swap());
+        }
+        let (code, output) = result?;
+        self.exit_code = code;
+        Ok((code, output))
+    }
+
+    /// Registers a hook to run before each interactive command, once it's parsed.
+    /// A hook returning `Err` cancels the command and the error is reported.
+    pub fn add_pre_exec_hook(&mut self, hook: impl Fn(&ParsedCommand) -> Result<()> + Send + Sync + 'static) {
+        self.exec_hooks.add_pre_exec(Box::new(hook));
+    }
+
+    /// Registers a hook to run after each interactive command with its exit code.
+    /// A hook returning `Err` is reported without changing the exit code.
+    pub fn add_post_exec_hook(&mut self, hook: impl Fn(&ParsedCommand, i32) -> Result<()> + Send + Sync + 'static) {
+        self.exec_hooks.add_post_exec(Box::new(hook));
+    }
+
+    /// Initializes `plugin` and loads it, unless its `on_init` fails.
+    pub fn add_plugin(&mut self, mut plugin: Box<dyn FlexPlugin>) -> Result<()> {
+        plugin.on_init(&mut self.context())?;
+        debug!("Loaded plugin '{}'", plugin.name());
+        if let Ok(mut plugins) = self.plugins.write() {
+            plugins.push(plugin);
+        }
+        Ok(())
+    }
+
+    /// A view of the shell for builtins and plugins.
+    fn context(&mut self) -> ShellContext<'_> {
+        ShellContext {
+            current_dir: &mut self.current_dir,
+            parser: &mut self.parser,
+            config: &mut self.config,
+            options: &mut self.options,
+            state: &mut self.state,
+            history: &mut self.history,
+            in_condition: false,
+        }
+    }
+
+    /// Runs `parsed_command` between the pre-exec and post-exec hooks and plugins.
+    /// A plugin cancelling the command leaves a status of 1.
+    async fn execute_hooked(&mut self, parsed_command: ParsedCommand) -> Result<()> {
+        self.exec_hooks.pre_exec(&parsed_command)?;
+        let plugins = self.plugins.clone();
+        if let Ok(plugins) = plugins.read() {
+            let ctx = self.context();
+            for plugin in plugins.iter() {
+                if plugin.on_pre_exec(&parsed_command, &ctx)? == PluginAction::Cancel {
+                    debug!("Plugin '{}' cancelled: {}", plugin.name(), parsed_command.program);
+                    self.exit_code = 1;
+                    return Ok(());
+                }
+            }
+        }
+
+        self.execute_parsed(parsed_command.clone()).await?;
+        for e in self.exec_hooks.post_exec(&parsed_command, self.exit_code) {
+            self.terminal.print_error(&format!("post-exec hook: {:#}", e)).await?;
+        }
+        let exit_code = self.exit_code;
+        if let Ok(plugins) = plugins.read() {
+            let ctx = self.context();
+            for plugin in plugins.iter() {
+                plugin.on_post_exec(&parsed_command, exit_code, &ctx);
+            }
+        }
+        Ok(())
+    }
+
+    async fn execute_parsed(&mut self, mut parsed_command: ParsedCommand) -> Result<()> {
+        debug!("Parsed command: {:?}", parsed_command);
+
+        if self.options.auto_cd && self.is_auto_cd_target(&parsed_command) {
+            println!("cd: {}", parsed_command.program);
+            let mut cd = ParsedCommand::new("cd".to_string());
+            cd.args.push(parsed_command.program.clone());
+            parsed_command = cd;
+        }
+
+        // Failures reported by the caller set the status after the last command finished
+        self.state.special.last_exit = self.exit_code;
+        let mut ctx = ShellContext {
+            current_dir: &mut self.current_dir,
+            parser: &mut self.parser,
+            config: &mut self.config,
+            options: &mut self.options,
+            state: &mut self.state,
+            history: &mut self.history,
+            in_condition: false,
+        };
+        let result = self.executor.execute(parsed_command, &mut ctx).await;
+        if self.history.take_modified() {
+            self.sync_editor_history()?;
+        }
+
+        self.exit_code = match result {
+            Ok(code) => code,
+            Err(e) => match e.downcast_ref::<ExitRequest>() {
+                Some(ExitRequest(code)) => {
+                    self.exit_code = *code;
+                    self.should_exit = true;
+                    return Ok(());
+                }
+                None => {
+                    // `set -e` tripped: stop with the failing status, leaving the report to the caller
+                    if let Some(ErrExit(code)) = e.downcast_ref::<ErrExit>() {
+                        self.exit_code = *code;
+                        self.should_exit = true;
+                    }
+                    return Err(e);
+                }
+            },
+        };
+
+        // Sync shell's current_dir with actual working directory after command execution,
+        // keeping the logical path when it still points at the same place
+        if let Some(real_cwd) = std::env::current_dir().ok().filter(|cwd| !crate::utils::path::same_location(&self.current_dir, cwd)) {
+            let canonical_real_cwd = if let Ok(canonical) = real_cwd.canonicalize() {
+                crate::utils::path::strip_windows_prefix(&canonical)
+            } else {
+                crate::utils::path::strip_windows_prefix(&real_cwd)
+            };
+            std::env::set_var("PWD", &canonical_real_cwd);
+            self.current_dir = canonical_real_cwd;
+        }
+
+        Ok(())
+    }
+
+    /// Reloads the line editor's history (arrow keys, Ctrl+R) from `self.history`
+    /// after the `history` builtin removed or replaced entries.
+    fn sync_editor_history(&mut self) -> Result<()> {
+        self.editor.clear_history()?;
+        for entry in self.history.get_entries() {
+            self.editor.add_history_entry(entry.as_str())?;
+        }
+        Ok(())
+    }
+
+    /// True if a command is just a directory path, which `autocd` turns into `cd <dir>`.
+    fn is_auto_cd_target(&self, command: &ParsedCommand) -> bool {
+        if command.program.is_empty() || !command.args.is_empty() || !command.pipes.is_empty() || crate::builtins::get_builtin(&command.program).is_some() {
+            return false;
+        }
+        let path = crate::utils::path::expand_tilde(&command.program);
+        self.current_dir.join(path).is_dir()
+    }
+
+    /// Before a prompt: ends the last command's output with its status, and reports
+    /// the working directory. The prompt marks themselves come from `ShellHelper`.
+    fn emit_shell_integration(&mut self) {
+        let multiplexer = self.state.capabilities.multiplexer;
+        if std::mem::take(&mut self.command_running) {
+            print!("{}", multiplexer.passthrough(&shell_integration::command_finished(self.exit_code)));
+        }
+        let hostname = gethostname::gethostname().to_string_lossy().to_string();
+        print!("{}", multiplexer.passthrough(&shell_integration::current_directory(&hostname, &self.current_dir)));
+        let _ = io::stdout().flush();
+    }
+
+    /// The working directory as the prompt shows it, `~` standing for the home directory.
+    fn window_title(&self) -> String {
+        let dir = crate::utils::path::logical_dir(&self.current_dir);
+        match dirs::home_dir().and_then(|home| dir.strip_prefix(home).ok().map(Path::to_path_buf)) {
+            Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+            Some(relative) => format!("~/{}", relative.display()),
+            None => dir.display().to_string(),
+        }
+    }
+
+    /// Logs the startup phases, and prints them to stderr with `--timings`. Only the
+    /// first call reports anything.
+    fn report_startup_timings(&mut self) {
+        let Some(mut timings) = self.startup_timings.take() else {
+            return;
+        };
+
+        match self.command_cache.read() {
+            Ok(cache) if cache.is_ready() => {
+                let detail = format!("{} directories, {} programs", cache.directories(), cache.len());
+                timings.record("PATH cache build", cache.build_time(), detail);
+            }
+            _ => timings.record_pending("PATH cache build", "still running in the background"),
+        }
+
+        if self.show_timings {
+            eprint!("{}", timings.table());
+        }
+    }
+
     pub async fn execute_command(&mut self, command: &str) -> Result<()> {
-    debug!("Executing single command: {}", command);
+        debug!("Executing single command: {}", command);
+        self.report_startup_timings();
+        let outer = self.sandbox_env.take().map(ProcessEnv::swap);
+        let result = match self.execute_line(command).await {
+            Err(e) if errors::is_control_flow(&e) => {
+                eprintln!("flex-sh: {}", e);
+                if e.is::<UnboundVariable>() {
+                    self.exit_code = 1;
+                }
+                Ok(())
+            }
+            result => result,
+        };
+        if let Some(outer) = outer {
+            self.sandbox_env = Some(outer.swap());
+        }
+        result
+    }
 
-    let parsed_command = self.parser.parse(command)?;
-    debug!("Parsed command: {:?}", parsed_command);
+    /// Runs a script file line by line. Parse errors abort the script and are
+    /// reported as `file:line:column: error: ...`; command failures are reported
+    /// with their line number and execution continues (unless `set -e` is on).
+    pub async fn execute_script(&mut self, path: &Path) -> Result<()> {
+        debug!("Executing script: {:?}", path);
+        self.report_startup_timings();
+        self.source_file(path).await
+    }
+
+    /// Runs the commands in a file in this shell, as `execute_script` describes.
+    /// `return` outside a function ends the file with its status.
+    async fn source_file(&mut self, path: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("{}: cannot read script", path.display()))?;
+        self.state.source_depth += 1;
+        let result = self.run_lines(&path.display().to_string(), &content).await;
+        self.state.source_depth -= 1;
+        self.parser.set_line(1);
+        self.state.special.line_number = 1;
+        result
+    }
 
-    self.exit_code = self.executor.execute(parsed_command, &mut self.current_dir, &mut self.parser).await?;
+    async fn run_lines(&mut self, filename: &str, content: &str) -> Result<()> {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut next_line = 0;
+        while next_line < lines.len() {
+            let line = lines[next_line];
+            let line_number = next_line + 1;
+            next_line += 1;
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            // A command can span lines, e.g. a function body; keep reading until it is complete
+            self.parser.set_line(line_number);
+            let mut source = line.to_string();
+            let parsed_command = loop {
+                match self.parser.parse(&source) {
+                    Ok(parsed) => break parsed,
+                    Err(e) if errors::needs_more_input(&e) && next_line < lines.len() => {
+                        source.push('\n');
+                        source.push_str(lines[next_line]);
+                        next_line += 1;
+                    }
+                    Err(e) => {
+                        let (line, column) = self.parser.position();
+                        return Err(e).with_context(|| format!("{}:{}:{}: error", filename, line, column));
+                    }
+                }
+            };
 
-    Ok(())
+            self.state.special.line_number = line_number;
+            if let Err(e) = self.execute_parsed(parsed_command).await {
+                if let Some(ReturnValue(code)) = e.downcast_ref() {
+                    self.exit_code = *code;
+                    break;
+                }
+                eprintln!("{}:{}: {}", filename, line_number, e);
+                if !e.is::<ErrExit>() {
+                    self.exit_code = errors::failure_status(&e);
+                    if self.options.exit_on_error || e.is::<UnboundVariable>() {
+                        break;
+                    }
+                }
+            }
+
+            if self.should_exit {
+                break;
+            }
+        }
+        Ok(())
     }
 
     pub fn exit_code(&self) -> i32 {
         self.exit_code
     }
-}
\ No newline at end of file
+}
+
+/// Puts the directories listed in `/etc/paths` and the files in `/etc/paths.d`
+/// (in name order) at the front of `PATH`.
+#[cfg(target_os = "macos")]
+fn load_system_paths() {
+    let mut files = vec![PathBuf::from("/etc/paths")];
+    if let Ok(entries) = std::fs::read_dir("/etc/paths.d") {
+        let mut extra: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        extra.sort();
+        files.extend(extra);
+    }
+    let listed: Vec<String> = files
+        .iter()
+        .filter_map(|file| std::fs::read_to_string(file).ok())
+        .flat_map(|content| content.lines().map(|line| line.trim().to_string()).collect::<Vec<_>>())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let current = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", crate::utils::path::merge_path_list(&listed, &current));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ShellConfig;
+
+    fn helper() -> ShellHelper {
+        ShellHelper::new(SharedCommandCache::default(), false, &ShellConfig::default().completion, SharedPlugins::default())
+    }
+
+    fn displays(pairs: Option<Vec<Pair>>) -> Vec<String> {
+        pairs.unwrap_or_default().into_iter().map(|pair| pair.display).collect()
+    }
+
+    #[test]
+    fn test_complete_signals() {
+        let helper = helper();
+        let names = displays(helper.complete_signals("kill -SI", 8, "-SI"));
+        for name in ["SIGINT", "SIGILL", "SIGSEGV"] {
+            assert!(names.contains(&name.to_string()), "missing {}", name);
+        }
+        let pairs = helper.complete_signals("kill -H", 7, "-H").unwrap();
+        assert_eq!(pairs[0].replacement, "-HUP");
+        assert_eq!(displays(helper.complete_signals("kill -s TE", 10, "TE")), vec!["TERM"]);
+        assert_eq!(displays(helper.complete_signals("trap 'echo' IN", 14, "IN")), vec!["INT"]);
+        assert!(helper.complete_signals("kill 12", 7, "12").is_none());
+        assert!(helper.complete_signals("trap IN", 7, "IN").is_none());
+    }
+
+    #[test]
+    fn test_multiline_prompt() {
+        let prompt = expand_prompt_newlines("[{user}@{host}]\\n{green}${reset} {newline}> ");
+        assert_eq!(prompt, "[{user}@{host}]\n{green}${reset} \n> ");
+
+        // The color codes stay within their lines, and rustyline's cursor goes after
+        // the last one
+        let mut helper = helper();
+        helper.set_colored_prompt(&prompt);
+        let lines: Vec<&str> = helper.colored_prompt.split('\n').collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].contains('$') && lines[1].contains("\x1b["));
+        assert_eq!(lines[2], "> ");
+    }
+
+    #[test]
+    fn test_complete_spec() {
+        let mut helper = helper();
+        let mut state = ShellState::default();
+        state.completion_specs.insert("svc".to_string(), CompletionSpec::Words("start stop status".to_string()));
+        state.completion_specs.insert("run".to_string(), CompletionSpec::Builtins);
+        state.completion_specs.insert("tool".to_string(), CompletionSpec::Function("_undefined".to_string()));
+        helper.completion_specs = Some(CompletionSnapshot {
+            state,
+            options: ShellOptions::default(),
+            config: Config::new(None, None).unwrap(),
+            current_dir: PathBuf::from("/"),
+        });
+
+        assert_eq!(displays(helper.complete_spec("svc sta", 7, "sta", 4)), vec!["start", "status"]);
+        assert_eq!(helper.complete_spec("svc x", 5, "x", 4).map(|pairs| pairs.len()), Some(0));
+        assert_eq!(displays(helper.complete_spec("run compg", 9, "compg", 4)), vec!["compgen"]);
+        assert!(helper.complete_spec("tool a", 6, "a", 5).is_none());
+        assert!(helper.complete_spec("ls a", 4, "a", 3).is_none());
+        assert!(helper.complete_spec("sv", 2, "sv", 0).is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::core::parser::ParsedCommand;
+use crate::utils::quote::shell_quote;
+use crate::utils::terminal_capabilities::TerminalCapabilities;
+use crate::utils::tool_versions::PyenvCache;
+use std::collections::{HashMap, HashSet};
+#[cfg(unix)]
+use std::os::fd::OwnedFd;
+#[cfg(unix)]
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Interpreter state that outlives a single command line.
+#[derive(Debug, Clone, Default)]
+pub struct ShellState {
+    /// Defined functions and their bodies
+    pub functions: HashMap<String, Vec<ParsedCommand>>,
+    /// `$1`, `$2`, ... of the running function, or else of the script
+    pub positional: Vec<String>,
+    /// Number of function calls currently executing
+    pub function_depth: usize,
+    /// Number of loops currently executing, for `break` and `continue`
+    pub loop_depth: usize,
+    /// Number of files being sourced, which `return` outside a function ends
+    pub source_depth: usize,
+    /// Variables declared with `local`, one frame per running function call. Each
+    /// maps a name to the value it shadowed (`None` if it was unset), which is put
+    /// back when the call returns.
+    pub local_scopes: Vec<HashMap<String, Option<String>>>,
+    /// How to complete commands' arguments, registered with `complete` or `compdef`,
+    /// by the command they complete
+    pub completion_specs: HashMap<String, CompletionSpec>,
+    /// One-line descriptions `help` found for external commands, `None` where
+    /// there was none
+    pub external_help: HashMap<String, Option<String>>,
+    /// Array variables by name; their elements aren't in the environment
+    pub arrays: HashMap<String, Vec<String>>,
+    /// Variables marked with `readonly`, which can't be assigned or unset
+    pub readonly_vars: HashSet<String>,
+    /// Coprocesses started with `coproc`, by name
+    pub coprocs: HashMap<String, Coproc>,
+    /// `$?`, `$$`, `$!` and `$0`
+    pub special: SpecialVars,
+    /// What the terminal was detected to support at startup
+    pub capabilities: TerminalCapabilities,
+    /// The last `pyenv version-name` answer, for the `{pyenv}` prompt token
+    pub pyenv_cache: Option<PyenvCache>,
+}
+
+/// How to complete a command's arguments, as registered with `complete`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompletionSpec {
+    /// `-F`: the candidates a shell function prints, as with `compdef`
+    Function(String),
+    /// `-W`: the words of a list, split like an unquoted expansion
+    Words(String),
+    /// `-f`: file and directory paths
+    Files,
+    /// `-d`: directory paths
+    Directories,
+    /// `-b`: builtin names
+    Builtins,
+    /// `-c`: command names
+    Commands,
+}
+
+impl CompletionSpec {
+    /// The `complete` options that register this spec.
+    pub fn options(&self) -> String {
+        match self {
+            CompletionSpec::Function(function) => format!("-F {}", shell_quote(function)),
+            CompletionSpec::Words(words) => format!("-W {}", shell_quote(words)),
+            CompletionSpec::Files => "-f".to_string(),
+            CompletionSpec::Directories => "-d".to_string(),
+            CompletionSpec::Builtins => "-b".to_string(),
+            CompletionSpec::Commands => "-c".to_string(),
+        }
+    }
+}
+
+/// A coprocess and the shell's ends of the pipes to it. Clones share the pipes,
+/// which close when the last clone is dropped.
+#[derive(Debug, Clone)]
+pub struct Coproc {
+    pub pid: u32,
+    /// Reads what the coprocess writes to its standard output
+    #[cfg(unix)]
+    pub output: Arc<OwnedFd>,
+    /// Writes to the coprocess's standard input; None once `coproc -c` has closed it
+    #[cfg(unix)]
+    pub input: Option<Arc<OwnedFd>>,
+}
+
+impl Coproc {
+    /// Closes the shell's end of the coprocess's input, which it then sees end.
+    pub fn close_input(&mut self) {
+        #[cfg(unix)]
+        self.input.take();
+    }
+}
+
+/// The special parameters, which aren't environment variables.
+#[derive(Debug, Clone)]
+pub struct SpecialVars {
+    /// `$?`: the status of the last command
+    pub last_exit: i32,
+    /// `$$`: the shell's process ID, which subshells keep
+    pub shell_pid: u32,
+    /// `$!`: the process ID of the last command started with `&`
+    pub last_bg_pid: Option<u32>,
+    /// `$0`: the script's path, or the name the shell was started as, with a
+    /// leading `-` for a login shell
+    pub script_name: String,
+    /// `$LINENO`: the line of the script command being run, 1 outside scripts
+    pub line_number: usize,
+    /// When the shell started, for `$SECONDS`
+    pub started: Instant,
+}
+
+impl Default for SpecialVars {
+    fn default() -> Self {
+        Self {
+            last_exit: 0,
+            shell_pid: std::process::id(),
+            last_bg_pid: None,
+            script_name: String::new(),
+            line_number: 1,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl ShellState {
+    /// Starts the frame for a function call.
+    pub fn push_scope(&mut self) {
+        self.local_scopes.push(HashMap::new());
+    }
+
+    /// Ends the innermost function call's frame, restoring the variables it shadowed.
+    pub fn pop_scope(&mut self) {
+        let Some(scope) = self.local_scopes.pop() else {
+            return;
+        };
+        for (name, shadowed) in scope {
+            match shadowed {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+    }
+
+    /// Makes `name` local to the innermost function call, remembering its current
+    /// value the first time. Returns false outside a function.
+    pub fn declare_local(&mut self, name: &str) -> bool {
+        let Some(scope) = self.local_scopes.last_mut() else {
+            return false;
+        };
+        scope.entry(name.to_string()).or_insert_with(|| std::env::var(name).ok());
+        true
+    }
+}
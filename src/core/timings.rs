@@ -0,0 +1,71 @@
+use log::debug;
+use std::time::Duration;
+
+/// Durations of the phases of shell startup, printed by `--timings`.
+#[derive(Debug, Default)]
+pub struct StartupTimings {
+    phases: Vec<Phase>,
+}
+
+#[derive(Debug)]
+struct Phase {
+    name: &'static str,
+    /// `None` for a phase that hadn't finished when the report was made
+    elapsed: Option<Duration>,
+    detail: String,
+}
+
+impl StartupTimings {
+    /// Records a finished phase; `detail` is extra context such as an entry count.
+    pub fn record(&mut self, name: &'static str, elapsed: Duration, detail: impl Into<String>) {
+        debug!("phase '{}' took {:?}", name, elapsed);
+        self.phases.push(Phase { name, elapsed: Some(elapsed), detail: detail.into() });
+    }
+
+    /// Records a phase that is still running in the background.
+    pub fn record_pending(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.phases.push(Phase { name, elapsed: None, detail: detail.into() });
+    }
+
+    /// Formats the phases as a table, one per line, with a total of the finished ones.
+    pub fn table(&self) -> String {
+        let mut table = format!("{:<22} {:>12}  {}\n", "phase", "time", "details");
+        for phase in &self.phases {
+            let elapsed = match phase.elapsed {
+                Some(elapsed) => format_duration(elapsed),
+                None => "pending".to_string(),
+            };
+            let row = format!("{:<22} {:>12}  {}", phase.name, elapsed, phase.detail);
+            table.push_str(row.trim_end());
+            table.push('\n');
+        }
+        let total: Duration = self.phases.iter().filter_map(|phase| phase.elapsed).sum();
+        table.push_str(&format!("{:<22} {:>12}\n", "total", format_duration(total)));
+        table
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let mut timings = StartupTimings::default();
+        timings.record("config load", Duration::from_micros(1500), "");
+        timings.record("history load", Duration::from_millis(2), "42 entries");
+        timings.record_pending("PATH cache build", "scanning in background");
+
+        let table = timings.table();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("config load") && lines[1].ends_with("1.500 ms"));
+        assert!(lines[2].ends_with("2.000 ms  42 entries"));
+        assert!(lines[3].contains("pending"));
+        assert!(lines[4].starts_with("total") && lines[4].ends_with("3.500 ms"));
+    }
+}
@@ -0,0 +1,15 @@
+//! Flex-SH as a library, for embedding the shell in other programs and writing
+//! plugins. The `flex-sh` binary is a thin wrapper around `Shell::run_with_args`.
+
+pub mod cli;
+pub mod config;
+pub mod core;
+pub mod builtins;
+pub mod terminal;
+pub mod utils;
+pub mod plugins;
+
+pub use cli::Cli;
+pub use config::{Config, ShellConfig};
+pub use core::{executor::Executor, history::History, parser::Parser, Shell, ShellContext};
+pub use plugins::FlexPlugin;
@@ -0,0 +1,127 @@
+use super::FlexPlugin;
+use crate::utils::completion::{CompletionCandidate, CompletionKind};
+use std::path::{Path, PathBuf};
+
+/// `git` subcommands offered for its first argument.
+const SUBCOMMANDS: &[&str] = &[
+    "add", "bisect", "blame", "branch", "checkout", "cherry-pick", "clone", "commit", "diff", "fetch",
+    "grep", "init", "log", "merge", "mv", "pull", "push", "rebase", "reset", "restore", "revert", "rm",
+    "show", "stash", "status", "switch", "tag",
+];
+/// Subcommands whose arguments are branch names.
+const BRANCH_SUBCOMMANDS: &[&str] = &["branch", "checkout", "cherry-pick", "merge", "rebase", "switch"];
+
+/// Completes `git` subcommands and, after the ones that take a branch, the
+/// branches of the repository the shell is in. A reference for writing plugins.
+pub struct GitPlugin;
+
+impl FlexPlugin for GitPlugin {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn completions(&self, word: &str, tokens: &[String]) -> Vec<CompletionCandidate> {
+        let names = match tokens {
+            [git] if git == "git" => SUBCOMMANDS.iter().map(|name| name.to_string()).collect(),
+            [git, subcommand, ..] if git == "git" && BRANCH_SUBCOMMANDS.contains(&subcommand.as_str()) => {
+                if word.starts_with('-') {
+                    return Vec::new();
+                }
+                let Some(git_dir) = std::env::current_dir().ok().and_then(|dir| find_git_dir(&dir)) else {
+                    return Vec::new();
+                };
+                branches(&git_dir)
+            }
+            _ => return Vec::new(),
+        };
+        let description = if tokens.len() == 1 { "subcommand" } else { "branch" };
+        names
+            .into_iter()
+            .filter(|name| name.starts_with(word))
+            .map(|name| CompletionCandidate {
+                text: name.clone(),
+                display: name,
+                kind: CompletionKind::Argument,
+                description: Some(description.to_string()),
+            })
+            .collect()
+    }
+}
+
+/// The `.git` directory of the repository containing `dir`, following the
+/// `gitdir:` file of a worktree or submodule.
+pub fn find_git_dir(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        let git = dir.join(".git");
+        if git.is_dir() {
+            return Some(git);
+        }
+        let contents = std::fs::read_to_string(&git).ok()?;
+        let target = PathBuf::from(contents.strip_prefix("gitdir:")?.trim());
+        Some(dir.join(target))
+    })
+}
+
+/// The local branch names in `git_dir`, loose and packed, sorted.
+pub fn branches(git_dir: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    collect_refs(&git_dir.join("refs/heads"), "", &mut names);
+    if let Ok(packed) = std::fs::read_to_string(git_dir.join("packed-refs")) {
+        names.extend(
+            packed
+                .lines()
+                .filter_map(|line| line.split_once(' ')?.1.strip_prefix("refs/heads/"))
+                .map(String::from),
+        );
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Adds the refs under `dir` to `names`, prefixed with `prefix`, as branch names
+/// such as `feature/login` are nested directories.
+fn collect_refs(dir: &Path, prefix: &str, names: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.path().is_dir() {
+            collect_refs(&entry.path(), &format!("{}/", name), names);
+        } else {
+            names.push(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branches() {
+        let repo = tempfile::tempdir().unwrap();
+        let git_dir = repo.path().join(".git");
+        std::fs::create_dir_all(git_dir.join("refs/heads/feature")).unwrap();
+        std::fs::write(git_dir.join("refs/heads/main"), "").unwrap();
+        std::fs::write(git_dir.join("refs/heads/feature/login"), "").unwrap();
+        std::fs::write(git_dir.join("packed-refs"), "# pack-refs\nabc123 refs/heads/release\nabc124 refs/tags/v1\n").unwrap();
+
+        let nested = repo.path().join("src/bin");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_git_dir(&nested), Some(git_dir.clone()));
+        assert_eq!(branches(&git_dir), vec!["feature/login", "main", "release"]);
+    }
+
+    #[test]
+    fn test_completions() {
+        let texts = |word: &str, tokens: &[&str]| -> Vec<String> {
+            let tokens: Vec<String> = tokens.iter().map(|token| token.to_string()).collect();
+            GitPlugin.completions(word, &tokens).into_iter().map(|candidate| candidate.text).collect()
+        };
+        assert_eq!(texts("sta", &["git"]), vec!["stash", "status"]);
+        assert!(texts("", &["ls"]).is_empty());
+        assert!(texts("--", &["git", "checkout"]).is_empty());
+    }
+}
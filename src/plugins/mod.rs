@@ -0,0 +1,55 @@
+pub mod git;
+
+use crate::core::parser::ParsedCommand;
+use crate::core::ShellContext;
+use crate::utils::completion::CompletionCandidate;
+use anyhow::Result;
+use std::sync::{Arc, RwLock};
+
+pub use git::GitPlugin;
+
+/// What the shell does with a command once a plugin has seen it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PluginAction {
+    /// Run the command
+    Continue,
+    /// Don't run it, leaving a status of 1
+    Cancel,
+}
+
+/// An extension to the shell. Plugins are told when the shell starts and around
+/// each interactive command, and can offer completions.
+pub trait FlexPlugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Called once when the plugin is loaded; an `Err` leaves it unloaded.
+    fn on_init(&mut self, _ctx: &mut ShellContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before each interactive command runs. An `Err` cancels the command
+    /// and is reported.
+    fn on_pre_exec(&self, _cmd: &ParsedCommand, _ctx: &ShellContext) -> Result<PluginAction> {
+        Ok(PluginAction::Continue)
+    }
+
+    /// Called after each interactive command with its exit code.
+    fn on_post_exec(&self, _cmd: &ParsedCommand, _exit_code: i32, _ctx: &ShellContext) {}
+
+    /// Candidates for `word`, the word being completed, where `tokens` are the words
+    /// before it on the line. An empty list means the shell completes as usual.
+    fn completions(&self, _word: &str, _tokens: &[String]) -> Vec<CompletionCandidate> {
+        Vec::new()
+    }
+}
+
+/// The loaded plugins, shared with the line editor for completions.
+pub type SharedPlugins = Arc<RwLock<Vec<Box<dyn FlexPlugin>>>>;
+
+/// The built-in plugin called `name`.
+pub fn builtin_plugin(name: &str) -> Option<Box<dyn FlexPlugin>> {
+    match name {
+        "git" => Some(Box::new(GitPlugin)),
+        _ => None,
+    }
+}
@@ -1,6 +1,20 @@
+use log::warn;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-#[derive(Debug, Clone)]
+/// Whether the terminal shows 24-bit colors; `#RRGGBB` falls back to 256 colors otherwise.
+static TRUECOLOR: AtomicBool = AtomicBool::new(true);
+
+/// Sets whether `#RRGGBB` colors are sent as 24-bit colors.
+pub fn set_truecolor(enabled: bool) {
+    TRUECOLOR.store(enabled, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct ColorScheme {
     pub name: String,
     pub colors: HashMap<String, String>,
@@ -95,6 +109,14 @@ impl ColorScheme {
         self.colors.get(key)
     }
 
+    /// `text` in the color this scheme gives `role`, or unchanged if it has none.
+    pub fn paint(&self, role: &str, text: &str) -> String {
+        match self.get_color(role).and_then(|color| ansi_code(color)) {
+            Some(code) => format!("{}{}\x1b[0m", code, text),
+            None => text.to_string(),
+        }
+    }
+
     pub fn get_available_schemes() -> Vec<ColorScheme> {
         vec![
             Self::default_scheme(),
@@ -103,6 +125,112 @@ impl ColorScheme {
             Self::monokai_scheme(),
         ]
     }
+
+    /// Reads the user's schemes from the `*.toml` files in `dir`, in file name order.
+    /// Each file gives a `name` and a `[colors]` table; roles it leaves out take
+    /// the default scheme's color, and files that don't parse are skipped.
+    pub fn load_from_dir(dir: &Path) -> Vec<ColorScheme> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<_> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        let mut schemes = Vec::new();
+        for path in paths {
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(toml::from_str::<ColorScheme>(&content)?));
+            match parsed {
+                Ok(mut scheme) => {
+                    scheme.fill_missing(&path);
+                    schemes.push(scheme);
+                }
+                Err(e) => warn!("Skipping color scheme {}: {}", path.display(), e),
+            }
+        }
+        schemes
+    }
+
+    /// Warns about unknown colors and roles missing from a scheme loaded from
+    /// `path`, giving the missing ones the default scheme's colors.
+    fn fill_missing(&mut self, path: &Path) {
+        for (role, color) in &self.colors {
+            if ansi_code(color).is_none() {
+                warn!("Color scheme {}: unknown color '{}' for '{}'", path.display(), color, role);
+            }
+        }
+        let mut defaults: Vec<_> = Self::default_scheme().colors.into_iter().collect();
+        defaults.sort();
+        for (role, color) in defaults {
+            if let Entry::Vacant(entry) = self.colors.entry(role) {
+                warn!("Color scheme {} has no '{}' color, using {}", path.display(), entry.key(), color);
+                entry.insert(color);
+            }
+        }
+    }
+}
+
+/// The escape sequence for a color or style name, as used in schemes and in the
+/// `{red}`-style codes of the prompt format. `#RRGGBB` gives a 24-bit color, or
+/// the nearest of 256 colors where the terminal lacks them.
+pub fn ansi_code(name: &str) -> Option<Cow<'static, str>> {
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        let code = rgb_code(channel(0)?, channel(2)?, channel(4)?, TRUECOLOR.load(Ordering::Relaxed));
+        return Some(Cow::Owned(code));
+    }
+    let code = match name {
+        "red" => "\x1b[31m",
+        "green" => "\x1b[32m",
+        "blue" => "\x1b[34m",
+        "yellow" => "\x1b[33m",
+        "magenta" => "\x1b[35m",
+        "cyan" => "\x1b[36m",
+        "white" => "\x1b[37m",
+        "black" => "\x1b[30m",
+        "bright_red" => "\x1b[91m",
+        "bright_green" => "\x1b[92m",
+        "bright_blue" => "\x1b[94m",
+        "bright_yellow" => "\x1b[93m",
+        "bright_magenta" => "\x1b[95m",
+        "bright_cyan" => "\x1b[96m",
+        "bright_white" => "\x1b[97m",
+        "bright_black" => "\x1b[90m",
+        "reset" => "\x1b[0m",
+        "bold" => "\x1b[1m",
+        "dim" => "\x1b[2m",
+        "italic" => "\x1b[3m",
+        "underline" => "\x1b[4m",
+        _ => return None,
+    };
+    Some(Cow::Borrowed(code))
+}
+
+/// The foreground escape for a color: 24-bit with `truecolor`, otherwise the closest
+/// entry of the 256-color palette's gray ramp or 6x6x6 cube.
+fn rgb_code(r: u8, g: u8, b: u8, truecolor: bool) -> String {
+    if truecolor {
+        return format!("\x1b[38;2;{};{};{}m", r, g, b);
+    }
+    let index = if r == g && g == b {
+        match r {
+            0..=7 => 16,
+            249..=255 => 231,
+            gray => 232 + (u16::from(gray) - 8) * 24 / 241,
+        }
+    } else {
+        let level = |channel: u8| (u16::from(channel) * 5 + 127) / 255;
+        16 + 36 * level(r) + 6 * level(g) + level(b)
+    };
+    format!("\x1b[38;5;{}m", index)
 }
 
 #[cfg(test)]
@@ -125,6 +253,46 @@ mod tests {
         assert_eq!(command_color.unwrap(), "blue");
     }
 
+    #[test]
+    fn test_paint() {
+        let scheme = ColorScheme::monokai_scheme();
+        assert_eq!(scheme.paint("command", "ls"), "\x1b[36mls\x1b[0m");
+        assert_eq!(scheme.paint("no_such_role", "ls"), "ls");
+    }
+
+    #[test]
+    fn test_ansi_code() {
+        assert_eq!(ansi_code("bright_red").as_deref(), Some("\x1b[91m"));
+        assert_eq!(ansi_code("#FF8000").as_deref(), Some("\x1b[38;2;255;128;0m"));
+        assert_eq!(rgb_code(255, 128, 0, false), "\x1b[38;5;214m");
+        assert_eq!(rgb_code(128, 128, 128, false), "\x1b[38;5;243m");
+        assert_eq!(rgb_code(0, 0, 0, false), "\x1b[38;5;16m");
+        assert_eq!(ansi_code("#FF80"), None);
+        assert_eq!(ansi_code("#GG0000"), None);
+        assert_eq!(ansi_code("purple"), None);
+    }
+
+    #[test]
+    fn test_load_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("ocean.toml"),
+            "name = \"ocean\"\n[colors]\ncommand = \"#1E90FF\"\nerror = \"bright_red\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.toml"), "name = ").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a scheme").unwrap();
+
+        let schemes = ColorScheme::load_from_dir(dir.path());
+        assert_eq!(schemes.len(), 1);
+        let scheme = &schemes[0];
+        assert_eq!(scheme.name, "ocean");
+        assert_eq!(scheme.paint("command", "ls"), "\x1b[38;2;30;144;255mls\x1b[0m");
+        // Missing roles come from the default scheme
+        assert_eq!(scheme.get_color("success").unwrap(), "green");
+        assert!(ColorScheme::load_from_dir(&dir.path().join("missing")).is_empty());
+    }
+
     #[test]
     fn test_available_schemes() {
         let schemes = ColorScheme::get_available_schemes();
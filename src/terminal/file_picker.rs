@@ -0,0 +1,142 @@
+use super::picker::{self, Picker};
+use crate::utils::completion::CompletionEngine;
+use anyhow::Result;
+use crossterm::event::KeyCode;
+use std::path::{Path, PathBuf};
+
+/// A directory entry shown in the picker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// The entries of `dir`, directories first and each group sorted by name. Hidden
+/// entries are left out.
+pub fn list_dir(dir: &Path) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| Entry {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    is_dir: entry.path().is_dir(),
+                })
+                .filter(|entry| !entry.name.starts_with('.'))
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+    entries
+}
+
+/// The entries whose names match `query`, keeping their order.
+pub fn filter<'a>(entries: &'a [Entry], query: &str) -> Vec<&'a Entry> {
+    let engine = CompletionEngine::new(false, false);
+    entries.iter().filter(|entry| engine.fuzzy_score(&entry.name, query).is_some()).collect()
+}
+
+/// Shows the file picker over the screen, starting in `start`. Typing filters the
+/// entries, Up/Down move the selection, Right opens a directory and Left goes to
+/// the parent. Enter returns the selected path, relative to `start` when it's
+/// inside it; Escape returns `None`.
+pub fn run(start: &Path) -> Result<Option<PathBuf>> {
+    let dir = start.to_path_buf();
+    picker::run(Files { start, entries: list_dir(&dir), dir }, "")
+}
+
+struct Files<'a> {
+    start: &'a Path,
+    /// The directory being shown
+    dir: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl Files<'_> {
+    fn open(&mut self, dir: PathBuf) {
+        self.entries = list_dir(&dir);
+        self.dir = dir;
+    }
+}
+
+impl Picker for Files<'_> {
+    type Row = Entry;
+    type Output = PathBuf;
+
+    fn title(&self) -> String {
+        display_path(self.start, &self.dir).to_string_lossy().to_string()
+    }
+
+    fn rows(&self, query: &str) -> Vec<Entry> {
+        filter(&self.entries, query).into_iter().cloned().collect()
+    }
+
+    fn label(&self, entry: &Entry) -> String {
+        if entry.is_dir { format!("{}/", entry.name) } else { entry.name.clone() }
+    }
+
+    fn choose(&self, entry: &Entry) -> PathBuf {
+        display_path(self.start, &self.dir.join(&entry.name))
+    }
+
+    fn key(&mut self, code: KeyCode, selected: Option<&Entry>) -> bool {
+        match code {
+            KeyCode::Right => match selected.filter(|entry| entry.is_dir) {
+                Some(entry) => {
+                    self.open(self.dir.join(&entry.name));
+                    true
+                }
+                None => false,
+            },
+            KeyCode::Left => match self.dir.parent() {
+                Some(parent) => {
+                    self.open(parent.to_path_buf());
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// `path` relative to `start` when it's inside it, otherwise as it is.
+fn display_path(start: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(start) {
+        Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_and_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.txt"), "").unwrap();
+        std::fs::write(dir.path().join("A.md"), "").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+        std::fs::create_dir(dir.path().join("zeta")).unwrap();
+
+        let entries = list_dir(dir.path());
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["zeta", "A.md", "b.txt"]);
+        assert!(entries[0].is_dir);
+
+        let matches = filter(&entries, "txt");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].name, "b.txt");
+        assert_eq!(filter(&entries, "").len(), 3);
+    }
+
+    #[test]
+    fn test_display_path() {
+        let start = Path::new("/home/me/project");
+        assert_eq!(display_path(start, Path::new("/home/me/project/src/main.rs")), PathBuf::from("src/main.rs"));
+        assert_eq!(display_path(start, Path::new("/home/me/notes.txt")), PathBuf::from("/home/me/notes.txt"));
+        assert_eq!(display_path(start, start), PathBuf::from("."));
+    }
+}
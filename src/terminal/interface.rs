@@ -63,18 +63,22 @@ impl TerminalInterface {
     }
 
     pub fn print_status_line(&self, status: &str) -> Result<()> {
-        let y = self.height - 1;
+        let (width, height) = self.get_dimensions();
+        let y = height.saturating_sub(1);
+        // Padded by what shows, as colors and hyperlinks take up no columns
+        let padding = (width as usize).saturating_sub(visible_width(status));
         execute!(
             io::stdout(),
             MoveTo(0, y),
             Clear(ClearType::CurrentLine),
-            Print(format!("{:width$}", status, width = self.width as usize))
+            Print(format!("{}{:padding$}", status, "", padding = padding))
         )?;
         Ok(())
     }
 
     pub fn print_completion_menu(&self, completions: &[String], selected: usize) -> Result<()> {
-        let start_y = self.height.saturating_sub(completions.len() as u16 + 1);
+        let (_, height) = self.get_dimensions();
+        let start_y = height.saturating_sub(completions.len() as u16 + 1);
 
         for (i, completion) in completions.iter().enumerate() {
             let y = start_y + i as u16;
@@ -90,8 +94,10 @@ impl TerminalInterface {
         Ok(())
     }
 
+    /// The terminal's current size, so drawing follows a resize; the size last
+    /// read is the fallback when the terminal can't be queried.
     pub fn get_dimensions(&self) -> (u16, u16) {
-        (self.width, self.height)
+        terminal::size().unwrap_or((self.width, self.height))
     }
 
     pub fn set_color_scheme(&mut self, scheme: ColorScheme) {
@@ -148,6 +154,39 @@ impl TerminalInterface {
     }
 }
 
+/// The number of characters in `text` outside escape sequences: CSI sequences
+/// such as colors, and OSC sequences such as hyperlinks.
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            width += 1;
+            continue;
+        }
+        match chars.next() {
+            // CSI ends at its final byte
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC ends at BEL or ST (ESC \)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    width
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,4 +207,11 @@ mod tests {
             assert!(height > 0);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_visible_width() {
+        assert_eq!(visible_width("plain"), 5);
+        assert_eq!(visible_width("\x1b[1;32mok\x1b[0m"), 2);
+        assert_eq!(visible_width("\x1b]8;;file://box/tmp/a\x1b\\a\x1b]8;;\x1b\\ b"), 3);
+    }
+}
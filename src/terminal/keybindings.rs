@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use rustyline::history::DefaultHistory;
+use rustyline::{
+    At, Cmd, ConditionalEventHandler, Editor, Event, EventContext, EventHandler, Helper, KeyCode,
+    KeyEvent, Modifiers, Movement, RepeatCount, Word,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// What a configured key combination does when pressed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyAction {
+    /// A line-editor command handled by rustyline itself.
+    Editor(Cmd),
+    /// A shell command run in place of the current line.
+    Run(String),
+    /// Opens the fuzzy history search palette.
+    HistoryPalette,
+    /// Opens the file picker, inserting the chosen path at the cursor.
+    FilePicker,
+}
+
+/// A bound action waiting for the shell, along with the line the user had typed
+/// and the cursor position in it. The action is never `KeyAction::Editor`, which
+/// rustyline handles itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingBinding {
+    pub action: KeyAction,
+    pub line: String,
+    pub pos: usize,
+}
+
+/// Shared slot written by `run:` and palette bindings and drained by the shell after
+/// readline returns.
+pub type PendingSlot = Arc<Mutex<Option<PendingBinding>>>;
+
+/// Key bound by default to the history palette.
+const HISTORY_PALETTE_KEY: &str = "ctrl-p";
+/// Key bound to the file picker when `completion.file_picker` is on.
+const FILE_PICKER_KEY: &str = "ctrl-t";
+
+struct ShellBinding {
+    action: KeyAction,
+    pending: PendingSlot,
+}
+
+impl ConditionalEventHandler for ShellBinding {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = Some(PendingBinding {
+                action: self.action.clone(),
+                line: ctx.line().to_string(),
+                pos: ctx.pos(),
+            });
+        }
+        Some(Cmd::AcceptLine)
+    }
+}
+
+/// Parses a key combination such as `ctrl-l`, `alt-f` or `ctrl-shift-p`.
+pub fn parse_key(spec: &str) -> Result<KeyEvent> {
+    let spec = spec.trim().to_lowercase();
+    let mut parts: Vec<&str> = spec.split('-').collect();
+    // A trailing '-' key splits into two empty parts ("alt--")
+    if spec.ends_with("--") {
+        parts.truncate(parts.len() - 2);
+        parts.push("-");
+    }
+
+    let key = parts.pop().filter(|k| !k.is_empty()).ok_or_else(|| anyhow!("Missing key in '{}'", spec))?;
+    let mut mods = Modifiers::NONE;
+    for part in parts {
+        match part {
+            "ctrl" | "control" => mods |= Modifiers::CTRL,
+            "alt" | "meta" => mods |= Modifiers::ALT,
+            "shift" => mods |= Modifiers::SHIFT,
+            _ => return Err(anyhow!("Unknown modifier '{}' in '{}'", part, spec)),
+        }
+    }
+
+    let code = match key {
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "esc" | "escape" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        f if f.len() > 1 && f.starts_with('f') && f[1..].parse::<u8>().is_ok() => {
+            KeyCode::F(f[1..].parse().unwrap())
+        }
+        k if k.chars().count() == 1 => {
+            let c = k.chars().next().unwrap();
+            return Ok(KeyEvent::normalize(KeyEvent::new(c, mods)));
+        }
+        _ => return Err(anyhow!("Unknown key '{}' in '{}'", key, spec)),
+    };
+
+    Ok(KeyEvent::normalize(KeyEvent(code, mods)))
+}
+
+/// Parses an action string: a line-editor action name, `run: <command>`, or
+/// an empty string to disable the key.
+pub fn parse_action(action: &str) -> Result<KeyAction> {
+    let action = action.trim();
+    if let Some(command) = action.strip_prefix("run:") {
+        let command = command.trim();
+        if command.is_empty() {
+            return Err(anyhow!("'run:' binding has no command"));
+        }
+        return Ok(KeyAction::Run(command.to_string()));
+    }
+
+    let cmd = match action {
+        "" | "noop" => Cmd::Noop,
+        "clear" | "clear-screen" => Cmd::ClearScreen,
+        "word-forward" | "forward-word" => Cmd::Move(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs)),
+        "word-backward" | "backward-word" => Cmd::Move(Movement::BackwardWord(1, Word::Emacs)),
+        "char-forward" | "forward-char" => Cmd::Move(Movement::ForwardChar(1)),
+        "char-backward" | "backward-char" => Cmd::Move(Movement::BackwardChar(1)),
+        "line-start" | "beginning-of-line" => Cmd::Move(Movement::BeginningOfLine),
+        "line-end" | "end-of-line" => Cmd::Move(Movement::EndOfLine),
+        "kill-line" => Cmd::Kill(Movement::EndOfLine),
+        "kill-whole-line" | "unix-line-discard" => Cmd::Kill(Movement::BeginningOfLine),
+        "kill-word" => Cmd::Kill(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs)),
+        "backward-kill-word" => Cmd::Kill(Movement::BackwardWord(1, Word::Emacs)),
+        // Back to the previous whitespace, as Ctrl+W does by default
+        "unix-word-rubout" => Cmd::Kill(Movement::BackwardWord(1, Word::Big)),
+        "yank" => Cmd::Yank(1, rustyline::Anchor::Before),
+        "undo" => Cmd::Undo(1),
+        "history-prev" | "previous-history" => Cmd::PreviousHistory,
+        "history-next" | "next-history" => Cmd::NextHistory,
+        "history-search-backward" | "reverse-search-history" => Cmd::ReverseSearchHistory,
+        "history-search-forward" | "forward-search-history" => Cmd::ForwardSearchHistory,
+        "complete" => Cmd::Complete,
+        "accept-line" => Cmd::AcceptLine,
+        "abort" => Cmd::Abort,
+        "history-palette" => return Ok(KeyAction::HistoryPalette),
+        "file-picker" => return Ok(KeyAction::FilePicker),
+        _ => return Err(anyhow!("Unknown keybinding action '{}'", action)),
+    };
+
+    Ok(KeyAction::Editor(cmd))
+}
+
+/// Installs the default bindings, then the configured ones on top. Invalid entries
+/// are skipped with a warning. `file_picker` adds the file picker's default key.
+pub fn apply<H: Helper>(
+    editor: &mut Editor<H, DefaultHistory>,
+    bindings: &HashMap<String, String>,
+    pending: &PendingSlot,
+    file_picker: bool,
+) {
+    // Binding a key again replaces it, so configured keys win over the defaults
+    let mut defaults = vec![(HISTORY_PALETTE_KEY, "history-palette")];
+    if file_picker {
+        defaults.push((FILE_PICKER_KEY, "file-picker"));
+    }
+    let configured = bindings.iter().map(|(spec, action)| (spec.as_str(), action.as_str()));
+    for (spec, action) in defaults.into_iter().chain(configured) {
+        let key = match parse_key(spec) {
+            Ok(key) => key,
+            Err(e) => {
+                warn!("Ignoring keybinding '{}': {}", spec, e);
+                continue;
+            }
+        };
+        let handler = match parse_action(action) {
+            Ok(KeyAction::Editor(cmd)) => EventHandler::Simple(cmd),
+            Ok(action) => EventHandler::Conditional(Box::new(ShellBinding {
+                action,
+                pending: Arc::clone(pending),
+            })),
+            Err(e) => {
+                warn!("Ignoring keybinding '{}': {}", spec, e);
+                continue;
+            }
+        };
+        debug!("Binding {:?} to '{}'", key, action);
+        editor.bind_sequence(key, handler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ctrl_l_clear() {
+        assert_eq!(parse_key("ctrl-l").unwrap(), KeyEvent::ctrl('L'));
+        assert_eq!(parse_action("clear").unwrap(), KeyAction::Editor(Cmd::ClearScreen));
+        assert_eq!(parse_key("alt-f").unwrap(), KeyEvent::alt('f'));
+        assert_eq!(parse_action("").unwrap(), KeyAction::Editor(Cmd::Noop));
+    }
+
+    #[test]
+    fn test_run_binding() {
+        assert_eq!(parse_key("ctrl-shift-p").unwrap(), KeyEvent::ctrl('P'));
+        assert_eq!(
+            parse_action("run: git status").unwrap(),
+            KeyAction::Run("git status".to_string())
+        );
+        assert!(parse_action("run:").is_err());
+        assert_eq!(parse_action("history-palette").unwrap(), KeyAction::HistoryPalette);
+        assert_eq!(parse_action("file-picker").unwrap(), KeyAction::FilePicker);
+        assert!(parse_action("launch-rockets").is_err());
+        assert!(parse_key("hyper-x").is_err());
+    }
+
+    /// Applies a movement or kill to `line` with the cursor at `pos`, returning the
+    /// line and cursor position after it.
+    fn edit(line: &str, pos: usize, action: &str) -> (String, usize) {
+        struct Ignore;
+        impl rustyline::line_buffer::DeleteListener for Ignore {
+            fn delete(&mut self, _: usize, _: &str, _: rustyline::line_buffer::Direction) {}
+        }
+        impl rustyline::line_buffer::ChangeListener for Ignore {
+            fn insert_char(&mut self, _: usize, _: char) {}
+            fn insert_str(&mut self, _: usize, _: &str) {}
+            fn replace(&mut self, _: usize, _: &str, _: &str) {}
+        }
+
+        let mut buffer = rustyline::line_buffer::LineBuffer::with_capacity(64);
+        buffer.update(line, pos, &mut Ignore);
+        match parse_action(action).unwrap() {
+            KeyAction::Editor(Cmd::Move(Movement::ForwardWord(n, at, word))) => {
+                buffer.move_to_next_word(at, word, n);
+            }
+            KeyAction::Editor(Cmd::Move(Movement::BackwardWord(n, word))) => {
+                buffer.move_to_prev_word(word, n);
+            }
+            KeyAction::Editor(Cmd::Kill(movement)) => {
+                buffer.kill(&movement, &mut Ignore);
+            }
+            other => panic!("not a word action: {:?}", other),
+        }
+        (buffer.as_str().to_string(), buffer.pos())
+    }
+
+    #[test]
+    fn test_word_actions() {
+        // The actions behind the default Alt+F, Alt+B, Alt+D and Ctrl+W in Emacs mode
+        assert_eq!(edit("hello world", 0, "forward-word"), ("hello world".to_string(), 5));
+        assert_eq!(edit("hello world", 5, "forward-word"), ("hello world".to_string(), 11));
+        assert_eq!(edit("hello world", 11, "backward-word"), ("hello world".to_string(), 6));
+        assert_eq!(edit("hello world", 0, "kill-word"), (" world".to_string(), 0));
+        assert_eq!(edit("ls src/core", 11, "unix-word-rubout"), ("ls ".to_string(), 3));
+        assert_eq!(edit("ls src/core", 11, "backward-kill-word"), ("ls src/".to_string(), 7));
+    }
+}
@@ -1,33 +1,70 @@
 pub mod colors;
 pub mod events;
+pub mod file_picker;
 pub mod interface;
+pub mod keybindings;
+pub mod palette;
+pub mod picker;
+pub mod shell_integration;
 
 use anyhow::Result;
 use colored::*;
+use colors::ColorScheme;
+use crate::utils::terminal_capabilities::MultiplexerKind;
+use crate::utils::termios::TermiosGuard;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::io::{self, Write};
+use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct Terminal {
     colors_enabled: bool,
+    /// Colors of the shell's own messages
+    scheme: ColorScheme,
+    /// tmux or Screen, which take their own title sequence
+    multiplexer: MultiplexerKind,
+    /// The terminal settings from before raw mode, put back when leaving it
+    saved_settings: Option<Arc<TermiosGuard>>,
 }
 
 impl Terminal {
-    pub fn new(colors_enabled: bool) -> Result<Self> {
-        Ok(Self { colors_enabled })
+    pub fn new(colors_enabled: bool, scheme: ColorScheme, multiplexer: MultiplexerKind) -> Result<Self> {
+        Ok(Self { colors_enabled, scheme, multiplexer, saved_settings: None })
     }
 
-    pub async fn enter_raw_mode(&self) -> Result<()> {
+    /// Sets the window title, or the window name inside tmux and Screen.
+    pub fn update_window_title(&self, title: &str) {
+        print!("{}", self.multiplexer.window_title(title));
+        let _ = io::stdout().flush();
+    }
+
+    pub fn scheme(&self) -> &ColorScheme {
+        &self.scheme
+    }
+
+    pub fn set_scheme(&mut self, scheme: ColorScheme) {
+        self.scheme = scheme;
+    }
+
+    pub async fn enter_raw_mode(&mut self) -> Result<()> {
+        self.saved_settings = TermiosGuard::capture().map(Arc::new);
         enable_raw_mode()?;
         Ok(())
     }
 
-    pub async fn leave_raw_mode(&self) -> Result<()> {
+    /// Leaves raw mode, putting back all the settings the terminal had before it,
+    /// not only the ones raw mode changes.
+    pub async fn leave_raw_mode(&mut self) -> Result<()> {
         disable_raw_mode()?;
+        if let Some(settings) = self.saved_settings.take() {
+            settings.restore()?;
+        }
         Ok(())
     }
 
     pub async fn print_info(&self, message: &str) -> Result<()> {
         if self.colors_enabled {
-            println!("{}", message.bright_blue());
+            println!("{}", self.scheme.paint("info", message));
         } else {
             println!("{}", message);
         }
@@ -36,7 +73,7 @@ impl Terminal {
 
     pub async fn print_error(&self, message: &str) -> Result<()> {
         if self.colors_enabled {
-            eprintln!("{}", message.bright_red());
+            eprintln!("{}", self.scheme.paint("error", message));
         } else {
             eprintln!("{}", message);
         }
@@ -45,7 +82,7 @@ impl Terminal {
 
     pub async fn print_success(&self, message: &str) -> Result<()> {
         if self.colors_enabled {
-            println!("{}", message.bright_green());
+            println!("{}", self.scheme.paint("success", message));
         } else {
             println!("{}", message);
         }
@@ -54,7 +91,7 @@ impl Terminal {
 
     pub async fn print_warning(&self, message: &str) -> Result<()> {
         if self.colors_enabled {
-            println!("{}", message.bright_yellow());
+            println!("{}", self.scheme.paint("warning", message));
         } else {
             println!("{}", message);
         }
@@ -66,7 +103,12 @@ impl Terminal {
             return prompt.to_string();
         }
 
-        // Apply colors with proper rustyline invisible character markers
+        // Apply colors with proper rustyline invisible character markers, except in
+        // tmux, which can show them
+        let (start, end) = match self.multiplexer {
+            MultiplexerKind::TmuxMode => ("", ""),
+            _ => ("\x01", "\x02"),
+        };
         let mut result = String::new();
         let mut chars = prompt.chars().peekable();
 
@@ -90,23 +132,23 @@ impl Terminal {
                     // Wrap ONLY the ANSI codes in \x01..\x02, not the visible text
                     // This is tricky - we need to separate ANSI codes from visible chars
                     let visible_chars = &bracket_content;
-                    result.push_str("\x01");
+                    result.push_str(start);
                     result.push_str(&format!("\x1b[96m")); // bright cyan
-                    result.push_str("\x02");
+                    result.push_str(end);
                     result.push_str(visible_chars);
-                    result.push_str("\x01");
+                    result.push_str(start);
                     result.push_str("\x1b[0m"); // reset
-                    result.push_str("\x02");
+                    result.push_str(end);
                 }
                 '$' | '#' | '%' => {
                     // Color prompt symbols
-                    result.push_str("\x01");
+                    result.push_str(start);
                     result.push_str(&format!("\x1b[95;1m")); // bright magenta bold
-                    result.push_str("\x02");
+                    result.push_str(end);
                     result.push(ch);
-                    result.push_str("\x01");
+                    result.push_str(start);
                     result.push_str("\x1b[0m"); // reset
-                    result.push_str("\x02");
+                    result.push_str(end);
                 }
                 ' ' => {
                     // Preserve spaces exactly
@@ -0,0 +1,71 @@
+use super::picker::{self, Picker};
+use crate::utils::completion::CompletionEngine;
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// History entries matching `query`, best first. Duplicates keep their most recent
+/// position, and ties go to the more recent entry.
+pub fn rank<'a>(entries: &'a [String], query: &str) -> Vec<&'a str> {
+    let engine = CompletionEngine::new(false, false);
+    let mut seen = HashSet::new();
+    let mut scored: Vec<(i64, &str)> = entries
+        .iter()
+        .rev()
+        .filter(|entry| seen.insert(entry.as_str()))
+        .filter_map(|entry| engine.fuzzy_score(entry, query).map(|score| (score, entry.as_str())))
+        .collect();
+    // Stable, so equal scores stay newest first
+    scored.sort_by_key(|&(score, _)| Reverse(score));
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Shows the history palette over the screen, starting from `query`. Typing filters
+/// the entries, Up/Down move the selection and Enter returns it; Escape returns `None`.
+pub fn run(entries: &[String], query: &str) -> Result<Option<String>> {
+    picker::run(History { entries }, query)
+}
+
+struct History<'a> {
+    entries: &'a [String],
+}
+
+impl Picker for History<'_> {
+    type Row = String;
+    type Output = String;
+
+    const MAX_ROWS: usize = 12;
+
+    fn title(&self) -> String {
+        "History".to_string()
+    }
+
+    fn rows(&self, query: &str) -> Vec<String> {
+        rank(self.entries, query).into_iter().map(str::to_string).collect()
+    }
+
+    fn label(&self, row: &String) -> String {
+        row.clone()
+    }
+
+    fn choose(&self, row: &String) -> String {
+        row.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        let entries: Vec<String> = ["cargo test", "git status", "cargo build", "git stash", "cargo test"]
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect();
+        assert_eq!(rank(&entries, "gsta"), vec!["git stash", "git status"]);
+        assert_eq!(rank(&entries, "cargo"), vec!["cargo test", "cargo build"]);
+        assert_eq!(rank(&entries, "").len(), 4);
+        assert!(rank(&entries, "xyz").is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use super::colors::ColorScheme;
+use super::interface::TerminalInterface;
+use anyhow::Result;
+use crossterm::{
+    cursor::{self, MoveTo},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io::{self, Write};
+
+/// A list shown in a box over the screen, filtered by a typed query.
+pub trait Picker {
+    /// One entry of the list.
+    type Row;
+    /// What choosing a row returns.
+    type Output;
+
+    /// Most rows shown in the box at once.
+    const MAX_ROWS: usize = usize::MAX;
+
+    /// Shown in the box's top border.
+    fn title(&self) -> String;
+    /// The rows matching `query`, in the order shown.
+    fn rows(&self, query: &str) -> Vec<Self::Row>;
+    /// How `row` is shown.
+    fn label(&self, row: &Self::Row) -> String;
+    /// What Enter returns for `row`.
+    fn choose(&self, row: &Self::Row) -> Self::Output;
+    /// Handles a key the shared ones don't, with the selected row if any. Returns
+    /// true when it changed the list, which clears the query.
+    fn key(&mut self, _code: KeyCode, _selected: Option<&Self::Row>) -> bool {
+        false
+    }
+}
+
+/// Shows `picker` over the screen, starting from `query`. Typing filters the rows,
+/// Up/Down move the selection and Enter returns it; Escape returns `None`.
+pub fn run<P: Picker>(picker: P, query: &str) -> Result<Option<P::Output>> {
+    let was_raw = terminal::is_raw_mode_enabled()?;
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+
+    let result = select(picker, query.to_string());
+
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, cursor::Show);
+    if !was_raw {
+        terminal::disable_raw_mode()?;
+    }
+    result
+}
+
+fn select<P: Picker>(mut picker: P, mut query: String) -> Result<Option<P::Output>> {
+    let mut selected = 0;
+    loop {
+        let rows = picker.rows(&query);
+        selected = selected.min(rows.len().saturating_sub(1));
+        let labels: Vec<String> = rows.iter().map(|row| picker.label(row)).collect();
+        draw_box(&picker.title(), &labels, selected, &query, P::MAX_ROWS)?;
+
+        let Event::Key(KeyEvent { code, modifiers, kind, .. }) = event::read()? else {
+            continue;
+        };
+        if kind == KeyEventKind::Release {
+            continue;
+        }
+        match code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c' | 'g') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Enter => return Ok(rows.get(selected).map(|row| picker.choose(row))),
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Char('p') if modifiers.contains(KeyModifiers::CONTROL) => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected += 1,
+            KeyCode::Char('n') if modifiers.contains(KeyModifiers::CONTROL) => selected += 1,
+            KeyCode::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                query.push(c);
+                selected = 0;
+            }
+            code => {
+                if picker.key(code, rows.get(selected)) {
+                    query.clear();
+                    selected = 0;
+                }
+            }
+        }
+    }
+}
+
+/// Draws the box near the top of the screen: the query, then a page of `rows`
+/// scrolled to keep the selection visible. At most `max_rows` rows, and `height - 5`,
+/// are shown.
+fn draw_box(title: &str, rows: &[String], selected: usize, query: &str, max_rows: usize) -> Result<()> {
+    let interface = TerminalInterface::new(ColorScheme::default_scheme())?;
+    let (width, height) = interface.get_dimensions();
+    let box_width = width.saturating_sub(4).max(10);
+    let inner = box_width as usize - 4;
+    let shown = rows.len().clamp(1, max_rows.max(1)).min(height.saturating_sub(5).max(1) as usize);
+    let first = (selected + 1).saturating_sub(shown);
+
+    let mut stdout = io::stdout();
+    queue!(stdout, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+    interface.draw_border(2, 1, box_width, shown as u16 + 3, Some(&fit_title(title, inner - 2)))?;
+
+    if rows.is_empty() {
+        queue!(stdout, MoveTo(4, 3), Print(truncate("no matches", inner)))?;
+    }
+    for (row, label) in rows.iter().skip(first).take(shown).enumerate() {
+        queue!(stdout, MoveTo(4, 3 + row as u16))?;
+        if first + row == selected {
+            queue!(stdout, SetAttribute(Attribute::Reverse), Print(format!("{:<inner$}", truncate(label, inner))))?;
+            queue!(stdout, SetAttribute(Attribute::Reset))?;
+        } else {
+            queue!(stdout, Print(truncate(label, inner)))?;
+        }
+    }
+
+    let prompt = truncate(&format!("> {}", query), inner);
+    queue!(stdout, MoveTo(4, 2), Print(&prompt), MoveTo(4 + prompt.chars().count() as u16, 2), cursor::Show)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The end of `title`, at most `max_bytes` long, as the border centers the title
+/// by its length in bytes.
+fn fit_title(title: &str, max_bytes: usize) -> String {
+    let title = title.replace('\n', " ");
+    let mut start = title.len().saturating_sub(max_bytes);
+    while !title.is_char_boundary(start) {
+        start += 1;
+    }
+    title[start..].to_string()
+}
+
+/// Cuts `text` to `width` characters, on one line.
+fn truncate(text: &str, width: usize) -> String {
+    text.replace('\n', " ").chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_and_truncate() {
+        assert_eq!(fit_title("/home/me/project", 7), "project");
+        assert_eq!(fit_title("History", 20), "History");
+        assert_eq!(fit_title("/tmp/é", 1), "");
+        assert_eq!(truncate("cargo\ntest", 8), "cargo te");
+    }
+}
@@ -0,0 +1,45 @@
+use crate::utils::hyperlink;
+use std::path::Path;
+
+/// `TERM_PROGRAM` values of terminals known to understand the OSC 133 marks.
+const SUPPORTED_TERMINALS: &[&str] = &["iTerm.app", "kitty", "WezTerm"];
+
+/// Marks the start of the prompt.
+pub const PROMPT_START: &str = "\x1b]133;A\x07";
+/// Marks the end of the prompt, where the user's input begins.
+pub const INPUT_START: &str = "\x1b]133;B\x07";
+/// Marks the start of a command's output.
+pub const OUTPUT_START: &str = "\x1b]133;C\x07";
+
+/// Whether the terminal named by `term_program` (the `TERM_PROGRAM` variable)
+/// supports semantic prompt marks.
+pub fn is_supported_terminal(term_program: Option<&str>) -> bool {
+    term_program.is_some_and(|program| SUPPORTED_TERMINALS.contains(&program))
+}
+
+/// Marks the end of a command's output with its exit status.
+pub fn command_finished(status: i32) -> String {
+    format!("\x1b]133;D;{}\x07", status)
+}
+
+/// Reports the working directory as a `file://` URL (OSC 7), so new tabs can open there.
+pub fn current_directory(hostname: &str, path: &Path) -> String {
+    format!("\x1b]7;{}\x07", hyperlink::file_url(hostname, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequences() {
+        assert!(is_supported_terminal(Some("WezTerm")));
+        assert!(!is_supported_terminal(Some("Apple_Terminal")));
+        assert!(!is_supported_terminal(None));
+        assert_eq!(command_finished(127), "\x1b]133;D;127\x07");
+        assert_eq!(
+            current_directory("box", Path::new("/home/me/my docs")),
+            "\x1b]7;file://box/home/me/my%20docs\x07"
+        );
+    }
+}
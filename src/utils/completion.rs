@@ -1,8 +1,21 @@
 use crate::builtins::list_builtins;
 use crate::utils::path::{expand_tilde, get_parent_and_name, is_hidden};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
 use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How long the host names read by `system_hosts` are reused before reading again.
+const HOSTS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+static HOSTS_CACHE: Mutex<Option<(Instant, Vec<String>)>> = Mutex::new(None);
+
+/// Accounts below this UID are system accounts rather than people.
+const FIRST_USER_UID: u32 = 1000;
+
+/// Login names with their UIDs, read on first use.
+static USERS: OnceLock<Vec<(String, u32)>> = OnceLock::new();
 
 #[derive(Debug, Clone)]
 pub struct CompletionCandidate {
@@ -20,6 +33,7 @@ pub enum CompletionKind {
     Builtin,
     Variable,
     Alias,
+    Argument,
 }
 
 pub struct CompletionEngine {
@@ -195,6 +209,48 @@ impl CompletionEngine {
         }
     }
 
+    /// Scores how well `query` fuzzily matches `candidate`: its characters must all
+    /// appear in order. Runs of consecutive characters and matches at the start of a
+    /// word score higher, skipped characters lower. `None` if it doesn't match.
+    pub fn fuzzy_score(&self, candidate: &str, query: &str) -> Option<i64> {
+        let fold = |c: char| if self.case_sensitive { c } else { c.to_ascii_lowercase() };
+        let mut query_chars = query.chars().map(fold).peekable();
+        let mut score = 0;
+        let mut previous: Option<char> = None;
+        let mut consecutive = false;
+        let mut started = false;
+
+        for ch in candidate.chars() {
+            let Some(&wanted) = query_chars.peek() else {
+                break;
+            };
+            if fold(ch) == wanted {
+                query_chars.next();
+                score += 1;
+                if consecutive {
+                    score += 5;
+                }
+                if previous.is_none_or(|p| p.is_whitespace() || "/-_.".contains(p)) {
+                    score += 8;
+                }
+                consecutive = true;
+                started = true;
+            } else {
+                // Only gaps inside the match count against it
+                if started {
+                    score -= 1;
+                }
+                consecutive = false;
+            }
+            previous = Some(ch);
+        }
+
+        if query_chars.peek().is_some() {
+            return None;
+        }
+        Some(score)
+    }
+
     fn is_executable(&self, path: &Path) -> bool {
         if !path.is_file() {
             return false;
@@ -220,6 +276,107 @@ impl CompletionEngine {
     }
 }
 
+/// Host names known to this machine, from the hosts file plus (on Unix) its own name
+/// and `/etc/networks`, sorted and without duplicates. Cached for a minute.
+pub fn system_hosts() -> Vec<String> {
+    let mut cache = HOSTS_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((read_at, hosts)) = cache.as_ref() {
+        if read_at.elapsed() < HOSTS_CACHE_TTL {
+            return hosts.clone();
+        }
+    }
+
+    let read = |path: &str| fs::read_to_string(path).unwrap_or_default();
+    let mut hosts = BTreeSet::new();
+    #[cfg(unix)]
+    {
+        hosts.extend(parse_hosts(&read("/etc/hosts")));
+        hosts.extend(read("/etc/hostname").split_whitespace().map(String::from));
+        hosts.extend(parse_networks(&read("/etc/networks")));
+    }
+    #[cfg(windows)]
+    {
+        let root = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
+        hosts.extend(parse_hosts(&read(&format!("{}\\System32\\drivers\\etc\\hosts", root))));
+    }
+
+    let hosts: Vec<String> = hosts.into_iter().collect();
+    *cache = Some((Instant::now(), hosts.clone()));
+    hosts
+}
+
+/// Login names of the system's users, sorted. System accounts are left out unless
+/// `show_system` is set.
+pub fn system_users(show_system: bool) -> Vec<String> {
+    let users = USERS.get_or_init(read_users);
+    let mut names: Vec<String> = users
+        .iter()
+        .filter(|(_, uid)| show_system || *uid >= FIRST_USER_UID)
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(unix)]
+fn read_users() -> Vec<(String, u32)> {
+    parse_passwd(&fs::read_to_string("/etc/passwd").unwrap_or_default())
+}
+
+/// Without a user database to read, the profile folders under `Users` stand in for
+/// the accounts. All of them count as people.
+#[cfg(not(unix))]
+fn read_users() -> Vec<(String, u32)> {
+    let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    let Ok(entries) = fs::read_dir(format!("{}\\Users", drive)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !matches!(name.as_str(), "Public" | "Default" | "Default User" | "All Users"))
+        .map(|name| (name, FIRST_USER_UID))
+        .collect()
+}
+
+/// Names and UIDs from `/etc/passwd` lines (`name:password:uid:gid:...`).
+fn parse_passwd(contents: &str) -> Vec<(String, u32)> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next().filter(|name| !name.is_empty())?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((name.to_string(), uid))
+        })
+        .collect()
+}
+
+/// The names in a hosts file: each line is an address followed by its names.
+fn parse_hosts(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .flat_map(|line| line.split('#').next().unwrap_or("").split_whitespace().skip(1))
+        .map(String::from)
+        .collect()
+}
+
+/// The names in `/etc/networks`: a name, a network number, then any aliases.
+fn parse_networks(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .flat_map(|line| {
+            let mut fields = line.split('#').next().unwrap_or("").split_whitespace();
+            let name = fields.next();
+            name.into_iter().chain(fields.skip(1))
+        })
+        .map(String::from)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,6 +410,31 @@ mod tests {
         assert!(engine_case_insensitive.matches_prefix("LS", "ls"));
     }
 
+    #[test]
+    fn test_fuzzy_score() {
+        let engine = CompletionEngine::new(false, false);
+        assert!(engine.fuzzy_score("git status", "gst").is_some());
+        assert_eq!(engine.fuzzy_score("git status", "sg"), None);
+        assert_eq!(engine.fuzzy_score("anything", ""), Some(0));
+        // Consecutive and word-start matches rank above scattered ones
+        assert!(engine.fuzzy_score("cargo test", "test") > engine.fuzzy_score("the best", "test"));
+        assert!(engine.fuzzy_score("Cargo Build", "cb").is_some());
+    }
+
+    #[test]
+    fn test_parse_hosts() {
+        let hosts = "127.0.0.1 localhost\n# 10.0.0.9 old\n::1\tip6-localhost ip6-loopback # v6\n\n";
+        assert_eq!(parse_hosts(hosts), vec!["localhost", "ip6-localhost", "ip6-loopback"]);
+        let networks = "loopback 127.0.0.0\nlink-local 169.254.0.0 linklocal\n";
+        assert_eq!(parse_networks(networks), vec!["loopback", "link-local", "linklocal"]);
+    }
+
+    #[test]
+    fn test_parse_passwd() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\n# comment\nalice:x:1000:1000::/home/alice:/bin/sh\nbroken\n";
+        assert_eq!(parse_passwd(passwd), vec![("root".to_string(), 0), ("alice".to_string(), 1000)]);
+    }
+
     #[test]
     fn test_complete_commands() {
         let engine = CompletionEngine::new(false, true);
@@ -0,0 +1,98 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Which backslash escapes `unescape` replaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnescapeMode {
+    /// Only `\n`, `\t`, `\r` and `\\`, what plain `echo` interprets
+    Basic,
+    /// Everything `echo -e` interprets, adding `\a \b \e \f \v`, `\0NNN` (octal),
+    /// `\xHH` (hex) and `\uXXXX` / `\UXXXXXXXX` (Unicode)
+    Extended,
+    /// No escapes at all (`echo -E`)
+    Off,
+}
+
+/// Replaces backslash escapes in `text` according to `mode`. Unknown or malformed
+/// escapes are kept as written.
+pub fn unescape(text: &str, mode: UnescapeMode) -> String {
+    if mode == UnescapeMode::Off {
+        return text.to_string();
+    }
+
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        let Some(escaped) = chars.next() else {
+            result.push(ch);
+            break;
+        };
+
+        let replacement = match escaped {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '\\' => Some('\\'),
+            _ if mode == UnescapeMode::Basic => None,
+            'a' => Some('\x07'),
+            'b' => Some('\x08'),
+            'e' | 'E' => Some('\x1b'),
+            'f' => Some('\x0c'),
+            'v' => Some('\x0b'),
+            '0' => Some(char::from(read_digits(&mut chars, 8, 3).unwrap_or(0) as u8)),
+            'x' => read_digits(&mut chars, 16, 2).map(|code| char::from(code as u8)),
+            'u' => read_digits(&mut chars, 16, 4).and_then(char::from_u32),
+            'U' => read_digits(&mut chars, 16, 8).and_then(char::from_u32),
+            _ => None,
+        };
+
+        match replacement {
+            Some(replacement) => result.push(replacement),
+            None => {
+                result.push('\\');
+                result.push(escaped);
+            }
+        }
+    }
+
+    result
+}
+
+/// Reads up to `max` digits in `radix`; `None` if there are none.
+fn read_digits(chars: &mut Peekable<Chars>, radix: u32, max: usize) -> Option<u32> {
+    let mut value = None;
+    for _ in 0..max {
+        let Some(digit) = chars.peek().and_then(|c| c.to_digit(radix)) else {
+            break;
+        };
+        chars.next();
+        value = Some(value.unwrap_or(0) * radix + digit);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        assert_eq!(unescape("a\\tb\\nc\\\\d", UnescapeMode::Basic), "a\tb\nc\\d");
+        assert_eq!(unescape("\\e[1m \\x41", UnescapeMode::Basic), "\\e[1m \\x41");
+        assert_eq!(unescape("a\\nb", UnescapeMode::Off), "a\\nb");
+    }
+
+    #[test]
+    fn test_extended() {
+        assert_eq!(unescape("\\e[1m\\a", UnescapeMode::Extended), "\x1b[1m\x07");
+        assert_eq!(unescape("\\0101\\x42\\x4", UnescapeMode::Extended), "AB\x04");
+        assert_eq!(unescape("\\u00e9\\U0001F600", UnescapeMode::Extended), "é😀");
+        assert_eq!(unescape("\\xZZ \\q \\", UnescapeMode::Extended), "\\xZZ \\q \\");
+        assert_eq!(unescape("\\0", UnescapeMode::Extended), "\0");
+    }
+}
@@ -1,14 +1,38 @@
+use crate::core::options::ShellOptions;
 use anyhow::Result;
-use glob::glob;
-use std::path::PathBuf;
+use glob::{glob_with, MatchOptions, Pattern};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
 
-pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+/// As in other shells, `*` doesn't match across `/` or the leading dot of hidden files.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: true,
+};
+
+/// Expands `pattern` to the paths it matches. With `globstar`, a `**` component
+/// matches zero or more directories; without it, `**` is the same as `*`. With
+/// `extglob`, the ksh-style groups `?(..)`, `*(..)`, `+(..)`, `@(..)` and `!(..)`
+/// are recognized.
+pub fn expand_glob(pattern: &str, options: &ShellOptions) -> Result<Vec<PathBuf>> {
     let mut results = Vec::new();
 
-    for entry in glob(pattern)? {
-        match entry {
-            Ok(path) => results.push(path),
-            Err(e) => eprintln!("Error expanding glob pattern: {}", e),
+    if options.globstar && pattern.contains("**") {
+        results = expand_globstar(pattern);
+    } else if options.extglob && has_extglob(pattern) {
+        results = expand_extglob(pattern);
+    } else {
+        let pattern_text = pattern.replace("**", "*");
+        // Words like `[` that aren't valid patterns are left as they are
+        if let Ok(paths) = glob_with(&pattern_text, MATCH_OPTIONS) {
+            for entry in paths {
+                match entry {
+                    Ok(path) => results.push(path),
+                    Err(e) => eprintln!("Error expanding glob pattern: {}", e),
+                }
+            }
         }
     }
 
@@ -20,16 +44,253 @@ pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
     Ok(results)
 }
 
+/// Walks the directory tree below the pattern's literal leading directories and
+/// keeps the paths that match. Symbolic links aren't followed, so they can't
+/// lead the walk around in a cycle.
+fn expand_globstar(pattern: &str) -> Vec<PathBuf> {
+    let Ok(compiled) = Pattern::new(pattern) else {
+        return Vec::new();
+    };
+    let base: PathBuf = Path::new(pattern)
+        .components()
+        .take_while(|component| !has_glob_chars(&component.as_os_str().to_string_lossy()))
+        .collect();
+    let relative = base.as_os_str().is_empty();
+    let root = if relative { Path::new(".") } else { base.as_path() };
+
+    let mut results = Vec::new();
+    for entry in WalkDir::new(root).follow_links(false).min_depth(1).into_iter().flatten() {
+        let path = if relative {
+            entry.path().strip_prefix(".").unwrap_or(entry.path())
+        } else {
+            entry.path()
+        };
+        if compiled.matches_path_with(path, MATCH_OPTIONS) {
+            results.push(path.to_path_buf());
+        }
+    }
+    results.sort();
+    results
+}
+
+/// Matches pattern components one directory level at a time, since each one is
+/// translated to its own regex.
+fn expand_extglob(pattern: &str) -> Vec<PathBuf> {
+    let mut matches = vec![PathBuf::new()];
+    for (i, component) in pattern.split('/').enumerate() {
+        if component.is_empty() {
+            if i == 0 {
+                matches = vec![PathBuf::from("/")];
+            }
+            continue;
+        }
+        if !has_glob_chars(component) && !has_extglob(component) {
+            for path in &mut matches {
+                path.push(component);
+            }
+            continue;
+        }
+
+        let Some(matcher) = ExtGlob::new(component) else {
+            return Vec::new();
+        };
+        let mut next = Vec::new();
+        for dir in &matches {
+            let root = if dir.as_os_str().is_empty() { Path::new(".") } else { dir.as_path() };
+            let entries = WalkDir::new(root).min_depth(1).max_depth(1).sort_by_file_name();
+            for entry in entries.into_iter().flatten() {
+                if matcher.matches(&entry.file_name().to_string_lossy()) {
+                    next.push(dir.join(entry.file_name()));
+                }
+            }
+        }
+        matches = next;
+    }
+    matches.retain(|path| path.symlink_metadata().is_ok());
+    matches
+}
+
+/// One path component of an extended glob.
+struct ExtGlob {
+    include: Regex,
+    /// Names matching the `!(..)` groups, which `include` lets through
+    exclude: Option<Regex>,
+    /// Whether the pattern itself starts with a dot, allowing hidden names
+    hidden: bool,
+}
+
+impl ExtGlob {
+    fn new(component: &str) -> Option<Self> {
+        let chars: Vec<char> = component.chars().collect();
+        let include = Regex::new(&extglob_to_regex(component)).ok()?;
+        let exclude = match component.contains("!(") {
+            true => Some(Regex::new(&format!("^{}$", translate(&chars, Negation::Matched))).ok()?),
+            false => None,
+        };
+        Some(Self { include, exclude, hidden: component.starts_with('.') })
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        if name.starts_with('.') && !self.hidden {
+            return false;
+        }
+        self.include.is_match(name) && !self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(name))
+    }
+}
+
+/// What a `!(..)` group turns into when translating.
+#[derive(Clone, Copy)]
+enum Negation {
+    /// Any text, for the including regex
+    Anything,
+    /// The group's own alternatives, for the excluding regex
+    Matched,
+}
+
+/// Translates an extended glob for one path component to an anchored regex. The
+/// regex crate has no lookahead to express `!(pat)`, so here it matches anything;
+/// `ExtGlob` then rejects names that match with the group as `@(pat)` instead.
+pub fn extglob_to_regex(pat: &str) -> String {
+    format!("^{}$", glob_to_regex(pat))
+}
+
+/// Translates a glob to an unanchored regex, for finding matches inside a string.
+pub fn glob_to_regex(pat: &str) -> String {
+    let chars: Vec<char> = pat.chars().collect();
+    translate(&chars, Negation::Anything)
+}
+
+fn translate(chars: &[char], negation: Negation) -> String {
+    let mut regex = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        if matches!(ch, '?' | '*' | '+' | '@' | '!') && chars.get(i + 1) == Some(&'(') {
+            if let Some(end) = closing_paren(chars, i + 1) {
+                let alternatives: Vec<String> = split_alternatives(&chars[i + 2..end])
+                    .into_iter()
+                    .map(|alternative| translate(alternative, negation))
+                    .collect();
+                let group = format!("(?:{})", alternatives.join("|"));
+                match (ch, negation) {
+                    ('?', _) => regex.push_str(&format!("{}?", group)),
+                    ('*', _) => regex.push_str(&format!("{}*", group)),
+                    ('+', _) => regex.push_str(&format!("{}+", group)),
+                    ('!', Negation::Anything) => regex.push_str(".*"),
+                    _ => regex.push_str(&group),
+                }
+                i = end + 1;
+                continue;
+            }
+        }
+
+        match ch {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => match bracket_end(chars, i) {
+                Some(end) => {
+                    regex.push_str(&translate_bracket(&chars[i + 1..end]));
+                    i = end;
+                }
+                None => regex.push_str(r"\["),
+            },
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                regex.push_str(&regex::escape(&chars[i].to_string()));
+            }
+            _ => regex.push_str(&regex::escape(&ch.to_string())),
+        }
+        i += 1;
+    }
+    regex
+}
+
+/// Index of the `)` closing the `(` at `open`.
+fn closing_paren(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, &ch) in chars.iter().enumerate().skip(open) {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits a group's contents on the `|`s that aren't inside a nested group.
+fn split_alternatives(chars: &[char]) -> Vec<&[char]> {
+    let mut alternatives = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, &ch) in chars.iter().enumerate() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '|' if depth == 0 => {
+                alternatives.push(&chars[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    alternatives.push(&chars[start..]);
+    alternatives
+}
+
+/// Index of the `]` closing the bracket expression at `open`. A `]` right after
+/// `[`, `[!` or `[^` is part of the set.
+fn bracket_end(chars: &[char], open: usize) -> Option<usize> {
+    let mut i = open + 1;
+    if matches!(chars.get(i), Some('!' | '^')) {
+        i += 1;
+    }
+    if chars.get(i) == Some(&']') {
+        i += 1;
+    }
+    chars[i.min(chars.len())..].iter().position(|&ch| ch == ']').map(|offset| i + offset)
+}
+
+fn translate_bracket(body: &[char]) -> String {
+    let mut class = String::from("[");
+    let mut body = body;
+    if let Some(('!' | '^', rest)) = body.split_first() {
+        class.push('^');
+        body = rest;
+    }
+    for &ch in body {
+        match ch {
+            '-' => class.push('-'),
+            _ => class.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+    class.push(']');
+    class
+}
+
 pub fn has_glob_chars(pattern: &str) -> bool {
     pattern.contains('*') || pattern.contains('?') || pattern.contains('[')
 }
 
-pub fn expand_args(args: &[String]) -> Result<Vec<String>> {
+/// Whether `pattern` contains an extended glob group such as `!(..)`.
+pub fn has_extglob(pattern: &str) -> bool {
+    ["?(", "*(", "+(", "@(", "!("].iter().any(|group| pattern.contains(group))
+}
+
+pub fn expand_args(args: &[String], options: &ShellOptions) -> Result<Vec<String>> {
+    if options.noglob {
+        return Ok(args.to_vec());
+    }
     let mut expanded_args = Vec::new();
 
     for arg in args {
-        if has_glob_chars(arg) {
-            let expanded = expand_glob(arg)?;
+        if has_glob_chars(arg) || (options.extglob && has_extglob(arg)) {
+            let expanded = expand_glob(arg, options)?;
             for path in expanded {
                 expanded_args.push(path.to_string_lossy().to_string());
             }
@@ -60,9 +321,76 @@ mod tests {
             "*.nonexistent".to_string(), // Should return as-is if no matches
         ];
 
-        let result = expand_args(&args).unwrap();
+        let result = expand_args(&args, &ShellOptions::default()).unwrap();
         assert_eq!(result.len(), 2);
         assert_eq!(result[0], "regular_file.txt");
         assert_eq!(result[1], "*.nonexistent");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_globstar() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".hidden")).unwrap();
+        for file in ["top.txt", "a/mid.txt", "a/b/deep.txt", "a/b/other.rs", ".hidden/secret.txt"] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+        let root = dir.path().to_string_lossy();
+        let relative = |paths: Vec<PathBuf>| -> Vec<String> {
+            paths
+                .iter()
+                .map(|path| path.strip_prefix(dir.path()).unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+
+        let globstar = ShellOptions { globstar: true, ..ShellOptions::default() };
+        let found = expand_glob(&format!("{}/**/*.txt", root), &globstar).unwrap();
+        assert_eq!(relative(found), vec!["a/b/deep.txt", "a/mid.txt", "top.txt"]);
+
+        let found = expand_glob(&format!("{}/**", root), &globstar).unwrap();
+        assert_eq!(relative(found), vec!["a", "a/b", "a/b/deep.txt", "a/b/other.rs", "a/mid.txt", "top.txt"]);
+
+        // Without globstar, `**` only matches one level
+        let found = expand_glob(&format!("{}/**/*.txt", root), &ShellOptions::default()).unwrap();
+        assert_eq!(relative(found), vec!["a/mid.txt"]);
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_kept() {
+        assert_eq!(expand_args(&["[".to_string()], &ShellOptions::default()).unwrap(), vec!["["]);
+    }
+
+    #[test]
+    fn test_extglob_to_regex() {
+        assert_eq!(extglob_to_regex("*.rs"), r"^.*\.rs$");
+        assert_eq!(extglob_to_regex("@(a|b)?"), "^(?:a|b).$");
+        assert_eq!(extglob_to_regex("+(ab)*(c)?(d)"), "^(?:ab)+(?:c)*(?:d)?$");
+        assert_eq!(extglob_to_regex("!(*.[ch])"), "^.*$");
+        assert_eq!(translate(&"!(*.[ch])".chars().collect::<Vec<_>>(), Negation::Matched), r"(?:.*\.[ch])");
+        assert_eq!(extglob_to_regex("[!a-c]x"), "^[^a-c]x$");
+    }
+
+    #[test]
+    fn test_extglob() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["a.txt", "b.rs", "c.h", "main.c", "abab.md", ".hidden.rs"] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+        let root = dir.path().to_string_lossy();
+        let options = ShellOptions { extglob: true, ..ShellOptions::default() };
+        let names = |pattern: &str| -> Vec<String> {
+            expand_glob(&format!("{}/{}", root, pattern), &options)
+                .unwrap()
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+
+        assert_eq!(names("!(*.txt)"), vec!["abab.md", "b.rs", "c.h", "main.c"]);
+        assert_eq!(names("!(*.[ch])"), vec!["a.txt", "abab.md", "b.rs"]);
+        assert_eq!(names("+(ab).md"), vec!["abab.md"]);
+        assert_eq!(names("@(a|b).*"), vec!["a.txt", "b.rs"]);
+        assert_eq!(names("main?(.c)"), vec!["main.c"]);
+        assert_eq!(names("*(x).zip"), vec!["*(x).zip"]);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::utils::terminal_capabilities::TerminalCapabilities;
+use std::io::IsTerminal;
+use std::path::Path;
+
+/// `path` as a `file://` URL on `hostname`, with everything but letters, digits,
+/// `/` and `-_.~:` percent-encoded.
+pub fn file_url(hostname: &str, path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut encoded = String::new();
+    if !path.starts_with('/') {
+        encoded.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    format!("file://{}{}", hostname, encoded)
+}
+
+/// `display` as an OSC 8 hyperlink to the absolute `path`, which the terminal
+/// opens when it's clicked.
+pub fn make_file_link(path: &Path, display: &str) -> String {
+    let hostname = gethostname::gethostname().to_string_lossy().to_string();
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", file_url(&hostname, path), display)
+}
+
+/// Whether builtins should print hyperlinks: the terminal supports them and
+/// standard output goes to it rather than to a file or pipe.
+pub fn links_enabled(capabilities: &TerminalCapabilities) -> bool {
+    capabilities.osc8_links && std::io::stdout().is_terminal()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_link() {
+        assert_eq!(file_url("box", Path::new("/home/me/my docs")), "file://box/home/me/my%20docs");
+        assert_eq!(file_url("box", Path::new("C:\\Users\\me")), "file://box/C:/Users/me");
+
+        let link = make_file_link(Path::new("/tmp/notes.txt"), "notes.txt");
+        assert!(link.starts_with("\x1b]8;;file://"));
+        assert!(link.ends_with("/tmp/notes.txt\x1b\\notes.txt\x1b]8;;\x1b\\"));
+    }
+}
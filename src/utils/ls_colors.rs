@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+/// SGR parameters for a color, e.g. `01;34`.
+pub type AnsiCode = String;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LsColorKey {
+    File,
+    Directory,
+    Executable,
+    Symlink,
+    /// Symlink whose target does not exist
+    Orphan,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Extension(String),
+}
+
+/// File-type colors parsed from `LS_COLORS` (GNU) or `LSCOLORS` (BSD).
+#[derive(Debug, Clone, Default)]
+pub struct LsColors {
+    colors: HashMap<LsColorKey, AnsiCode>,
+}
+
+impl LsColors {
+    /// Reads `LS_COLORS`, falling back to `LSCOLORS`. Returns `None` if neither is set.
+    pub fn from_env() -> Option<Self> {
+        if let Ok(spec) = std::env::var("LS_COLORS") {
+            if !spec.is_empty() {
+                return Some(Self::parse(&spec));
+            }
+        }
+        match std::env::var("LSCOLORS") {
+            Ok(spec) if !spec.is_empty() => Some(Self::parse_bsd(&spec)),
+            _ => None,
+        }
+    }
+
+    /// Parses the GNU format: `di=01;34:ex=01;32:*.rs=36`.
+    pub fn parse(spec: &str) -> Self {
+        let mut colors = HashMap::new();
+
+        for entry in spec.split(':') {
+            let Some((key, code)) = entry.split_once('=') else {
+                continue;
+            };
+            let key = match key {
+                "fi" => LsColorKey::File,
+                "di" => LsColorKey::Directory,
+                "ex" => LsColorKey::Executable,
+                "ln" => LsColorKey::Symlink,
+                "or" => LsColorKey::Orphan,
+                "pi" => LsColorKey::Fifo,
+                "so" => LsColorKey::Socket,
+                "bd" => LsColorKey::BlockDevice,
+                "cd" => LsColorKey::CharDevice,
+                ext if ext.starts_with("*.") => LsColorKey::Extension(ext[2..].to_lowercase()),
+                _ => continue,
+            };
+            colors.insert(key, code.to_string());
+        }
+
+        Self { colors }
+    }
+
+    /// Parses the BSD format: pairs of foreground/background letters in a fixed
+    /// order (`exfxcxdxbxegedabagacad`).
+    pub fn parse_bsd(spec: &str) -> Self {
+        const ORDER: [LsColorKey; 7] = [
+            LsColorKey::Directory,
+            LsColorKey::Symlink,
+            LsColorKey::Socket,
+            LsColorKey::Fifo,
+            LsColorKey::Executable,
+            LsColorKey::BlockDevice,
+            LsColorKey::CharDevice,
+        ];
+
+        let letters: Vec<char> = spec.chars().collect();
+        let mut colors = HashMap::new();
+
+        for (key, pair) in ORDER.into_iter().zip(letters.chunks(2)) {
+            let mut codes = Vec::new();
+            if let Some(fg) = pair.first().and_then(|c| bsd_color(*c, 30)) {
+                codes.push(fg);
+            }
+            if let Some(bg) = pair.get(1).and_then(|c| bsd_color(*c, 40)) {
+                codes.push(bg);
+            }
+            if !codes.is_empty() {
+                colors.insert(key, codes.join(";"));
+            }
+        }
+
+        Self { colors }
+    }
+
+    pub fn get(&self, key: &LsColorKey) -> Option<&str> {
+        self.colors.get(key).map(|code| code.as_str())
+    }
+
+    /// Color for a file extension (case-insensitive).
+    pub fn for_extension(&self, name: &str) -> Option<&str> {
+        let (_, ext) = name.rsplit_once('.')?;
+        self.get(&LsColorKey::Extension(ext.to_lowercase()))
+    }
+
+    pub fn paint(code: &str, text: &str) -> String {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+}
+
+/// Maps a BSD color letter to an SGR code (`a`..`h` normal, `A`..`H` bold, `x` default).
+fn bsd_color(letter: char, base: u8) -> Option<String> {
+    match letter {
+        'a'..='h' => Some((base + (letter as u8 - b'a')).to_string()),
+        'A'..='H' => Some(format!("1;{}", base + (letter as u8 - b'A'))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gnu() {
+        let colors = LsColors::parse("rs=0:di=01;34:ex=32:*.rs=36:*.TAR=31:bogus");
+        assert_eq!(colors.get(&LsColorKey::Directory), Some("01;34"));
+        assert_eq!(colors.get(&LsColorKey::Executable), Some("32"));
+        assert_eq!(colors.for_extension("main.rs"), Some("36"));
+        assert_eq!(colors.for_extension("backup.tar"), Some("31"));
+        assert_eq!(colors.for_extension("README"), None);
+    }
+
+    #[test]
+    fn test_parse_bsd() {
+        let colors = LsColors::parse_bsd("Exfxcxdxbxegedabagacad");
+        assert_eq!(colors.get(&LsColorKey::Directory), Some("1;34"));
+        assert_eq!(colors.get(&LsColorKey::Symlink), Some("35"));
+        assert_eq!(colors.get(&LsColorKey::BlockDevice), Some("34;46"));
+    }
+}
@@ -1,6 +1,15 @@
 pub mod path;
 pub mod completion;
 pub mod glob_expand;
+pub mod hyperlink;
+pub mod ls_colors;
+pub mod param_expand;
+pub mod escape;
+pub mod quote;
+pub mod signals;
+pub mod terminal_capabilities;
+pub mod termios;
+pub mod tool_versions;
 
 // Re-export commonly used path utilities
 pub use path::expand_tilde;
@@ -0,0 +1,720 @@
+use crate::core::errors::UnboundVariable;
+use crate::core::parser;
+use crate::core::state::ShellState;
+use crate::utils::glob_expand;
+use anyhow::{anyhow, Result};
+use regex::{NoExpand, Regex};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Field separators used when `IFS` is unset.
+pub const DEFAULT_IFS: &str = " \t\n";
+
+/// Expands a word as written (quotes included) into the arguments it stands for.
+/// `$@` and `"$@"` produce one argument per positional parameter. The results of
+/// unquoted expansions are split on the characters of `$IFS`, so they may produce
+/// several arguments, or none at all. With `strict_vars` (`set -u`), unset
+/// variables are an error.
+pub fn expand_word(raw: &str, state: &ShellState, strict_vars: bool) -> Result<Vec<String>> {
+    if raw == "$@" || raw == "\"$@\"" {
+        return Ok(state.positional.clone());
+    }
+    // Like "$@", "${name[@]}" gives one argument per element
+    if let Some(expr) = raw.strip_prefix("\"${").and_then(|rest| rest.strip_suffix("}\"")) {
+        if let Some((name, "@", slice)) = split_subscript(expr).filter(|_| !expr.contains('}')) {
+            return array_slice(name, slice, state, strict_vars);
+        }
+    }
+    let ifs = std::env::var("IFS").unwrap_or_else(|_| DEFAULT_IFS.to_string());
+    expand_fields(raw, state, strict_vars, Some(&ifs))
+}
+
+/// Removes quotes and expands `$name`, `${...}` (array elements included), `$1`,
+/// `$#`, `$@` and `$((...))`.
+/// Nothing is expanded inside single quotes.
+pub fn expand_text(raw: &str, state: &ShellState, strict_vars: bool) -> Result<String> {
+    Ok(expand_fields(raw, state, strict_vars, None)?.pop().unwrap_or_default())
+}
+
+/// Expands `raw`, splitting the results of unquoted expansions on `ifs` when given.
+/// Without `ifs` there is always exactly one field.
+fn expand_fields(raw: &str, state: &ShellState, strict_vars: bool, ifs: Option<&str>) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    // Whether `field` holds a word yet; an unquoted empty expansion adds none, `""` does
+    let mut started = ifs.is_none();
+    let mut quote: Option<char> = None;
+    let mut chars = raw.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if quote == Some('"') && matches!(chars.peek(), Some('$' | '`' | '"' | '\\')) => {
+                field.push(chars.next().unwrap());
+            }
+            // Outside quotes a backslash is literal (Windows paths), except before `$`
+            '\\' if chars.peek() == Some(&'$') => {
+                field.push(chars.next().unwrap());
+                started = true;
+            }
+            '"' | '\'' if quote.is_none() => {
+                quote = Some(ch);
+                started = true;
+            }
+            c if Some(c) == quote => quote = None,
+            '$' if quote.is_none() && ifs.is_some() => {
+                let ifs = ifs.unwrap_or_default();
+                let mut value = String::new();
+                expand_dollar(&mut chars, &mut value, state, strict_vars)?;
+
+                let is_separator = |c: char| ifs.contains(c);
+                if value.starts_with(is_separator) && started {
+                    fields.push(std::mem::take(&mut field));
+                    started = false;
+                }
+                let pieces = ifs_split(&value, ifs);
+                let piece_count = pieces.len();
+                for (i, piece) in pieces.into_iter().enumerate() {
+                    if i > 0 {
+                        fields.push(std::mem::take(&mut field));
+                    }
+                    field.push_str(&piece);
+                    started = true;
+                }
+                if piece_count > 0 && value.ends_with(is_separator) {
+                    fields.push(std::mem::take(&mut field));
+                    started = false;
+                }
+            }
+            '$' if quote != Some('\'') => expand_dollar(&mut chars, &mut field, state, strict_vars)?,
+            _ => {
+                field.push(ch);
+                started = true;
+            }
+        }
+    }
+
+    if started {
+        fields.push(field);
+    }
+    Ok(fields)
+}
+
+/// Splits `s` into fields on the characters of `ifs`, as the shell does with the
+/// result of an unquoted expansion. Runs of IFS whitespace count as one separator
+/// and are trimmed from both ends; every other IFS character ends a field, so
+/// `a::b` with `IFS=:` gives `a`, an empty field and `b`. An empty `ifs` disables
+/// splitting.
+pub fn ifs_split(s: &str, ifs: &str) -> Vec<String> {
+    if ifs.is_empty() {
+        return if s.is_empty() { Vec::new() } else { vec![s.to_string()] };
+    }
+    let is_whitespace = |c: char| ifs.contains(c) && c.is_whitespace();
+
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = s.trim_matches(is_whitespace).chars().peekable();
+    if chars.peek().is_none() {
+        return fields;
+    }
+    while let Some(ch) = chars.next() {
+        if !ifs.contains(ch) {
+            field.push(ch);
+            continue;
+        }
+        // Whitespace around a separator belongs to it
+        while chars.peek().is_some_and(|&c| is_whitespace(c)) {
+            chars.next();
+        }
+        if is_whitespace(ch) && chars.peek().is_some_and(|&c| ifs.contains(c)) {
+            chars.next();
+            while chars.peek().is_some_and(|&c| is_whitespace(c)) {
+                chars.next();
+            }
+        }
+        fields.push(std::mem::take(&mut field));
+    }
+    // A trailing separator doesn't start another field
+    if !field.is_empty() || !s.trim_matches(is_whitespace).ends_with(|c: char| ifs.contains(c)) {
+        fields.push(field);
+    }
+    fields
+}
+
+/// Expands the parameter after a `$`, or pushes the `$` itself if nothing follows it.
+fn expand_dollar(chars: &mut Peekable<Chars>, result: &mut String, state: &ShellState, strict_vars: bool) -> Result<()> {
+    match chars.peek().copied() {
+        Some('{') => {
+            chars.next();
+            let mut expr = String::new();
+            let mut found_closing = false;
+            for ch in chars.by_ref() {
+                if ch == '}' {
+                    found_closing = true;
+                    break;
+                }
+                expr.push(ch);
+            }
+            if found_closing {
+                result.push_str(&expand_braced(&expr, state, strict_vars)?);
+            } else {
+                result.push_str("${");
+                result.push_str(&expr);
+            }
+        }
+        Some('(') => {
+            let mut inner = String::new();
+            let mut depth = 0;
+            for ch in chars.by_ref() {
+                match ch {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    break;
+                }
+                inner.push(ch);
+            }
+            // inner is "((expr)" for arithmetic, "(command" for command substitution
+            match inner.strip_prefix("((").and_then(|expr| expr.strip_suffix(')')) {
+                Some(expr) => {
+                    let expr = expand_text(expr, state, strict_vars)?;
+                    result.push_str(&evaluate_arithmetic(&expr)?.to_string());
+                }
+                None => return Err(anyhow!("command substitution is not supported: $({})", &inner[1..])),
+            }
+        }
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    name.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            result.push_str(&lookup(&name, state, strict_vars)?);
+        }
+        Some(c) if c.is_ascii_digit() || matches!(c, '?' | '$' | '!') => {
+            chars.next();
+            result.push_str(&lookup(&c.to_string(), state, strict_vars)?);
+        }
+        Some('#') => {
+            chars.next();
+            result.push_str(&state.positional.len().to_string());
+        }
+        Some('@' | '*') => {
+            chars.next();
+            result.push_str(&state.positional.join(" "));
+        }
+        _ => result.push('$'),
+    }
+    Ok(())
+}
+
+/// Value of a variable, special parameter or (for a number) positional parameter,
+/// `0` being the shell's name; unset is empty unless `strict_vars`. `RANDOM`,
+/// `SECONDS` and `LINENO` are computed on each use, whatever is assigned to them.
+fn lookup(name: &str, state: &ShellState, strict_vars: bool) -> Result<String> {
+    let value = match name {
+        "?" => Some(state.special.last_exit.to_string()),
+        "$" => Some(state.special.shell_pid.to_string()),
+        "!" => state.special.last_bg_pid.map(|pid| pid.to_string()),
+        "RANDOM" => Some((rand::random::<u16>() % 32768).to_string()),
+        "SECONDS" => Some(state.special.started.elapsed().as_secs().to_string()),
+        "LINENO" => Some(state.special.line_number.to_string()),
+        _ => match name.parse::<usize>() {
+            Ok(0) => Some(state.special.script_name.clone()),
+            Ok(index) => state.positional.get(index - 1).cloned(),
+            // An array's name alone stands for its first element
+            _ => match state.arrays.get(name) {
+                Some(elements) => Some(elements.first().cloned().unwrap_or_default()),
+                None => std::env::var(name).ok(),
+            },
+        },
+    };
+    match value {
+        Some(value) => Ok(value),
+        None if strict_vars => Err(UnboundVariable(name.to_string()).into()),
+        None => Ok(String::new()),
+    }
+}
+
+/// Expands the inside of `${...}`: a plain name, or `name:-word`, `name-word`,
+/// `name:+word` and `name+word`, which handle unset variables themselves, the
+/// `name/pattern/replacement` substitutions, the indirect `!name` and `!prefix*`,
+/// or the array forms `name[index]`, `name[@]`, `name[*]`, `name[@]:offset:length`
+/// and `#name[@]`.
+fn expand_braced(expr: &str, state: &ShellState, strict_vars: bool) -> Result<String> {
+    if matches!(expr, "?" | "$" | "!") {
+        return lookup(expr, state, strict_vars);
+    }
+    if let Some(name) = expr.strip_prefix('!') {
+        return expand_indirect(name, state, strict_vars);
+    }
+    if let Some((name, subscript)) = expr.strip_prefix('#').and_then(parser::array_element) {
+        let length = match subscript {
+            "@" | "*" => array_slice(name, "", state, strict_vars)?.len(),
+            _ => array_element(name, subscript, state, strict_vars)?.chars().count(),
+        };
+        return Ok(length.to_string());
+    }
+    if let Some((name, subscript, rest)) = split_subscript(expr) {
+        return match subscript {
+            "@" => Ok(array_slice(name, rest, state, strict_vars)?.join(" ")),
+            // Joined with the first character of IFS, as "$*" is
+            "*" => {
+                let ifs = std::env::var("IFS").unwrap_or_else(|_| DEFAULT_IFS.to_string());
+                let separator = ifs.chars().next().map(String::from).unwrap_or_default();
+                Ok(array_slice(name, rest, state, strict_vars)?.join(&separator))
+            }
+            _ if rest.is_empty() => array_element(name, subscript, state, strict_vars),
+            _ => Err(anyhow!("${{{}}}: bad substitution", expr)),
+        };
+    }
+    let Some(split) = expr.find(|c: char| !(c.is_alphanumeric() || c == '_')) else {
+        return lookup(expr, state, strict_vars);
+    };
+    let (name, rest) = expr.split_at(split);
+    if let Some(substitution) = rest.strip_prefix('/') {
+        return substitute(&lookup(name, state, strict_vars)?, substitution);
+    }
+    let (null_counts_as_unset, rest) = match rest.strip_prefix(':') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let value = lookup(name, state, false)
+        .ok()
+        .filter(|v| !v.is_empty() || (!null_counts_as_unset && is_set(name, state)));
+
+    if let Some(word) = rest.strip_prefix('-') {
+        Ok(value.unwrap_or_else(|| word.to_string()))
+    } else if let Some(word) = rest.strip_prefix('+') {
+        Ok(value.map(|_| word.to_string()).unwrap_or_default())
+    } else {
+        lookup(expr, state, strict_vars)
+    }
+}
+
+/// Splits `name[subscript]rest` into its three parts.
+fn split_subscript(expr: &str) -> Option<(&str, &str, &str)> {
+    let (name, rest) = expr.split_once('[')?;
+    let (subscript, rest) = rest.split_once(']')?;
+    parser::is_name(name).then_some((name, subscript, rest))
+}
+
+/// The elements of array `name`, or of the part `slice` selects: `:offset` or
+/// `:offset:length`, both arithmetic, a negative offset counting from the end. A
+/// variable that isn't an array acts as an array of its one value.
+fn array_slice(name: &str, slice: &str, state: &ShellState, strict_vars: bool) -> Result<Vec<String>> {
+    let elements = match state.arrays.get(name) {
+        Some(elements) => elements.clone(),
+        None => std::env::var(name).map(|value| vec![value]).unwrap_or_default(),
+    };
+    if slice.is_empty() {
+        return Ok(elements);
+    }
+    let Some(slice) = slice.strip_prefix(':') else {
+        return Err(anyhow!("${{{}[@]{}}}: bad substitution", name, slice));
+    };
+    let (offset, length) = match slice.split_once(':') {
+        Some((offset, length)) => (offset, Some(length)),
+        None => (slice, None),
+    };
+    let offset = evaluate_arithmetic(&expand_text(offset, state, strict_vars)?)?;
+    let start = if offset < 0 { elements.len().saturating_sub(offset.unsigned_abs() as usize) } else { offset as usize };
+    let length = match length {
+        Some(length) => match evaluate_arithmetic(&expand_text(length, state, strict_vars)?)? {
+            length if length < 0 => return Err(anyhow!("{}: substring expression < 0", length)),
+            length => length as usize,
+        },
+        None => usize::MAX,
+    };
+    Ok(elements.into_iter().skip(start).take(length).collect())
+}
+
+/// The element of array `name` at `index`, an arithmetic expression; negative
+/// indices count from the end. Index 0 of a variable that isn't an array is its value.
+fn array_element(name: &str, index: &str, state: &ShellState, strict_vars: bool) -> Result<String> {
+    let elements = array_slice(name, "", state, strict_vars)?;
+    let position = evaluate_arithmetic(&expand_text(index, state, strict_vars)?)?;
+    let position = if position < 0 { elements.len() as i64 + position } else { position };
+    match usize::try_from(position).ok().and_then(|position| elements.get(position)) {
+        Some(value) => Ok(value.clone()),
+        None if strict_vars => Err(UnboundVariable(format!("{}[{}]", name, index)).into()),
+        None => Ok(String::new()),
+    }
+}
+
+/// `${!name}`, the value of the variable named by `name`'s value, or `${!prefix*}`
+/// (also `${!prefix@}`), the names of the variables starting with `prefix` in order.
+fn expand_indirect(name: &str, state: &ShellState, strict_vars: bool) -> Result<String> {
+    if let Some(prefix) = name.strip_suffix('*').or_else(|| name.strip_suffix('@')) {
+        let mut names: Vec<String> = std::env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        return Ok(names.join(" "));
+    }
+    let target = lookup(name, state, strict_vars)?;
+    if target.is_empty() && strict_vars {
+        return Err(UnboundVariable(name.to_string()).into());
+    }
+    if target.is_empty() {
+        return Ok(String::new());
+    }
+    lookup(&target, state, strict_vars)
+}
+
+/// Applies the `pattern/replacement` part of `${name/pattern/replacement}` to `value`.
+/// The pattern is a glob; a leading `/` (from `${name//...}`) replaces every match
+/// instead of the first, and a leading `#` or `%` anchors it to the start or end.
+fn substitute(value: &str, substitution: &str) -> Result<String> {
+    let (all, substitution) = match substitution.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, substitution),
+    };
+    // The pattern ends at the first `/` that isn't escaped
+    let mut escaped = false;
+    let separator = substitution.char_indices().find_map(|(i, c)| {
+        let found = c == '/' && !escaped;
+        escaped = c == '\\' && !escaped;
+        found.then_some(i)
+    });
+    let (pattern, replacement) = match separator {
+        Some(i) => (&substitution[..i], &substitution[i + 1..]),
+        None => (substitution, ""),
+    };
+    let (anchor, pattern) = match pattern.chars().next() {
+        Some('#') => ("^", &pattern[1..]),
+        Some('%') => ("$", &pattern[1..]),
+        _ => ("", pattern),
+    };
+    if pattern.is_empty() {
+        return Ok(value.to_string());
+    }
+
+    let regex = match anchor {
+        "^" => format!("^{}", glob_expand::glob_to_regex(pattern)),
+        "$" => format!("{}$", glob_expand::glob_to_regex(pattern)),
+        _ => glob_expand::glob_to_regex(pattern),
+    };
+    let regex = Regex::new(&regex).map_err(|e| anyhow!("bad substitution pattern '{}': {}", pattern, e))?;
+    let replaced = if all {
+        regex.replace_all(value, NoExpand(replacement))
+    } else {
+        regex.replacen(value, 1, NoExpand(replacement))
+    };
+    Ok(replaced.into_owned())
+}
+
+fn is_set(name: &str, state: &ShellState) -> bool {
+    match name.parse::<usize>() {
+        Ok(0) => true,
+        Ok(index) => index <= state.positional.len(),
+        _ if name == "!" => state.special.last_bg_pid.is_some(),
+        _ if matches!(name, "?" | "$" | "RANDOM" | "SECONDS" | "LINENO") => true,
+        _ => state.arrays.contains_key(name) || std::env::var_os(name).is_some(),
+    }
+}
+
+/// Evaluates integer arithmetic as in `$((...))`: `+ - * / %`, comparisons, `&& || !`
+/// and parentheses. Bare names are read from the environment, unset or non-numeric as 0.
+pub fn evaluate_arithmetic(expr: &str) -> Result<i64> {
+    let mut parser = Arithmetic { chars: expr.chars().peekable() };
+    let value = parser.logical_or()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some(c) => Err(anyhow!("arithmetic syntax error near `{}' in `{}'", c, expr.trim())),
+    }
+}
+
+struct Arithmetic<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Arithmetic<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    /// Consumes `op` if the input continues with it.
+    fn eat(&mut self, op: &str) -> bool {
+        self.skip_whitespace();
+        let mut lookahead = self.chars.clone();
+        for expected in op.chars() {
+            if lookahead.next() != Some(expected) {
+                return false;
+            }
+        }
+        // `<`, `>` and `!` must not match the start of `<=`, `>=` and `!=`
+        if matches!(op, "<" | ">" | "!") && lookahead.peek() == Some(&'=') {
+            return false;
+        }
+        self.chars = lookahead;
+        true
+    }
+
+    fn logical_or(&mut self) -> Result<i64> {
+        let mut value = self.logical_and()?;
+        while self.eat("||") {
+            let rhs = self.logical_and()?;
+            value = (value != 0 || rhs != 0) as i64;
+        }
+        Ok(value)
+    }
+
+    fn logical_and(&mut self) -> Result<i64> {
+        let mut value = self.comparison()?;
+        while self.eat("&&") {
+            let rhs = self.comparison()?;
+            value = (value != 0 && rhs != 0) as i64;
+        }
+        Ok(value)
+    }
+
+    fn comparison(&mut self) -> Result<i64> {
+        let mut value = self.sum()?;
+        loop {
+            let compare: fn(i64, i64) -> bool = if self.eat("==") {
+                |a, b| a == b
+            } else if self.eat("!=") {
+                |a, b| a != b
+            } else if self.eat("<=") {
+                |a, b| a <= b
+            } else if self.eat(">=") {
+                |a, b| a >= b
+            } else if self.eat("<") {
+                |a, b| a < b
+            } else if self.eat(">") {
+                |a, b| a > b
+            } else {
+                return Ok(value);
+            };
+            let rhs = self.sum()?;
+            value = compare(value, rhs) as i64;
+        }
+    }
+
+    fn sum(&mut self) -> Result<i64> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat("+") {
+                value = value.wrapping_add(self.product()?);
+            } else if self.eat("-") {
+                value = value.wrapping_sub(self.product()?);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<i64> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat("*") {
+                value = value.wrapping_mul(self.unary()?);
+            } else if self.eat("/") {
+                value = value.checked_div(self.unary()?).ok_or_else(|| anyhow!("division by 0"))?;
+            } else if self.eat("%") {
+                value = value.checked_rem(self.unary()?).ok_or_else(|| anyhow!("division by 0"))?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<i64> {
+        if self.eat("-") {
+            Ok(self.unary()?.wrapping_neg())
+        } else if self.eat("+") {
+            self.unary()
+        } else if self.eat("!") {
+            Ok((self.unary()? == 0) as i64)
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<i64> {
+        if self.eat("(") {
+            let value = self.logical_or()?;
+            if !self.eat(")") {
+                return Err(anyhow!("arithmetic syntax error: missing `)'"));
+            }
+            return Ok(value);
+        }
+
+        self.skip_whitespace();
+        let mut token = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                token.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+
+        if token.is_empty() {
+            return Err(anyhow!("arithmetic syntax error: operand expected"));
+        }
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            return token.parse().map_err(|_| anyhow!("arithmetic syntax error: invalid number `{}'", token));
+        }
+        Ok(std::env::var(&token).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::state::SpecialVars;
+
+    #[test]
+    fn test_positional_and_quotes() {
+        let state = ShellState {
+            positional: vec!["a b".to_string(), "c".to_string()],
+            special: SpecialVars {
+                last_exit: 3,
+                shell_pid: 42,
+                last_bg_pid: None,
+                script_name: "-flex-sh".to_string(),
+                ..SpecialVars::default()
+            },
+            ..ShellState::default()
+        };
+        assert_eq!(expand_word("\"$1\"-$2", &state, false).unwrap(), vec!["a b-c"]);
+        assert_eq!(expand_word("'$1'", &state, false).unwrap(), vec!["$1"]);
+        assert_eq!(expand_word("\"$@\"", &state, false).unwrap(), vec!["a b", "c"]);
+        assert_eq!(expand_word("$#", &state, false).unwrap(), vec!["2"]);
+        assert_eq!(expand_word("${0}:$0", &state, false).unwrap(), vec!["-flex-sh:-flex-sh"]);
+        assert_eq!(expand_word("$?-$$-${?}x", &state, false).unwrap(), vec!["3-42-3x"]);
+        assert_eq!(expand_word("[$!]", &state, false).unwrap(), vec!["[]"]);
+        assert!(expand_word("$!", &state, true).is_err());
+        assert_eq!(expand_word("$((3 * (4 - $#)))", &state, false).unwrap(), vec!["6"]);
+    }
+
+    #[test]
+    fn test_ifs_split() {
+        assert_eq!(ifs_split("/usr/local/bin:/usr/bin:/bin", ":"), vec!["/usr/local/bin", "/usr/bin", "/bin"]);
+        assert_eq!(ifs_split("  a \t b\n", DEFAULT_IFS), vec!["a", "b"]);
+        assert_eq!(ifs_split("a::b:", ":"), vec!["a", "", "b"]);
+        assert_eq!(ifs_split("a : b", " :"), vec!["a", "b"]);
+        assert_eq!(ifs_split("a b", ""), vec!["a b"]);
+        assert!(ifs_split("   ", DEFAULT_IFS).is_empty());
+    }
+
+    #[test]
+    fn test_field_splitting() {
+        let state = ShellState {
+            positional: vec!["/usr/local/bin:/usr/bin:/bin".to_string(), "".to_string(), " x y ".to_string()],
+            ..ShellState::default()
+        };
+        let split = |raw: &str, ifs: &str| expand_fields(raw, &state, false, Some(ifs)).unwrap();
+        assert_eq!(split("$1", ":"), vec!["/usr/local/bin", "/usr/bin", "/bin"]);
+        assert_eq!(split("\"$1\"", ":"), vec!["/usr/local/bin:/usr/bin:/bin"]);
+        assert_eq!(split("$2", DEFAULT_IFS), Vec::<String>::new());
+        assert_eq!(split("\"$2\"", DEFAULT_IFS), vec![""]);
+        assert_eq!(split("a$3b", DEFAULT_IFS), vec!["a", "x", "y", "b"]);
+        assert_eq!(split("a${1}b", ":"), vec!["a/usr/local/bin", "/usr/bin", "/binb"]);
+    }
+
+    #[test]
+    fn test_substitution() {
+        let state = ShellState {
+            positional: vec!["/usr/foo/lib/foo.so".to_string()],
+            ..ShellState::default()
+        };
+        let expand = |raw: &str| expand_text(raw, &state, false).unwrap();
+        assert_eq!(expand("${1/foo/bar}"), "/usr/bar/lib/foo.so");
+        assert_eq!(expand("${1//foo/bar}"), "/usr/bar/lib/bar.so");
+        assert_eq!(expand("${1//foo/}"), "/usr//lib/.so");
+        assert_eq!(expand("${1/foo}"), "/usr//lib/foo.so");
+        assert_eq!(expand("${1/f*b/X}"), "/usr/X/foo.so");
+        assert_eq!(expand("${1//f?o/X}"), "/usr/X/lib/X.so");
+        assert_eq!(expand("${1//[ou]/_}"), "/_sr/f__/lib/f__.s_");
+        assert_eq!(expand("${1/#\\/usr/~}"), "~/foo/lib/foo.so");
+        assert_eq!(expand("${1/%.so/.dylib}"), "/usr/foo/lib/foo.dylib");
+        assert_eq!(expand("${1/x/\\$0}"), "/usr/foo/lib/foo.so");
+    }
+
+    #[test]
+    fn test_arrays() {
+        let mut state = ShellState::default();
+        let elements = ["a", "b c", "d", "e"].iter().map(|element| element.to_string()).collect();
+        state.arrays.insert("arr".to_string(), elements);
+        let expand = |raw: &str| expand_word(raw, &state, false).unwrap();
+        assert_eq!(expand("${arr[0]}"), vec!["a"]);
+        assert_eq!(expand("\"${arr[1]}\""), vec!["b c"]);
+        assert_eq!(expand("${arr[-1]}$arr"), vec!["ea"]);
+        assert_eq!(expand("\"${arr[@]}\""), vec!["a", "b c", "d", "e"]);
+        assert_eq!(expand("${arr[@]}"), vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(expand("\"${arr[*]}\""), vec!["a b c d e"]);
+        assert_eq!(expand("${#arr[@]}"), vec!["4"]);
+        assert_eq!(expand("${#arr[1]}"), vec!["3"]);
+        assert_eq!(expand("\"${arr[@]:1:2}\""), vec!["b c", "d"]);
+        assert_eq!(expand("\"${arr[@]: -1}\""), vec!["e"]);
+        assert_eq!(expand("[${arr[9]}]"), vec!["[]"]);
+        assert!(expand_word("${arr[9]}", &state, true).is_err());
+        assert!(expand_word("${arr[@]:0:-1}", &state, false).is_err());
+    }
+
+    #[test]
+    fn test_dynamic_variables() {
+        let mut state = ShellState::default();
+        state.special.line_number = 7;
+        state.special.started -= std::time::Duration::from_secs(5);
+        let expand = |raw: &str| expand_text(raw, &state, true).unwrap();
+        assert!(expand("$RANDOM").parse::<u16>().unwrap() < 32768);
+        assert_eq!(expand("$SECONDS"), "5");
+        assert_eq!(expand("${LINENO}"), "7");
+    }
+
+    #[test]
+    fn test_indirect() {
+        let state = ShellState {
+            positional: vec!["FLEXSH_TEST_INDIRECT_TARGET".to_string()],
+            ..ShellState::default()
+        };
+        std::env::set_var("FLEXSH_TEST_INDIRECT_TARGET", "value");
+        std::env::set_var("FLEXSH_TEST_INDIRECT_NAME", "FLEXSH_TEST_INDIRECT_TARGET");
+        std::env::set_var("FLEXSH_TEST_INDIRECT_EMPTY", "");
+        let expand = |raw: &str, strict: bool| expand_text(raw, &state, strict);
+        assert_eq!(expand("${!FLEXSH_TEST_INDIRECT_NAME}", false).unwrap(), "value");
+        assert_eq!(expand("${!1}", false).unwrap(), "value");
+        assert_eq!(
+            expand("${!FLEXSH_TEST_INDIRECT_*}", false).unwrap(),
+            "FLEXSH_TEST_INDIRECT_EMPTY FLEXSH_TEST_INDIRECT_NAME FLEXSH_TEST_INDIRECT_TARGET"
+        );
+        assert_eq!(expand("${!FLEXSH_TEST_INDIRECT_EMPTY}", false).unwrap(), "");
+        assert!(expand("${!FLEXSH_TEST_INDIRECT_EMPTY}", true).is_err());
+        assert!(expand("${!FLEXSH_TEST_INDIRECT_UNSET}", true).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(evaluate_arithmetic("2 + 3 * (4 - 1)").unwrap(), 11);
+        assert_eq!(evaluate_arithmetic("7 % 3 == 1 && 2 <= 1").unwrap(), 0);
+        assert_eq!(evaluate_arithmetic("-5 / 2").unwrap(), -2);
+        assert!(evaluate_arithmetic("1 / 0").is_err());
+        assert!(evaluate_arithmetic("1 +").is_err());
+    }
+
+    #[test]
+    fn test_strict_vars() {
+        let state = ShellState::default();
+        std::env::remove_var("FLEXSH_TEST_UNSET");
+        assert_eq!(expand_text("[$FLEXSH_TEST_UNSET]", &state, false).unwrap(), "[]");
+        let err = expand_text("$FLEXSH_TEST_UNSET", &state, true).unwrap_err();
+        assert_eq!(err.to_string(), "FLEXSH_TEST_UNSET: unbound variable");
+        assert_eq!(expand_text("${FLEXSH_TEST_UNSET:-fallback}", &state, true).unwrap(), "fallback");
+        assert_eq!(expand_text("${FLEXSH_TEST_UNSET+set}", &state, true).unwrap(), "");
+        assert!(expand_text("$1", &state, true).is_err());
+    }
+}
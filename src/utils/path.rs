@@ -11,9 +11,21 @@ pub fn strip_windows_prefix(path: &std::path::PathBuf) -> std::path::PathBuf {
     path.clone()
 }
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
+/// Whether Windows paths are translated under WSL (`wsl_interop` in the config).
+static WSL_INTEROP: AtomicBool = AtomicBool::new(true);
+
+/// Expands a leading `~`. Under WSL, a Windows path such as `C:\Users\me` (often
+/// from a variable like `$USERPROFILE`) becomes its mount, `/mnt/c/Users/me`.
 pub fn expand_tilde<P: AsRef<Path>>(path: P) -> PathBuf {
     let path = path.as_ref();
+    if WSL_INTEROP.load(Ordering::Relaxed) && is_wsl() {
+        if let Some(translated) = windows_to_wsl(path) {
+            return translated;
+        }
+    }
     if path.starts_with("~") {
         if let Some(home) = dirs::home_dir() {
             let home = strip_windows_prefix(&home);
@@ -38,6 +50,120 @@ pub fn normalize_path<P: AsRef<Path>>(path: P) -> PathBuf {
     }
 }
 
+/// Resolves `.` and `..` lexically, without following symlinks (the `cd -L` view).
+pub fn normalize_logical<P: AsRef<Path>>(path: P) -> PathBuf {
+    use std::path::Component;
+
+    let mut result = PathBuf::new();
+    for component in path.as_ref().components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                // Never pop past the root
+                if !matches!(result.components().next_back(), None | Some(Component::RootDir) | Some(Component::Prefix(_))) {
+                    result.pop();
+                }
+            }
+            other => result.push(other.as_os_str()),
+        }
+    }
+    result
+}
+
+/// True if both paths refer to the same directory on disk.
+pub fn same_location<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> bool {
+    match (a.as_ref().canonicalize(), b.as_ref().canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Turns the translation of Windows paths under WSL on or off.
+pub fn set_wsl_interop(enabled: bool) {
+    WSL_INTEROP.store(enabled, Ordering::Relaxed);
+}
+
+/// True when running under Windows Subsystem for Linux, which `/proc/version` names.
+pub fn is_wsl() -> bool {
+    static WSL: OnceLock<bool> = OnceLock::new();
+    *WSL.get_or_init(|| {
+        std::fs::read_to_string("/proc/version")
+            .is_ok_and(|version| version.to_lowercase().contains("microsoft") || version.contains("WSL"))
+    })
+}
+
+/// A Windows path with a drive letter, `C:\Users\me` or `C:/Users/me`, as WSL
+/// mounts it: `/mnt/c/Users/me`. `None` for any other path.
+pub fn windows_to_wsl(path: &Path) -> Option<PathBuf> {
+    let path = path.to_str()?;
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let rest = chars.as_str().strip_prefix(':')?;
+    if !(rest.is_empty() || rest.starts_with(['\\', '/'])) {
+        return None;
+    }
+    let mut translated = PathBuf::from(format!("/mnt/{}", drive.to_ascii_lowercase()));
+    translated.extend(rest.split(['\\', '/']).filter(|part| !part.is_empty()));
+    Some(translated)
+}
+
+/// A path under WSL's drive mounts, `/mnt/c/Users/me`, as Windows sees it:
+/// `C:\Users\me`. `None` for any other path.
+pub fn wsl_to_windows(path: &Path) -> Option<PathBuf> {
+    let rest = path.to_str()?.strip_prefix("/mnt/")?;
+    let (drive, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    let mut chars = drive.chars();
+    let letter = chars.next().filter(char::is_ascii_alphabetic)?;
+    if chars.next().is_some() {
+        return None;
+    }
+    let parts: Vec<&str> = rest.split('/').filter(|part| !part.is_empty()).collect();
+    Some(PathBuf::from(format!("{}:\\{}", letter.to_ascii_uppercase(), parts.join("\\"))))
+}
+
+/// The working directory as `pwd -L` shows it: `$PWD` when it's an absolute path
+/// to the same place as `current_dir` (keeping the symlinks `cd` went through),
+/// otherwise `current_dir`.
+pub fn logical_dir(current_dir: &Path) -> PathBuf {
+    match std::env::var_os("PWD").map(PathBuf::from) {
+        Some(pwd) if pwd.is_absolute() && same_location(&pwd, current_dir) => pwd,
+        _ => current_dir.to_path_buf(),
+    }
+}
+
+/// `path` abbreviated as fish shows it: starting with `~` when it's under `home`,
+/// with every component but the last cut to its first character, or two for one
+/// starting with `.`. `/home/me/.config/flex-sh` under `/home/me` is `~/.c/flex-sh`.
+pub fn shorten_path(path: &Path, home: Option<&Path>) -> String {
+    let display = match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(relative) if relative.as_os_str().is_empty() => return "~".to_string(),
+        Some(relative) => format!("~/{}", relative.to_string_lossy()),
+        None => path.to_string_lossy().to_string(),
+    };
+    let Some((dirs, last)) = display.rsplit_once(std::path::is_separator) else {
+        return display;
+    };
+    let dirs: Vec<String> = dirs
+        .split(std::path::is_separator)
+        .map(|part| part.chars().take(if part.starts_with('.') { 2 } else { 1 }).collect())
+        .collect();
+    format!("{}/{}", dirs.join("/"), last)
+}
+
+/// A `PATH` value as macOS's `path_helper` builds it: the directories listed in
+/// `/etc/paths` and `/etc/paths.d` first, in order, then the rest of `current`.
+/// Each directory appears once.
+#[cfg(any(target_os = "macos", test))]
+pub fn merge_path_list(listed: &[String], current: &str) -> String {
+    let mut dirs: Vec<&str> = Vec::new();
+    for dir in listed.iter().map(String::as_str).chain(current.split(':')) {
+        if !dir.is_empty() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs.join(":")
+}
+
 pub fn is_hidden<P: AsRef<Path>>(path: P) -> bool {
     path.as_ref()
         .file_name()
@@ -48,7 +174,11 @@ pub fn is_hidden<P: AsRef<Path>>(path: P) -> bool {
 
 pub fn get_parent_and_name<P: AsRef<Path>>(path: P) -> (PathBuf, String) {
     let path = path.as_ref();
-    let parent = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
     let name = path
         .file_name()
         .and_then(|n| n.to_str())
@@ -57,6 +187,45 @@ pub fn get_parent_and_name<P: AsRef<Path>>(path: P) -> (PathBuf, String) {
     (parent, name)
 }
 
+/// The last component of `path` as POSIX `basename` computes it: trailing slashes
+/// are ignored, a path of only slashes is `/`, and `suffix` is removed unless it
+/// is the whole name.
+pub fn basename(path: &str, suffix: &str) -> String {
+    if path.is_empty() {
+        return String::new();
+    }
+    let trimmed = path.trim_end_matches(std::path::is_separator);
+    if trimmed.is_empty() {
+        return "/".to_string();
+    }
+    let name = match trimmed.rfind(std::path::is_separator) {
+        Some(i) => &trimmed[i + 1..],
+        None => trimmed,
+    };
+    match name.strip_suffix(suffix) {
+        Some(stripped) if !suffix.is_empty() && !stripped.is_empty() => stripped.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// Everything before the last component of `path`, as POSIX `dirname` computes it:
+/// `.` when there is no directory part, and `/` for the root.
+pub fn dirname(path: &str) -> String {
+    let trimmed = path.trim_end_matches(std::path::is_separator);
+    if trimmed.is_empty() {
+        return if path.is_empty() { ".".to_string() } else { "/".to_string() };
+    }
+    let Some(i) = trimmed.rfind(std::path::is_separator) else {
+        return ".".to_string();
+    };
+    let parent = trimmed[..i].trim_end_matches(std::path::is_separator);
+    if parent.is_empty() {
+        "/".to_string()
+    } else {
+        parent.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,6 +238,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_wsl_translation() {
+        assert_eq!(windows_to_wsl(Path::new(r"C:\Users\me")), Some(PathBuf::from("/mnt/c/Users/me")));
+        assert_eq!(windows_to_wsl(Path::new("d:/work/src/")), Some(PathBuf::from("/mnt/d/work/src")));
+        assert_eq!(windows_to_wsl(Path::new("C:")), Some(PathBuf::from("/mnt/c")));
+        assert_eq!(windows_to_wsl(Path::new("/usr/bin")), None);
+        assert_eq!(windows_to_wsl(Path::new("C:file")), None);
+
+        assert_eq!(wsl_to_windows(Path::new("/mnt/c/Users/me")), Some(PathBuf::from(r"C:\Users\me")));
+        assert_eq!(wsl_to_windows(Path::new("/mnt/d")), Some(PathBuf::from(r"D:\")));
+        assert_eq!(wsl_to_windows(Path::new("/mnt/wsl/x")), None);
+        assert_eq!(wsl_to_windows(Path::new("/home/me")), None);
+    }
+
+    #[test]
+    fn test_normalize_logical() {
+        assert_eq!(normalize_logical("/home/user/link/../other/./dir"), PathBuf::from("/home/user/other/dir"));
+        assert_eq!(normalize_logical("/.."), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn test_merge_path_list() {
+        let listed = vec!["/usr/local/bin".to_string(), "/usr/bin".to_string(), "/bin".to_string()];
+        assert_eq!(merge_path_list(&listed, "/opt/bin:/usr/bin"), "/usr/local/bin:/usr/bin:/bin:/opt/bin");
+        assert_eq!(merge_path_list(&listed, ""), "/usr/local/bin:/usr/bin:/bin");
+        assert_eq!(merge_path_list(&[], "/a::/b:/a"), "/a:/b");
+    }
+
     #[test]
     fn test_is_hidden() {
         assert!(is_hidden(".hidden"));
@@ -77,6 +274,35 @@ mod tests {
         assert!(!is_hidden("/path/to/visible"));
     }
 
+    #[test]
+    fn test_basename_and_dirname() {
+        assert_eq!(basename("/usr/local/bin/git.exe", ".exe"), "git");
+        assert_eq!(basename("/usr/lib/", ""), "lib");
+        assert_eq!(basename("//", ""), "/");
+        assert_eq!(basename("", ""), "");
+        assert_eq!(basename(".exe", ".exe"), ".exe");
+        assert_eq!(basename("a/..", ""), "..");
+
+        assert_eq!(dirname("/usr/local/bin/git"), "/usr/local/bin");
+        assert_eq!(dirname("/usr//lib//"), "/usr");
+        assert_eq!(dirname("/usr"), "/");
+        assert_eq!(dirname("/"), "/");
+        assert_eq!(dirname("file"), ".");
+        assert_eq!(dirname(""), ".");
+    }
+
+    #[test]
+    fn test_shorten_path() {
+        let home = Path::new("/home/me");
+        assert_eq!(shorten_path(Path::new("/home/me/projects/myapp/src"), Some(home)), "~/p/m/src");
+        assert_eq!(shorten_path(Path::new("/home/me/.config/flex-sh"), Some(home)), "~/.c/flex-sh");
+        assert_eq!(shorten_path(Path::new("/home/me"), Some(home)), "~");
+        assert_eq!(shorten_path(Path::new("/home/me/src"), Some(home)), "~/src");
+        assert_eq!(shorten_path(Path::new("/usr/local/lib"), Some(home)), "/u/l/lib");
+        assert_eq!(shorten_path(Path::new("/usr"), None), "/usr");
+        assert_eq!(shorten_path(Path::new("/"), None), "/");
+    }
+
     #[test]
     fn test_get_parent_and_name() {
         let (parent, name) = get_parent_and_name("/path/to/file.txt");
@@ -0,0 +1,37 @@
+/// Characters that never need quoting in a word.
+const PLAIN_CHARS: &str = "-_./:=,+@%^";
+
+/// Quotes `word` so the shell reads it back unchanged. Plain words are left as
+/// they are; anything else is single-quoted, with each `'` spliced in as `'"'"'`
+/// since backslashes outside quotes are literal here.
+pub fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty() && word.chars().all(|c| c.is_alphanumeric() || PLAIN_CHARS.contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\"'\"'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::state::ShellState;
+    use crate::utils::param_expand::expand_text;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/usr/bin:/bin"), "/usr/bin:/bin");
+        assert_eq!(shell_quote("hello world"), "'hello world'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("it's"), "'it'\"'\"'s'");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let state = ShellState::default();
+        for word in ["it's $HOME", "a\\b \"c\"", "*.rs"] {
+            assert_eq!(expand_text(&shell_quote(word), &state, false).unwrap(), word);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/// Every signal this platform knows as `(name, number)`, in number order. Names are
+/// without the `SIG` prefix, as `kill -l` prints them.
+#[cfg(unix)]
+pub fn signal_names() -> Vec<(String, i32)> {
+    use nix::sys::signal::Signal;
+
+    let mut signals: Vec<(String, i32)> = Signal::iterator()
+        .map(|signal| (signal.as_str().trim_start_matches("SIG").to_string(), signal as i32))
+        .collect();
+    signals.sort_by_key(|(_, number)| *number);
+    signals
+}
+
+/// The signals the C runtime defines on Windows.
+#[cfg(not(unix))]
+pub fn signal_names() -> Vec<(String, i32)> {
+    [("INT", 2), ("ILL", 4), ("FPE", 8), ("KILL", 9), ("SEGV", 11), ("TERM", 15), ("BREAK", 21), ("ABRT", 22)]
+        .iter()
+        .map(|(name, number)| (name.to_string(), *number))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_names() {
+        let signals = signal_names();
+        assert!(signals.contains(&("INT".to_string(), 2)));
+        assert!(signals.contains(&("KILL".to_string(), 9)));
+        assert!(signals.contains(&("TERM".to_string(), 15)));
+        assert!(signals.iter().all(|(name, _)| !name.starts_with("SIG")));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::terminal::shell_integration;
+
+/// `TERM_PROGRAM` values of terminals with 24-bit color.
+const TRUECOLOR_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"];
+/// `TERM` fragments of terminals with 24-bit color.
+const TRUECOLOR_TERMS: &[&str] = &["kitty", "alacritty", "ghostty", "wezterm", "foot", "direct"];
+/// `TERM_PROGRAM` values of terminals that open OSC 8 hyperlinks.
+const LINK_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "vscode", "ghostty"];
+/// `TERM` fragments of terminals that open OSC 8 hyperlinks.
+const LINK_TERMS: &[&str] = &["kitty", "alacritty", "ghostty", "wezterm", "foot"];
+/// `TERM_PROGRAM` values of terminals that draw sixel images.
+const SIXEL_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm"];
+/// `TERM` fragments of terminals that draw sixel images.
+const SIXEL_TERMS: &[&str] = &["foot", "mlterm", "contour", "wezterm"];
+/// First VTE release (0.36, as `VTE_VERSION` 3600) with 24-bit color.
+const VTE_TRUECOLOR: u32 = 3600;
+/// First VTE release (0.50) with OSC 8 hyperlinks.
+const VTE_LINKS: u32 = 5000;
+
+/// The terminal multiplexer the shell runs inside, if any.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum MultiplexerKind {
+    #[default]
+    None,
+    /// tmux, which sets `$TMUX`
+    TmuxMode,
+    /// GNU Screen, where `$TERM` starts with `screen`
+    ScreenMode,
+}
+
+impl MultiplexerKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MultiplexerKind::None => "none",
+            MultiplexerKind::TmuxMode => "tmux",
+            MultiplexerKind::ScreenMode => "screen",
+        }
+    }
+
+    /// The sequence that sets the window title: the multiplexer's own window name
+    /// (`ESC k title ESC \`), or OSC 2 for the terminal.
+    pub fn window_title(&self, title: &str) -> String {
+        let title: String = title.chars().filter(|c| !c.is_control()).collect();
+        match self {
+            MultiplexerKind::None => format!("\x1b]2;{}\x07", title),
+            _ => format!("\x1bk{}\x1b\\", title),
+        }
+    }
+
+    /// `sequence` wrapped so the multiplexer passes it through to the outer terminal
+    /// instead of swallowing it. tmux only does this with `allow-passthrough` on.
+    pub fn passthrough(&self, sequence: &str) -> String {
+        match self {
+            MultiplexerKind::None => sequence.to_string(),
+            MultiplexerKind::TmuxMode => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+            MultiplexerKind::ScreenMode => format!("\x1bP{}\x1b\\", sequence),
+        }
+    }
+}
+
+/// What the terminal the shell runs in supports, going by `$TERM_PROGRAM`, `$TERM`,
+/// `$COLORTERM` and `$VTE_VERSION`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TerminalCapabilities {
+    /// 24-bit colors; without them `#RRGGBB` colors use the nearest of 256
+    pub truecolor: bool,
+    /// OSC 8 hyperlinks
+    pub osc8_links: bool,
+    /// Sixel images
+    pub sixel_graphics: bool,
+    /// Bracketed paste mode
+    pub bracketed_paste: bool,
+    /// OSC 133 prompt marks and OSC 7 directory reports
+    pub shell_integration: bool,
+    /// The multiplexer in between, which changes how titles and other sequences are sent
+    pub multiplexer: MultiplexerKind,
+}
+
+impl TerminalCapabilities {
+    /// The capabilities of the terminal described by the environment.
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    /// The capabilities of the terminal described by `var`, which looks up a variable.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        if term == "dumb" {
+            return Self::default();
+        }
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let colorterm = var("COLORTERM").unwrap_or_default();
+        let vte = var("VTE_VERSION").and_then(|version| version.parse::<u32>().ok()).unwrap_or(0);
+        let multiplexer = if var("TMUX").is_some_and(|tmux| !tmux.is_empty()) {
+            MultiplexerKind::TmuxMode
+        } else if term.starts_with("screen") {
+            MultiplexerKind::ScreenMode
+        } else {
+            MultiplexerKind::None
+        };
+        let known = |programs: &[&str], terms: &[&str]| {
+            programs.contains(&program.as_str()) || terms.iter().any(|fragment| term.contains(fragment))
+        };
+
+        Self {
+            truecolor: matches!(colorterm.as_str(), "truecolor" | "24bit")
+                || known(TRUECOLOR_PROGRAMS, TRUECOLOR_TERMS)
+                || vte >= VTE_TRUECOLOR,
+            osc8_links: known(LINK_PROGRAMS, LINK_TERMS) || vte >= VTE_LINKS,
+            sixel_graphics: known(SIXEL_PROGRAMS, SIXEL_TERMS),
+            // tmux handles bracketed paste itself, whatever the outer terminal does;
+            // Screen doesn't pass it on
+            bracketed_paste: multiplexer != MultiplexerKind::ScreenMode,
+            shell_integration: shell_integration::is_supported_terminal(Some(&program)) || term.contains("kitty"),
+            multiplexer,
+        }
+    }
+
+    /// Each capability's name and whether it's supported, in display order.
+    pub fn list(&self) -> [(&'static str, bool); 5] {
+        [
+            ("truecolor", self.truecolor),
+            ("osc8_links", self.osc8_links),
+            ("sixel_graphics", self.sixel_graphics),
+            ("bracketed_paste", self.bracketed_paste),
+            ("shell_integration", self.shell_integration),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)]) -> TerminalCapabilities {
+        TerminalCapabilities::from_env(|name| {
+            vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_detect() {
+        let wezterm = detect(&[("TERM_PROGRAM", "WezTerm"), ("TERM", "xterm-256color")]);
+        assert!(wezterm.truecolor && wezterm.osc8_links && wezterm.sixel_graphics && wezterm.shell_integration);
+
+        let kitty = detect(&[("TERM", "xterm-kitty")]);
+        assert!(kitty.truecolor && kitty.shell_integration && !kitty.sixel_graphics);
+
+        let gnome = detect(&[("TERM", "xterm-256color"), ("VTE_VERSION", "6800")]);
+        assert!(gnome.truecolor && gnome.osc8_links && !gnome.shell_integration);
+
+        let plain = detect(&[("TERM", "xterm-256color")]);
+        assert!(!plain.truecolor && plain.bracketed_paste);
+        assert!(detect(&[("TERM", "xterm"), ("COLORTERM", "truecolor")]).truecolor);
+
+        assert_eq!(detect(&[("TERM", "dumb"), ("COLORTERM", "truecolor")]), TerminalCapabilities::default());
+    }
+
+    #[test]
+    fn test_multiplexer() {
+        let tmux = detect(&[("TMUX", "/tmp/tmux-1000/default,1,0"), ("TERM", "screen-256color")]);
+        assert_eq!(tmux.multiplexer, MultiplexerKind::TmuxMode);
+        assert!(tmux.bracketed_paste);
+        let screen = detect(&[("TERM", "screen.xterm-256color")]);
+        assert_eq!(screen.multiplexer, MultiplexerKind::ScreenMode);
+        assert!(!screen.bracketed_paste);
+        assert_eq!(detect(&[("TERM", "xterm")]).multiplexer, MultiplexerKind::None);
+
+        assert_eq!(MultiplexerKind::None.window_title("~/src"), "\x1b]2;~/src\x07");
+        assert_eq!(MultiplexerKind::TmuxMode.window_title("~/src\x07"), "\x1bk~/src\x1b\\");
+        assert_eq!(MultiplexerKind::TmuxMode.passthrough("\x1b]7;x\x07"), "\x1bPtmux;\x1b\x1b]7;x\x07\x1b\\");
+        assert_eq!(MultiplexerKind::ScreenMode.passthrough("\x1b]7;x\x07"), "\x1bP\x1b]7;x\x07\x1b\\");
+    }
+}
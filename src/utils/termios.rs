@@ -0,0 +1,248 @@
+use anyhow::{anyhow, bail, Result};
+
+/// Control characters `STTY` can set, by their `stty` names.
+const CONTROL_CHARS: &[&str] = &["intr", "quit", "erase", "kill", "eof", "start", "stop", "susp", "werase"];
+/// Mode flags `STTY` can turn on, or off with a leading `-`.
+const FLAGS: &[&str] = &["echo", "echoe", "echok", "echoctl", "icanon", "isig", "iexten", "ixon", "ixoff", "icrnl", "opost", "onlcr"];
+/// Line speeds `STTY` can set.
+const SPEEDS: &[u32] = &[1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400];
+
+/// One setting from the `STTY` variable, in the argument syntax of `stty`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SttySetting {
+    /// A control character (`intr ^C`); None is `undef`, which disables it
+    Char(&'static str, Option<u8>),
+    /// A mode flag (`echoctl`, `-ixon`)
+    Flag(&'static str, bool),
+    /// The input and output speed (`38400`), or only one of them (`ispeed 9600`)
+    Speed { input: bool, output: bool, baud: u32 },
+}
+
+/// Reads settings such as `intr ^C erase ^? -ixon 38400`.
+pub fn parse_stty(settings: &str) -> Result<Vec<SttySetting>> {
+    let mut parsed = Vec::new();
+    let mut words = settings.split_whitespace();
+    while let Some(word) = words.next() {
+        let mut argument = || words.next().ok_or_else(|| anyhow!("{}: missing argument", word));
+        if let Some(name) = CONTROL_CHARS.iter().copied().find(|name| *name == word) {
+            parsed.push(SttySetting::Char(name, parse_char(argument()?)?));
+        } else if let Some(name) = FLAGS.iter().copied().find(|name| *name == word.trim_start_matches('-')) {
+            parsed.push(SttySetting::Flag(name, !word.starts_with('-')));
+        } else if word == "ispeed" || word == "ospeed" {
+            let baud = parse_speed(argument()?)?;
+            parsed.push(SttySetting::Speed { input: word == "ispeed", output: word == "ospeed", baud });
+        } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+            parsed.push(SttySetting::Speed { input: true, output: true, baud: parse_speed(word)? });
+        } else {
+            bail!("{}: unknown setting", word);
+        }
+    }
+    Ok(parsed)
+}
+
+/// A control character written as `^C`, `^?`, `undef` or the character itself.
+fn parse_char(value: &str) -> Result<Option<u8>> {
+    let bytes = value.as_bytes();
+    match bytes {
+        b"undef" | b"^-" => Ok(None),
+        [b'^', b'?'] => Ok(Some(0x7f)),
+        [b'^', c] if c.is_ascii_alphabetic() || b"@[\\]^_".contains(c) => Ok(Some(c.to_ascii_uppercase() & 0x1f)),
+        [c] => Ok(Some(*c)),
+        _ => bail!("{}: invalid control character", value),
+    }
+}
+
+fn parse_speed(value: &str) -> Result<u32> {
+    value
+        .parse()
+        .ok()
+        .filter(|baud| SPEEDS.contains(baud))
+        .ok_or_else(|| anyhow!("{}: unsupported speed", value))
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::SttySetting;
+    use anyhow::Result;
+    use nix::sys::termios::{self, BaudRate, InputFlags, LocalFlags, OutputFlags, SetArg, SpecialCharacterIndices, Termios};
+    use std::io::{self, IsTerminal};
+
+    /// The terminal settings standard input had when this was made, put back when
+    /// it's dropped, so a shell that stops unexpectedly doesn't leave the terminal
+    /// in raw mode.
+    pub struct TermiosGuard {
+        saved: Termios,
+    }
+
+    impl TermiosGuard {
+        /// Saves the current settings; None if standard input isn't a terminal.
+        pub fn capture() -> Option<Self> {
+            let stdin = io::stdin();
+            if !stdin.is_terminal() {
+                return None;
+            }
+            termios::tcgetattr(&stdin).ok().map(|saved| Self { saved })
+        }
+
+        pub fn restore(&self) -> Result<()> {
+            termios::tcsetattr(io::stdin(), SetArg::TCSADRAIN, &self.saved)?;
+            Ok(())
+        }
+    }
+
+    impl Drop for TermiosGuard {
+        fn drop(&mut self) {
+            let _ = self.restore();
+        }
+    }
+
+    /// Applies `settings` to the terminal on standard input, if there is one.
+    pub fn apply_stty(settings: &[SttySetting]) -> Result<()> {
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            return Ok(());
+        }
+        let mut attrs = termios::tcgetattr(&stdin)?;
+        for setting in settings {
+            match *setting {
+                SttySetting::Char(name, value) => {
+                    // 0 disables a control character on the systems the shell runs on
+                    attrs.control_chars[control_char(name) as usize] = value.unwrap_or(0);
+                }
+                SttySetting::Flag(name, enable) => set_flag(&mut attrs, name, enable),
+                SttySetting::Speed { input, output, baud } => {
+                    let baud = baud_rate(baud);
+                    if input {
+                        termios::cfsetispeed(&mut attrs, baud)?;
+                    }
+                    if output {
+                        termios::cfsetospeed(&mut attrs, baud)?;
+                    }
+                }
+            }
+        }
+        termios::tcsetattr(&stdin, SetArg::TCSANOW, &attrs)?;
+        Ok(())
+    }
+
+    fn control_char(name: &str) -> SpecialCharacterIndices {
+        match name {
+            "intr" => SpecialCharacterIndices::VINTR,
+            "quit" => SpecialCharacterIndices::VQUIT,
+            "erase" => SpecialCharacterIndices::VERASE,
+            "kill" => SpecialCharacterIndices::VKILL,
+            "eof" => SpecialCharacterIndices::VEOF,
+            "start" => SpecialCharacterIndices::VSTART,
+            "stop" => SpecialCharacterIndices::VSTOP,
+            "susp" => SpecialCharacterIndices::VSUSP,
+            _ => SpecialCharacterIndices::VWERASE,
+        }
+    }
+
+    fn set_flag(attrs: &mut Termios, name: &str, enable: bool) {
+        match name {
+            "echo" => attrs.local_flags.set(LocalFlags::ECHO, enable),
+            "echoe" => attrs.local_flags.set(LocalFlags::ECHOE, enable),
+            "echok" => attrs.local_flags.set(LocalFlags::ECHOK, enable),
+            "echoctl" => attrs.local_flags.set(LocalFlags::ECHOCTL, enable),
+            "icanon" => attrs.local_flags.set(LocalFlags::ICANON, enable),
+            "isig" => attrs.local_flags.set(LocalFlags::ISIG, enable),
+            "iexten" => attrs.local_flags.set(LocalFlags::IEXTEN, enable),
+            "ixon" => attrs.input_flags.set(InputFlags::IXON, enable),
+            "ixoff" => attrs.input_flags.set(InputFlags::IXOFF, enable),
+            "icrnl" => attrs.input_flags.set(InputFlags::ICRNL, enable),
+            "opost" => attrs.output_flags.set(OutputFlags::OPOST, enable),
+            _ => attrs.output_flags.set(OutputFlags::ONLCR, enable),
+        }
+    }
+
+    fn baud_rate(baud: u32) -> BaudRate {
+        match baud {
+            1200 => BaudRate::B1200,
+            2400 => BaudRate::B2400,
+            4800 => BaudRate::B4800,
+            9600 => BaudRate::B9600,
+            19200 => BaudRate::B19200,
+            57600 => BaudRate::B57600,
+            115200 => BaudRate::B115200,
+            230400 => BaudRate::B230400,
+            _ => BaudRate::B38400,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::SttySetting;
+    use anyhow::Result;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
+    use winapi::um::processenv::GetStdHandle;
+    use winapi::um::winbase::STD_INPUT_HANDLE;
+
+    /// The console input mode when this was made, put back when it's dropped, so a
+    /// shell that stops unexpectedly doesn't leave the console in raw mode.
+    pub struct TermiosGuard {
+        saved: DWORD,
+    }
+
+    impl TermiosGuard {
+        /// Saves the current mode; None if standard input isn't a console.
+        pub fn capture() -> Option<Self> {
+            let mut saved = 0;
+            // SAFETY: GetConsoleMode only writes the mode of the handle it's given
+            let ok = unsafe { GetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), &mut saved) };
+            (ok != 0).then_some(Self { saved })
+        }
+
+        pub fn restore(&self) -> Result<()> {
+            // SAFETY: SetConsoleMode only reads the handle and mode it's given
+            if unsafe { SetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), self.saved) } == 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for TermiosGuard {
+        fn drop(&mut self) {
+            let _ = self.restore();
+        }
+    }
+
+    /// The Windows console has no `stty` settings, so there's nothing to apply.
+    pub fn apply_stty(_settings: &[SttySetting]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{apply_stty, TermiosGuard};
+#[cfg(windows)]
+pub use windows::{apply_stty, TermiosGuard};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stty() {
+        assert_eq!(
+            parse_stty("intr ^C erase ^? kill undef -ixon echoctl 38400 ispeed 9600").unwrap(),
+            vec![
+                SttySetting::Char("intr", Some(3)),
+                SttySetting::Char("erase", Some(0x7f)),
+                SttySetting::Char("kill", None),
+                SttySetting::Flag("ixon", false),
+                SttySetting::Flag("echoctl", true),
+                SttySetting::Speed { input: true, output: true, baud: 38400 },
+                SttySetting::Speed { input: true, output: false, baud: 9600 },
+            ]
+        );
+        assert_eq!(parse_stty("werase w").unwrap(), vec![SttySetting::Char("werase", Some(b'w'))]);
+        assert!(parse_stty("intr").is_err());
+        assert!(parse_stty("intr ^^^").is_err());
+        assert!(parse_stty("12345").is_err());
+        assert!(parse_stty("raw").is_err());
+    }
+}
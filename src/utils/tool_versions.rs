@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a `pyenv version-name` result is reused in the same directory.
+const PYENV_TTL: Duration = Duration::from_secs(5);
+
+/// The last `pyenv version-name` result, with the directory it was run in.
+#[derive(Debug, Clone)]
+pub struct PyenvCache {
+    dir: PathBuf,
+    checked: Instant,
+    version: String,
+}
+
+/// The active conda environment, from `$CONDA_DEFAULT_ENV`.
+pub fn conda_env() -> String {
+    std::env::var("CONDA_DEFAULT_ENV").unwrap_or_default()
+}
+
+/// The Python version pyenv selects in `dir`, or nothing when pyenv isn't installed
+/// or falls back to the system Python. `cache` keeps the last answer, which is
+/// reused for a few seconds while the directory stays the same.
+pub fn pyenv_version(dir: &Path, cache: &mut Option<PyenvCache>) -> String {
+    if let Some(cached) = cache.as_ref().filter(|cached| cached.dir == dir && cached.checked.elapsed() < PYENV_TTL) {
+        return cached.version.clone();
+    }
+    let version = Command::new("pyenv")
+        .arg("version-name")
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|version| version != "system")
+        .unwrap_or_default();
+    *cache = Some(PyenvCache { dir: dir.to_path_buf(), checked: Instant::now(), version: version.clone() });
+    version
+}
+
+/// The Node.js version nvm put on `PATH`: the name of the directory holding
+/// `$NVM_BIN`, such as `v20.11.0`.
+pub fn nvm_version() -> String {
+    std::env::var_os("NVM_BIN")
+        .and_then(|bin| Some(Path::new(&bin).parent()?.file_name()?.to_string_lossy().to_string()))
+        .unwrap_or_default()
+}
+
+/// The Node.js version wanted in `dir`: `$NODE_VERSION`, or the first line of the
+/// nearest `.nvmrc`.
+pub fn node_version(dir: &Path) -> String {
+    if let Some(version) = std::env::var("NODE_VERSION").ok().filter(|version| !version.is_empty()) {
+        return version;
+    }
+    dir.ancestors()
+        .find_map(|dir| std::fs::read_to_string(dir.join(".nvmrc")).ok())
+        .and_then(|nvmrc| nvmrc.lines().next().map(|line| line.trim().to_string()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_version() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join(".nvmrc"), "lts/iron\n").unwrap();
+        let nested = project.path().join("src");
+        std::fs::create_dir(&nested).unwrap();
+        if std::env::var_os("NODE_VERSION").is_none() {
+            assert_eq!(node_version(&nested), "lts/iron");
+        }
+    }
+
+    #[test]
+    fn test_pyenv_cache() {
+        let dir = PathBuf::from("/");
+        let mut cache = Some(PyenvCache { dir: dir.clone(), checked: Instant::now(), version: "3.12.1".to_string() });
+        assert_eq!(pyenv_version(&dir, &mut cache), "3.12.1");
+
+        // An expired answer is asked for again
+        let expired = Instant::now().checked_sub(PYENV_TTL * 2).unwrap();
+        let mut cache = Some(PyenvCache { dir: dir.clone(), checked: expired, version: "3.12.1".to_string() });
+        pyenv_version(&dir, &mut cache);
+        assert!(cache.unwrap().checked > expired);
+    }
+}
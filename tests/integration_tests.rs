@@ -45,6 +45,70 @@ fn test_ls_command() {
         .stdout(predicate::str::contains("test.txt"));
 }
 
+#[test]
+fn test_ls_recursive() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path();
+
+    fs::create_dir_all(temp_path.join("sub").join("deeper")).unwrap();
+    fs::create_dir(temp_path.join(".hidden")).unwrap();
+    fs::write(temp_path.join("sub").join("deeper").join("leaf.txt"), "x").unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_path);
+    cmd.arg("-c").arg("ls -R");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("{}:", temp_path.join("sub").join("deeper").display())))
+        .stdout(predicate::str::contains("leaf.txt"))
+        .stdout(predicate::str::contains(".hidden").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ls_long_symlink() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path();
+
+    fs::write(temp_path.join("target.txt"), "x").unwrap();
+    std::os::unix::fs::symlink("target.txt", temp_path.join("link")).unwrap();
+    std::os::unix::fs::symlink("missing.txt", temp_path.join("dangling")).unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_path);
+    cmd.arg("-c").arg("ls -lF");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("lrwxrwxrwx"))
+        .stdout(predicate::str::contains("link -> target.txt"))
+        .stdout(predicate::str::contains("dangling -> missing.txt"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ls_inode() {
+    use std::os::unix::fs::MetadataExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("file.txt");
+    fs::write(&file, "x").unwrap();
+    let inode = fs::metadata(&file).unwrap().ino();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg("ls -i");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("{} file.txt", inode)));
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg("ls -li");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!("{} -rw", inode)));
+}
+
 #[test]
 fn test_clear_command() {
     let mut cmd = Command::cargo_bin("flex-sh").unwrap();
@@ -78,6 +142,69 @@ fn test_cd_command() {
     cmd.assert().success();
 }
 
+#[test]
+fn test_cd_cdpath() {
+    let search_root = TempDir::new().unwrap();
+    let start_dir = TempDir::new().unwrap();
+    fs::create_dir(search_root.path().join("myrepo")).unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(start_dir.path());
+    cmd.env("CDPATH", search_root.path());
+    cmd.arg("-c").arg("cd myrepo");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(search_root.path().join("myrepo").display().to_string()));
+}
+
+#[test]
+fn test_auto_cd() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        r#"auto_cd = true
+
+[prompt]
+format = "$ "
+show_git = false
+show_time = false
+show_exit_code = true
+
+[colors]
+enabled = false
+scheme = "default"
+command_color = "white"
+argument_color = "white"
+error_color = "red"
+success_color = "green"
+
+[history]
+max_entries = 100
+ignore_duplicates = true
+ignore_space_prefixed = true
+
+[completion]
+enabled = true
+case_sensitive = false
+fuzzy_matching = true
+
+[aliases]
+
+[environment]
+"#,
+    )
+    .unwrap();
+    fs::create_dir(temp_dir.path().join("subdir")).unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("--config").arg(&config_path).arg("-c").arg("subdir");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("cd: subdir"));
+}
+
 #[test]
 fn test_exit_command() {
     let mut cmd = Command::cargo_bin("flex-sh").unwrap();
@@ -85,6 +212,1391 @@ fn test_exit_command() {
     cmd.assert().success();
 }
 
+#[test]
+fn test_script_error_location() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("script.sh");
+    fs::write(&script, "# comment\necho first\n\necho \"unterminated\necho never\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("script.sh");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("first"))
+        .stdout(predicate::str::contains("never").not())
+        .stderr(predicate::str::contains("script.sh:4:6: error: Unterminated quote"));
+}
+
+#[test]
+fn test_script_exit_status() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("exit.sh");
+    fs::write(&script, "echo before\nexit 4\necho after\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg(&script);
+    cmd.assert()
+        .code(4)
+        .stdout(predicate::str::contains("before"))
+        .stdout(predicate::str::contains("after").not());
+}
+
+#[test]
+fn test_errexit_script() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("errexit.sh");
+    fs::write(
+        &script,
+        "set -e\necho one\nfalse || true\nfalse && echo no\necho two\nfalse\necho three\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg(&script);
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("one"))
+        .stdout(predicate::str::contains("two"))
+        .stdout(predicate::str::contains("three").not())
+        .stdout(predicate::str::contains("no").not())
+        .stderr(predicate::str::contains(":6:"));
+}
+
+#[test]
+fn test_exit_on_error_flag() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-e").arg("-c").arg("false; echo after");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("after").not());
+}
+
+#[test]
+fn test_xtrace() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("set -x; echo hello; set +x; echo quiet");
+    cmd.assert()
+        .success()
+        .stdout("hello\nquiet\n")
+        .stderr(predicate::str::contains("+ echo hello\n"))
+        .stderr(predicate::str::contains("echo quiet").not());
+}
+
+#[test]
+fn test_nounset() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env_remove("FLEXSH_UNDEFINED_VAR");
+    cmd.arg("-c").arg("set -u; echo $FLEXSH_UNDEFINED_VAR; echo after");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("after").not())
+        .stderr(predicate::str::contains("flex-sh: FLEXSH_UNDEFINED_VAR: unbound variable"));
+}
+
+#[test]
+fn test_pipefail() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("false | true");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("set -o pipefail; false | true");
+    cmd.assert().code(1);
+}
+
+#[test]
+fn test_pipeline_connects_commands() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("printf 'one\ntwo\n' | grep two");
+    cmd.assert()
+        .success()
+        .stdout("two\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_builtin_pipeline() {
+    // Only the last builtin's output reaches the terminal
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("echo hello | basename /src/main.rs .rs; echo hi | echo there");
+    cmd.assert().success().stdout("main\nthere\n");
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("set -o pipefail; basename | echo ok; echo \"status $?\"");
+    cmd.assert().success().stdout("ok\nstatus 1\n");
+}
+
+#[test]
+fn test_recursive_function() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("fact.sh");
+    fs::write(
+        &script,
+        "fact() {\n  [ \"$1\" -le 1 ] && { echo \"$2\"; return 0; }\n  fact $(( $1 - 1 )) $(( $1 * $2 ))\n}\n\
+         function check {\n  return $1\n}\nfact 5 1\ncheck 3 || echo \"returned 3\"\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg(&script);
+    cmd.assert()
+        .success()
+        .stdout("120\nreturned 3\n");
+}
+
+#[test]
+fn test_if_statement() {
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("log.txt");
+    fs::write(&log, "an error here\n").unwrap();
+    let script = temp_dir.path().join("if.sh");
+    fs::write(
+        &script,
+        "set -e\n\
+         if grep -q error log.txt; then echo found; fi\n\
+         sign() {\n\
+           if [ \"$1\" -lt 0 ]; then\n\
+             echo negative\n\
+           elif [ \"$1\" -eq 0 ]; then\n\
+             echo zero\n\
+           else\n\
+             if [ \"$1\" -gt 100 ]; then echo big; else echo positive; fi\n\
+           fi\n\
+         }\n\
+         sign -3; sign 0; sign 5; sign 500\n\
+         if false; then echo unreachable; fi\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg(&script);
+    cmd.assert()
+        .success()
+        .stdout("found\nnegative\nzero\npositive\nbig\n");
+}
+
+#[test]
+fn test_loops() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("one.rs"), "").unwrap();
+    fs::write(temp_dir.path().join("two.rs"), "").unwrap();
+    let script = temp_dir.path().join("loops.sh");
+    fs::write(
+        &script,
+        "for f in *.rs; do echo \"file $f\"; done\n\
+         i=0\n\
+         while [ $i -lt 4 ]; do\n\
+           i=$((i + 1))\n\
+           if [ $i -eq 2 ]; then continue; fi\n\
+           echo \"i=$i\"\n\
+         done\n\
+         until [ $i -eq 0 ]; do i=$((i - 1)); done\n\
+         for x in 1 2; do for y in a b; do echo $x$y; break; done; done\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg(&script);
+    cmd.assert()
+        .success()
+        .stdout("file one.rs\nfile two.rs\ni=1\ni=3\ni=4\n1a\n2a\n");
+}
+
+#[test]
+fn test_nested_loop_control() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("nested.sh");
+    fs::write(
+        &script,
+        "for x in 1 2 3; do\n\
+           for y in a b c; do\n\
+             if [ $y = b ]; then continue 2; fi\n\
+             if [ $x = 3 ]; then break 2; fi\n\
+             echo $x$y\n\
+           done\n\
+           echo unreachable\n\
+         done\n\
+         echo after\n\
+         break\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg(&script);
+    cmd.assert()
+        .code(1)
+        .stdout("1a\n2a\nafter\n")
+        .stderr(predicate::str::contains("break: only meaningful in a `for', `while', or `until' loop"));
+}
+
+#[test]
+fn test_shift() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("shift.sh");
+    fs::write(
+        &script,
+        "echo \"$# $1\"\n\
+         shift\n\
+         echo \"$# $1\"\n\
+         f() { shift 2; echo \"f: $# $*\"; }\n\
+         f a b c d\n\
+         echo \"$# $1\"\n\
+         shift 5\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg(&script).args(["one", "-two", "three"]);
+    cmd.assert()
+        .code(1)
+        .stdout("3 one\n2 -two\nf: 2 c d\n2 -two\n")
+        .stderr("shift: 5: shift count out of range\n");
+}
+
+#[test]
+fn test_readonly() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg(
+        "readonly PI=3.14159 FLEXSH_UNSET_CONST; readonly PI=3; export PI=3; PI=3; PI=3 env; unset PI; \
+         unset -f missing; TMPVAR=1; unset TMPVAR; echo \"$PI ${TMPVAR:-gone}\"; readonly -p",
+    );
+    cmd.assert()
+        .code(0)
+        .stdout("3.14159 gone\nreadonly FLEXSH_UNSET_CONST\nreadonly PI=3.14159\n")
+        .stderr(
+            "readonly: PI: readonly variable\nexport: PI: readonly variable\nflex-sh: PI: readonly variable\n\
+             flex-sh: PI: readonly variable\nunset: PI: cannot unset: readonly variable\n",
+        );
+}
+
+#[test]
+fn test_return() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("return.sh");
+    fs::write(
+        &script,
+        "f() { false; return; }\n\
+         g() { for i in 1 2; do return 3; done; }\n\
+         f; echo \"f $?\"\n\
+         g; echo \"g $?\"\n\
+         return 5\n\
+         echo unreachable\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg(&script);
+    cmd.assert().code(5).stdout("f 1\ng 3\n");
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("echo before; return 7; echo after");
+    cmd.assert().code(7).stdout("before\n");
+
+    // Returning from a sourced file leaves the shell running
+    fs::write(temp_dir.path().join(".flexshrc"), "echo profile\nreturn\necho unreachable\n").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("HOME", temp_dir.path()).env("XDG_DATA_HOME", temp_dir.path());
+    cmd.arg("-l").arg("-c").arg("echo still running");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("profile\nstill running\n"))
+        .stdout(predicate::str::contains("unreachable").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_coproc() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("coproc.sh");
+    fs::write(
+        &script,
+        "coproc { cat; }\n\
+         echo first line >&${COPROC[1]}\n\
+         read -r reply <&${COPROC[0]}\n\
+         echo \"got $reply\"\n\
+         coproc UPPER { tr a-z A-Z; }\n\
+         echo shout >&${UPPER[1]}\n\
+         printf 'and again\\n' >&${UPPER[1]}\n\
+         coproc -c UPPER\n\
+         read first <&${UPPER[0]}\n\
+         read second third <&${UPPER[0]}\n\
+         echo \"$first/$second/$third\"\n\
+         read rest <&${UPPER[0]} || echo \"end of output\"\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg(&script);
+    cmd.assert().success().stdout("got first line\nSHOUT/AND/AGAIN\nend of output\n");
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("echo hi >&99");
+    cmd.assert().stderr(predicate::str::contains("99: bad file descriptor"));
+}
+
+#[test]
+fn test_case_statement() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("case.sh");
+    fs::write(
+        &script,
+        "kind() {\n\
+           case \"$1\" in\n\
+             *.rs|*.toml) echo rust;;\n\
+             *.py) echo python;;\n\
+             *) echo unknown;;\n\
+           esac\n\
+         }\n\
+         kind main.rs; kind Cargo.toml; kind app.py; kind README\n\
+         case a in a) echo one;& b) echo two;; c) echo three;; esac\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg(&script);
+    cmd.assert()
+        .success()
+        .stdout("rust\nrust\npython\nunknown\none\ntwo\n");
+}
+
+#[test]
+fn test_subshell_isolation() {
+    let temp_dir = TempDir::new().unwrap();
+    let sub_dir = temp_dir.path().join("sub");
+    fs::create_dir(&sub_dir).unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg(
+        "X=outer; (cd sub && X=inner && echo \"in $X\" && (exit 3)); echo \"status $X\"; (echo nested; (exit 4)) || pwd",
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("in inner\nstatus outer\nnested\n"))
+        .stdout(predicate::str::ends_with(format!("{}\n", temp_dir.path().file_name().unwrap().to_string_lossy())));
+}
+
+#[test]
+fn test_echo_escapes() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg(r#"echo -n one; echo -e " \x41\u00e9\e[0m"; echo -E 'a\nb'; echo "c\td""#);
+    cmd.assert()
+        .success()
+        .stdout("one A\u{e9}\x1b[0m\na\\nb\nc\td\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_env_modified_environment() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("FLEXSH_TEST_VAR", "kept");
+    cmd.arg("-c").arg(
+        "env -i FOO='a b' sh -c 'echo \"$FOO|$FLEXSH_TEST_VAR\"'; env -u FLEXSH_TEST_VAR sh -c 'echo \"[$FLEXSH_TEST_VAR]\"'; echo $FLEXSH_TEST_VAR",
+    );
+    cmd.assert()
+        .success()
+        .stdout("a b|\n[]\nkept\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_which_all_matches() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("which -a ls; which --type builtin ls; which --type function ls");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::starts_with("ls: shell built-in command\n/"))
+        .stdout(predicate::str::ends_with("/ls\nls: shell built-in command\n"))
+        .stderr(predicate::str::contains("which: no ls in PATH"));
+}
+
+#[test]
+fn test_interactive_multiline_input() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_DATA_HOME", temp_dir.path());
+    cmd.env_remove("TERM_PROGRAM");
+    cmd.write_stdin("echo \"line\none\"\necho a \\\nb\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout("line\none\na b\n");
+}
+
+#[test]
+fn test_history_builtin() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_DATA_HOME", temp_dir.path());
+    cmd.env_remove("TERM_PROGRAM");
+    cmd.write_stdin("echo one\necho two\nhistory -d 1\nhistory 2\nhistory -c\nhistory\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout("one\ntwo\n    2  history -d 1\n    3  history 2\n    1  history\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_combined_output_redirect() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg(
+        "sh -c 'echo out; echo err >&2' &> both.txt; sh -c 'echo again >&2' &>> both.txt; /bin/ls /flexsh-nonexistent &> ls.txt",
+    );
+    cmd.assert().stdout("").stderr("");
+
+    let both = std::fs::read_to_string(temp_dir.path().join("both.txt")).unwrap();
+    assert_eq!(both, "out\nerr\nagain\n");
+    let ls = std::fs::read_to_string(temp_dir.path().join("ls.txt")).unwrap();
+    assert!(ls.contains("flexsh-nonexistent"));
+}
+
+#[test]
+fn test_globstar() {
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::create_dir_all(temp_dir.path().join("a/b")).unwrap();
+    for file in ["top.txt", "a/mid.txt", "a/b/deep.txt", "a/b/skip.rs"] {
+        std::fs::write(temp_dir.path().join(file), "").unwrap();
+    }
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg("echo **/*.txt; set -o globstar; echo **/*.txt; echo '**/*.txt'");
+    cmd.assert()
+        .success()
+        .stdout("a/mid.txt\na/b/deep.txt a/mid.txt top.txt\n**/*.txt\n");
+}
+
+#[test]
+fn test_extglob() {
+    let temp_dir = TempDir::new().unwrap();
+    for file in ["notes.txt", "main.rs", "lib.rs"] {
+        std::fs::write(temp_dir.path().join(file), "").unwrap();
+    }
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg("echo !(*.txt); set -o extglob; echo !(*.txt); echo @(main|notes).*");
+    cmd.assert()
+        .success()
+        .stdout("!(*.txt)\nlib.rs main.rs\nmain.rs notes.txt\n");
+}
+
+#[test]
+fn test_ifs_word_splitting() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg(
+        "var=/usr/local/bin:/usr/bin:/bin; IFS=:; for p in $var; do echo \"[$p]\"; done; for p in \"$var\"; do echo \"[$p]\"; done",
+    );
+    cmd.assert()
+        .success()
+        .stdout("[/usr/local/bin]\n[/usr/bin]\n[/bin]\n[/usr/local/bin:/usr/bin:/bin]\n");
+}
+
+#[test]
+fn test_pattern_substitution() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("path=/src/foo/lib/foo.rs; echo ${path/foo/bar}; echo ${path//foo/bar}; echo ${path//f*o/}");
+    cmd.assert()
+        .success()
+        .stdout("/src/bar/lib/foo.rs\n/src/bar/lib/bar.rs\n/src/.rs\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sigterm_exits_cleanly() {
+    use std::io::Write;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("flex-sh"))
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .env("XDG_DATA_HOME", temp_dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Stdin stays open, so the shell waits at the prompt after running this
+    child.stdin.as_mut().unwrap().write_all(b"echo before-term\n").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    std::process::Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(143));
+    assert_eq!(status.signal(), None);
+
+    let history = fs::read_to_string(temp_dir.path().join("flex-sh").join("history")).unwrap();
+    assert!(history.contains("echo before-term"));
+}
+
+#[test]
+fn test_shell_integration_marks() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_DATA_HOME", temp_dir.path());
+    cmd.env("TERM_PROGRAM", "WezTerm").env_remove("TMUX");
+    cmd.write_stdin("echo hi\nfalse\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b]133;C\x07hi\n\x1b]133;D;0\x07\x1b]7;file://"))
+        .stdout(predicate::str::contains("\x1b]133;D;1\x07"));
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_DATA_HOME", temp_dir.path());
+    cmd.env_remove("TERM_PROGRAM");
+    cmd.write_stdin("echo hi\nexit\n");
+    cmd.assert().success().stdout("hi\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_time_builtin() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("TIMEFORMAT='took %R'; time printf 'hi\\n' | tr a-z A-Z; time sh -c 'exit 3'");
+    cmd.assert()
+        .code(3)
+        .stdout("HI\n")
+        .stderr(predicate::str::is_match(r"^took \d+\.\d{3}\ntook \d+\.\d{3}\n$").unwrap());
+}
+
+#[test]
+fn test_basename_dirname() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("basename /usr/local/bin/git.exe .exe; basename -a /usr/lib/ //; dirname /usr/local/bin/git; dirname / file");
+    cmd.assert()
+        .success()
+        .stdout("git\nlib\n/\n/usr/local/bin\n/\n.\n");
+}
+
+#[test]
+fn test_date_builtin() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("date +%Y; date -u '+%H:%M %Z'");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^\d{4}\n\d{2}:\d{2} UTC\n$").unwrap());
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("date -d yesterday");
+    cmd.assert().failure().stderr(predicate::str::contains("not supported"));
+}
+
+#[test]
+fn test_mkdir_builtin() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg("mkdir -pv deep/path; mkdir -p deep/path; mkdir missing/child later");
+    cmd.assert()
+        .code(1)
+        .stdout("mkdir: created directory 'deep'\nmkdir: created directory 'deep/path'\n")
+        .stderr("mkdir: cannot create directory 'missing/child': No such file or directory\n");
+
+    assert!(temp_dir.path().join("deep/path").is_dir());
+    assert!(!temp_dir.path().join("later").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_mkfifo_builtin() {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("plain.txt"), "").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg(
+        "mkfifo queue && mkfifo -m 600 private && mkfifo queue && echo again; \
+         mkfifo -e queue; mkfifo plain.txt skipped; mkfifo -k plain.txt kept",
+    );
+    cmd.assert().code(1).stdout("again\n").stderr(
+        "mkfifo: cannot create fifo 'queue': File exists\n\
+         mkfifo: cannot create fifo 'plain.txt': File exists\n\
+         mkfifo: cannot create fifo 'plain.txt': File exists\n",
+    );
+
+    let metadata = |name: &str| fs::metadata(temp_dir.path().join(name)).unwrap();
+    assert!(metadata("queue").file_type().is_fifo());
+    assert!(metadata("kept").file_type().is_fifo());
+    assert_eq!(metadata("private").permissions().mode() & 0o777, 0o600);
+    assert!(!temp_dir.path().join("skipped").exists());
+}
+
+#[test]
+fn test_tee_builtin() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("log.txt"), "earlier\n").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg("echo first | tee out.txt copy.txt; echo second | tee -a log.txt; echo lost | tee no/such.txt");
+    cmd.assert().code(1).stdout("first\nsecond\nlost\n").stderr(predicate::str::contains("tee: no/such.txt: No such file or directory"));
+
+    let read = |name: &str| fs::read_to_string(temp_dir.path().join(name)).unwrap();
+    assert_eq!(read("out.txt"), "first\n");
+    assert_eq!(read("copy.txt"), "first\n");
+    assert_eq!(read("log.txt"), "earlier\nsecond\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tee_between_programs() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg("printf 'a\\nb\\n' | tee f | cat; echo hi | tee g | tr a-z A-Z; seq 1 50000 | tee h | cat | tee i | wc -l");
+    cmd.assert().success().stdout(predicate::str::is_match("^a\nb\nHI\n *50000\n$").unwrap());
+
+    let read = |name: &str| fs::read_to_string(temp_dir.path().join(name)).unwrap();
+    assert_eq!(read("f"), "a\nb\n");
+    assert_eq!(read("g"), "hi\n");
+    assert_eq!(read("h").lines().count(), 50000);
+    assert_eq!(read("i"), read("h"));
+}
+
+#[test]
+fn test_xargs_builtin() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg(
+        "echo a b c d e | xargs -n 2 echo got; \
+         echo \"'two words' three\" | xargs -t -I {} echo [{}]; \
+         echo x y | xargs; \
+         greet() { echo \"hello $1\"; }; echo world | xargs greet; \
+         echo one | xargs -P 2 touch; \
+         echo a | xargs false || echo \"status $?\"",
+    );
+    cmd.assert()
+        .success()
+        .stdout("got a b\ngot c d\ngot e\n['two words' three]\nx y\nhello world\nstatus 123\n")
+        .stderr("echo ['two words' three]\n");
+    assert!(temp_dir.path().join("one").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_xargs_fed_by_a_program() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "foo here\n").unwrap();
+    fs::write(temp_dir.path().join("b.txt"), "nothing\n").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg(
+        "printf 'one\\ntwo\\n' | xargs echo got; \
+         printf 'a.txt\\nb.txt\\n' | xargs grep -l foo; \
+         greet() { echo \"hello $1\"; }; printf 'world\\n' | xargs greet; \
+         export X=1; echo a | xargs -P 2 sh -c 'echo \"X=$X\"'; echo a | Y=2 xargs -P 2 sh -c 'echo \"Y=$Y\"'",
+    );
+    cmd.assert().success().stdout("got one two\na.txt\nhello world\nX=1\nY=2\n");
+}
+
+#[test]
+fn test_touch_builtin() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-c").arg("touch new.txt; touch -t 200001021530 old.txt; touch -m -r old.txt new.txt; touch -c absent.txt; touch no/such.txt other.txt");
+    cmd.assert()
+        .code(1)
+        .stderr("touch: cannot touch 'no/such.txt': No such file or directory\n");
+
+    let modified = |name: &str| fs::metadata(temp_dir.path().join(name)).unwrap().modified().unwrap();
+    assert_eq!(fs::read_to_string(temp_dir.path().join("new.txt")).unwrap(), "");
+    assert_eq!(modified("new.txt"), modified("old.txt"));
+    assert!(!temp_dir.path().join("absent.txt").exists());
+    assert!(temp_dir.path().join("other.txt").exists());
+}
+
+#[test]
+fn test_export_print() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("export GREETING=\"it's here\" PLAIN=a/b; export -p; export 1x=y");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("export GREETING='it'\"'\"'s here'\n"))
+        .stdout(predicate::str::contains("export PLAIN=a/b\n"))
+        .stderr("export: `1x=y': not a valid identifier\n");
+}
+
+#[test]
+fn test_local_variables() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg(
+        "x=outer; inner() { echo \"inner $x\"; x=changed; }; f() { local x=mine y; inner; echo \"f $x\"; return 3; }; \
+         f || echo \"after $x [$y]\"; local z=1",
+    );
+    cmd.assert()
+        .code(1)
+        .stdout("inner mine\nf changed\nafter outer []\n")
+        .stderr("local: can only be used in a function\n");
+}
+
+#[test]
+fn test_compgen_and_compdef() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg(
+        "compgen -W 'foo bar baz' -- b; compdef mycmd _mycmd; compdef tool _tool; compdef -d tool; compdef; compgen -W 'foo' x",
+    );
+    cmd.assert()
+        .code(1)
+        .stdout("bar\nbaz\ncompdef mycmd _mycmd\n");
+}
+
+#[test]
+fn test_complete() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg(
+        "complete -W 'start stop' svc; complete -F _git git; compdef mycmd _mycmd; complete -d cd pushd; \
+         complete -r pushd; complete; complete -p svc nope; complete -f",
+    );
+    cmd.assert()
+        .code(2)
+        .stdout("complete -d cd\ncomplete -F _git git\ncomplete -F _mycmd mycmd\ncomplete -W 'start stop' svc\ncomplete -W 'start stop' svc\n")
+        .stderr(predicate::str::starts_with("complete: nope: no completion specification\ncomplete: usage:"));
+}
+
+#[test]
+fn test_no_hyperlinks_when_piped() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("notes.txt"), "").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("TERM_PROGRAM", "WezTerm").env("TERM", "xterm-256color").env_remove("TMUX");
+    cmd.arg("-c").arg("ls; pwd");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("notes.txt"))
+        .stdout(predicate::str::contains("\x1b]8;").not());
+}
+
+#[test]
+fn test_setopt() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir(temp_dir.path().join("inner")).unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    let script = temp_dir.path().join("options.sh");
+    fs::write(&script, "setopt AUTO_CD noglob\necho *.txt\nunsetopt noglob\necho *.txt\ninner\npwd -P\nsetopt bogus\n").unwrap();
+    cmd.arg(&script);
+    let inner = temp_dir.path().canonicalize().unwrap().join("inner");
+    cmd.assert()
+        .code(1)
+        .stdout(format!("*.txt\na.txt\ncd: inner\n{}\n", inner.display()))
+        .stderr("setopt: no such option: bogus\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_alt_f_moves_forward_a_word() {
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new().unwrap();
+    let pty = nix::pty::openpty(None, None).unwrap();
+    // The history file is relative to the working directory
+    let mut shell = std::process::Command::new(assert_cmd::cargo::cargo_bin("flex-sh"))
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path())
+        .env("XDG_DATA_HOME", temp_dir.path())
+        .env("TERM", "xterm")
+        .env_remove("TMUX")
+        .stdin(pty.slave.try_clone().unwrap())
+        .stdout(pty.slave.try_clone().unwrap())
+        .stderr(pty.slave)
+        .spawn()
+        .unwrap();
+
+    let mut terminal = fs::File::from(pty.master);
+    let mut reader = terminal.try_clone().unwrap();
+    let (sender, output) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0; 4096];
+        while let Ok(n @ 1..) = reader.read(&mut buf) {
+            let _ = sender.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+        }
+    });
+    let mut seen = String::new();
+    // Reads up to and including `text`
+    let mut wait_for = |text: &str| loop {
+        if let Some(at) = seen.find(text) {
+            seen.drain(..at + text.len());
+            break;
+        }
+        let more = output.recv_timeout(Duration::from_secs(10));
+        seen.push_str(&more.unwrap_or_else(|_| panic!("expected {:?} in {:?}", text, seen)));
+    };
+
+    wait_for("]$ ");
+    // Ctrl+A, then Alt+F twice puts the cursor after "hello"
+    terminal.write_all(b"echo hello world\x01\x1bf\x1bfX\r").unwrap();
+    wait_for("\nhelloX world\n");
+    wait_for("]$ ");
+    terminal.write_all(b"exit\r").unwrap();
+    assert!(shell.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_prompt_jobs_count() {
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir(temp_dir.path().join("flex-sh")).unwrap();
+    fs::write(
+        temp_dir.path().join("flex-sh").join("config.toml"),
+        r#"[prompt]
+format = "jobs{jobs}> "
+show_git = false
+show_time = false
+show_exit_code = false
+
+[colors]
+enabled = false
+scheme = "default"
+command_color = "white"
+argument_color = "white"
+error_color = "red"
+success_color = "green"
+
+[history]
+max_entries = 100
+ignore_duplicates = true
+ignore_space_prefixed = true
+
+[completion]
+enabled = true
+case_sensitive = false
+fuzzy_matching = true
+
+[aliases]
+
+[environment]
+"#,
+    )
+    .unwrap();
+    let pty = nix::pty::openpty(None, None).unwrap();
+    let mut shell = std::process::Command::new(assert_cmd::cargo::cargo_bin("flex-sh"))
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path())
+        .env("XDG_DATA_HOME", temp_dir.path())
+        .env("TERM", "xterm")
+        .env_remove("TMUX")
+        .env_remove("FLEXSH_PROFILE")
+        .stdin(pty.slave.try_clone().unwrap())
+        .stdout(pty.slave.try_clone().unwrap())
+        .stderr(pty.slave)
+        .spawn()
+        .unwrap();
+
+    let mut terminal = fs::File::from(pty.master);
+    let mut reader = terminal.try_clone().unwrap();
+    let (sender, output) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0; 4096];
+        while let Ok(n @ 1..) = reader.read(&mut buf) {
+            let _ = sender.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+        }
+    });
+    let mut seen = String::new();
+    // Reads up to and including `text`
+    let mut wait_for = |text: &str| loop {
+        if let Some(at) = seen.find(text) {
+            seen.drain(..at + text.len());
+            break;
+        }
+        let more = output.recv_timeout(Duration::from_secs(10));
+        seen.push_str(&more.unwrap_or_else(|_| panic!("expected {:?} in {:?}", text, seen)));
+    };
+
+    wait_for("jobs> ");
+    terminal.write_all(b"sleep 1 &\r").unwrap();
+    wait_for("jobs[1]> ");
+    // The background sleep ends while this one runs, so the next prompt has no count
+    terminal.write_all(b"sleep 2\r").unwrap();
+    wait_for("jobs> ");
+    terminal.write_all(b"exit\r").unwrap();
+    assert!(shell.wait().unwrap().success());
+}
+
+#[test]
+fn test_completion_scripts_loaded() {
+    let temp_dir = TempDir::new().unwrap();
+    let completions = temp_dir.path().join("flex-sh").join("completions");
+    fs::create_dir_all(&completions).unwrap();
+    fs::copy("completions/cargo.completion", completions.join("cargo.completion")).unwrap();
+    fs::write(completions.join("broken.flexsh"), "echo \"unterminated\n").unwrap();
+    fs::write(completions.join("notes.txt"), "compdef ignored _ignored\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path());
+    cmd.env("XDG_DATA_HOME", temp_dir.path());
+    cmd.env("XDG_CONFIG_HOME", temp_dir.path());
+    cmd.env_remove("TERM_PROGRAM");
+    cmd.write_stdin("compdef\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("compdef cargo _cargo"))
+        .stdout(predicate::str::contains("ignored").not())
+        .stderr(predicate::str::contains("broken.flexsh:1"));
+}
+
+#[test]
+fn test_color_scheme() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path());
+    cmd.env("XDG_CONFIG_HOME", temp_dir.path());
+    cmd.arg("-c").arg("color scheme monokai; color scheme; color scheme list; color scheme preview light; color scheme nope");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::starts_with("monokai\n"))
+        .stdout(predicate::str::contains("* monokai"))
+        .stdout(predicate::str::contains("  dark"))
+        .stdout(predicate::str::contains("\x1b[30m$\x1b[0m"))
+        .stderr("color: nope: no such scheme (see `color scheme list`)\n");
+}
+
+#[test]
+fn test_custom_color_scheme() {
+    let temp_dir = TempDir::new().unwrap();
+    let schemes = temp_dir.path().join("flex-sh").join("schemes");
+    fs::create_dir_all(&schemes).unwrap();
+    fs::write(schemes.join("ocean.toml"), "name = \"ocean\"\n[colors]\nerror = \"#FF0000\"\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path());
+    cmd.env("XDG_CONFIG_HOME", temp_dir.path());
+    // #RRGGBB colors come out as 24-bit colors only where the terminal has them
+    cmd.env("COLORTERM", "truecolor");
+    cmd.arg("-c").arg("color scheme list; color scheme ocean; color scheme");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("  ocean"))
+        .stdout(predicate::str::contains("\x1b[38;2;255;0;0merror\x1b[0m"))
+        .stdout(predicate::str::ends_with("ocean\n"));
+}
+
+#[test]
+fn test_clear_soft() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("clear -x; clear -q");
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::str::starts_with("\n"))
+        .stdout(predicate::str::contains("\x1b[2J").not())
+        .stderr(predicate::str::starts_with("clear: -q: invalid option\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pwd_logical_and_physical() {
+    let temp_dir = TempDir::new().unwrap();
+    let real = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(real.join("target")).unwrap();
+    std::os::unix::fs::symlink("target", real.join("link")).unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(&real);
+    cmd.env("PWD", &real);
+    cmd.arg("-c").arg("cd link; pwd; pwd -L; pwd -P; echo $OLDPWD");
+    cmd.assert().success().stdout(format!(
+        "{link}\n{link}\n{target}\n{real}\n",
+        link = real.join("link").display(),
+        target = real.join("target").display(),
+        real = real.display(),
+    ));
+}
+
+#[test]
+fn test_recursive_alias() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_DATA_HOME", temp_dir.path());
+    cmd.env_remove("TERM_PROGRAM");
+    cmd.write_stdin("alias echo='echo hi'\necho there\nexit\n");
+    cmd.assert().success().stdout(predicate::str::ends_with("hi there\n"));
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        r#"[prompt]
+format = "$ "
+show_git = false
+show_time = false
+show_exit_code = true
+
+[colors]
+enabled = false
+scheme = "default"
+command_color = "white"
+argument_color = "white"
+error_color = "red"
+success_color = "green"
+
+[history]
+max_entries = 100
+ignore_duplicates = true
+ignore_space_prefixed = true
+
+[completion]
+enabled = true
+case_sensitive = false
+fuzzy_matching = true
+prevent_recursive_aliases = true
+
+[aliases]
+
+[environment]
+"#,
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("--config").arg(&config_path).arg("-c").arg("alias a='b x' b='a y' ll='ls -l'");
+    cmd.assert()
+        .code(1)
+        .stdout("alias a='b x'\nalias ll='ls -l'\n")
+        .stderr("alias: b: alias refers to itself\n");
+}
+
+#[test]
+fn test_alias_save_keeps_the_rest_of_the_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        r#"# History lives with the dotfiles
+[prompt]
+format = "$ "
+show_git = false
+show_time = false
+show_exit_code = true
+
+[colors]
+enabled = false
+scheme = "default"
+command_color = "white"
+argument_color = "white"
+error_color = "red"
+success_color = "green"
+
+[history]
+max_entries = 100
+ignore_duplicates = true
+ignore_space_prefixed = true
+file_path = "${HOME}/.flexsh_history"
+
+[completion]
+enabled = true
+case_sensitive = false
+fuzzy_matching = true
+
+[aliases]
+ll = "ls -l"
+
+[environment]
+"#,
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path())
+        .env_remove("FLEXSH_PROFILE");
+    cmd.arg("--config").arg(&config_path).arg("-c").arg("alias --save gs='git status'; alias --remove ll");
+    cmd.assert().success();
+
+    let saved = fs::read_to_string(&config_path).unwrap();
+    assert!(saved.starts_with("# History lives with the dotfiles\n"));
+    assert!(saved.contains("file_path = \"${HOME}/.flexsh_history\""));
+    assert!(saved.contains("gs = \"git status\""));
+    assert!(!saved.contains("ll ="));
+}
+
+#[test]
+fn test_history_export_import() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("in.csv"), "command,timestamp\n\"echo \"\"a, b\"\"\",\nls,1700000000\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_DATA_HOME", temp_dir.path());
+    cmd.env_remove("TERM_PROGRAM");
+    cmd.write_stdin("echo one\nhistory --export json out.json\nhistory --import csv in.csv\nhistory 3\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout("one\n    4  echo \"a, b\"\n    5  ls\n    6  history 3\n");
+
+    let exported = fs::read_to_string(temp_dir.path().join("out.json")).unwrap();
+    assert!(exported.contains("\"command\": \"echo one\""));
+    assert!(exported.contains("\"timestamp\": null"));
+}
+
+#[test]
+fn test_histignore() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_DATA_HOME", temp_dir.path());
+    cmd.env_remove("TERM_PROGRAM");
+    cmd.env("HISTIGNORE", "echo secret*:ls");
+    cmd.write_stdin("echo secret 1\nls\necho public\nhistory\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::ends_with("public\n    1  echo public\n    2  history\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_help_external_command() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let tool = temp_dir.path().join("mytool");
+    fs::write(&tool, "#!/bin/sh\necho\necho 'mytool 1.0 - frobnicates widgets'\n").unwrap();
+    fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", temp_dir.path().display(), std::env::var("PATH").unwrap_or_default());
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("PATH", path);
+    cmd.arg("-c").arg("help mytool; help --all; help no-such-tool");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::starts_with("mytool - mytool 1.0 - frobnicates widgets\n"))
+        .stdout(predicate::str::contains("Commands in PATH:"))
+        .stdout(predicate::str::contains("  mytool"))
+        .stdout(predicate::str::ends_with("Unknown command: no-such-tool\n"));
+}
+
+#[test]
+fn test_which_alias_first() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c").arg("alias history='history 10'; which history; which -a history");
+    cmd.assert().success().stdout(
+        "alias history='history 10'\nhistory: aliased to history 10\n\
+         history: aliased to history 10\nhistory: shell built-in command\n",
+    );
+}
+
+#[test]
+fn test_restricted_shell() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("--restricted").arg("-c").arg(
+        "cd /; echo hi > out.txt; PATH=/tmp; export SHELL=/bin/sh; echo allowed | sort; ./script",
+    );
+    cmd.assert()
+        .failure()
+        .stdout("allowed\n")
+        .stderr(predicate::str::contains("cd: restricted\n"))
+        .stderr(predicate::str::contains("flex-sh: out.txt: restricted: cannot redirect output\n"))
+        .stderr(predicate::str::contains("flex-sh: PATH: readonly variable\n"))
+        .stderr(predicate::str::contains("export: SHELL: readonly variable\n"))
+        .stderr(predicate::str::contains("flex-sh: ./script: restricted: cannot specify `/' in command names\n"));
+    assert!(!temp_dir.path().join("out.txt").exists());
+}
+
+#[test]
+fn test_login_shell() {
+    let home = tempfile::tempdir().unwrap();
+    std::fs::write(home.path().join(".profile"), "export FROM_PROFILE=profile\n").unwrap();
+    std::fs::write(home.path().join(".flexsh_logout"), "echo logging out\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("HOME", home.path())
+        .args(["-l", "-c", "echo $FROM_PROFILE $0"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("profile -flex-sh"))
+        .stdout(predicate::str::contains("logging out"));
+
+    // ~/.flexshrc is read instead of ~/.profile
+    std::fs::write(home.path().join(".flexshrc"), "export FROM_PROFILE=flexshrc\n").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("HOME", home.path())
+        .args(["--login", "-c", "echo $FROM_PROFILE"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("flexshrc"));
+
+    // Without -l neither file is read
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("HOME", home.path())
+        .env_remove("FROM_PROFILE")
+        .args(["-c", "echo [$FROM_PROFILE] $0"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[] flex-sh"))
+        .stdout(predicate::str::contains("logging out").not());
+}
+
+#[test]
+fn test_special_parameters() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c")
+        .arg("false; echo status=$?; true && echo status=${?}; echo [$!]")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("status=1"))
+        .stdout(predicate::str::contains("status=0"))
+        .stdout(predicate::str::contains("[]"));
+
+    let temp = tempfile::tempdir().unwrap();
+    let script = temp.path().join("script.sh");
+    std::fs::write(&script, "sleep 0.1 &\necho pid=$! shell=$$ name=$0\n").unwrap();
+    let output = Command::cargo_bin("flex-sh").unwrap().arg(&script).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("name={}", script.display())), "{}", stdout);
+    let pid_of = |key: &str| stdout.split_whitespace().find_map(|word| word.strip_prefix(key)).unwrap().to_string();
+    assert!(pid_of("pid=").parse::<u32>().is_ok(), "{}", stdout);
+    assert_ne!(pid_of("pid="), pid_of("shell="));
+}
+
+#[test]
+fn test_indirect_expansion() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("HOME", home.path())
+        .arg("-c")
+        .arg("X=HOME; echo ${!X}")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(home.path().to_str().unwrap()));
+}
+
+#[test]
+fn test_dynamic_variables() {
+    let temp = tempfile::tempdir().unwrap();
+    let script = temp.path().join("script.sh");
+    std::fs::write(&script, "RANDOM=5\n\necho line=$LINENO seconds=$SECONDS random=$RANDOM\n").unwrap();
+    let output = Command::cargo_bin("flex-sh").unwrap().arg(&script).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("line=3 seconds=0 random="), "{}", stdout);
+    let random = stdout.trim().rsplit('=').next().unwrap();
+    assert!(random.parse::<u16>().unwrap() < 32768);
+}
+
+#[test]
+fn test_arrays() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("-c")
+        .arg("arr=(1 \"two three\" 3); arr[4]=five; echo count=${#arr[@]} first=${arr[0]} last=${arr[-1]}; for x in \"${arr[@]:1:2}\"; do echo \"[$x]\"; done")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("count=5 first=1 last=five"))
+        .stdout(predicate::str::contains("[two three]\n[3]\n"));
+}
+
+#[test]
+fn test_terminal_capabilities() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("TERM", "xterm-256color")
+        .env("COLORTERM", "truecolor")
+        .env_remove("TERM_PROGRAM")
+        .env_remove("VTE_VERSION")
+        .args(["-c", "terminal capabilities"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("truecolor          yes"))
+        .stdout(predicate::str::contains("shell_integration  no"));
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.env("TMUX", "/tmp/tmux-1000/default,1,0")
+        .args(["-c", "terminal capabilities"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("multiplexer        tmux"));
+
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.args(["-c", "terminal"]).assert().code(2);
+}
+
+#[test]
+fn test_dump_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_CONFIG_HOME", temp_dir.path());
+    cmd.arg("--dump-config");
+    let output = cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("# No config file found; these are the defaults\n"))
+        .stdout(predicate::str::contains("[prompt]"))
+        .stdout(predicate::str::contains("window_title = true"))
+        .get_output()
+        .stdout
+        .clone();
+
+    // What's printed is a config file in its own right
+    let config_file = temp_dir.path().join("custom.toml");
+    let config = String::from_utf8(output).unwrap().replace("scheme = \"default\"", "scheme = \"monokai\"");
+    fs::write(&config_file, config).unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.env("HOME", temp_dir.path()).env("XDG_CONFIG_HOME", temp_dir.path());
+    cmd.arg("--config").arg(&config_file).arg("-c").arg("config show");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!("# Loaded from {}\n", config_file.display())))
+        .stdout(predicate::str::contains("scheme = \"monokai\""));
+}
+
+#[test]
+fn test_check_flag() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = temp_dir.path().join("script.sh");
+    fs::write(&script, "touch ran\necho a )\nfor x in a b; do\n  echo $x\ndone\ncat <\n").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.current_dir(temp_dir.path());
+    cmd.arg("-n").arg(&script);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains(format!("{}:2:9: error: syntax error near unexpected token `)'", script.display())))
+        .stderr(predicate::str::contains(format!("{}:6:6: error: Expected filename after '<'", script.display())));
+    assert!(!temp_dir.path().join("ran").exists());
+
+    fs::write(&script, "if true; then\n  echo ok\nfi\n").unwrap();
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("--check").arg(&script);
+    cmd.assert().success().stdout("").stderr("");
+}
+
+#[test]
+fn test_timings_flag() {
+    let mut cmd = Command::cargo_bin("flex-sh").unwrap();
+    cmd.arg("--timings").arg("-c").arg("echo hi");
+    cmd.assert()
+        .success()
+        .stdout("hi\n")
+        .stderr(predicate::str::contains("config load"))
+        .stderr(predicate::str::contains("history load"))
+        .stderr(predicate::str::contains("PATH cache build"));
+}
+
 #[test]
 fn test_version_flag() {
     let mut cmd = Command::cargo_bin("flex-sh").unwrap();
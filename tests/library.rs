@@ -0,0 +1,17 @@
+//! Uses flex-sh as a library. Runs without the test harness, whose capture of
+//! `print!` would keep builtin output from reaching the shell's standard output.
+
+use flex_sh::{Cli, Shell};
+
+#[tokio::main]
+async fn main() {
+    let args = <Cli as clap::Parser>::parse_from(["flex-sh", "--no-color"]);
+    let mut shell = Shell::new(args).await.expect("shell should start");
+
+    assert_eq!(shell.execute_capturing("echo hello").await.unwrap(), (0, "hello\n".to_string()));
+    assert_eq!(shell.execute_capturing("printf 'a b' | tr ' ' -").await.unwrap(), (0, "a-b".to_string()));
+
+    shell.execute_command("false").await.unwrap();
+    assert_eq!(shell.exit_code(), 1);
+    println!("library: ok");
+}